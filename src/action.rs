@@ -1,18 +1,107 @@
 use serde::{Deserialize, Serialize};
-use strum::Display;
+use strum::{Display, EnumIter, EnumMessage};
 
-#[derive(Debug, Clone, PartialEq, Eq, Display, Serialize, Deserialize)]
+/// Actions which can be bound to keys are documented with a `message`, which is shown in the help
+/// popup. Actions without one are only used internally.
+#[derive(Debug, Clone, PartialEq, Eq, Display, EnumIter, EnumMessage, Serialize, Deserialize)]
 pub enum Action {
     Tick,
+    #[strum(message = "Select the next item")]
     Next,
+    #[strum(message = "Select the previous item")]
     Previous,
+    #[strum(message = "Open the selected item")]
     Enter,
+    #[strum(message = "Close the popup or cancel the input")]
+    Close,
+    #[strum(message = "Go to the next page")]
+    NextPage,
+    #[strum(message = "Go to the previous page")]
+    PreviousPage,
+    #[strum(message = "Select the item on the left")]
+    SelectLeft,
+    #[strum(message = "Select the item on the right")]
+    SelectRight,
+    #[strum(message = "Go to the top")]
+    GoToTop,
+    #[strum(message = "Go to the bottom")]
+    GoToBottom,
+    #[strum(message = "Sort by the next column")]
+    SortByNextColumn,
+    #[strum(message = "Reverse the sort order")]
+    ReverseSortOrder,
+    #[strum(message = "Expand or collapse the selected item")]
+    ToggleExpand,
+    #[strum(message = "Open the project of the selected pipeline")]
+    OpenProject,
+    #[strum(message = "Go back to the previous project")]
+    GoBack,
+    #[strum(message = "Browse the artifacts of the selected pipeline")]
+    ShowArtifacts,
+    // Show the artifacts of the jobs of a pipeline, given its project and ID
+    OpenArtifacts(String, u32),
+    #[strum(message = "Download the selected file")]
+    Download,
+    #[strum(message = "Download the whole archive")]
+    DownloadAll,
+    #[strum(message = "Search the current view")]
+    Search,
+    #[strum(message = "Jump to the next match of the search")]
+    NextMatch,
+    #[strum(message = "Jump to the previous match of the search")]
+    PreviousMatch,
+    #[strum(message = "Switch to the next tab")]
+    NextTab,
+    #[strum(message = "Only show the tests failing since the target branch")]
+    ToggleNewFailures,
+    #[strum(message = "Show the jobs of the selected pipeline as a graph")]
+    ShowGraph,
+    // Show the log of a job, given its project and ID
+    OpenJobLog(String, u32),
+    #[strum(message = "Switch between the pipelines and their statistics")]
+    ToggleStats,
+    #[strum(message = "Switch between the pipelines and the flaky jobs report")]
+    ToggleFlakyJobs,
+    #[strum(message = "Switch between the pipelines and the environments")]
+    ToggleEnvironments,
+    #[strum(message = "Switch between the pipelines and the pipeline schedules")]
+    ToggleSchedules,
+    #[strum(message = "Switch between the pipelines and the runners")]
+    ToggleRunners,
+    #[strum(message = "Switch between the pipelines and the CI/CD variables")]
+    ToggleVariables,
+    #[strum(message = "Switch between the pipelines and my pipelines, merge requests and to-dos")]
+    ToggleMyWork,
+    #[strum(message = "Show or hide the value of the selected variable")]
+    RevealValue,
+    #[strum(message = "Run the selected schedule now")]
+    RunSchedule,
+    #[strum(message = "Activate or deactivate the selected schedule")]
+    ToggleActive,
+    #[strum(message = "Add an item")]
+    NewItem,
+    #[strum(message = "Delete the selected item")]
+    Delete,
+    #[strum(message = "Switch to a project by typing part of its path")]
+    SwitchProject,
+    #[strum(message = "Focus the pane above")]
     FocusUp,
+    #[strum(message = "Focus the pane below")]
     FocusDown,
+    #[strum(message = "Focus the pane on the left")]
     FocusLeft,
+    #[strum(message = "Focus the pane on the right")]
     FocusRight,
+    #[strum(message = "Grow the focused pane")]
+    GrowPane,
+    #[strum(message = "Shrink the focused pane")]
+    ShrinkPane,
+    #[strum(message = "Zoom the focused pane in or out")]
+    ToggleZoom,
     Render,
+    #[strum(message = "Quit the application")]
     Quit,
     Error(String),
+    #[strum(message = "Show this help")]
     Help,
 }
@@ -1,32 +1,45 @@
-use std::collections::HashMap;
-
 use crossterm::event::KeyEvent;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 
 use crate::action::Action;
+use crate::components::artifacts_component::ArtifactsComponent;
+use crate::components::ci_variables_component::CiVariablesComponent;
+use crate::components::environments_component::EnvironmentsComponent;
+use crate::components::flaky_jobs_component::FlakyJobsComponent;
 use crate::components::footer_component::FooterComponent;
 use crate::components::header_component::HeaderComponent;
+use crate::components::help_component::HelpComponent;
+use crate::components::log_viewer_component::LogViewerComponent;
+use crate::components::my_work_component::MyWorkComponent;
+use crate::components::notifications_component::NotificationsComponent;
+use crate::components::pipeline_graph_component::PipelineGraphComponent;
 use crate::components::pipelines_viewer_component::PipelinesViewerComponent;
 use crate::components::project_selector_component::ProjectSelectorComponent;
-use crate::components::Component;
-use crate::config::Config;
-use crate::state::State;
+use crate::components::project_switcher_component::ProjectSwitcherComponent;
+use crate::components::runners_component::RunnersComponent;
+use crate::components::schedules_component::SchedulesComponent;
+use crate::components::stats_component::StatsComponent;
+use crate::components::{Component, ComponentId};
+use crate::config::{normalize_key_event, Config, Scope};
+use crate::state::{InputMode, PaneLayout, State, View};
 use crate::tui::{Event, Tui};
 use color_eyre::Result;
 
 pub struct App {
     config: Config,
-    components: HashMap<usize, Box<dyn Component>>,
+    // Components are drawn in order, so overlays have to come last
+    components: Vec<(ComponentId, Box<dyn Component>)>,
     should_quit: bool,
-    mode: Mode,
     last_tick_key_events: Vec<KeyEvent>,
     action_tx: mpsc::UnboundedSender<Action>,
     action_rx: mpsc::UnboundedReceiver<Action>,
     state: State,
 }
 
-#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(
+    Default, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, strum::Display,
+)]
 pub enum Mode {
     #[default]
     Home,
@@ -35,21 +48,69 @@ pub enum Mode {
 impl App {
     pub fn new(config: Config) -> Result<Self> {
         let (action_tx, action_rx) = mpsc::unbounded_channel();
-        let components_vec: Vec<Box<dyn Component>> = vec![
-            Box::new(HeaderComponent::new()),
-            Box::new(ProjectSelectorComponent::new()),
-            Box::new(PipelinesViewerComponent::new()),
-            Box::new(FooterComponent::new()),
+        let components: Vec<(ComponentId, Box<dyn Component>)> = vec![
+            (ComponentId::Header, Box::new(HeaderComponent::new())),
+            (
+                ComponentId::ProjectSelector,
+                Box::new(ProjectSelectorComponent::new()),
+            ),
+            (
+                ComponentId::PipelinesViewer,
+                Box::new(PipelinesViewerComponent::new()),
+            ),
+            (ComponentId::Stats, Box::new(StatsComponent::new())),
+            (ComponentId::FlakyJobs, Box::new(FlakyJobsComponent::new())),
+            (
+                ComponentId::Environments,
+                Box::new(EnvironmentsComponent::new()),
+            ),
+            (ComponentId::Schedules, Box::new(SchedulesComponent::new())),
+            (ComponentId::Runners, Box::new(RunnersComponent::new())),
+            (
+                ComponentId::CiVariables,
+                Box::new(CiVariablesComponent::new()),
+            ),
+            (ComponentId::MyWork, Box::new(MyWorkComponent::new())),
+            (
+                ComponentId::PipelineGraph,
+                Box::new(PipelineGraphComponent::new()),
+            ),
+            (ComponentId::Footer, Box::new(FooterComponent::new())),
+            (
+                ComponentId::Notifications,
+                Box::new(NotificationsComponent::new()),
+            ),
+            (ComponentId::LogViewer, Box::new(LogViewerComponent::new())),
+            (ComponentId::Artifacts, Box::new(ArtifactsComponent::new())),
+            (
+                ComponentId::ProjectSwitcher,
+                Box::new(ProjectSwitcherComponent::new()),
+            ),
+            (ComponentId::Help, Box::new(HelpComponent::new())),
         ];
-        let state = State {
-            focused_component: 1, // Focus on project selector when the application starts
-            ..Default::default()
+        let layout = PaneLayout::new(config.ui.layout.clone());
+        let state = if layout.config.project_list {
+            State {
+                // Focus on project selector when the application starts
+                focused_component: ComponentId::ProjectSelector,
+                projects: config.core.gitlab_projects.clone(),
+                layout,
+                ..Default::default()
+            }
+        } else {
+            // Without the project list, there is no way to pick a project, so open the first one
+            State {
+                focused_component: ComponentId::PipelinesViewer,
+                projects: config.core.gitlab_projects.clone(),
+                active_gitlab_project: config.core.gitlab_projects.first().cloned(),
+                layout,
+                ..Default::default()
+            }
         };
         Ok(Self {
             config,
-            components: HashMap::from_iter(components_vec.into_iter().enumerate()),
+            components,
             should_quit: false,
-            mode: Mode::Home,
             last_tick_key_events: Vec::new(),
             action_tx,
             action_rx,
@@ -58,8 +119,7 @@ impl App {
     }
 
     pub async fn run(&mut self) -> Result<()> {
-        let mut tui = Tui::new()?;
-        // .mouse(true) // uncomment this line to enable mouse support
+        let mut tui = Tui::new()?.mouse(self.config.ui.mouse);
         tui.enter()?;
 
         for (_, component) in self.components.iter_mut() {
@@ -89,13 +149,18 @@ impl App {
             return Ok(());
         };
         let action_tx = self.action_tx.clone();
+        let mut key_bound = false;
         match event {
             Event::Quit => action_tx.send(Action::Quit)?,
             Event::Tick => action_tx.send(Action::Tick)?,
             Event::Render => action_tx.send(Action::Render)?,
-            Event::Key(key) => self.handle_key_event(key)?,
+            Event::Key(key) => key_bound = self.handle_key_event(key)?,
             _ => {}
         }
+        // Keys bound to an action are not passed on as raw events (e.g. text input)
+        if key_bound {
+            return Ok(());
+        }
         for (_, component) in self.components.iter_mut() {
             if let Some(action) = component.handle_events(Some(event.clone()), &mut self.state)? {
                 action_tx.send(action)?;
@@ -104,24 +169,43 @@ impl App {
         Ok(())
     }
 
-    fn handle_key_event(&mut self, key: KeyEvent) -> Result<()> {
-        let action_tx = self.action_tx.clone();
-        let Some(keymap) = self.config.keybindings.get(&self.mode) else {
-            return Ok(());
-        };
-        match keymap.get(&vec![key]) {
-            Some(action) => {
-                action_tx.send(action.clone())?;
-            }
-            _ => {
+    /// Resolve a key event using the keybinding scopes which currently apply and dispatch the
+    /// bound action. Returns whether the key was bound to an action.
+    fn handle_key_event(&mut self, key: KeyEvent) -> Result<bool> {
+        let key = normalize_key_event(key);
+        let scopes = self.state.active_scopes();
+        let keybindings = &self.config.keybindings;
+        let resolved = match keybindings.resolve(self.state.mode, &scopes, &[key]) {
+            Some(resolved) => Some(resolved),
+            // Text input only uses single keys
+            None if self.state.input_mode == InputMode::Insert => None,
+            None => {
                 // If the key was not handled as a single key action,
                 // then consider it for multi-key combinations.
                 self.last_tick_key_events.push(key);
 
                 // Check for multi-key combinations
-                if let Some(action) = keymap.get(&self.last_tick_key_events) {
-                    action_tx.send(action.clone())?;
-                }
+                keybindings.resolve(self.state.mode, &scopes, &self.last_tick_key_events)
+            }
+        };
+        let Some((scope, action)) = resolved else {
+            return Ok(false);
+        };
+        self.dispatch(scope, action)?;
+        Ok(true)
+    }
+
+    /// Global actions are broadcast to every component, actions from other scopes only go to the
+    /// component showing a popup or, if there is none, the focused one.
+    fn dispatch(&mut self, scope: Scope, action: Action) -> Result<()> {
+        if scope == Scope::Global {
+            self.action_tx.send(action)?;
+            return Ok(());
+        }
+        let target = self.state.popup.unwrap_or(self.state.focused_component);
+        if let Some((_, component)) = self.components.iter_mut().find(|(id, _)| *id == target) {
+            if let Some(action) = component.update(action, &mut self.state)? {
+                self.action_tx.send(action)?;
             }
         }
         Ok(())
@@ -135,6 +219,45 @@ impl App {
                 }
                 Action::Quit => self.should_quit = true,
                 Action::Render => self.render(tui)?,
+                Action::FocusUp | Action::FocusDown | Action::FocusLeft | Action::FocusRight
+                    if self.state.popup.is_none() =>
+                {
+                    if let Some(neighbour) =
+                        self.state.focused_component.neighbour(&action, &self.state)
+                    {
+                        self.state.focused_component = neighbour;
+                    }
+                }
+                Action::GrowPane | Action::ShrinkPane if self.state.popup.is_none() => {
+                    let focused = self.state.focused_component;
+                    self.state
+                        .layout
+                        .resize(focused, action == Action::GrowPane);
+                }
+                Action::ToggleStats if self.state.popup.is_none() => {
+                    self.toggle_view(View::Stats);
+                }
+                Action::ToggleFlakyJobs if self.state.popup.is_none() => {
+                    self.toggle_view(View::FlakyJobs);
+                }
+                Action::ToggleEnvironments if self.state.popup.is_none() => {
+                    self.toggle_view(View::Environments);
+                }
+                Action::ToggleSchedules if self.state.popup.is_none() => {
+                    self.toggle_view(View::Schedules);
+                }
+                Action::ToggleRunners if self.state.popup.is_none() => {
+                    self.toggle_view(View::Runners);
+                }
+                Action::ToggleVariables if self.state.popup.is_none() => {
+                    self.toggle_view(View::Variables);
+                }
+                Action::ToggleMyWork if self.state.popup.is_none() => {
+                    self.toggle_view(View::MyWork);
+                }
+                Action::ToggleZoom if self.state.popup.is_none() => {
+                    self.state.layout.zoomed = !self.state.layout.zoomed;
+                }
                 _ => {}
             }
             for (_, component) in self.components.iter_mut() {
@@ -146,7 +269,22 @@ impl App {
         Ok(())
     }
 
+    /// Show a view next to the project list, or go back to the pipelines if it is shown already.
+    fn toggle_view(&mut self, view: View) {
+        let shown = self.state.view.component();
+        self.state.view = if self.state.view == view {
+            View::Pipelines
+        } else {
+            view
+        };
+        // Keep the focus on the pane next to the project list
+        if self.state.focused_component == shown {
+            self.state.focused_component = self.state.view.component();
+        }
+    }
+
     fn render(&mut self, tui: &mut Tui) -> Result<()> {
+        self.state.layout.width = tui.size()?.width;
         tui.draw(|frame| {
             for (_, component) in self.components.iter_mut() {
                 if let Err(err) = component.draw(frame, frame.area(), &self.state) {
@@ -0,0 +1,241 @@
+use std::{fs, path::PathBuf};
+
+use chrono::{DateTime, Utc};
+use color_eyre::{eyre::eyre, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tracing::warn;
+
+/// Kinds of data stored in the cache. Each kind is kept in a separate file per project, except
+/// for the ones which are not about a single project.
+#[derive(Clone, Copy, strum::Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum CacheKind {
+    Pipelines,
+    Stats,
+    Jobs,
+    Environments,
+    Schedules,
+    Runners,
+    MergeRequests,
+    // The kinds below are not about a single project
+    DiscoveredProjects,
+    RecentProjects,
+}
+
+/// A cached payload together with the time it was fetched from GitLab.
+#[derive(Serialize, Deserialize)]
+pub struct CacheEntry<T> {
+    pub fetched_at: DateTime<Utc>,
+    pub data: T,
+}
+
+/// On-disk cache of the last data fetched from GitLab, stored as JSON files under
+/// `$XDG_CACHE_HOME/gitlab-dashboard/`.
+///
+/// The cache lets the dashboard start instantly and keep showing (stale) data while the GitLab
+/// instance is unreachable.
+#[derive(Clone, Default)]
+pub struct Cache {
+    root: Option<PathBuf>,
+}
+
+impl Cache {
+    pub fn new(enabled: bool) -> Self {
+        let root = if enabled {
+            dirs::cache_dir().map(|dir| dir.join(env!("CARGO_PKG_NAME")))
+        } else {
+            None
+        };
+        Self { root }
+    }
+
+    /// Path of the cache file of a kind of data, for a project unless `gitlab_project` is None.
+    fn path(&self, gitlab_project: Option<&str>, kind: CacheKind) -> Option<PathBuf> {
+        let root = self.root.as_ref()?;
+        let file_name = format!("{}.json", kind);
+        Some(match gitlab_project {
+            Some(gitlab_project) => root.join("projects").join(gitlab_project).join(file_name),
+            None => root.join(file_name),
+        })
+    }
+
+    /// Load cached data of a given kind for a project, if any. Unreadable or outdated cache files
+    /// are ignored.
+    pub fn load<T: DeserializeOwned>(
+        &self,
+        gitlab_project: &str,
+        kind: CacheKind,
+    ) -> Option<CacheEntry<T>> {
+        self.load_file(self.path(Some(gitlab_project), kind)?)
+    }
+
+    /// Load cached data of a kind which is not about a single project, if any.
+    pub fn load_shared<T: DeserializeOwned>(&self, kind: CacheKind) -> Option<CacheEntry<T>> {
+        self.load_file(self.path(None, kind)?)
+    }
+
+    fn load_file<T: DeserializeOwned>(&self, path: PathBuf) -> Option<CacheEntry<T>> {
+        let content = fs::read_to_string(&path).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(entry) => Some(entry),
+            Err(error) => {
+                warn!("Ignoring invalid cache file {}: {}", path.display(), error);
+                None
+            }
+        }
+    }
+
+    /// Store data of a given kind for a project, replacing the previous cache entry.
+    pub fn store<T: Serialize>(
+        &self,
+        gitlab_project: &str,
+        kind: CacheKind,
+        data: &T,
+    ) -> Result<()> {
+        match self.path(Some(gitlab_project), kind) {
+            Some(path) => Self::store_file(path, data),
+            None => Ok(()),
+        }
+    }
+
+    /// Store data of a kind which is not about a single project.
+    pub fn store_shared<T: Serialize>(&self, kind: CacheKind, data: &T) -> Result<()> {
+        match self.path(None, kind) {
+            Some(path) => Self::store_file(path, data),
+            None => Ok(()),
+        }
+    }
+
+    fn store_file<T: Serialize>(path: PathBuf, data: &T) -> Result<()> {
+        let parent = path
+            .parent()
+            .ok_or_else(|| eyre!("Invalid cache path {}", path.display()))?;
+        fs::create_dir_all(parent)?;
+        let entry = CacheEntry {
+            fetched_at: Utc::now(),
+            data,
+        };
+        // Write to a temporary file first, so that a crash never leaves a truncated cache behind
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_vec(&entry)?)?;
+        fs::rename(tmp_path, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+
+    /// Cache rooted in a directory of its own, removed when dropped.
+    struct TestCache {
+        cache: Cache,
+        root: PathBuf,
+    }
+
+    impl TestCache {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!(
+                "{}-{}-{}",
+                env!("CARGO_PKG_NAME"),
+                std::process::id(),
+                name
+            ));
+            Self {
+                cache: Cache {
+                    root: Some(root.clone()),
+                },
+                root,
+            }
+        }
+    }
+
+    impl Drop for TestCache {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    #[test]
+    fn stored_data_is_loaded_back() {
+        let test = TestCache::new("round-trip");
+        let cache = &test.cache;
+        let stored = vec!["a".to_string(), "b".to_string()];
+        cache
+            .store("group/project", CacheKind::Pipelines, &stored)
+            .unwrap();
+        cache
+            .store_shared(CacheKind::RecentProjects, &["group/project"])
+            .unwrap();
+
+        let entry = cache
+            .load::<Vec<String>>("group/project", CacheKind::Pipelines)
+            .unwrap();
+        assert_eq!(entry.data, stored);
+        assert!(Utc::now() - entry.fetched_at < TimeDelta::minutes(1));
+        let entry = cache
+            .load_shared::<Vec<String>>(CacheKind::RecentProjects)
+            .unwrap();
+        assert_eq!(entry.data, ["group/project"]);
+        // Kinds and projects are kept apart
+        assert!(cache
+            .load::<Vec<String>>("group/project", CacheKind::Jobs)
+            .is_none());
+        assert!(cache
+            .load::<Vec<String>>("group/other", CacheKind::Pipelines)
+            .is_none());
+    }
+
+    #[test]
+    fn stale_entries_keep_the_time_they_were_fetched_at() {
+        let test = TestCache::new("stale");
+        let fetched_at = Utc::now() - TimeDelta::days(3);
+        let path = test
+            .cache
+            .path(Some("group/project"), CacheKind::Pipelines)
+            .unwrap();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let entry = CacheEntry {
+            fetched_at,
+            data: vec![1, 2, 3],
+        };
+        fs::write(&path, serde_json::to_vec(&entry).unwrap()).unwrap();
+
+        let entry = test
+            .cache
+            .load::<Vec<u32>>("group/project", CacheKind::Pipelines)
+            .unwrap();
+        assert_eq!(entry.fetched_at, fetched_at);
+        assert_eq!(entry.data, [1, 2, 3]);
+    }
+
+    #[test]
+    fn outdated_cache_files_are_ignored() {
+        let test = TestCache::new("outdated");
+        // Data stored in a shape which is not the one expected anymore
+        test.cache
+            .store("group/project", CacheKind::Pipelines, &"not a list")
+            .unwrap();
+        assert!(test
+            .cache
+            .load::<Vec<String>>("group/project", CacheKind::Pipelines)
+            .is_none());
+    }
+
+    #[test]
+    fn disabled_cache_stores_and_loads_nothing() {
+        let cache = Cache::new(false);
+        cache
+            .store("group/project", CacheKind::Pipelines, &[1])
+            .unwrap();
+        cache.store_shared(CacheKind::RecentProjects, &[1]).unwrap();
+        assert!(cache
+            .load::<Vec<u32>>("group/project", CacheKind::Pipelines)
+            .is_none());
+        assert!(cache
+            .load_shared::<Vec<u32>>(CacheKind::RecentProjects)
+            .is_none());
+    }
+}
@@ -3,14 +3,139 @@ use crossterm::event::{KeyEvent, MouseEvent};
 use ratatui::{layout::Rect, Frame};
 use tokio::sync::mpsc::UnboundedSender;
 
-use crate::{action::Action, config::Config, state::State, tui::Event};
+use crate::{
+    action::Action,
+    config::{Config, Scope},
+    state::State,
+    tui::Event,
+};
 
+pub mod artifacts_component;
+pub mod ci_variables_component;
+pub mod environments_component;
+pub mod flaky_jobs_component;
 pub mod footer_component;
 pub mod header_component;
+pub mod help_component;
+pub mod log_viewer_component;
+pub mod my_work_component;
+pub mod notifications_component;
+pub mod pipeline_graph_component;
 pub mod pipelines_viewer_component;
 pub mod project_selector_component;
+pub mod project_switcher_component;
+pub mod runners_component;
+pub mod schedules_component;
+pub mod stats_component;
 pub mod utils;
 
+/// Identifies components registered with the application, e.g. to track which one is focused.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ComponentId {
+    Header,
+    #[default]
+    ProjectSelector,
+    PipelinesViewer,
+    Stats,
+    FlakyJobs,
+    Environments,
+    Schedules,
+    Runners,
+    CiVariables,
+    MyWork,
+    PipelineGraph,
+    Footer,
+    Notifications,
+    LogViewer,
+    Artifacts,
+    ProjectSwitcher,
+    Help,
+}
+
+impl ComponentId {
+    /// Keybinding scope which applies while the component is focused, on top of the global one.
+    pub fn scope(&self) -> Option<Scope> {
+        match self {
+            ComponentId::ProjectSelector => Some(Scope::ProjectList),
+            ComponentId::PipelinesViewer => Some(Scope::PipelinesTable),
+            ComponentId::FlakyJobs => Some(Scope::FlakyJobs),
+            ComponentId::Environments => Some(Scope::Environments),
+            ComponentId::Schedules => Some(Scope::Schedules),
+            ComponentId::Runners => Some(Scope::Runners),
+            ComponentId::CiVariables => Some(Scope::Variables),
+            ComponentId::MyWork => Some(Scope::MyWork),
+            ComponentId::PipelineGraph => Some(Scope::PipelineGraph),
+            _ => None,
+        }
+    }
+
+    /// Keybinding scope which applies while the component shows a popup.
+    pub fn popup_scope(&self) -> Scope {
+        match self {
+            ComponentId::LogViewer => Scope::LogViewer,
+            ComponentId::Artifacts => Scope::Artifacts,
+            ComponentId::Schedules | ComponentId::CiVariables => Scope::Variables,
+            _ => Scope::Popup,
+        }
+    }
+
+    /// Component which receives focus when moving away from this one in the direction given by a
+    /// `Focus*` action. Hidden panes are skipped.
+    pub fn neighbour(&self, action: &Action, state: &State) -> Option<ComponentId> {
+        let layout = &state.layout;
+        // Views share the pane next to the project list, so they share their neighbours as well
+        let position = if *self == state.view.component() {
+            ComponentId::PipelinesViewer
+        } else {
+            *self
+        };
+        let neighbour = if layout.stacked_vertically() {
+            position.vertical_neighbour(action)
+        } else {
+            position.horizontal_neighbour(action)
+        }?;
+        let neighbour = if neighbour == ComponentId::PipelinesViewer {
+            state.view.component()
+        } else {
+            neighbour
+        };
+        if layout.is_focusable(neighbour) {
+            Some(neighbour)
+        } else {
+            // E.g. move from the pipelines straight to the header when the project list is hidden
+            neighbour.neighbour(action, state)
+        }
+    }
+
+    fn vertical_neighbour(&self, action: &Action) -> Option<ComponentId> {
+        match (self, action) {
+            (ComponentId::Header, Action::FocusDown) => Some(ComponentId::ProjectSelector),
+            (ComponentId::ProjectSelector, Action::FocusUp) => Some(ComponentId::Header),
+            (ComponentId::ProjectSelector, Action::FocusDown) => Some(ComponentId::PipelinesViewer),
+            (ComponentId::PipelinesViewer, Action::FocusUp) => Some(ComponentId::ProjectSelector),
+            (ComponentId::PipelinesViewer, Action::FocusDown) => Some(ComponentId::Footer),
+            (ComponentId::Footer, Action::FocusUp) => Some(ComponentId::PipelinesViewer),
+            _ => None,
+        }
+    }
+
+    fn horizontal_neighbour(&self, action: &Action) -> Option<ComponentId> {
+        match (self, action) {
+            (ComponentId::Header, Action::FocusDown) => Some(ComponentId::PipelinesViewer),
+            (ComponentId::ProjectSelector, Action::FocusUp) => Some(ComponentId::Header),
+            (ComponentId::ProjectSelector, Action::FocusDown) => Some(ComponentId::Footer),
+            (ComponentId::ProjectSelector, Action::FocusRight) => {
+                Some(ComponentId::PipelinesViewer)
+            }
+            (ComponentId::PipelinesViewer, Action::FocusUp) => Some(ComponentId::Header),
+            (ComponentId::PipelinesViewer, Action::FocusDown) => Some(ComponentId::Footer),
+            (ComponentId::PipelinesViewer, Action::FocusLeft) => Some(ComponentId::ProjectSelector),
+            (ComponentId::Footer, Action::FocusUp) => Some(ComponentId::PipelinesViewer),
+            _ => None,
+        }
+    }
+}
+
 /// `Component` is a trait that represents a visual and interactive element of the user interface.
 ///
 /// Implementors of this trait can be registered with the main application loop and will be able to
@@ -51,7 +176,7 @@ pub trait Component {
     /// # Returns
     ///
     /// * `Result<()>` - An Ok result or an error.
-    fn init(&mut self, state: &State) -> Result<()> {
+    fn init(&mut self, _state: &State) -> Result<()> {
         Ok(())
     }
     /// Handle incoming events and produce actions if necessary.
@@ -80,7 +205,7 @@ pub trait Component {
     /// # Returns
     ///
     /// * `Result<Option<Action>>` - An action to be processed or none.
-    fn handle_key_event(&mut self, key: KeyEvent, state: &mut State) -> Result<Option<Action>> {
+    fn handle_key_event(&mut self, key: KeyEvent, _state: &mut State) -> Result<Option<Action>> {
         let _ = key; // to appease clippy
         Ok(None)
     }
@@ -96,7 +221,7 @@ pub trait Component {
     fn handle_mouse_event(
         &mut self,
         mouse: MouseEvent,
-        state: &mut State,
+        _state: &mut State,
     ) -> Result<Option<Action>> {
         let _ = mouse; // to appease clippy
         Ok(None)
@@ -110,7 +235,7 @@ pub trait Component {
     /// # Returns
     ///
     /// * `Result<Option<Action>>` - An action to be processed or none.
-    fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>> {
+    fn update(&mut self, action: Action, _state: &mut State) -> Result<Option<Action>> {
         let _ = action; // to appease clippy
         Ok(None)
     }
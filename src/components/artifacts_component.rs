@@ -0,0 +1,720 @@
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+
+use color_eyre::{eyre::eyre, Result};
+use ratatui::{prelude::*, widgets::*};
+use zip::ZipArchive;
+
+use super::{
+    utils::{format_size, popup_area, PendingRequest},
+    Component, ComponentId,
+};
+use crate::{
+    action::Action,
+    config::Config,
+    gitlab::{DownloadProgress, GitlabClient, GitlabJob},
+    state::{Download, State},
+};
+
+/// Content of the artifacts archive of a job. The archive itself is only downloaded to list its
+/// content, files are then fetched one by one.
+struct Archive {
+    job: GitlabJob,
+    entries: Vec<ArchiveEntry>,
+}
+
+struct ArchiveEntry {
+    path: String,
+    size: u64,
+    is_dir: bool,
+}
+
+impl ArchiveEntry {
+    fn depth(&self) -> usize {
+        self.path.matches('/').count()
+    }
+
+    fn name(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or(&self.path)
+    }
+}
+
+/// Text file of an archive shown in the popup.
+struct Preview {
+    path: String,
+    lines: Vec<String>,
+    scroll: usize,
+}
+
+/// Popup listing the jobs of a pipeline which kept artifacts, then the content of their archive.
+/// Small text files can be previewed, and files or whole archives downloaded. Archives larger than
+/// `artifacts.max_archive_size` are only browsed once confirmed.
+#[derive(Default)]
+pub struct ArtifactsComponent {
+    config: Config,
+    client: GitlabClient,
+    // Project and ID of the pipeline, while the popup is open
+    pipeline: Option<(String, u32)>,
+    jobs: Option<Vec<GitlabJob>>,
+    jobs_request: Option<PendingRequest<Vec<GitlabJob>>>,
+    archive: Option<Archive>,
+    archive_request: Option<PendingRequest<Archive>>,
+    // Job whose large archive is browsed once confirmed
+    confirmation: Option<GitlabJob>,
+    preview: Option<Preview>,
+    preview_request: Option<PendingRequest<Preview>>,
+    download_request: Option<PendingRequest<PathBuf>>,
+    // Outcome of the last request, e.g. where a file was saved
+    message: Option<std::result::Result<String, String>>,
+    table_state: TableState,
+    // Number of lines of the preview during the last render, to scroll page by page
+    page_height: usize,
+}
+
+impl ArtifactsComponent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn open(&mut self, gitlab_project: String, pipeline_id: u32, state: &mut State) {
+        self.reset();
+        self.pipeline = Some((gitlab_project.clone(), pipeline_id));
+        let client = self.client.clone();
+        self.jobs_request = Some(PendingRequest::spawn(async move {
+            let jobs = client
+                .fetch_pipeline_jobs(&gitlab_project, pipeline_id, false)
+                .await?;
+            Ok(jobs
+                .into_iter()
+                .filter(|job| job.artifacts_file.is_some())
+                .collect())
+        }));
+        state.popup = Some(ComponentId::Artifacts);
+    }
+
+    fn reset(&mut self) {
+        self.pipeline = None;
+        self.jobs = None;
+        self.jobs_request = None;
+        self.archive = None;
+        self.archive_request = None;
+        self.confirmation = None;
+        self.preview = None;
+        self.preview_request = None;
+        self.message = None;
+        self.table_state = TableState::default();
+    }
+
+    /// Go back to the previous screen, or close the popup from the list of jobs.
+    fn close(&mut self, state: &mut State) {
+        if self.confirmation.take().is_some() {
+            self.message = None;
+            return;
+        }
+        if self.preview.take().is_some() || self.preview_request.take().is_some() {
+            return;
+        }
+        if self.archive.is_some() || self.archive_request.is_some() {
+            if self.archive_request.take().is_some() {
+                state.download = None;
+            }
+            let job_id = self.archive.take().map(|archive| archive.job.id);
+            let index = self
+                .jobs
+                .iter()
+                .flatten()
+                .position(|job| Some(job.id) == job_id);
+            self.table_state.select(index.or(Some(0)));
+            return;
+        }
+        self.reset();
+        state.popup = None;
+    }
+
+    fn poll(&mut self, state: &mut State) {
+        if let Some(result) = self.jobs_request.as_mut().and_then(PendingRequest::poll) {
+            self.jobs_request = None;
+            match result {
+                Ok(jobs) => {
+                    self.table_state.select((!jobs.is_empty()).then_some(0));
+                    self.jobs = Some(jobs);
+                }
+                Err(error) => self.message = Some(Err(error.to_string())),
+            }
+        }
+        if let Some(result) = self.archive_request.as_mut().and_then(PendingRequest::poll) {
+            self.archive_request = None;
+            state.download = None;
+            match result {
+                Ok(archive) => {
+                    self.table_state
+                        .select((!archive.entries.is_empty()).then_some(0));
+                    self.archive = Some(archive);
+                }
+                Err(error) => self.message = Some(Err(error.to_string())),
+            }
+        }
+        if let Some(result) = self.preview_request.as_mut().and_then(PendingRequest::poll) {
+            self.preview_request = None;
+            match result {
+                Ok(preview) => self.preview = Some(preview),
+                Err(error) => self.message = Some(Err(error.to_string())),
+            }
+        }
+        if let Some(result) = self
+            .download_request
+            .as_mut()
+            .and_then(PendingRequest::poll)
+        {
+            self.download_request = None;
+            state.download = None;
+            self.message = Some(
+                result
+                    .map(|path| format!("Saved to {}", path.display()))
+                    .map_err(|error| error.to_string()),
+            );
+        }
+    }
+
+    fn selected_job(&self) -> Option<&GitlabJob> {
+        self.jobs.as_ref()?.get(self.table_state.selected()?)
+    }
+
+    fn selected_entry(&self) -> Option<&ArchiveEntry> {
+        self.archive
+            .as_ref()?
+            .entries
+            .get(self.table_state.selected()?)
+    }
+
+    /// Whether a download is running already, in which case another one cannot start.
+    fn busy(&mut self, state: &State) -> bool {
+        let busy = state.download.is_some() || self.download_request.is_some();
+        if busy {
+            self.message = Some(Err("Another download is running".to_string()));
+        }
+        busy
+    }
+
+    /// Fetch the archive of the selected job to list its content, once confirmed if it is large.
+    fn open_archive(&mut self, confirmed: bool, state: &mut State) {
+        let (Some((gitlab_project, _)), Some(job)) = (&self.pipeline, self.selected_job()) else {
+            return;
+        };
+        let (gitlab_project, job) = (gitlab_project.clone(), job.clone());
+        let size = job.artifacts_file.as_ref().map_or(0, |file| file.size);
+        if !confirmed && size > self.config.artifacts.max_archive_size {
+            self.message = Some(Ok(format!(
+                "The archive of {} is {}, download it to browse it? enter: confirm, esc: cancel",
+                job.name,
+                format_size(size)
+            )));
+            self.confirmation = Some(job);
+            return;
+        }
+        if self.busy(state) {
+            return;
+        }
+        let progress = DownloadProgress::default();
+        state.download = Some(Download {
+            label: format!("Artifacts of {}", job.name),
+            progress: progress.clone(),
+        });
+        let client = self.client.clone();
+        self.message = None;
+        self.archive_request = Some(PendingRequest::spawn(async move {
+            // A new file only readable by the user, removed once the archive is listed
+            let archive = tempfile::Builder::new()
+                .prefix("gitlab-dashboard-")
+                .suffix(".zip")
+                .tempfile()?;
+            let mut file = tokio::fs::File::from_std(archive.reopen()?);
+            client
+                .download_job_artifacts(&gitlab_project, job.id, None, &progress, &mut file)
+                .await?;
+            drop(file);
+            let entries =
+                tokio::task::spawn_blocking(move || list_entries(archive.reopen()?)).await??;
+            Ok(Archive { job, entries })
+        }));
+    }
+
+    /// Browse the large archive waiting for a confirmation.
+    fn confirm(&mut self, state: &mut State) {
+        let Some(job) = self.confirmation.take() else {
+            return;
+        };
+        self.message = None;
+        // The selection may have moved while the confirmation was shown
+        let index = self
+            .jobs
+            .iter()
+            .flatten()
+            .position(|candidate| candidate.id == job.id);
+        self.table_state.select(index);
+        self.open_archive(true, state);
+    }
+
+    /// Fetch the selected file of the archive, to show it if it is a small text file.
+    fn open_preview(&mut self) {
+        let (Some((gitlab_project, _)), Some(archive)) = (&self.pipeline, &self.archive) else {
+            return;
+        };
+        let Some(entry) = self.selected_entry().filter(|entry| !entry.is_dir) else {
+            return;
+        };
+        if entry.size > self.config.artifacts.max_preview_size {
+            self.message = Some(Err(format!(
+                "{} is too large to be previewed, download it instead",
+                entry.name()
+            )));
+            return;
+        }
+        let client = self.client.clone();
+        let (gitlab_project, job_id) = (gitlab_project.clone(), archive.job.id);
+        let path = entry.path.clone();
+        self.message = None;
+        self.preview_request = Some(PendingRequest::spawn(async move {
+            let mut content = vec![];
+            client
+                .download_job_artifacts(
+                    &gitlab_project,
+                    job_id,
+                    Some(&path),
+                    &DownloadProgress::default(),
+                    &mut content,
+                )
+                .await?;
+            let text = String::from_utf8(content).map_err(|_| {
+                eyre!(
+                    "{} is not a text file, download it instead",
+                    file_name(&path, &path)
+                )
+            })?;
+            Ok(Preview {
+                lines: text.lines().map(str::to_string).collect(),
+                path,
+                scroll: 0,
+            })
+        }));
+    }
+
+    fn enter(&mut self, state: &mut State) {
+        if self.confirmation.is_some() {
+            self.confirm(state);
+            return;
+        }
+        if self.preview.is_some() || self.preview_request.is_some() {
+            return;
+        }
+        if self.archive.is_some() {
+            self.open_preview();
+        } else if self.archive_request.is_none() {
+            self.open_archive(false, state);
+        }
+    }
+
+    /// Save the selected (or previewed) file of the archive, or the whole archive of the
+    /// selected job.
+    fn download(&mut self, state: &mut State) {
+        let Some(archive) = &self.archive else {
+            self.download_all(state);
+            return;
+        };
+        let path = match &self.preview {
+            Some(preview) => Some(preview.path.clone()),
+            None => self
+                .selected_entry()
+                .filter(|entry| !entry.is_dir)
+                .map(|entry| entry.path.clone()),
+        };
+        let Some(path) = path else {
+            return;
+        };
+        let job_id = archive.job.id;
+        let name = file_name(&path, "artifact").to_string();
+        self.save(job_id, Some(path), &name, state);
+    }
+
+    /// Save the whole archive of the job being browsed, or else of the selected job.
+    fn download_all(&mut self, state: &mut State) {
+        let job = match &self.archive {
+            Some(archive) => Some(&archive.job),
+            None => self.selected_job(),
+        };
+        let Some(job) = job else {
+            return;
+        };
+        let (job_id, name) = (job.id, archive_name(job));
+        self.save(job_id, None, &name, state);
+    }
+
+    /// Download the archive of a job, or one of its files, to a new file of the download
+    /// directory.
+    fn save(&mut self, job_id: u32, artifact_path: Option<String>, name: &str, state: &mut State) {
+        let Some((gitlab_project, _)) = &self.pipeline else {
+            return;
+        };
+        let gitlab_project = gitlab_project.clone();
+        if self.busy(state) {
+            return;
+        }
+        let target = unique_path(&self.config.artifacts.download_dir(), name);
+        let progress = DownloadProgress::default();
+        state.download = Some(Download {
+            label: name.to_string(),
+            progress: progress.clone(),
+        });
+        let client = self.client.clone();
+        self.message = None;
+        self.download_request = Some(PendingRequest::spawn(async move {
+            download(
+                &client,
+                &gitlab_project,
+                job_id,
+                artifact_path.as_deref(),
+                &progress,
+                &target,
+            )
+            .await?;
+            Ok(target)
+        }));
+    }
+
+    fn next(&mut self, lines: usize) {
+        match &mut self.preview {
+            Some(preview) => {
+                let max_scroll = preview.lines.len().saturating_sub(self.page_height.max(1));
+                preview.scroll = (preview.scroll + lines).min(max_scroll);
+            }
+            None => self.table_state.scroll_down_by(lines as u16),
+        }
+    }
+
+    fn previous(&mut self, lines: usize) {
+        match &mut self.preview {
+            Some(preview) => preview.scroll = preview.scroll.saturating_sub(lines),
+            None => self.table_state.scroll_up_by(lines as u16),
+        }
+    }
+
+    fn draw_jobs(&mut self, frame: &mut Frame, area: Rect, block: Block) {
+        let theme = &self.config.ui.theme;
+        let Some(jobs) = &self.jobs else {
+            let message = if self.jobs_request.is_some() {
+                "Loading..."
+            } else {
+                ""
+            };
+            frame.render_widget(
+                Paragraph::new(message)
+                    .alignment(Alignment::Center)
+                    .block(block),
+                area,
+            );
+            return;
+        };
+        if jobs.is_empty() {
+            frame.render_widget(
+                Paragraph::new("No job kept artifacts")
+                    .alignment(Alignment::Center)
+                    .block(block),
+                area,
+            );
+            return;
+        }
+        let rows = jobs.iter().map(|job| {
+            Row::new([
+                Line::from(job.name.as_str()),
+                Line::from(job.stage.as_str()),
+                Line::styled(
+                    format!("{} {}", theme.status_icon(&job.status), job.status),
+                    theme.status_style(&job.status),
+                ),
+                Line::from(
+                    job.artifacts_file
+                        .as_ref()
+                        .map(|file| format_size(file.size))
+                        .unwrap_or_default(),
+                )
+                .right_aligned(),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(1),
+                Constraint::Length(16),
+                Constraint::Length(24),
+                Constraint::Length(12),
+            ],
+        )
+        .header(Row::new(["Job", "Stage", "Status", "Size"]).bold())
+        .column_spacing(2)
+        .row_highlight_style(
+            theme.highlight(theme.palette.highlight_fg, theme.palette.highlight_bg),
+        )
+        .block(block);
+        frame.render_stateful_widget(table, area, &mut self.table_state);
+    }
+
+    fn draw_archive(&mut self, frame: &mut Frame, area: Rect, block: Block, state: &State) {
+        let theme = &self.config.ui.theme;
+        let Some(archive) = &self.archive else {
+            let progress = state
+                .download
+                .as_ref()
+                .and_then(|download| {
+                    let total = download.progress.total()?;
+                    Some(download.progress.received() * 100 / total)
+                })
+                .map_or_else(String::new, |percent| format!(" {}%", percent));
+            frame.render_widget(
+                Paragraph::new(format!("Loading...{}", progress))
+                    .alignment(Alignment::Center)
+                    .block(block),
+                area,
+            );
+            return;
+        };
+        let rows = archive.entries.iter().map(|entry| {
+            let name = format!(
+                "{}{}{}",
+                "  ".repeat(entry.depth()),
+                entry.name(),
+                if entry.is_dir { "/" } else { "" }
+            );
+            let size = if entry.is_dir {
+                String::new()
+            } else {
+                format_size(entry.size)
+            };
+            Row::new([Line::from(name), Line::from(size).right_aligned()])
+        });
+        let table = Table::new(rows, [Constraint::Fill(1), Constraint::Length(12)])
+            .column_spacing(2)
+            .row_highlight_style(
+                theme.highlight(theme.palette.highlight_fg, theme.palette.highlight_bg),
+            )
+            .block(block);
+        frame.render_stateful_widget(table, area, &mut self.table_state);
+    }
+}
+
+impl Component for ArtifactsComponent {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.client = GitlabClient::new(config.core.gitlab_url.clone());
+        self.config = config;
+        Ok(())
+    }
+
+    fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>> {
+        if let Action::OpenArtifacts(gitlab_project, pipeline_id) = action {
+            self.open(gitlab_project, pipeline_id, state);
+            return Ok(None);
+        }
+        // Downloads go on once the popup is closed
+        if action == Action::Tick {
+            self.poll(state);
+        }
+        if self.pipeline.is_none() {
+            return Ok(None);
+        }
+        let page = self.page_height.max(1);
+        match action {
+            Action::Next => self.next(1),
+            Action::Previous => self.previous(1),
+            Action::NextPage => self.next(page),
+            Action::PreviousPage => self.previous(page),
+            Action::Enter => self.enter(state),
+            Action::Download => self.download(state),
+            Action::DownloadAll => self.download_all(state),
+            Action::Close => self.close(state),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect, state: &State) -> Result<()> {
+        let Some((_, pipeline_id)) = &self.pipeline else {
+            return Ok(());
+        };
+        let theme = &self.config.ui.theme;
+        let area = popup_area(area, 80, 80);
+        let title = match (&self.preview, &self.archive) {
+            (Some(preview), _) => preview.path.clone(),
+            (None, Some(archive)) => format!("Artifacts of {}", archive.job.name),
+            (None, None) => format!("Artifacts of pipeline {}", pipeline_id),
+        };
+        let mut block = Block::bordered()
+            .border_type(BorderType::Thick)
+            .border_style(theme.fg(theme.palette.popup_border))
+            .padding(Padding::horizontal(1))
+            .title(title);
+        match &self.message {
+            Some(Ok(message)) => block = block.title_bottom(Line::from(message.clone())),
+            Some(Err(error)) => {
+                block = block.title_bottom(Line::styled(
+                    error.clone(),
+                    theme.fg(theme.palette.error).add_modifier(Modifier::BOLD),
+                ))
+            }
+            None => {}
+        }
+        self.page_height = block.inner(area).height as usize;
+        frame.render_widget(Clear, area);
+
+        if self.preview_request.is_some() {
+            frame.render_widget(
+                Paragraph::new("Loading...")
+                    .alignment(Alignment::Center)
+                    .block(block),
+                area,
+            );
+        } else if let Some(preview) = &self.preview {
+            // Only the visible lines are rendered, as files can be long
+            let lines: Vec<Line> = preview
+                .lines
+                .iter()
+                .skip(preview.scroll)
+                .take(self.page_height)
+                .map(|line| Line::from(line.as_str()))
+                .collect();
+            frame.render_widget(Paragraph::new(lines).block(block), area);
+        } else if self.archive.is_some() || self.archive_request.is_some() {
+            self.draw_archive(frame, area, block, state);
+        } else {
+            self.draw_jobs(frame, area, block);
+        }
+        Ok(())
+    }
+}
+
+/// Files and directories of a ZIP archive, each directory followed by its content.
+fn list_entries<R: Read + Seek>(reader: R) -> Result<Vec<ArchiveEntry>> {
+    let mut archive = ZipArchive::new(reader)?;
+    let mut entries: Vec<ArchiveEntry> = vec![];
+    for index in 0..archive.len() {
+        let file = archive.by_index(index)?;
+        entries.push(ArchiveEntry {
+            path: file.name().trim_end_matches('/').to_string(),
+            size: file.size(),
+            is_dir: file.is_dir(),
+        });
+    }
+    // Archives do not always list the directories themselves
+    let mut directories: Vec<String> = vec![];
+    for entry in &entries {
+        let mut path = entry.path.as_str();
+        while let Some((parent, _)) = path.rsplit_once('/') {
+            if !directories.iter().any(|directory| directory == parent) {
+                directories.push(parent.to_string());
+            }
+            path = parent;
+        }
+    }
+    for directory in directories {
+        if !entries.iter().any(|entry| entry.path == directory) {
+            entries.push(ArchiveEntry {
+                path: directory,
+                size: 0,
+                is_dir: true,
+            });
+        }
+    }
+    entries.sort_by(|a, b| a.path.split('/').cmp(b.path.split('/')));
+    Ok(entries)
+}
+
+/// Name of a file to save under the download directory: the last component of a path coming
+/// from GitLab, or `fallback` if it has none or it is `.` or `..`, so that it never points outside
+/// of the directory.
+fn file_name<'a>(path: &'a str, fallback: &'a str) -> &'a str {
+    match path.rsplit(['/', '\\']).next() {
+        Some("" | "." | "..") | None => fallback,
+        Some(name) => name,
+    }
+}
+
+/// File name of the archive of a job once downloaded.
+fn archive_name(job: &GitlabJob) -> String {
+    let filename = job
+        .artifacts_file
+        .as_ref()
+        .map_or("artifacts.zip", |file| file.filename.as_str());
+    format!("job-{}-{}", job.id, file_name(filename, "artifacts.zip"))
+}
+
+/// Path of a new file in a directory, numbered so that existing files are never overwritten.
+fn unique_path(directory: &Path, name: &str) -> PathBuf {
+    let path = directory.join(name);
+    if !path.exists() {
+        return path;
+    }
+    let (stem, extension) = match name.split_once('.') {
+        Some((stem, extension)) => (stem, format!(".{}", extension)),
+        None => (name, String::new()),
+    };
+    (1..)
+        .map(|number| directory.join(format!("{} ({}){}", stem, number, extension)))
+        .find(|path| !path.exists())
+        .unwrap_or(path)
+}
+
+/// Download the artifacts of a job to a file. The download goes to a temporary file first, so
+/// that an interrupted download never leaves a truncated file behind.
+async fn download(
+    client: &GitlabClient,
+    gitlab_project: &str,
+    job_id: u32,
+    artifact_path: Option<&str>,
+    progress: &DownloadProgress,
+    path: &Path,
+) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".part");
+    let tmp_path = path.with_file_name(tmp_name);
+    let mut file = tokio::fs::File::create(&tmp_path).await?;
+    let result = client
+        .download_job_artifacts(gitlab_project, job_id, artifact_path, progress, &mut file)
+        .await;
+    drop(file);
+    if let Err(error) = result {
+        let _ = tokio::fs::remove_file(&tmp_path).await;
+        return Err(error);
+    }
+    tokio::fs::rename(&tmp_path, path).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_names_stay_in_the_download_directory() {
+        assert_eq!(file_name("build/out/app.tar.gz", "x"), "app.tar.gz");
+        assert_eq!(file_name("..\\..\\evil.sh", "x"), "evil.sh");
+        assert_eq!(file_name("../..", "x"), "x");
+        assert_eq!(file_name("dir/", "x"), "x");
+        assert_eq!(file_name(".", "x"), "x");
+    }
+
+    #[test]
+    fn archive_names_only_keep_the_basename() {
+        let job: GitlabJob = serde_json::from_value(serde_json::json!({
+            "id": 7,
+            "name": "build",
+            "stage": "build",
+            "status": "success",
+            "web_url": "",
+            "created_at": "2024-01-01T00:00:00Z",
+            "artifacts_file": { "filename": "../../.bashrc", "size": 1 },
+        }))
+        .unwrap();
+        assert_eq!(archive_name(&job), "job-7-.bashrc");
+    }
+}
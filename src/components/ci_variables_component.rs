@@ -0,0 +1,696 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use color_eyre::{eyre::Error, Result};
+use crossterm::event::{KeyEvent, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::{prelude::*, widgets::*};
+
+use super::{
+    utils::{
+        get_block, popup_area, prepare_layout, Body, Element, Form, PendingRequest, OFFLINE_MESSAGE,
+    },
+    Component, ComponentId,
+};
+use crate::{
+    action::Action,
+    config::Config,
+    gitlab::{is_unreachable, CiVariable, GitlabClient},
+    state::{InputMode, State, View},
+};
+
+/// A variable of the project, or of one of its groups.
+struct VariableRow {
+    variable: CiVariable,
+    // Group defining the variable, None for the variables of the project itself
+    group: Option<String>,
+}
+
+impl VariableRow {
+    /// Identifies a variable across refreshes: its group, key and environment scope.
+    fn id(&self) -> (Option<String>, String, String) {
+        (
+            self.group.clone(),
+            self.variable.key.clone(),
+            self.variable.environment_scope.clone(),
+        )
+    }
+}
+
+/// Change waiting for the user to confirm it.
+enum Change {
+    Save {
+        variable: CiVariable,
+        exists: bool,
+    },
+    Delete {
+        key: String,
+        environment_scope: String,
+    },
+}
+
+impl Change {
+    fn prompt(&self) -> String {
+        match self {
+            Change::Save { variable, exists } => format!(
+                "{} variable {} ({})? enter: confirm, esc: cancel",
+                if *exists { "Update" } else { "Create" },
+                variable.key,
+                variable.environment_scope
+            ),
+            Change::Delete {
+                key,
+                environment_scope,
+            } => format!(
+                "Delete variable {} ({})? enter: confirm, esc: cancel",
+                key, environment_scope
+            ),
+        }
+    }
+}
+
+/// Form to create a variable, or update an existing one.
+struct VariableForm {
+    fields: Form,
+    // Variable being updated, whose key and environment scope identify it and cannot change
+    existing: Option<CiVariable>,
+    // Whether the value of the variable being updated was revealed, and is thus filled in
+    revealed: bool,
+}
+
+impl VariableForm {
+    /// Form filled in with the variable being updated, if any. Its value is only filled in if it
+    /// was revealed, and is typed as a secret otherwise.
+    fn new(existing: Option<CiVariable>, revealed: bool) -> Self {
+        let variable = existing.as_ref();
+        let editable = existing.is_none();
+        let fields = Form::new()
+            .text(
+                "Key",
+                variable.map_or("", |variable| &variable.key),
+                editable,
+            )
+            .secret(
+                "Value",
+                variable
+                    .and_then(|variable| variable.value.as_deref())
+                    .filter(|_| revealed)
+                    .unwrap_or_default(),
+                true,
+                revealed,
+            )
+            .text(
+                "Environments",
+                variable.map_or("*", |variable| &variable.environment_scope),
+                editable,
+            )
+            .flag(
+                "Protected",
+                variable.is_some_and(|variable| variable.protected),
+                true,
+            )
+            .flag(
+                "Masked",
+                variable.is_some_and(|variable| variable.masked),
+                true,
+            );
+        Self {
+            fields,
+            existing,
+            revealed,
+        }
+    }
+
+    /// Variable typed in the form, or why it cannot be saved.
+    fn variable(&self) -> Result<CiVariable, String> {
+        let mut value = self.fields.text_value("Value").to_string();
+        // Leaving the value of a hidden variable empty keeps it as it is. GitLab requires a value
+        // to update a variable, so the one it returned is sent back, unless it hides it.
+        if let Some(existing) = self.existing.as_ref().filter(|_| !self.revealed) {
+            if value.is_empty() {
+                value = existing.value.clone().ok_or_else(|| {
+                    format!(
+                        "GitLab does not return the value of {}, type it again",
+                        existing.key
+                    )
+                })?;
+            }
+        }
+        Ok(CiVariable {
+            key: self.fields.text_value("Key").trim().to_string(),
+            value: Some(value),
+            variable_type: self
+                .existing
+                .as_ref()
+                .map_or("env_var".to_string(), |variable| {
+                    variable.variable_type.clone()
+                }),
+            protected: self.fields.flag_value("Protected"),
+            masked: self.fields.flag_value("Masked"),
+            environment_scope: self.fields.text_value("Environments").trim().to_string(),
+        })
+    }
+}
+
+/// CI/CD variables of the selected project, and the ones it inherits from its groups. Values stay
+/// hidden until revealed one by one. The variables of the project can be created, updated and
+/// deleted, each change being confirmed first.
+///
+/// Variables hold secrets: they are neither cached nor logged.
+#[derive(Default)]
+pub struct CiVariablesComponent {
+    config: Config,
+    client: GitlabClient,
+    // Project for which `variables` was loaded
+    loaded_project: Option<String>,
+    variables: Option<Vec<VariableRow>>,
+    error: Option<Error>,
+    request: Option<PendingRequest<Vec<VariableRow>>>,
+    last_request_at: Option<Instant>,
+    // Variables whose value is shown
+    revealed: HashSet<(Option<String>, String, String)>,
+    form: Option<VariableForm>,
+    confirmation: Option<Change>,
+    // Change sent to GitLab, resolving to a message telling what was done
+    change_request: Option<PendingRequest<String>>,
+    message: Option<std::result::Result<String, String>>,
+    table_state: TableState,
+    // Area computed while drawing, used for mouse hit-testing
+    area: Rect,
+}
+
+impl CiVariablesComponent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Refresh the variables while they are shown, forgetting everything about the previous
+    /// project whenever the active project changes.
+    fn refresh(&mut self, state: &mut State) {
+        if state.view != View::Variables {
+            return;
+        }
+        if self.loaded_project != state.active_gitlab_project {
+            self.loaded_project = state.active_gitlab_project.clone();
+            self.request = None;
+            self.last_request_at = None;
+            self.error = None;
+            self.message = None;
+            self.variables = None;
+            self.revealed.clear();
+            self.confirmation = None;
+            self.close_form(state);
+        }
+        let Some(gitlab_project) = self.loaded_project.clone() else {
+            return;
+        };
+
+        if let Some(result) = self.request.as_mut().and_then(PendingRequest::poll) {
+            self.request = None;
+            match result {
+                Ok(variables) => {
+                    state.offline = false;
+                    let len = variables.len();
+                    let selected = self.table_state.selected().unwrap_or(0);
+                    self.table_state
+                        .select((len > 0).then(|| selected.min(len - 1)));
+                    self.variables = Some(variables);
+                    self.error = None;
+                }
+                Err(error) => {
+                    state.offline = is_unreachable(&error);
+                    self.error = Some(error);
+                }
+            }
+        }
+        if let Some(result) = self.change_request.as_mut().and_then(PendingRequest::poll) {
+            self.change_request = None;
+            self.message = Some(result.map_err(|error| error.to_string()));
+            // Show the outcome of the change right away
+            self.request = None;
+            self.last_request_at = None;
+        }
+
+        let refresh_interval = Duration::from_secs(self.config.core.refresh_interval);
+        let refresh_due = self
+            .last_request_at
+            .is_none_or(|requested_at| requested_at.elapsed() >= refresh_interval);
+        if self.request.is_none() && refresh_due {
+            let client = self.client.clone();
+            self.last_request_at = Some(Instant::now());
+            self.request = Some(PendingRequest::spawn(async move {
+                fetch_variables(client, gitlab_project).await
+            }));
+        }
+    }
+
+    fn selected_row(&self) -> Option<&VariableRow> {
+        self.variables.as_ref()?.get(self.table_state.selected()?)
+    }
+
+    /// Selected variable of the project itself, as the ones of its groups are read-only.
+    fn selected_project_variable(&mut self) -> Option<CiVariable> {
+        let row = self.selected_row()?;
+        if let Some(group) = &row.group {
+            self.message = Some(Err(format!("Variables of {} are read-only", group)));
+            return None;
+        }
+        Some(row.variable.clone())
+    }
+
+    fn toggle_reveal(&mut self) {
+        let Some(id) = self.selected_row().map(VariableRow::id) else {
+            return;
+        };
+        if !self.revealed.remove(&id) {
+            self.revealed.insert(id);
+        }
+    }
+
+    fn open_form(&mut self, existing: Option<CiVariable>, state: &mut State) {
+        let revealed = existing.is_some()
+            && self
+                .selected_row()
+                .is_some_and(|row| self.revealed.contains(&row.id()));
+        self.form = Some(VariableForm::new(existing, revealed));
+        self.message = None;
+        state.popup = Some(ComponentId::CiVariables);
+        state.input_mode = InputMode::Insert;
+    }
+
+    fn close_form(&mut self, state: &mut State) {
+        if self.form.take().is_some() {
+            state.popup = None;
+            state.input_mode = InputMode::Normal;
+        }
+    }
+
+    /// Ask for a confirmation of the variable typed in the form.
+    fn submit_form(&mut self, state: &mut State) {
+        let Some(form) = &self.form else {
+            return;
+        };
+        let variable = match form.variable() {
+            Ok(variable) => variable,
+            Err(message) => {
+                self.message = Some(Err(message));
+                return;
+            }
+        };
+        if variable.key.is_empty() || variable.environment_scope.is_empty() {
+            self.message = Some(Err(
+                "The key and the environments of the variable are required".to_string(),
+            ));
+            return;
+        }
+        self.message = None;
+        self.confirmation = Some(Change::Save {
+            variable,
+            exists: form.existing.is_some(),
+        });
+        state.input_mode = InputMode::Normal;
+    }
+
+    fn confirm(&mut self, state: &mut State) {
+        let (Some(gitlab_project), Some(change)) =
+            (self.loaded_project.clone(), self.confirmation.take())
+        else {
+            return;
+        };
+        if state.offline {
+            // Back to the form, so that nothing typed is lost
+            self.cancel(state);
+            self.message = Some(Err(OFFLINE_MESSAGE.to_string()));
+            return;
+        }
+        if self.change_request.is_some() {
+            self.message = Some(Err("Another change is in progress".to_string()));
+            return;
+        }
+        self.close_form(state);
+        let client = self.client.clone();
+        self.message = None;
+        self.change_request = Some(PendingRequest::spawn(async move {
+            match change {
+                Change::Save { variable, exists } => {
+                    client
+                        .save_project_variable(&gitlab_project, &variable, exists)
+                        .await?;
+                    Ok(format!("Variable {} saved", variable.key))
+                }
+                Change::Delete {
+                    key,
+                    environment_scope,
+                } => {
+                    client
+                        .delete_project_variable(&gitlab_project, &key, &environment_scope)
+                        .await?;
+                    Ok(format!("Variable {} deleted", key))
+                }
+            }
+        }));
+    }
+
+    /// Drop the change waiting for a confirmation, going back to the form it came from.
+    fn cancel(&mut self, state: &mut State) {
+        self.confirmation = None;
+        if self.form.is_some() {
+            state.input_mode = InputMode::Insert;
+        }
+    }
+
+    fn next(&mut self) {
+        let len = self.variables.as_ref().map_or(0, Vec::len);
+        if let Some(selected) = self.table_state.selected() {
+            if selected + 1 < len {
+                self.table_state.select(Some(selected + 1));
+            }
+        }
+    }
+
+    fn previous(&mut self) {
+        if let Some(selected) = self.table_state.selected() {
+            self.table_state.select(Some(selected.saturating_sub(1)));
+        }
+    }
+
+    /// Pending confirmation, or the outcome of the last change.
+    fn message_line(&self) -> Option<Line<'static>> {
+        let theme = &self.config.ui.theme;
+        let warning = theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD);
+        if let Some(change) = &self.confirmation {
+            return Some(Line::styled(change.prompt(), warning));
+        }
+        match &self.message {
+            Some(Ok(message)) => Some(Line::from(message.clone())),
+            Some(Err(error)) => Some(Line::styled(
+                error.clone(),
+                theme.fg(theme.palette.error).add_modifier(Modifier::BOLD),
+            )),
+            None if self.change_request.is_some() => Some(Line::styled("Saving...", warning)),
+            None => None,
+        }
+    }
+
+    fn draw_form(&self, frame: &mut Frame, area: Rect) {
+        let Some(form) = &self.form else {
+            return;
+        };
+        let theme = &self.config.ui.theme;
+        let title = match &form.existing {
+            Some(variable) => format!("Update {}", variable.key),
+            None => "New variable".to_string(),
+        };
+        let mut block = Block::bordered()
+            .border_type(BorderType::Thick)
+            .border_style(theme.fg(theme.palette.popup_border))
+            .padding(Padding::uniform(1))
+            .title(title);
+        if let Some(message) = self.message_line() {
+            block = block.title_bottom(message);
+        }
+        let area = popup_area(area, 60, 40);
+        let editing = self.confirmation.is_none();
+        let mut lines = form.fields.lines(editing);
+        if editing {
+            lines.push(Line::default());
+            lines.push(
+                Line::from(
+                    "tab: next field, space: toggle flag, ctrl-r: show value, enter: save, \
+                     esc: cancel",
+                )
+                .italic(),
+            );
+        }
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+}
+
+impl Component for CiVariablesComponent {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.client = GitlabClient::new(config.core.gitlab_url.clone());
+        self.config = config;
+        Ok(())
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent, _state: &mut State) -> Result<Option<Action>> {
+        if let Some(form) = self.form.as_mut().filter(|_| self.confirmation.is_none()) {
+            form.fields.handle_key(key);
+        }
+        Ok(None)
+    }
+
+    fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>> {
+        if action == Action::Tick {
+            self.refresh(state);
+            return Ok(None);
+        }
+        if self.confirmation.is_some() {
+            match action {
+                Action::Enter => self.confirm(state),
+                Action::Close => self.cancel(state),
+                _ => {}
+            }
+            return Ok(None);
+        }
+        if let Some(form) = &mut self.form {
+            match action {
+                Action::Next => form.fields.next_field(),
+                Action::Previous => form.fields.previous_field(),
+                Action::Enter => self.submit_form(state),
+                Action::Close => self.close_form(state),
+                _ => {}
+            }
+            return Ok(None);
+        }
+        match action {
+            Action::Next => self.next(),
+            Action::Previous => self.previous(),
+            Action::RevealValue => self.toggle_reveal(),
+            Action::Enter => {
+                if let Some(variable) = self.selected_project_variable() {
+                    self.open_form(Some(variable), state);
+                }
+            }
+            Action::NewItem => self.open_form(None, state),
+            Action::Delete => {
+                if let Some(variable) = self.selected_project_variable() {
+                    self.confirmation = Some(Change::Delete {
+                        key: variable.key,
+                        environment_scope: variable.environment_scope,
+                    });
+                }
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn handle_mouse_event(
+        &mut self,
+        mouse: MouseEvent,
+        state: &mut State,
+    ) -> Result<Option<Action>> {
+        if state.popup.is_some() || !self.area.contains(Position::new(mouse.column, mouse.row)) {
+            return Ok(None);
+        }
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                state.focused_component = ComponentId::CiVariables;
+                // Rows start below the border, the padding and the header row
+                let first_row = self.area.y + 3;
+                let len = self.variables.as_ref().map_or(0, Vec::len);
+                if mouse.row >= first_row {
+                    let index = (mouse.row - first_row) as usize + self.table_state.offset();
+                    if index < len {
+                        self.table_state.select(Some(index));
+                    }
+                }
+            }
+            MouseEventKind::ScrollDown => self.next(),
+            MouseEventKind::ScrollUp => self.previous(),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect, state: &State) -> Result<()> {
+        let full_area = area;
+        let area = prepare_layout(area, Element::Body(Body::RightColumn), state)
+            .filter(|_| state.view == View::Variables);
+        self.area = area.unwrap_or_default();
+        let Some(area) = area else {
+            return Ok(());
+        };
+        let theme = &self.config.ui.theme;
+        let mut block = get_block(
+            state,
+            ComponentId::CiVariables,
+            theme.color(theme.palette.pipelines_border),
+        )
+        .padding(Padding::uniform(1))
+        .title("CI/CD variables");
+
+        let Some(variables) = &self.variables else {
+            let message = match &self.error {
+                Some(error) => Span::styled(
+                    format!("ERROR: {}", error),
+                    theme.fg(theme.palette.error).add_modifier(Modifier::BOLD),
+                ),
+                None if self.loaded_project.is_none() => Span::raw("Project not selected"),
+                None => Span::styled(
+                    "Loading...",
+                    theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD),
+                ),
+            };
+            let paragraph = Paragraph::new(Line::from(message))
+                .block(block)
+                .alignment(Alignment::Center);
+            frame.render_widget(paragraph, area);
+            return Ok(());
+        };
+
+        if let Some(error) = &self.error {
+            block = block.title(Line::styled(
+                format!(" STALE ({}) ", error),
+                theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD),
+            ));
+        }
+        if let Some(message) = self.message_line().filter(|_| self.form.is_none()) {
+            block = block.title_bottom(message);
+        }
+
+        let header_row = Row::new([
+            "Key",
+            "Value",
+            "Environments",
+            "Protected",
+            "Masked",
+            "Type",
+            "Defined in",
+        ])
+        .bold();
+        let flag = |value: bool| if value { "yes" } else { "no" };
+        let rows = variables.iter().map(|row| {
+            let variable = &row.variable;
+            let value = match &variable.value {
+                Some(value) if self.revealed.contains(&row.id()) => value.clone(),
+                Some(_) => "********".to_string(),
+                None => "(hidden)".to_string(),
+            };
+            Row::new([
+                variable.key.clone(),
+                value,
+                variable.environment_scope.clone(),
+                flag(variable.protected).to_string(),
+                flag(variable.masked).to_string(),
+                variable.variable_type.clone(),
+                row.group.clone().unwrap_or_else(|| "project".to_string()),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Min(20),
+                Constraint::Fill(1),
+                Constraint::Length(14),
+                Constraint::Length(9),
+                Constraint::Length(6),
+                Constraint::Length(7),
+                Constraint::Min(12),
+            ],
+        )
+        .column_spacing(2)
+        .header(header_row)
+        .row_highlight_style(
+            theme.highlight(theme.palette.highlight_fg, theme.palette.highlight_bg),
+        )
+        .block(block);
+        frame.render_stateful_widget(table, area, &mut self.table_state);
+
+        self.draw_form(frame, full_area);
+        Ok(())
+    }
+}
+
+/// Fetch the variables of a project, followed by the ones of its groups from the closest one.
+/// Groups whose variables the user may not see are skipped.
+async fn fetch_variables(client: GitlabClient, gitlab_project: String) -> Result<Vec<VariableRow>> {
+    let mut variables: Vec<VariableRow> = client
+        .fetch_project_variables(&gitlab_project)
+        .await?
+        .into_iter()
+        .map(|variable| VariableRow {
+            variable,
+            group: None,
+        })
+        .collect();
+    let groups = gitlab_project
+        .match_indices('/')
+        .map(|(index, _)| &gitlab_project[..index])
+        .rev();
+    for group in groups {
+        match client.fetch_group_variables(group).await {
+            Ok(group_variables) => {
+                variables.extend(group_variables.into_iter().map(|variable| VariableRow {
+                    variable,
+                    group: Some(group.to_string()),
+                }))
+            }
+            Err(error) if is_unreachable(&error) => return Err(error),
+            Err(error) => tracing::debug!("Skipping the variables of {}: {}", group, error),
+        }
+    }
+    Ok(variables)
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent};
+    use serde_json::json;
+
+    use super::*;
+
+    fn masked_variable(value: Option<&str>) -> CiVariable {
+        serde_json::from_value(json!({
+            "key": "TOKEN",
+            "value": value,
+            "masked": true,
+            "environment_scope": "production",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn hidden_value_left_empty_is_sent_unchanged() {
+        let form = VariableForm::new(Some(masked_variable(Some("s3cr3t-value"))), false);
+        let variable = form.variable().unwrap();
+        assert_eq!(variable.value.as_deref(), Some("s3cr3t-value"));
+        assert!(variable.masked);
+        assert_eq!(variable.environment_scope, "production");
+    }
+
+    #[test]
+    fn hidden_value_can_be_replaced() {
+        let mut form = VariableForm::new(Some(masked_variable(Some("s3cr3t-value"))), false);
+        for char in "new".chars() {
+            form.fields.handle_key(KeyEvent::from(KeyCode::Char(char)));
+        }
+        assert_eq!(form.variable().unwrap().value.as_deref(), Some("new"));
+    }
+
+    #[test]
+    fn value_hidden_by_gitlab_must_be_typed_again() {
+        let form = VariableForm::new(Some(masked_variable(None)), false);
+        assert!(form.variable().is_err());
+    }
+
+    #[test]
+    fn revealed_value_cleared_is_saved_empty() {
+        let mut form = VariableForm::new(Some(masked_variable(Some("abc"))), true);
+        for _ in 0..3 {
+            form.fields.handle_key(KeyEvent::from(KeyCode::Backspace));
+        }
+        assert_eq!(form.variable().unwrap().value.as_deref(), Some(""));
+    }
+}
@@ -0,0 +1,546 @@
+use std::time::{Duration, Instant};
+
+use color_eyre::{eyre::Error, Result};
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use futures::{stream, StreamExt};
+use ratatui::{prelude::*, widgets::*};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    utils::{format_timestamp, get_block, prepare_layout, Body, Element, PendingRequest},
+    Component, ComponentId,
+};
+use crate::{
+    action::Action,
+    cache::{Cache, CacheKind},
+    config::{Config, PipelinesConfig},
+    gitlab::{is_unreachable, GitlabClient, GitlabDeployment, GitlabEnvironment, PipelineStatus},
+    state::{PipelineRef, State, View},
+    theme::Theme,
+};
+
+/// Number of environments whose deployments are fetched at the same time.
+const CONCURRENT_REQUESTS: usize = 8;
+
+/// An environment together with its latest deployment and the one currently running in it.
+#[derive(Clone, Serialize, Deserialize)]
+struct EnvironmentStatus {
+    environment: GitlabEnvironment,
+    latest: Option<GitlabDeployment>,
+    // The last successful deployment
+    current: Option<GitlabDeployment>,
+}
+
+impl EnvironmentStatus {
+    /// Deployment currently running in the environment, i.e. the last successful one.
+    fn current(&self) -> Option<&GitlabDeployment> {
+        self.current.as_ref()
+    }
+
+    fn latest(&self) -> Option<&GitlabDeployment> {
+        self.latest.as_ref()
+    }
+}
+
+/// Deployment history of an environment.
+struct History {
+    environment: String,
+    deployments: Vec<GitlabDeployment>,
+}
+
+/// Environments of the selected project with what is deployed in each of them, and the
+/// deployment history of an environment.
+#[derive(Default)]
+pub struct EnvironmentsComponent {
+    config: Config,
+    client: GitlabClient,
+    cache: Cache,
+    // Project for which `environments` was loaded
+    loaded_project: Option<String>,
+    environments: Option<Vec<EnvironmentStatus>>,
+    error: Option<Error>,
+    request: Option<PendingRequest<Vec<EnvironmentStatus>>>,
+    last_request_at: Option<Instant>,
+    history: Option<History>,
+    history_request: Option<PendingRequest<History>>,
+    table_state: TableState,
+    history_state: TableState,
+    // Area computed while drawing, used for mouse hit-testing
+    area: Rect,
+}
+
+impl EnvironmentsComponent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Refresh the environments while they are shown, starting from the cached ones whenever the
+    /// active project changes.
+    fn refresh(&mut self, state: &mut State) {
+        if state.view != View::Environments {
+            return;
+        }
+        if self.loaded_project != state.active_gitlab_project {
+            self.loaded_project = state.active_gitlab_project.clone();
+            self.request = None;
+            self.last_request_at = None;
+            self.error = None;
+            self.close_history();
+            let environments = self.loaded_project.as_ref().and_then(|gitlab_project| {
+                self.cache
+                    .load::<Vec<EnvironmentStatus>>(gitlab_project, CacheKind::Environments)
+                    .map(|entry| entry.data)
+            });
+            self.set_environments(environments);
+        }
+        let Some(gitlab_project) = self.loaded_project.clone() else {
+            return;
+        };
+
+        if let Some(result) = self.request.as_mut().and_then(PendingRequest::poll) {
+            self.request = None;
+            match result {
+                Ok(environments) => {
+                    state.offline = false;
+                    if let Err(error) =
+                        self.cache
+                            .store(&gitlab_project, CacheKind::Environments, &environments)
+                    {
+                        tracing::warn!("Failed to cache environments: {}", error);
+                    }
+                    self.set_environments(Some(environments));
+                    self.error = None;
+                }
+                Err(error) => {
+                    state.offline = is_unreachable(&error);
+                    self.error = Some(error);
+                }
+            }
+        }
+        if let Some(result) = self.history_request.as_mut().and_then(PendingRequest::poll) {
+            self.history_request = None;
+            match result {
+                Ok(history) => {
+                    self.history_state
+                        .select((!history.deployments.is_empty()).then_some(0));
+                    self.history = Some(history);
+                }
+                Err(error) => self.error = Some(error),
+            }
+        }
+
+        let refresh_interval = Duration::from_secs(self.config.core.refresh_interval);
+        let refresh_due = self
+            .last_request_at
+            .is_none_or(|requested_at| requested_at.elapsed() >= refresh_interval);
+        if self.request.is_none() && refresh_due {
+            let client = self.client.clone();
+            let include_stopped = self.config.environments.include_stopped;
+            self.last_request_at = Some(Instant::now());
+            self.request = Some(PendingRequest::spawn(async move {
+                fetch_environments(client, gitlab_project, include_stopped).await
+            }));
+        }
+    }
+
+    fn set_environments(&mut self, environments: Option<Vec<EnvironmentStatus>>) {
+        let len = environments.as_ref().map_or(0, Vec::len);
+        let selected = self.table_state.selected().unwrap_or(0);
+        self.table_state
+            .select((len > 0).then(|| selected.min(len - 1)));
+        self.environments = environments;
+    }
+
+    fn selected_environment(&self) -> Option<&EnvironmentStatus> {
+        self.environments
+            .as_ref()?
+            .get(self.table_state.selected()?)
+    }
+
+    /// Deployment whose pipeline is shown by `ShowGraph`: the selected one of the history, or
+    /// the current one of the selected environment.
+    fn selected_deployment(&self) -> Option<&GitlabDeployment> {
+        match &self.history {
+            Some(history) => history.deployments.get(self.history_state.selected()?),
+            None => {
+                let environment = self.selected_environment()?;
+                environment.current().or(environment.latest())
+            }
+        }
+    }
+
+    fn open_history(&mut self) {
+        let (Some(gitlab_project), Some(environment)) =
+            (self.loaded_project.clone(), self.selected_environment())
+        else {
+            return;
+        };
+        let environment = environment.environment.name.clone();
+        let client = self.client.clone();
+        let count = self.config.environments.history;
+        self.history_request = Some(PendingRequest::spawn(async move {
+            let deployments = client
+                .fetch_deployments(&gitlab_project, &environment, count)
+                .await?;
+            Ok(History {
+                environment,
+                deployments,
+            })
+        }));
+    }
+
+    fn close_history(&mut self) {
+        self.history = None;
+        self.history_request = None;
+        self.history_state = TableState::default();
+    }
+
+    fn show_graph(&mut self, state: &mut State) {
+        let Some(gitlab_project) = self.loaded_project.clone() else {
+            return;
+        };
+        let pipeline = self
+            .selected_deployment()
+            .and_then(|deployment| deployment.deployable.as_ref()?.pipeline.as_ref());
+        if let Some(pipeline) = pipeline {
+            state.show_graph(PipelineRef {
+                gitlab_project,
+                id: pipeline.id,
+            });
+        }
+    }
+
+    fn table_state(&mut self) -> (&mut TableState, usize) {
+        match &self.history {
+            Some(history) => (&mut self.history_state, history.deployments.len()),
+            None => (
+                &mut self.table_state,
+                self.environments.as_ref().map_or(0, Vec::len),
+            ),
+        }
+    }
+
+    fn next(&mut self) {
+        let (table_state, len) = self.table_state();
+        if let Some(selected) = table_state.selected() {
+            if selected + 1 < len {
+                table_state.select(Some(selected + 1));
+            }
+        }
+    }
+
+    fn previous(&mut self) {
+        let (table_state, _) = self.table_state();
+        if let Some(selected) = table_state.selected() {
+            table_state.select(Some(selected.saturating_sub(1)));
+        }
+    }
+
+    fn draw_environments(&mut self, frame: &mut Frame, area: Rect, block: Block) {
+        let theme = &self.config.ui.theme;
+        let timestamps = &self.config.ui.pipelines;
+        let environments = self.environments.as_deref().unwrap_or_default();
+        let header_row = Row::new([
+            "Environment",
+            "Tier",
+            "Status",
+            "Ref",
+            "SHA",
+            "Deployed by",
+            "Deployed",
+            "Pipeline",
+        ])
+        .bold();
+        let rows = environments.iter().map(|environment| {
+            let current = environment.current();
+            let mut cells = vec![
+                Line::from(environment.environment.name.clone()),
+                Line::from(environment.environment.tier.clone().unwrap_or_default()),
+                // The latest deployment tells whether another one is on its way, or failed
+                environment
+                    .latest()
+                    .map(|latest| status_line(&latest.status, theme))
+                    .unwrap_or_else(|| Line::from("not deployed")),
+            ];
+            cells.extend(
+                current
+                    .map(|deployment| deployment_cells(deployment, timestamps))
+                    .unwrap_or_default(),
+            );
+            let row = Row::new(cells);
+            if environment.environment.state == "available" {
+                row
+            } else {
+                row.add_modifier(Modifier::DIM)
+            }
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Min(20),
+                Constraint::Length(12),
+                Constraint::Length(14),
+                Constraint::Min(16),
+                Constraint::Length(8),
+                Constraint::Length(16),
+                Constraint::Length(20),
+                Constraint::Length(10),
+            ],
+        )
+        .column_spacing(2)
+        .header(header_row)
+        .row_highlight_style(
+            theme.highlight(theme.palette.highlight_fg, theme.palette.highlight_bg),
+        )
+        .block(block);
+        frame.render_stateful_widget(table, area, &mut self.table_state);
+    }
+
+    fn draw_history(&mut self, frame: &mut Frame, area: Rect, block: Block) {
+        let theme = &self.config.ui.theme;
+        let timestamps = &self.config.ui.pipelines;
+        let Some(history) = &self.history else {
+            return;
+        };
+        let header_row = Row::new([
+            "Deployment",
+            "Status",
+            "Ref",
+            "SHA",
+            "Deployed by",
+            "Deployed",
+            "Pipeline",
+        ])
+        .bold();
+        let rows = history.deployments.iter().map(|deployment| {
+            let mut cells = vec![
+                Line::from(format!("#{}", deployment.iid)),
+                status_line(&deployment.status, theme),
+            ];
+            cells.extend(deployment_cells(deployment, timestamps));
+            Row::new(cells)
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(10),
+                Constraint::Length(14),
+                Constraint::Min(16),
+                Constraint::Length(8),
+                Constraint::Length(16),
+                Constraint::Length(20),
+                Constraint::Length(10),
+            ],
+        )
+        .column_spacing(2)
+        .header(header_row)
+        .row_highlight_style(
+            theme.highlight(theme.palette.highlight_fg, theme.palette.highlight_bg),
+        )
+        .block(block.title(format!("Deployments to {}", history.environment)));
+        frame.render_stateful_widget(table, area, &mut self.history_state);
+    }
+}
+
+impl Component for EnvironmentsComponent {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.client = GitlabClient::new(config.core.gitlab_url.clone());
+        self.cache = Cache::new(config.cache.enabled);
+        self.config = config;
+        Ok(())
+    }
+
+    fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>> {
+        match action {
+            Action::Tick => self.refresh(state),
+            Action::Next => self.next(),
+            Action::Previous => self.previous(),
+            Action::Enter if self.history.is_none() => self.open_history(),
+            Action::ShowGraph => self.show_graph(state),
+            Action::Close => self.close_history(),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn handle_mouse_event(
+        &mut self,
+        mouse: MouseEvent,
+        state: &mut State,
+    ) -> Result<Option<Action>> {
+        if state.popup.is_some() || !self.area.contains(Position::new(mouse.column, mouse.row)) {
+            return Ok(None);
+        }
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                state.focused_component = ComponentId::Environments;
+                // Rows start below the border, the padding and the header row
+                let first_row = self.area.y + 3;
+                if mouse.row >= first_row {
+                    let (table_state, len) = self.table_state();
+                    let index = (mouse.row - first_row) as usize + table_state.offset();
+                    if index < len {
+                        table_state.select(Some(index));
+                    }
+                }
+            }
+            MouseEventKind::ScrollDown => self.next(),
+            MouseEventKind::ScrollUp => self.previous(),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect, state: &State) -> Result<()> {
+        let area = prepare_layout(area, Element::Body(Body::RightColumn), state)
+            .filter(|_| state.view == View::Environments);
+        self.area = area.unwrap_or_default();
+        let Some(area) = area else {
+            return Ok(());
+        };
+        let theme = &self.config.ui.theme;
+        let mut block = get_block(
+            state,
+            ComponentId::Environments,
+            theme.color(theme.palette.pipelines_border),
+        )
+        .padding(Padding::uniform(1));
+
+        if self.environments.is_none() || self.history_request.is_some() {
+            let message = match &self.error {
+                Some(error) if self.history_request.is_none() => Span::styled(
+                    format!("ERROR: {}", error),
+                    theme.fg(theme.palette.error).add_modifier(Modifier::BOLD),
+                ),
+                _ if self.loaded_project.is_none() => Span::raw("Project not selected"),
+                _ => Span::styled(
+                    "Loading...",
+                    theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD),
+                ),
+            };
+            let paragraph = Paragraph::new(Line::from(message))
+                .block(block.title("Environments"))
+                .alignment(Alignment::Center);
+            frame.render_widget(paragraph, area);
+            return Ok(());
+        }
+
+        if let Some(error) = &self.error {
+            block = block.title(Line::styled(
+                format!(" STALE ({}) ", error),
+                theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD),
+            ));
+        }
+        // Link to the pipeline which `ShowGraph` opens
+        if let Some(pipeline) = self
+            .selected_deployment()
+            .and_then(|deployment| deployment.deployable.as_ref()?.pipeline.as_ref())
+        {
+            block = block.title_bottom(Line::from(format!("Pipeline: {}", pipeline.web_url)));
+        }
+        if self.history.is_some() {
+            self.draw_history(frame, area, block);
+        } else {
+            self.draw_environments(frame, area, block.title("Environments"));
+        }
+        Ok(())
+    }
+}
+
+fn status_line(status: &PipelineStatus, theme: &Theme) -> Line<'static> {
+    Line::styled(
+        format!("{} {}", theme.status_icon(status), status),
+        theme.status_style(status),
+    )
+}
+
+/// Ref, SHA, deployer, time and pipeline of a deployment.
+fn deployment_cells(deployment: &GitlabDeployment, config: &PipelinesConfig) -> Vec<Line<'static>> {
+    vec![
+        Line::from(deployment.git_ref.clone()),
+        Line::from(deployment.sha.chars().take(8).collect::<String>()),
+        Line::from(
+            deployment
+                .user
+                .as_ref()
+                .map(|user| user.username.clone())
+                .unwrap_or_default(),
+        ),
+        Line::from(format_timestamp(
+            deployment.finished_at.unwrap_or(deployment.created_at),
+            config,
+        )),
+        Line::from(
+            deployment
+                .deployable
+                .as_ref()
+                .and_then(|deployable| deployable.pipeline.as_ref())
+                .map(|pipeline| format!("#{}", pipeline.id))
+                .unwrap_or_default(),
+        ),
+    ]
+}
+
+/// Fetch the environments of a project with their latest and current deployments, the most
+/// important tiers first. The last successful deployment is only looked up when the latest one
+/// did not succeed.
+async fn fetch_environments(
+    client: GitlabClient,
+    gitlab_project: String,
+    include_stopped: bool,
+) -> Result<Vec<EnvironmentStatus>> {
+    let environments = client
+        .fetch_environments(&gitlab_project, include_stopped)
+        .await?;
+    let mut environments: Vec<EnvironmentStatus> = stream::iter(environments)
+        .map(|environment| {
+            let client = &client;
+            let gitlab_project = &gitlab_project;
+            async move {
+                let latest = client
+                    .fetch_last_deployment(gitlab_project, &environment.name, None)
+                    .await?;
+                let current = match &latest {
+                    Some(latest) if latest.status == PipelineStatus::Success => {
+                        Some(latest.clone())
+                    }
+                    Some(_) => {
+                        client
+                            .fetch_last_deployment(
+                                gitlab_project,
+                                &environment.name,
+                                Some("success"),
+                            )
+                            .await?
+                    }
+                    None => None,
+                };
+                Ok(EnvironmentStatus {
+                    environment,
+                    latest,
+                    current,
+                })
+            }
+        })
+        .buffered(CONCURRENT_REQUESTS)
+        .collect::<Vec<Result<_>>>()
+        .await
+        .into_iter()
+        .collect::<Result<_>>()?;
+    environments.sort_by(|a, b| {
+        let rank =
+            |environment: &EnvironmentStatus| tier_rank(environment.environment.tier.as_deref());
+        rank(a)
+            .cmp(&rank(b))
+            .then_with(|| a.environment.name.cmp(&b.environment.name))
+    });
+    Ok(environments)
+}
+
+/// Tiers of environments from the most important one, see
+/// https://docs.gitlab.com/ee/ci/environments/#deployment-tier-of-environments.
+fn tier_rank(tier: Option<&str>) -> usize {
+    const TIERS: [&str; 4] = ["production", "staging", "testing", "development"];
+    tier.and_then(|tier| TIERS.iter().position(|known| *known == tier))
+        .unwrap_or(TIERS.len())
+}
@@ -0,0 +1,369 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use color_eyre::{eyre::Error, Result};
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use futures::{stream, StreamExt};
+use ratatui::{prelude::*, widgets::*};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    utils::{get_block, prepare_layout, Body, Element, PendingRequest},
+    Component, ComponentId,
+};
+use crate::{
+    action::Action,
+    cache::{Cache, CacheKind},
+    config::Config,
+    flaky::{find_flaky_jobs, FlakyJob},
+    gitlab::{is_unreachable, GitlabClient, GitlabJob, GitlabPipeline},
+    state::{State, View},
+};
+
+/// Number of job lists fetched at the same time.
+const CONCURRENT_REQUESTS: usize = 8;
+
+/// Recent pipelines on a ref together with their jobs, including the retried ones.
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct JobHistory {
+    git_ref: String,
+    pipelines: Vec<GitlabPipeline>,
+    jobs: HashMap<u32, Vec<GitlabJob>>,
+}
+
+/// Report of the jobs of the selected project which fail most often, and of the ones which pass
+/// when retried on the same commit.
+#[derive(Default)]
+pub struct FlakyJobsComponent {
+    config: Config,
+    client: GitlabClient,
+    cache: Cache,
+    // Project for which `history` was loaded
+    loaded_project: Option<String>,
+    history: Option<JobHistory>,
+    report: Vec<FlakyJob>,
+    error: Option<Error>,
+    request: Option<PendingRequest<JobHistory>>,
+    last_request_at: Option<Instant>,
+    table_state: TableState,
+    // Area computed while drawing, used for mouse hit-testing
+    area: Rect,
+}
+
+impl FlakyJobsComponent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Refresh the job history while the report is shown, starting from the cached one whenever
+    /// the active project changes.
+    fn refresh(&mut self, state: &mut State) {
+        if state.view != View::FlakyJobs {
+            return;
+        }
+        if self.loaded_project != state.active_gitlab_project {
+            self.loaded_project = state.active_gitlab_project.clone();
+            self.request = None;
+            self.last_request_at = None;
+            self.error = None;
+            let history = self.loaded_project.as_ref().and_then(|gitlab_project| {
+                self.cache
+                    .load::<JobHistory>(gitlab_project, CacheKind::Jobs)
+                    .map(|entry| entry.data)
+            });
+            self.set_history(history);
+        }
+        let Some(gitlab_project) = self.loaded_project.clone() else {
+            return;
+        };
+
+        if let Some(result) = self.request.as_mut().and_then(PendingRequest::poll) {
+            self.request = None;
+            match result {
+                Ok(history) => {
+                    state.offline = false;
+                    if let Err(error) = self.cache.store(&gitlab_project, CacheKind::Jobs, &history)
+                    {
+                        tracing::warn!("Failed to cache jobs: {}", error);
+                    }
+                    self.set_history(Some(history));
+                    self.error = None;
+                }
+                Err(error) => {
+                    state.offline = is_unreachable(&error);
+                    self.error = Some(error);
+                }
+            }
+        }
+
+        let refresh_interval = Duration::from_secs(self.config.core.refresh_interval);
+        let refresh_due = self
+            .last_request_at
+            .is_none_or(|requested_at| requested_at.elapsed() >= refresh_interval);
+        if self.request.is_none() && refresh_due {
+            let client = self.client.clone();
+            let git_ref = self.config.flaky_jobs.git_ref.clone();
+            let count = self.config.flaky_jobs.pipelines;
+            // Jobs of finished pipelines do not change, so they are only fetched once
+            let known = self.history.clone().unwrap_or_default();
+            self.last_request_at = Some(Instant::now());
+            self.request = Some(PendingRequest::spawn(async move {
+                fetch_job_history(client, gitlab_project, git_ref, count, known).await
+            }));
+        }
+    }
+
+    /// Replace the job history, recomputing the report from it.
+    fn set_history(&mut self, history: Option<JobHistory>) {
+        self.report = history
+            .as_ref()
+            .map(|history| find_flaky_jobs(&history.pipelines, &history.jobs))
+            .unwrap_or_default();
+        self.history = history;
+        let selected = self.table_state.selected().unwrap_or(0);
+        self.table_state
+            .select((!self.report.is_empty()).then(|| selected.min(self.report.len() - 1)));
+    }
+
+    /// Open the log of the last failure of the selected job.
+    fn open_log(&self) -> Option<Action> {
+        let gitlab_project = self.loaded_project.clone()?;
+        let last_failure = self
+            .report
+            .get(self.table_state.selected()?)?
+            .last_failure
+            .as_ref()?;
+        Some(Action::OpenJobLog(gitlab_project, last_failure.id))
+    }
+
+    fn next(&mut self) {
+        if let Some(selected) = self.table_state.selected() {
+            if selected + 1 < self.report.len() {
+                self.table_state.select(Some(selected + 1));
+            }
+        }
+    }
+
+    fn previous(&mut self) {
+        if let Some(selected) = self.table_state.selected() {
+            self.table_state.select(Some(selected.saturating_sub(1)));
+        }
+    }
+}
+
+impl Component for FlakyJobsComponent {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.client = GitlabClient::new(config.core.gitlab_url.clone());
+        self.cache = Cache::new(config.cache.enabled);
+        self.config = config;
+        Ok(())
+    }
+
+    fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>> {
+        match action {
+            Action::Tick => self.refresh(state),
+            Action::Next => self.next(),
+            Action::Previous => self.previous(),
+            Action::Enter => return Ok(self.open_log()),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn handle_mouse_event(
+        &mut self,
+        mouse: MouseEvent,
+        state: &mut State,
+    ) -> Result<Option<Action>> {
+        if state.popup.is_some() || !self.area.contains(Position::new(mouse.column, mouse.row)) {
+            return Ok(None);
+        }
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                state.focused_component = ComponentId::FlakyJobs;
+                // Rows start below the border, the padding and the header row
+                let first_row = self.area.y + 3;
+                if mouse.row >= first_row {
+                    let index = (mouse.row - first_row) as usize + self.table_state.offset();
+                    if index < self.report.len() {
+                        self.table_state.select(Some(index));
+                    }
+                }
+            }
+            MouseEventKind::ScrollDown => self.next(),
+            MouseEventKind::ScrollUp => self.previous(),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect, state: &State) -> Result<()> {
+        let area = prepare_layout(area, Element::Body(Body::RightColumn), state)
+            .filter(|_| state.view == View::FlakyJobs);
+        self.area = area.unwrap_or_default();
+        let Some(area) = area else {
+            return Ok(());
+        };
+        let theme = &self.config.ui.theme;
+        let mut block = get_block(
+            state,
+            ComponentId::FlakyJobs,
+            theme.color(theme.palette.pipelines_border),
+        )
+        .padding(Padding::uniform(1));
+
+        let Some(history) = &self.history else {
+            let message = match &self.error {
+                Some(error) => Span::styled(
+                    format!("ERROR: {}", error),
+                    theme.fg(theme.palette.error).add_modifier(Modifier::BOLD),
+                ),
+                None if self.loaded_project.is_none() => Span::raw("Project not selected"),
+                None => Span::styled(
+                    "Loading...",
+                    theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD),
+                ),
+            };
+            let paragraph = Paragraph::new(Line::from(message))
+                .block(block.title("Flaky jobs"))
+                .alignment(Alignment::Center);
+            frame.render_widget(paragraph, area);
+            return Ok(());
+        };
+
+        block = block.title(format!(
+            "Flaky jobs in the last {} pipelines on {}",
+            history.pipelines.len(),
+            history.git_ref
+        ));
+        if let Some(error) = &self.error {
+            block = block.title(Line::styled(
+                format!(" STALE ({}) ", error),
+                theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD),
+            ));
+        }
+        // Link to the log of the last failure of the selected job
+        if let Some(last_failure) = self
+            .table_state
+            .selected()
+            .and_then(|selected| self.report.get(selected))
+            .and_then(|flaky_job| flaky_job.last_failure.as_ref())
+        {
+            block = block.title_bottom(Line::from(format!(
+                "Last failure: {}",
+                last_failure.web_url
+            )));
+        }
+
+        let header_row = Row::new([
+            "Job",
+            "Stage",
+            "Runs",
+            "Failures",
+            "Failure rate",
+            "Passed on retry",
+            "Last failure",
+        ])
+        .bold();
+        let rows = self.report.iter().map(|flaky_job| {
+            Row::new([
+                flaky_job.name.clone(),
+                flaky_job.stage.clone(),
+                flaky_job.runs.to_string(),
+                flaky_job.failures.to_string(),
+                format!("{:.1}%", flaky_job.failure_rate()),
+                flaky_job.flaky_commits.to_string(),
+                flaky_job
+                    .last_failure
+                    .as_ref()
+                    .map(|job| format!("#{}", job.id))
+                    .unwrap_or_default(),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Min(30),
+                Constraint::Length(16),
+                Constraint::Length(6),
+                Constraint::Length(9),
+                Constraint::Length(13),
+                Constraint::Length(16),
+                Constraint::Length(14),
+            ],
+        )
+        .column_spacing(2)
+        .header(header_row)
+        .row_highlight_style(
+            theme.highlight(theme.palette.highlight_fg, theme.palette.highlight_bg),
+        )
+        .block(block);
+        frame.render_stateful_widget(table, area, &mut self.table_state);
+        Ok(())
+    }
+}
+
+async fn fetch_job_history(
+    client: GitlabClient,
+    gitlab_project: String,
+    git_ref: Option<String>,
+    count: usize,
+    mut known: JobHistory,
+) -> Result<JobHistory> {
+    let git_ref = match git_ref {
+        Some(git_ref) => git_ref,
+        None => client.fetch_default_branch(&gitlab_project).await?,
+    };
+    let pipelines = client
+        .fetch_recent_pipelines(&gitlab_project, Some(&git_ref), count)
+        .await?;
+
+    let known_pipelines: HashMap<u32, &GitlabPipeline> = known
+        .pipelines
+        .iter()
+        .map(|pipeline| (pipeline.id, pipeline))
+        .collect();
+    // Jobs change until their pipeline is finished, which also updates the pipeline
+    let outdated: Vec<u32> = pipelines
+        .iter()
+        .filter(|pipeline| {
+            !known.jobs.contains_key(&pipeline.id)
+                || known_pipelines
+                    .get(&pipeline.id)
+                    .is_none_or(|known| known.updated_at != pipeline.updated_at)
+        })
+        .map(|pipeline| pipeline.id)
+        .collect();
+    let fetched: Vec<(u32, Result<Vec<GitlabJob>>)> = stream::iter(outdated)
+        .map(|id| {
+            let client = &client;
+            let gitlab_project = &gitlab_project;
+            async move {
+                (
+                    id,
+                    client.fetch_pipeline_jobs(gitlab_project, id, true).await,
+                )
+            }
+        })
+        .buffer_unordered(CONCURRENT_REQUESTS)
+        .collect()
+        .await;
+    // A pipeline whose jobs cannot be fetched is left out until the next refresh
+    for (id, jobs) in fetched {
+        match jobs {
+            Ok(jobs) => {
+                known.jobs.insert(id, jobs);
+            }
+            Err(error) => tracing::warn!("Failed to fetch the jobs of pipeline {}: {}", id, error),
+        }
+    }
+
+    // Forget about the jobs of pipelines which are out of the window
+    let mut jobs = known.jobs;
+    jobs.retain(|id, _| pipelines.iter().any(|pipeline| pipeline.id == *id));
+    Ok(JobHistory {
+        git_ref,
+        pipelines,
+        jobs,
+    })
+}
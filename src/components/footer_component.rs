@@ -1,49 +1,103 @@
 use color_eyre::Result;
 use ratatui::{
     prelude::*,
-    widgets::{Block, BorderType, Paragraph},
+    widgets::{LineGauge, Paragraph},
 };
+use strum::{EnumMessage, IntoEnumIterator};
 
-use crate::{action::Action, state::State};
+use crate::{action::Action, config::Config, state::State};
 
 use super::{
-    utils::{get_block, prepare_layout, Element},
-    Component,
+    utils::{format_size, get_block, prepare_layout, Element},
+    Component, ComponentId,
 };
 
 #[derive(Default)]
-pub struct FooterComponent {}
+pub struct FooterComponent {
+    config: Config,
+}
 
 impl FooterComponent {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Documented actions bound in the scopes which currently apply, together with their keys.
+    fn bound_actions(&self, state: &State) -> Vec<(String, Action)> {
+        let mut bound_actions: Vec<(String, Action)> = vec![];
+        for scope in state.active_scopes() {
+            for action in Action::iter().filter(|action| action.get_message().is_some()) {
+                // Actions bound in a more specific scope take precedence
+                if bound_actions.iter().any(|(_, bound)| *bound == action) {
+                    continue;
+                }
+                let keys = self.config.keybindings.keys_for(state.mode, scope, &action);
+                if !keys.is_empty() {
+                    bound_actions.push((keys.join("/"), action));
+                }
+            }
+        }
+        bound_actions
+    }
 }
 
 impl Component for FooterComponent {
-    fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>> {
-        match action {
-            Action::FocusUp => state.focused_component = 2, // change to pipelines viewer
-            _ => {}
-        }
-        Ok(None)
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.config = config;
+        Ok(())
     }
 
     fn draw(&mut self, frame: &mut Frame, area: Rect, state: &State) -> Result<()> {
-        let area = prepare_layout(area, Element::Footer);
-        let footer = Line::from_iter(
-            vec![
-                "Keybindigs: ",
-                "j/k - next/prev item | ",
-                "ENTER - select item | ",
-                "SHIFT+h/j/k/l - change focus | ",
-                "q - quit",
-            ]
-            .into_iter(),
+        let Some(area) = prepare_layout(area, Element::Footer, state) else {
+            return Ok(());
+        };
+        let mut spans = vec![Span::raw("Keybindings: ")];
+        for (keys, action) in self.bound_actions(state) {
+            if spans.len() > 1 {
+                spans.push(Span::raw(" | "));
+            }
+            spans.push(Span::styled(
+                keys,
+                self.config
+                    .ui
+                    .theme
+                    .fg(self.config.ui.theme.palette.keys)
+                    .bold(),
+            ));
+            spans.push(Span::raw(format!(" - {}", action)));
+        }
+        let theme = &self.config.ui.theme;
+        let block = get_block(
+            state,
+            ComponentId::Footer,
+            theme.color(theme.palette.footer_border),
         );
-        let block = get_block(state, 3, Color::LightBlue);
-        let paragraph = Paragraph::new(footer).block(block);
-        frame.render_widget(paragraph, area);
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        let Some(download) = &state.download else {
+            frame.render_widget(Paragraph::new(Line::from(spans)), inner);
+            return Ok(());
+        };
+
+        // A running download takes the right of the footer
+        let [keys_area, download_area] =
+            Layout::horizontal([Constraint::Fill(1), Constraint::Length(50)])
+                .spacing(2)
+                .areas(inner);
+        frame.render_widget(Paragraph::new(Line::from(spans)), keys_area);
+        let received = download.progress.received();
+        let (ratio, size) = match download.progress.total() {
+            Some(total) if total > 0 => (
+                (received as f64 / total as f64).min(1.0),
+                format!("{}/{}", format_size(received), format_size(total)),
+            ),
+            _ => (0.0, format_size(received)),
+        };
+        let gauge = LineGauge::default()
+            .label(format!("{} {}", download.label, size))
+            .filled_style(theme.fg(theme.palette.keys))
+            .ratio(ratio);
+        frame.render_widget(gauge, download_area);
         Ok(())
     }
 }
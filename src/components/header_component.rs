@@ -1,7 +1,7 @@
 use color_eyre::Result;
 use ratatui::prelude::*;
 
-use crate::{action::Action, state::State};
+use crate::{config::Config, state::State};
 
 use super::{
     utils::{prepare_layout, Element},
@@ -9,7 +9,9 @@ use super::{
 };
 
 #[derive(Default)]
-pub struct HeaderComponent {}
+pub struct HeaderComponent {
+    config: Config,
+}
 
 impl HeaderComponent {
     pub fn new() -> Self {
@@ -18,27 +20,27 @@ impl HeaderComponent {
 }
 
 impl Component for HeaderComponent {
-    fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>> {
-        match action {
-            Action::FocusDown => state.focused_component = 2, // change to pipelines viewer
-            _ => {}
-        }
-        Ok(None)
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.config = config;
+        Ok(())
     }
 
     fn draw(&mut self, frame: &mut Frame, area: Rect, state: &State) -> Result<()> {
-        let area = prepare_layout(area, Element::Header);
+        let Some(area) = prepare_layout(area, Element::Header, state) else {
+            return Ok(());
+        };
         // Split the area into two horizontal chunks
         let chunks = Layout::default()
             .direction(Direction::Horizontal)
             .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
             .split(area);
 
+        let theme = &self.config.ui.theme;
         // Render the left-aligned text
         frame.render_widget(
             Line::from(vec![Span::styled(
                 format!("[ GitLab Pipelines Viewer {} v0.0.1 ]", symbols::DOT), // TODO: read project version dynamically
-                Style::default().fg(Color::LightBlue),
+                theme.fg(theme.palette.accent),
             )]),
             chunks[0],
         );
@@ -47,15 +49,19 @@ impl Component for HeaderComponent {
             Some(name) => name,
             None => "<None>",
         };
+        let mut spans = vec![];
+        if state.offline {
+            spans.push(Span::styled(
+                "[ OFFLINE (read-only) ] ",
+                theme.fg(theme.palette.warning).bold(),
+            ));
+        }
+        spans.push(Span::styled(
+            project_name,
+            theme.fg(theme.palette.active_project),
+        ));
         // Render the right-aligned text
-        frame.render_widget(
-            Line::from(vec![Span::styled(
-                project_name,
-                Style::default().fg(Color::LightRed),
-            )])
-            .right_aligned(),
-            chunks[1],
-        );
+        frame.render_widget(Line::from(spans).right_aligned(), chunks[1]);
 
         Ok(())
     }
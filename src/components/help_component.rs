@@ -0,0 +1,222 @@
+use color_eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent, MouseEvent, MouseEventKind};
+use ratatui::{prelude::*, widgets::*};
+use strum::{EnumMessage, IntoEnumIterator};
+
+use super::{utils::popup_area, Component, ComponentId};
+use crate::{
+    action::Action,
+    config::{Config, Scope},
+    state::{InputMode, State},
+};
+
+struct HelpEntry {
+    // Unbound actions have no scope
+    scope: Option<Scope>,
+    keys: String,
+    action: Action,
+    description: &'static str,
+}
+
+/// Popup listing every action together with the keys bound to it in the current mode. The list
+/// is generated from the keybindings configuration and can be searched by typing.
+#[derive(Default)]
+pub struct HelpComponent {
+    config: Config,
+    show_popup: bool,
+    search: String,
+    table_state: TableState,
+}
+
+impl HelpComponent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn open(&mut self, state: &mut State) {
+        self.show_popup = true;
+        self.search.clear();
+        self.table_state.select(Some(0));
+        state.input_mode = InputMode::Insert;
+        state.popup = Some(ComponentId::Help);
+    }
+
+    fn close(&mut self, state: &mut State) {
+        self.show_popup = false;
+        state.input_mode = InputMode::Normal;
+        state.popup = None;
+    }
+
+    /// Documented actions matching the search, together with the keys bound to them in each
+    /// scope. Scopes which currently apply are listed first.
+    fn entries(&self, state: &State) -> Vec<HelpEntry> {
+        let active_scopes = state.active_scopes();
+        let mut scopes: Vec<Scope> = Scope::iter().collect();
+        scopes.sort_by_key(|scope| (!active_scopes.contains(scope), *scope));
+
+        let mut entries = vec![];
+        for action in Action::iter() {
+            let Some(description) = action.get_message() else {
+                continue;
+            };
+            let mut bound = false;
+            for scope in &scopes {
+                let keys = self
+                    .config
+                    .keybindings
+                    .keys_for(state.mode, *scope, &action);
+                if !keys.is_empty() {
+                    bound = true;
+                    entries.push(HelpEntry {
+                        scope: Some(*scope),
+                        keys: keys.join(" "),
+                        action: action.clone(),
+                        description,
+                    });
+                }
+            }
+            if !bound {
+                entries.push(HelpEntry {
+                    scope: None,
+                    keys: "-".to_string(),
+                    action,
+                    description,
+                });
+            }
+        }
+        entries.sort_by_key(|entry| {
+            entry
+                .scope
+                .and_then(|scope| scopes.iter().position(|s| *s == scope))
+                .unwrap_or(scopes.len())
+        });
+
+        let search = self.search.to_lowercase();
+        entries.retain(|entry| {
+            [
+                &entry
+                    .scope
+                    .map_or("-".to_string(), |scope| scope.to_string()),
+                entry.keys.as_str(),
+                &entry.action.to_string(),
+                entry.description,
+            ]
+            .iter()
+            .any(|text| text.to_lowercase().contains(&search))
+        });
+        entries
+    }
+}
+
+impl Component for HelpComponent {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.config = config;
+        Ok(())
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent, _state: &mut State) -> Result<Option<Action>> {
+        if !self.show_popup {
+            return Ok(None);
+        }
+        match key.code {
+            KeyCode::Backspace => {
+                self.search.pop();
+                self.table_state.select(Some(0));
+            }
+            KeyCode::Char(c) => {
+                self.search.push(c);
+                self.table_state.select(Some(0));
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn handle_mouse_event(
+        &mut self,
+        mouse: MouseEvent,
+        _state: &mut State,
+    ) -> Result<Option<Action>> {
+        if self.show_popup {
+            match mouse.kind {
+                MouseEventKind::ScrollDown => self.table_state.select_next(),
+                MouseEventKind::ScrollUp => self.table_state.select_previous(),
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+
+    fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>> {
+        match action {
+            Action::Help if !self.show_popup => self.open(state),
+            Action::Close if self.show_popup => self.close(state),
+            Action::Next if self.show_popup => self.table_state.select_next(),
+            Action::Previous if self.show_popup => self.table_state.select_previous(),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect, state: &State) -> Result<()> {
+        if !self.show_popup {
+            return Ok(());
+        }
+        let theme = &self.config.ui.theme;
+        let area = popup_area(area, 60, 70);
+        let block = Block::bordered()
+            .border_type(BorderType::Thick)
+            .border_style(theme.fg(theme.palette.popup_border))
+            .title(format!("Help ({} mode)", state.mode))
+            .title_bottom(Line::from(" type to search ").right_aligned());
+        let inner_area = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let [search_area, table_area] =
+            Layout::vertical([Constraint::Length(2), Constraint::Fill(1)]).areas(inner_area);
+        frame.render_widget(
+            Line::from(vec![
+                Span::styled("Search: ", Style::default().bold()),
+                Span::raw(&self.search),
+                Span::styled("_", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+            ]),
+            search_area,
+        );
+
+        let rows: Vec<Row> = self
+            .entries(state)
+            .into_iter()
+            .map(|entry| {
+                Row::new(vec![
+                    Span::raw(
+                        entry
+                            .scope
+                            .map_or("-".to_string(), |scope| scope.to_string()),
+                    ),
+                    Span::styled(entry.keys, theme.fg(theme.palette.keys)),
+                    Span::raw(entry.action.to_string()),
+                    Span::raw(entry.description),
+                ])
+            })
+            .collect();
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(15),
+                Constraint::Length(20),
+                Constraint::Length(15),
+                Constraint::Fill(1),
+            ],
+        )
+        .column_spacing(2)
+        .header(
+            Row::new(vec!["Scope", "Keys", "Action", "Description"]).style(Style::default().bold()),
+        )
+        .row_highlight_style(
+            theme.highlight(theme.palette.highlight_fg, theme.palette.highlight_bg),
+        );
+        frame.render_stateful_widget(table, table_area, &mut self.table_state);
+        Ok(())
+    }
+}
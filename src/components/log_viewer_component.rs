@@ -0,0 +1,502 @@
+use color_eyre::{eyre::Error, Result};
+use crossterm::event::KeyEvent;
+use ratatui::{prelude::*, widgets::*};
+
+use super::{
+    utils::{popup_area, PendingRequest, Search, Seek},
+    Component, ComponentId,
+};
+use crate::{action::Action, config::Config, gitlab::GitlabClient, state::State};
+
+/// Popup showing the log of a job, with the colours of the terminal it ran in.
+#[derive(Default)]
+pub struct LogViewerComponent {
+    config: Config,
+    client: GitlabClient,
+    job_id: Option<u32>,
+    lines: Vec<Line<'static>>,
+    // Text of the lines without their styles, to search them
+    texts: Vec<String>,
+    search: Search,
+    // Number of lines matching the search, counted again when the search or the log changes
+    matches: usize,
+    // Line of the last match jumped to
+    current_match: Option<usize>,
+    error: Option<Error>,
+    request: Option<PendingRequest<String>>,
+    // Index of the first line shown
+    scroll: usize,
+    // Number of lines shown during the last render, to scroll page by page
+    page_height: usize,
+}
+
+impl LogViewerComponent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn open(&mut self, gitlab_project: String, job_id: u32, state: &mut State) {
+        self.job_id = Some(job_id);
+        self.lines.clear();
+        self.texts.clear();
+        self.search = Search::default();
+        self.current_match = None;
+        self.error = None;
+        self.scroll = 0;
+        let client = self.client.clone();
+        self.request = Some(PendingRequest::spawn(async move {
+            client.fetch_job_log(&gitlab_project, job_id).await
+        }));
+        state.popup = Some(ComponentId::LogViewer);
+    }
+
+    fn close(&mut self, state: &mut State) {
+        self.job_id = None;
+        self.lines.clear();
+        self.request = None;
+        state.popup = None;
+    }
+
+    fn poll(&mut self) {
+        let Some(result) = self.request.as_mut().and_then(PendingRequest::poll) else {
+            return;
+        };
+        self.request = None;
+        match result {
+            // The end of a log is usually the interesting part
+            Ok(log) => {
+                self.lines = parse_log(&log);
+                self.texts = self
+                    .lines
+                    .iter()
+                    .map(|line| {
+                        line.spans
+                            .iter()
+                            .map(|span| span.content.as_ref())
+                            .collect()
+                    })
+                    .collect();
+                self.count_matches();
+                self.scroll = self.max_scroll();
+            }
+            Err(error) => self.error = Some(error),
+        }
+    }
+
+    fn count_matches(&mut self) {
+        self.matches = self
+            .texts
+            .iter()
+            .filter(|text| self.search.is_match(text))
+            .count();
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.lines.len().saturating_sub(self.page_height.max(1))
+    }
+
+    /// Scroll to a line matching the search, looking from the first line shown. The line is shown
+    /// a few lines below the top, so that what led to it is visible as well.
+    fn jump_to_match(&mut self, seek: Seek) {
+        let (texts, search) = (&self.texts, &self.search);
+        // Go on from the last match, unless it was scrolled out of sight
+        let shown = self.scroll..self.scroll + self.page_height;
+        let current = self
+            .current_match
+            .filter(|line| shown.contains(line))
+            .unwrap_or(self.scroll);
+        if let Some(index) = search.find(texts.len(), current, seek, |index| {
+            search.is_match(&texts[index])
+        }) {
+            self.current_match = Some(index);
+            self.scroll = index
+                .saturating_sub(self.page_height / 3)
+                .min(self.max_scroll());
+        }
+    }
+
+    fn scroll_by(&mut self, lines: isize) {
+        self.scroll = self
+            .scroll
+            .saturating_add_signed(lines)
+            .min(self.max_scroll());
+    }
+}
+
+impl Component for LogViewerComponent {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.client = GitlabClient::new(config.core.gitlab_url.clone());
+        self.config = config;
+        Ok(())
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent, _state: &mut State) -> Result<Option<Action>> {
+        if self.search.handle_key(key) {
+            self.count_matches();
+            self.jump_to_match(Seek::Current);
+        }
+        Ok(None)
+    }
+
+    fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>> {
+        if let Action::OpenJobLog(gitlab_project, job_id) = action {
+            self.open(gitlab_project, job_id, state);
+            return Ok(None);
+        }
+        if self.job_id.is_none() {
+            return Ok(None);
+        }
+        // Keys bound while typing the search only apply to it
+        if self.search.is_typing() {
+            match action {
+                Action::Enter => self.search.finish(state),
+                Action::Close => self.search.cancel(state),
+                Action::Next => self.jump_to_match(Seek::Next),
+                Action::Previous => self.jump_to_match(Seek::Previous),
+                Action::Tick => self.poll(),
+                _ => {}
+            }
+            return Ok(None);
+        }
+        let page = self.page_height.max(1) as isize;
+        match action {
+            Action::Tick => self.poll(),
+            Action::Next => self.scroll_by(1),
+            Action::Previous => self.scroll_by(-1),
+            Action::NextPage => self.scroll_by(page),
+            Action::PreviousPage => self.scroll_by(-page),
+            Action::GoToTop => self.scroll = 0,
+            Action::GoToBottom => self.scroll = self.max_scroll(),
+            Action::Search => {
+                self.search.start(state);
+                self.current_match = None;
+            }
+            Action::NextMatch => self.jump_to_match(Seek::Next),
+            Action::PreviousMatch => self.jump_to_match(Seek::Previous),
+            Action::Close => self.close(state),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect, _state: &State) -> Result<()> {
+        let Some(job_id) = self.job_id else {
+            return Ok(());
+        };
+        let theme = &self.config.ui.theme;
+        let area = popup_area(area, 90, 90);
+        let block = Block::bordered()
+            .border_type(BorderType::Thick)
+            .border_style(theme.fg(theme.palette.popup_border))
+            .title(format!("Log of job #{}", job_id))
+            .title_bottom(
+                Line::from(format!(
+                    "{} of {}",
+                    (self.scroll + 1).min(self.lines.len()),
+                    self.lines.len()
+                ))
+                .right_aligned(),
+            );
+        let block = match self.search.prompt(self.matches) {
+            Some(prompt) => block.title_bottom(prompt.left_aligned()),
+            None => block,
+        };
+        let inner = block.inner(area);
+        self.page_height = inner.height as usize;
+        frame.render_widget(Clear, area);
+
+        let paragraph = if let Some(error) = &self.error {
+            Paragraph::new(Span::styled(
+                format!("ERROR: {}", error),
+                theme.fg(theme.palette.error).add_modifier(Modifier::BOLD),
+            ))
+            .alignment(Alignment::Center)
+        } else if self.request.is_some() {
+            Paragraph::new(Span::styled(
+                "Loading...",
+                theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD),
+            ))
+            .alignment(Alignment::Center)
+        } else {
+            // Only the visible lines are rendered, as logs can be very long
+            let lines: Vec<Line> = self
+                .lines
+                .iter()
+                .skip(self.scroll)
+                .take(self.page_height)
+                .map(|line| self.search.highlight(line.clone(), theme.search_match()))
+                .collect();
+            Paragraph::new(lines)
+        };
+        frame.render_widget(paragraph.block(block), area);
+        Ok(())
+    }
+}
+
+/// Split a job log into lines styled after its ANSI escape codes. GitLab marks collapsible
+/// sections with `section_start:<time>:<name>` and `section_end:<time>:<name>` followed by a
+/// carriage return, which are left out.
+fn parse_log(log: &str) -> Vec<Line<'static>> {
+    let mut style = Style::default();
+    log.lines()
+        .filter_map(|line| {
+            // Carriage returns rewrite the line, e.g. for progress bars, so only the last
+            // version of the line is kept
+            let visible = line.split('\r').rfind(|part| {
+                let text = strip_escape_codes(part);
+                !text.is_empty()
+                    && !text.starts_with("section_start:")
+                    && !text.starts_with("section_end:")
+            });
+            match visible {
+                Some(visible) => Some(Line::from(parse_ansi(visible, &mut style))),
+                // Lines only made of section markers
+                None if line.contains("section_") => None,
+                None => Some(Line::default()),
+            }
+        })
+        .collect()
+}
+
+/// Columns between two tab stops.
+const TAB_WIDTH: usize = 8;
+
+/// Styled spans of a line, starting with the style left by the previous line. Escape sequences
+/// other than SGR ones, e.g. hyperlinks or window titles, and control characters are dropped, and
+/// tabs are expanded to spaces.
+fn parse_ansi(line: &str, style: &mut Style) -> Vec<Span<'static>> {
+    let mut spans = vec![];
+    let mut text = String::new();
+    // Column of the next character, for tab stops
+    let mut column = 0;
+    let mut chars = line.chars().peekable();
+    while let Some(char) = chars.next() {
+        match char {
+            '\t' => {
+                let spaces = TAB_WIDTH - column % TAB_WIDTH;
+                text.extend(std::iter::repeat_n(' ', spaces));
+                column += spaces;
+            }
+            '\x1b' => match chars.next() {
+                // CSI sequence: parameters come first, the final byte tells what it does
+                Some('[') => {
+                    let mut parameters = String::new();
+                    let mut command = None;
+                    for char in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&char) {
+                            command = Some(char);
+                            break;
+                        }
+                        parameters.push(char);
+                    }
+                    if command == Some('m') {
+                        if !text.is_empty() {
+                            spans.push(Span::styled(std::mem::take(&mut text), *style));
+                        }
+                        *style = apply_sgr(*style, &parameters);
+                    }
+                }
+                // OSC (e.g. hyperlinks and titles), DCS, SOS, PM and APC strings, which end with
+                // a bell or a string terminator (`ESC \`)
+                Some(']' | 'P' | 'X' | '^' | '_') => {
+                    while let Some(char) = chars.next() {
+                        if char == '\x07' || (char == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                // Intermediate bytes, up to the final byte, e.g. `ESC ( B`
+                Some('\x20'..='\x2f') => {
+                    for char in chars.by_ref() {
+                        if ('\x30'..='\x7e').contains(&char) {
+                            break;
+                        }
+                    }
+                }
+                // Any other sequence is a single character, e.g. `ESC 7`
+                _ => {}
+            },
+            char if char.is_control() => {}
+            char => {
+                text.push(char);
+                column += 1;
+            }
+        }
+    }
+    if !text.is_empty() {
+        spans.push(Span::styled(text, *style));
+    }
+    spans
+}
+
+/// Apply the parameters of an SGR ("Select Graphic Rendition") sequence to a style.
+fn apply_sgr(mut style: Style, parameters: &str) -> Style {
+    const COLORS: [Color; 8] = [
+        Color::Black,
+        Color::Red,
+        Color::Green,
+        Color::Yellow,
+        Color::Blue,
+        Color::Magenta,
+        Color::Cyan,
+        Color::Gray,
+    ];
+    const BRIGHT_COLORS: [Color; 8] = [
+        Color::DarkGray,
+        Color::LightRed,
+        Color::LightGreen,
+        Color::LightYellow,
+        Color::LightBlue,
+        Color::LightMagenta,
+        Color::LightCyan,
+        Color::White,
+    ];
+    let mut codes = parameters
+        .split(';')
+        .map(|code| code.parse::<u8>().unwrap_or(0));
+    while let Some(code) = codes.next() {
+        style = match code {
+            0 => Style::default(),
+            1 => style.add_modifier(Modifier::BOLD),
+            2 => style.add_modifier(Modifier::DIM),
+            3 => style.add_modifier(Modifier::ITALIC),
+            4 => style.add_modifier(Modifier::UNDERLINED),
+            22 => style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style.remove_modifier(Modifier::ITALIC),
+            24 => style.remove_modifier(Modifier::UNDERLINED),
+            30..=37 => style.fg(COLORS[(code - 30) as usize]),
+            39 => style.fg(Color::Reset),
+            40..=47 => style.bg(COLORS[(code - 40) as usize]),
+            49 => style.bg(Color::Reset),
+            90..=97 => style.fg(BRIGHT_COLORS[(code - 90) as usize]),
+            100..=107 => style.bg(BRIGHT_COLORS[(code - 100) as usize]),
+            // 256 colours (e.g. `38;5;208`) or true colours (e.g. `38;2;255;128;0`)
+            38 | 48 => {
+                let color = match codes.next() {
+                    Some(5) => codes.next().map(Color::Indexed),
+                    Some(2) => match (codes.next(), codes.next(), codes.next()) {
+                        (Some(red), Some(green), Some(blue)) => Some(Color::Rgb(red, green, blue)),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                match color {
+                    Some(color) if code == 38 => style.fg(color),
+                    Some(color) => style.bg(color),
+                    None => style,
+                }
+            }
+            _ => style,
+        };
+    }
+    style
+}
+
+/// Text of a part of a line without its escape codes.
+fn strip_escape_codes(text: &str) -> String {
+    parse_ansi(text, &mut Style::default())
+        .into_iter()
+        .map(|span| span.content)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_colours_and_modifiers() {
+        let style = apply_sgr(Style::default(), "1;31");
+        assert_eq!(style, Style::default().fg(Color::Red).bold());
+        let style = apply_sgr(style, "22;44");
+        assert_eq!(
+            style,
+            Style::default()
+                .fg(Color::Red)
+                .bg(Color::Blue)
+                .remove_modifier(Modifier::BOLD | Modifier::DIM)
+        );
+        assert_eq!(apply_sgr(style, "0"), Style::default());
+        assert_eq!(apply_sgr(style, ""), Style::default());
+    }
+
+    #[test]
+    fn applies_extended_colours() {
+        assert_eq!(
+            apply_sgr(Style::default(), "38;5;208"),
+            Style::default().fg(Color::Indexed(208))
+        );
+        assert_eq!(
+            apply_sgr(Style::default(), "48;2;255;128;0"),
+            Style::default().bg(Color::Rgb(255, 128, 0))
+        );
+        // Incomplete sequences are ignored
+        assert_eq!(apply_sgr(Style::default(), "38;2;255"), Style::default());
+    }
+
+    #[test]
+    fn splits_lines_into_styled_spans() {
+        let mut style = Style::default();
+        let spans = parse_ansi("\x1b[32mok\x1b[0m done \x1b[1m", &mut style);
+        assert_eq!(
+            spans,
+            [
+                Span::styled("ok", Style::default().fg(Color::Green)),
+                Span::raw(" done "),
+            ]
+        );
+        // The style is left for the next line
+        assert_eq!(style, Style::default().bold());
+        // Sequences other than SGR are dropped
+        let spans = parse_ansi("\x1b[2Kcleared", &mut Style::default());
+        assert_eq!(spans, [Span::raw("cleared")]);
+    }
+
+    #[test]
+    fn drops_other_escape_sequences_and_control_characters() {
+        let texts = |line: &str| strip_escape_codes(line);
+        // Hyperlinks, ended by a string terminator or a bell
+        assert_eq!(
+            texts("see \x1b]8;;https://example.com\x1b\\docs\x1b]8;;\x1b\\ here"),
+            "see docs here"
+        );
+        assert_eq!(texts("\x1b]0;window title\x07build"), "build");
+        // Character sets, cursor saves and stray control characters
+        assert_eq!(texts("\x1b(Bplain\x1b7 text\x08\x07"), "plain text");
+        // The style survives the dropped sequences
+        let mut style = Style::default();
+        let spans = parse_ansi("\x1b[31m\x1b]8;;url\x07red\x1b]8;;\x07", &mut style);
+        assert_eq!(
+            spans,
+            [Span::styled("red", Style::default().fg(Color::Red))]
+        );
+    }
+
+    #[test]
+    fn expands_tabs_to_the_next_stop() {
+        assert_eq!(strip_escape_codes("\tx"), "        x");
+        assert_eq!(strip_escape_codes("abc\tx"), "abc     x");
+        assert_eq!(strip_escape_codes("abcdefgh\tx"), "abcdefgh        x");
+        // Escape sequences take no room
+        assert_eq!(strip_escape_codes("\x1b[1mab\x1b[0m\tx"), "ab      x");
+    }
+
+    #[test]
+    fn keeps_the_last_version_of_lines_and_drops_section_markers() {
+        let log = "section_start:1:build\r\x1b[0KBuilding\n\
+                   10%\r50%\r100%\n\
+                   \x1b[0Ksection_end:2:build\r\x1b[0K\n\
+                   \n\
+                   done";
+        let texts: Vec<String> = parse_log(log)
+            .iter()
+            .map(|line| {
+                line.spans
+                    .iter()
+                    .map(|span| span.content.as_ref())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(texts, ["Building", "100%", "", "done"]);
+    }
+}
@@ -0,0 +1,690 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use color_eyre::{eyre::Error, Result};
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use futures::{stream, StreamExt};
+use ratatui::{prelude::*, widgets::*};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    utils::{format_age, get_block, prepare_layout, Body, Element, PendingRequest},
+    Component, ComponentId,
+};
+use crate::{
+    action::Action,
+    cache::{Cache, CacheKind},
+    config::Config,
+    gitlab::{
+        is_unreachable, GitlabClient, GitlabMergeRequest, GitlabPipeline, GitlabTodo, GitlabUser,
+    },
+    state::{PipelineRef, State, View},
+    theme::Theme,
+};
+
+/// Number of projects whose pipelines are fetched at the same time.
+const CONCURRENT_REQUESTS: usize = 8;
+/// Pipelines of the user fetched for each project.
+const PIPELINES_PER_PROJECT: usize = 10;
+
+/// Role of the user in a merge request.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+enum Role {
+    Author,
+    Assignee,
+    Reviewer,
+}
+
+const ROLES: [Role; 3] = [Role::Author, Role::Assignee, Role::Reviewer];
+
+/// Something the user is involved in, in one of the projects of the project list.
+#[derive(Clone)]
+enum WorkItem {
+    Pipeline {
+        gitlab_project: String,
+        pipeline: Box<GitlabPipeline>,
+    },
+    MergeRequest {
+        gitlab_project: String,
+        merge_request: GitlabMergeRequest,
+        roles: Vec<Role>,
+    },
+    Todo {
+        gitlab_project: String,
+        todo: GitlabTodo,
+    },
+}
+
+impl WorkItem {
+    fn gitlab_project(&self) -> &str {
+        match self {
+            WorkItem::Pipeline { gitlab_project, .. }
+            | WorkItem::MergeRequest { gitlab_project, .. }
+            | WorkItem::Todo { gitlab_project, .. } => gitlab_project,
+        }
+    }
+
+    fn updated_at(&self) -> DateTime<Utc> {
+        match self {
+            WorkItem::Pipeline { pipeline, .. } => pipeline.updated_at,
+            WorkItem::MergeRequest { merge_request, .. } => merge_request.updated_at,
+            WorkItem::Todo { todo, .. } => todo.updated_at,
+        }
+    }
+
+    fn row(&self, theme: &Theme) -> Row<'static> {
+        let (kind, title, details) = match self {
+            WorkItem::Pipeline { pipeline, .. } => (
+                "Pipeline",
+                Line::styled(
+                    format!(
+                        "{} #{} {}",
+                        theme.status_icon(&pipeline.status),
+                        pipeline.id,
+                        pipeline.git_ref
+                    ),
+                    theme.status_style(&pipeline.status),
+                ),
+                pipeline.source.to_string(),
+            ),
+            WorkItem::MergeRequest {
+                merge_request,
+                roles,
+                ..
+            } => {
+                let mut title =
+                    Line::from(format!("!{} {}", merge_request.iid, merge_request.title));
+                if merge_request.draft {
+                    title = title.dim();
+                }
+                let roles: Vec<String> = roles.iter().map(Role::to_string).collect();
+                ("Merge request", title, roles.join(", "))
+            }
+            WorkItem::Todo { todo, .. } => (
+                "To-do",
+                Line::from(
+                    todo.target
+                        .title
+                        .clone()
+                        .unwrap_or_else(|| todo.target_type.clone()),
+                ),
+                todo.action_name.replace('_', " "),
+            ),
+        };
+        Row::new([
+            Line::from(kind),
+            Line::from(self.gitlab_project().to_string()),
+            title,
+            Line::from(details),
+            Line::from(format_age(self.updated_at())),
+        ])
+    }
+}
+
+/// Items of the view, along with the user and what could not be fetched.
+struct MyWork {
+    user: GitlabUser,
+    /// Projects whose merge requests were fetched
+    projects: Vec<String>,
+    /// Projects whose merge requests could not be fetched
+    stale_projects: Vec<String>,
+    items: Vec<WorkItem>,
+    /// Parts of the view which could not be fetched, e.g. `to-do items`
+    failures: Vec<String>,
+}
+
+/// Merge requests the user is involved in, e.g. in a project, along with their roles.
+type ProjectMergeRequests = Vec<(GitlabMergeRequest, Vec<Role>)>;
+
+/// Pipelines triggered by the user, merge requests they author, are assigned to or review, and
+/// their to-do items, across the projects of the project list and the most recently active first.
+#[derive(Default)]
+pub struct MyWorkComponent {
+    config: Config,
+    client: GitlabClient,
+    cache: Cache,
+    items: Option<Vec<WorkItem>>,
+    error: Option<Error>,
+    failures: Vec<String>,
+    request: Option<PendingRequest<MyWork>>,
+    last_request_at: Option<Instant>,
+    table_state: TableState,
+    // Area computed while drawing, used for mouse hit-testing
+    area: Rect,
+}
+
+impl MyWorkComponent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Refresh the items while they are shown, starting from the cached merge requests. The user
+    /// is resolved by the first request and kept in the state afterwards.
+    fn refresh(&mut self, state: &mut State) {
+        if state.view != View::MyWork {
+            return;
+        }
+        if self.items.is_none() && self.last_request_at.is_none() {
+            let items = self.cached_items(&state.projects);
+            if !items.is_empty() {
+                self.set_items(items);
+            }
+        }
+        if let Some(result) = self.request.as_mut().and_then(PendingRequest::poll) {
+            self.request = None;
+            match result {
+                Ok(my_work) => {
+                    state.offline = false;
+                    state.user = Some(my_work.user);
+                    self.store_merge_requests(&my_work.projects, &my_work.items);
+                    // Merge requests which could not be fetched are shown as last cached
+                    let mut items = my_work.items;
+                    items.extend(self.cached_items(&my_work.stale_projects));
+                    items.sort_by_key(|item| std::cmp::Reverse(item.updated_at()));
+                    self.set_items(items);
+                    self.failures = my_work.failures;
+                    self.error = None;
+                }
+                Err(error) => {
+                    state.offline = is_unreachable(&error);
+                    self.error = Some(error);
+                }
+            }
+        }
+
+        let refresh_interval = Duration::from_secs(self.config.core.refresh_interval);
+        let refresh_due = self
+            .last_request_at
+            .is_none_or(|requested_at| requested_at.elapsed() >= refresh_interval);
+        if self.request.is_none() && refresh_due {
+            let client = self.client.clone();
+            let user = state.user.clone();
+            let projects = state.projects.clone();
+            self.last_request_at = Some(Instant::now());
+            self.request = Some(PendingRequest::spawn(async move {
+                fetch_my_work(client, user, projects).await
+            }));
+        }
+    }
+
+    /// Merge requests of the given projects cached by earlier refreshes.
+    fn cached_items(&self, projects: &[String]) -> Vec<WorkItem> {
+        let mut items: Vec<WorkItem> =
+            projects
+                .iter()
+                .filter_map(|gitlab_project| {
+                    let entry = self
+                        .cache
+                        .load::<ProjectMergeRequests>(gitlab_project, CacheKind::MergeRequests)?;
+                    Some(entry.data.into_iter().map(|(merge_request, roles)| {
+                        WorkItem::MergeRequest {
+                            gitlab_project: gitlab_project.clone(),
+                            merge_request,
+                            roles,
+                        }
+                    }))
+                })
+                .flatten()
+                .collect();
+        items.sort_by_key(|item| std::cmp::Reverse(item.updated_at()));
+        items
+    }
+
+    /// Cache the merge requests of each project, including projects left without any.
+    fn store_merge_requests(&self, projects: &[String], items: &[WorkItem]) {
+        for project in projects {
+            let merge_requests: ProjectMergeRequests = items
+                .iter()
+                .filter_map(|item| match item {
+                    WorkItem::MergeRequest {
+                        gitlab_project,
+                        merge_request,
+                        roles,
+                    } if gitlab_project == project => Some((merge_request.clone(), roles.clone())),
+                    _ => None,
+                })
+                .collect();
+            if let Err(error) = self
+                .cache
+                .store(project, CacheKind::MergeRequests, &merge_requests)
+            {
+                tracing::warn!("Failed to cache merge requests: {}", error);
+            }
+        }
+    }
+
+    fn set_items(&mut self, items: Vec<WorkItem>) {
+        let selected = self.table_state.selected().unwrap_or(0);
+        self.table_state
+            .select((!items.is_empty()).then(|| selected.min(items.len() - 1)));
+        self.items = Some(items);
+    }
+
+    fn selected_item(&self) -> Option<&WorkItem> {
+        self.items.as_ref()?.get(self.table_state.selected()?)
+    }
+
+    /// Show the pipelines of the project of the selected item.
+    fn open_project(&mut self, state: &mut State) {
+        let Some(gitlab_project) = self.selected_item().map(WorkItem::gitlab_project) else {
+            return;
+        };
+        if state.active_gitlab_project.as_deref() != Some(gitlab_project) {
+            if let Some(previous) = state
+                .active_gitlab_project
+                .replace(gitlab_project.to_string())
+            {
+                state.project_history.push(previous);
+            }
+        }
+        state.view = View::Pipelines;
+        if state.focused_component == ComponentId::MyWork {
+            state.focused_component = ComponentId::PipelinesViewer;
+        }
+    }
+
+    fn show_graph(&mut self, state: &mut State) {
+        if let Some(WorkItem::Pipeline {
+            gitlab_project,
+            pipeline,
+        }) = self.selected_item()
+        {
+            state.show_graph(PipelineRef {
+                gitlab_project: gitlab_project.clone(),
+                id: pipeline.id,
+            });
+        }
+    }
+
+    fn next(&mut self) {
+        let len = self.items.as_ref().map_or(0, Vec::len);
+        if let Some(selected) = self.table_state.selected() {
+            if selected + 1 < len {
+                self.table_state.select(Some(selected + 1));
+            }
+        }
+    }
+
+    fn previous(&mut self) {
+        if let Some(selected) = self.table_state.selected() {
+            self.table_state.select(Some(selected.saturating_sub(1)));
+        }
+    }
+}
+
+impl Component for MyWorkComponent {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.client = GitlabClient::new(config.core.gitlab_url.clone());
+        self.cache = Cache::new(config.cache.enabled);
+        self.config = config;
+        Ok(())
+    }
+
+    fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>> {
+        match action {
+            Action::Tick => self.refresh(state),
+            Action::Next => self.next(),
+            Action::Previous => self.previous(),
+            Action::Enter => self.open_project(state),
+            Action::ShowGraph => self.show_graph(state),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn handle_mouse_event(
+        &mut self,
+        mouse: MouseEvent,
+        state: &mut State,
+    ) -> Result<Option<Action>> {
+        if state.popup.is_some() || !self.area.contains(Position::new(mouse.column, mouse.row)) {
+            return Ok(None);
+        }
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                state.focused_component = ComponentId::MyWork;
+                // Rows start below the border, the padding and the header row
+                let first_row = self.area.y + 3;
+                if mouse.row >= first_row {
+                    let len = self.items.as_ref().map_or(0, Vec::len);
+                    let index = (mouse.row - first_row) as usize + self.table_state.offset();
+                    if index < len {
+                        self.table_state.select(Some(index));
+                    }
+                }
+            }
+            MouseEventKind::ScrollDown => self.next(),
+            MouseEventKind::ScrollUp => self.previous(),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect, state: &State) -> Result<()> {
+        let area = prepare_layout(area, Element::Body(Body::RightColumn), state)
+            .filter(|_| state.view == View::MyWork);
+        self.area = area.unwrap_or_default();
+        let Some(area) = area else {
+            return Ok(());
+        };
+        let theme = &self.config.ui.theme;
+        let mut block = get_block(
+            state,
+            ComponentId::MyWork,
+            theme.color(theme.palette.pipelines_border),
+        )
+        .padding(Padding::uniform(1))
+        .title(match &state.user {
+            Some(user) => format!("My work (@{})", user.username),
+            None => "My work".to_string(),
+        });
+
+        let Some(items) = &self.items else {
+            let message = match &self.error {
+                Some(error) => Span::styled(
+                    format!("ERROR: {}", error),
+                    theme.fg(theme.palette.error).add_modifier(Modifier::BOLD),
+                ),
+                None => Span::styled(
+                    "Loading...",
+                    theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD),
+                ),
+            };
+            let paragraph = Paragraph::new(Line::from(message))
+                .block(block)
+                .alignment(Alignment::Center);
+            frame.render_widget(paragraph, area);
+            return Ok(());
+        };
+
+        if let Some(error) = &self.error {
+            block = block.title(Line::styled(
+                format!(" STALE ({}) ", error),
+                theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD),
+            ));
+        }
+        if !self.failures.is_empty() {
+            block = block.title(Line::styled(
+                format!(" Failed to fetch {} ", self.failures.join(", ")),
+                theme.fg(theme.palette.error).add_modifier(Modifier::BOLD),
+            ));
+        }
+        let header_row = Row::new(["Kind", "Project", "Title", "Details", "Updated"]).bold();
+        let rows = items.iter().map(|item| item.row(theme));
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(13),
+                Constraint::Min(20),
+                Constraint::Min(30),
+                Constraint::Length(24),
+                Constraint::Length(10),
+            ],
+        )
+        .column_spacing(2)
+        .header(header_row)
+        .row_highlight_style(
+            theme.highlight(theme.palette.highlight_fg, theme.palette.highlight_bg),
+        )
+        .block(block);
+        frame.render_stateful_widget(table, area, &mut self.table_state);
+        Ok(())
+    }
+}
+
+/// Fetch what the user is involved in across the given projects, resolving the user first if it
+/// is not known yet. A part which cannot be fetched does not hide the other ones, unless nothing
+/// could be fetched at all.
+async fn fetch_my_work(
+    client: GitlabClient,
+    user: Option<GitlabUser>,
+    projects: Vec<String>,
+) -> Result<MyWork> {
+    let user = match user {
+        Some(user) => user,
+        None => client.fetch_current_user().await?,
+    };
+    let mut items = vec![];
+    let mut fetched_projects = vec![];
+    let mut stale_projects = vec![];
+    let mut failures: Vec<(String, Error)> = vec![];
+    let mut fetched_any = false;
+
+    let pipelines: Vec<(String, Result<Vec<GitlabPipeline>>)> = stream::iter(projects.clone())
+        .map(|gitlab_project| {
+            let client = &client;
+            let username = &user.username;
+            async move {
+                let pipelines = client
+                    .fetch_user_pipelines(&gitlab_project, username, PIPELINES_PER_PROJECT)
+                    .await;
+                (gitlab_project, pipelines)
+            }
+        })
+        .buffered(CONCURRENT_REQUESTS)
+        .collect()
+        .await;
+    for (gitlab_project, result) in pipelines {
+        match result {
+            Ok(pipelines) => {
+                fetched_any = true;
+                items.extend(pipelines.into_iter().map(|pipeline| WorkItem::Pipeline {
+                    gitlab_project: gitlab_project.clone(),
+                    pipeline: Box::new(pipeline),
+                }));
+            }
+            Err(error) => failures.push((format!("pipelines of {}", gitlab_project), error)),
+        }
+    }
+
+    let (merge_requests, merge_request_failures) =
+        fetch_merge_requests(&client, &projects, user.id).await;
+    for (gitlab_project, merge_requests) in merge_requests {
+        fetched_any = true;
+        items.extend(merge_requests.into_iter().map(|(merge_request, roles)| {
+            WorkItem::MergeRequest {
+                gitlab_project: gitlab_project.clone(),
+                merge_request,
+                roles,
+            }
+        }));
+        fetched_projects.push(gitlab_project);
+    }
+    for (gitlab_project, error) in merge_request_failures {
+        failures.push((format!("merge requests of {}", gitlab_project), error));
+        stale_projects.push(gitlab_project);
+    }
+
+    let listed: HashSet<&str> = projects.iter().map(String::as_str).collect();
+    match client.fetch_todos().await {
+        Ok(todos) => {
+            fetched_any = true;
+            for todo in todos {
+                let Some(gitlab_project) = todo
+                    .project
+                    .as_ref()
+                    .map(|project| project.path_with_namespace.clone())
+                    .filter(|path| listed.contains(path.as_str()))
+                else {
+                    continue;
+                };
+                items.push(WorkItem::Todo {
+                    gitlab_project,
+                    todo,
+                });
+            }
+        }
+        Err(error) => failures.push(("to-do items".to_string(), error)),
+    }
+
+    for (what, error) in &failures {
+        tracing::warn!("Failed to fetch {}: {}", what, error);
+    }
+    if !fetched_any && !failures.is_empty() {
+        return Err(failures.swap_remove(0).1);
+    }
+    items.sort_by_key(|item| std::cmp::Reverse(item.updated_at()));
+    Ok(MyWork {
+        user,
+        projects: fetched_projects,
+        stale_projects,
+        items,
+        failures: failures.into_iter().map(|(what, _)| what).collect(),
+    })
+}
+
+/// Open merge requests in which the user has a role, for each of the given projects. They are
+/// searched across all projects at once, and only searched project by project if that fails, so
+/// that the projects which cannot be fetched are told apart from the other ones.
+async fn fetch_merge_requests(
+    client: &GitlabClient,
+    projects: &[String],
+    user_id: u32,
+) -> (Vec<(String, ProjectMergeRequests)>, Vec<(String, Error)>) {
+    match fetch_role_merge_requests(client, None, user_id).await {
+        Ok(merge_requests) => {
+            let mut by_project: Vec<(String, ProjectMergeRequests)> = projects
+                .iter()
+                .map(|gitlab_project| (gitlab_project.clone(), vec![]))
+                .collect();
+            let index: HashMap<&str, usize> = projects
+                .iter()
+                .enumerate()
+                .map(|(i, gitlab_project)| (gitlab_project.as_str(), i))
+                .collect();
+            for (merge_request, roles) in merge_requests {
+                if let Some(&i) = merge_request
+                    .project_path()
+                    .and_then(|path| index.get(path))
+                {
+                    by_project[i].1.push((merge_request, roles));
+                }
+            }
+            (by_project, vec![])
+        }
+        Err(error) => {
+            tracing::warn!(
+                "Failed to fetch merge requests across projects, fetching them per project: {}",
+                error
+            );
+            let results: Vec<(String, Result<ProjectMergeRequests>)> =
+                stream::iter(projects.to_vec())
+                    .map(|gitlab_project| async move {
+                        let merge_requests =
+                            fetch_role_merge_requests(client, Some(&gitlab_project), user_id).await;
+                        (gitlab_project, merge_requests)
+                    })
+                    .buffered(CONCURRENT_REQUESTS)
+                    .collect()
+                    .await;
+            let mut fetched = vec![];
+            let mut failures = vec![];
+            for (gitlab_project, result) in results {
+                match result {
+                    Ok(merge_requests) => fetched.push((gitlab_project, merge_requests)),
+                    Err(error) => failures.push((gitlab_project, error)),
+                }
+            }
+            (fetched, failures)
+        }
+    }
+}
+
+/// Open merge requests in which the user has a role, across all projects unless `gitlab_project`
+/// is given.
+async fn fetch_role_merge_requests(
+    client: &GitlabClient,
+    gitlab_project: Option<&str>,
+    user_id: u32,
+) -> Result<ProjectMergeRequests> {
+    let mut found = vec![];
+    for role in ROLES {
+        let merge_requests = client
+            .fetch_user_merge_requests(gitlab_project, &role.to_string(), user_id)
+            .await?;
+        found.push((role, merge_requests));
+    }
+    Ok(merge_roles(found))
+}
+
+/// Merge requests found for each role of the user, each listed once with all the roles of the
+/// user.
+fn merge_roles(found: Vec<(Role, Vec<GitlabMergeRequest>)>) -> ProjectMergeRequests {
+    let mut merge_requests: ProjectMergeRequests = vec![];
+    let mut positions: HashMap<u32, usize> = HashMap::new();
+    for (role, found) in found {
+        for merge_request in found {
+            match positions.get(&merge_request.id) {
+                Some(&position) => merge_requests[position].1.push(role),
+                None => {
+                    positions.insert(merge_request.id, merge_requests.len());
+                    merge_requests.push((merge_request, vec![role]));
+                }
+            }
+        }
+    }
+    merge_requests
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn merge_request(id: u32, gitlab_project: &str) -> GitlabMergeRequest {
+        serde_json::from_value(json!({
+            "id": id,
+            "iid": id,
+            "title": format!("Merge request {}", id),
+            "source_branch": "feature",
+            "target_branch": "main",
+            "web_url": "",
+            "updated_at": "2024-01-01T00:00:00Z",
+            "references": { "full": format!("{}!{}", gitlab_project, id) },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn merge_roles_lists_each_merge_request_once_with_all_roles() {
+        let merged = merge_roles(vec![
+            (
+                Role::Author,
+                vec![merge_request(1, "group/a"), merge_request(2, "group/b")],
+            ),
+            (Role::Assignee, vec![merge_request(1, "group/a")]),
+            (
+                Role::Reviewer,
+                vec![merge_request(3, "group/a"), merge_request(1, "group/a")],
+            ),
+        ]);
+        let merged: Vec<(u32, String)> = merged
+            .iter()
+            .map(|(merge_request, roles)| {
+                let roles: Vec<String> = roles.iter().map(Role::to_string).collect();
+                (merge_request.id, roles.join(", "))
+            })
+            .collect();
+        assert_eq!(
+            merged,
+            [
+                (1, "author, assignee, reviewer".to_string()),
+                (2, "author".to_string()),
+                (3, "reviewer".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn project_path_comes_from_the_full_reference() {
+        assert_eq!(
+            merge_request(1, "group/sub/project").project_path(),
+            Some("group/sub/project")
+        );
+    }
+}
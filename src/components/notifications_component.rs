@@ -0,0 +1,219 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use color_eyre::Result;
+use futures::{stream, StreamExt};
+use ratatui::{prelude::*, widgets::*};
+
+use super::{utils::PendingRequest, Component};
+use crate::{
+    action::Action,
+    config::Config,
+    gitlab::{GitlabClient, GitlabPipeline, PipelineStatus},
+    notifications::{detect_transitions, Notifier, PipelineEvent},
+    state::State,
+};
+
+/// Number of projects polled at the same time.
+const CONCURRENT_REQUESTS: usize = 8;
+const TOAST_DURATION: Duration = Duration::from_secs(6);
+const TOAST_WIDTH: u16 = 60;
+const TOAST_HEIGHT: u16 = 4;
+
+/// Pipelines of each polled project, or None if they could not be fetched.
+type Polled = Vec<(String, Option<Vec<GitlabPipeline>>)>;
+
+/// Watches pipelines of configured projects in the background and notifies about their status
+/// changes. In-app notifications are drawn as toasts in the top right corner of the screen.
+#[derive(Default)]
+pub struct NotificationsComponent {
+    config: Config,
+    client: GitlabClient,
+    notifier: Notifier,
+    // Last fetched pipelines of every watched project
+    snapshots: HashMap<String, Vec<GitlabPipeline>>,
+    request: Option<PendingRequest<Polled>>,
+    last_poll_at: Option<Instant>,
+    toasts: Vec<(PipelineEvent, Instant)>,
+}
+
+impl NotificationsComponent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fetch the pipelines of every watched project in a single background task, a few projects
+    /// at a time.
+    fn poll_projects(&mut self, state: &State) {
+        let refresh_interval = Duration::from_secs(self.config.core.refresh_interval);
+        if self.request.is_some()
+            || self
+                .last_poll_at
+                .is_some_and(|polled_at| polled_at.elapsed() < refresh_interval)
+        {
+            return;
+        }
+        self.last_poll_at = Some(Instant::now());
+
+        let projects: Vec<(String, bool)> = state
+            .projects
+            .iter()
+            .filter(|project| self.notifier.watches(project))
+            .map(|project| {
+                let watch_manual = self
+                    .notifier
+                    .watches_status(project, &PipelineStatus::Manual);
+                (project.clone(), watch_manual)
+            })
+            .collect();
+        if projects.is_empty() {
+            return;
+        }
+        let client = self.client.clone();
+        let page_size = self.config.ui.max_page_size;
+        self.request = Some(PendingRequest::spawn(async move {
+            Ok(stream::iter(projects)
+                .map(|(gitlab_project, watch_manual)| {
+                    let client = &client;
+                    async move {
+                        let pipelines =
+                            fetch_pipelines(client, &gitlab_project, page_size, watch_manual)
+                                .await
+                                .inspect_err(|err| {
+                                    tracing::warn!(
+                                        "Failed to watch pipelines of {}: {}",
+                                        gitlab_project,
+                                        err
+                                    )
+                                })
+                                .ok();
+                        (gitlab_project, pipelines)
+                    }
+                })
+                .buffer_unordered(CONCURRENT_REQUESTS)
+                .collect()
+                .await)
+        }));
+    }
+
+    fn handle_responses(&mut self) {
+        let Some(result) = self.request.as_mut().and_then(PendingRequest::poll) else {
+            return;
+        };
+        self.request = None;
+        let polled = match result {
+            Ok(polled) => polled,
+            Err(err) => {
+                tracing::warn!("Failed to watch pipelines: {}", err);
+                return;
+            }
+        };
+        for (project, pipelines) in polled {
+            let Some(pipelines) = pipelines else {
+                continue;
+            };
+            // The first fetch only establishes the baseline for future comparisons
+            if let Some(previous) = self.snapshots.get(&project) {
+                for event in detect_transitions(&project, previous, &pipelines) {
+                    if self.notifier.should_notify(&event) {
+                        self.notifier.deliver(&event);
+                        if self.notifier.shows_toasts() {
+                            self.toasts.push((event, Instant::now()));
+                        }
+                    }
+                }
+            }
+            self.snapshots.insert(project, pipelines);
+        }
+    }
+}
+
+impl Component for NotificationsComponent {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.client = GitlabClient::new(config.core.gitlab_url.clone());
+        self.notifier = Notifier::new(&config.notifications)?;
+        self.config = config;
+        Ok(())
+    }
+
+    fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>> {
+        if action == Action::Tick {
+            self.handle_responses();
+            self.poll_projects(state);
+            self.toasts
+                .retain(|(_, shown_at)| shown_at.elapsed() < TOAST_DURATION);
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect, _state: &State) -> Result<()> {
+        let width = TOAST_WIDTH.min(area.width);
+        let mut y = area.y + 1;
+        // Newest toasts come first
+        for (event, _) in self.toasts.iter().rev() {
+            if y + TOAST_HEIGHT > area.bottom() {
+                break;
+            }
+            let toast_area = Rect::new(area.right() - width, y, width, TOAST_HEIGHT);
+            let paragraph = Paragraph::new(vec![
+                Line::from(event.body()),
+                Line::styled(event.web_url.clone(), Style::default().italic()),
+            ])
+            .block(
+                Block::bordered()
+                    .border_type(BorderType::Rounded)
+                    .border_style(
+                        self.config
+                            .ui
+                            .theme
+                            .fg(self.config.ui.theme.palette.toast_border),
+                    )
+                    .title(event.title()),
+            );
+            frame.render_widget(Clear, toast_area);
+            frame.render_widget(paragraph, toast_area);
+            y += TOAST_HEIGHT;
+        }
+        Ok(())
+    }
+}
+
+/// Fetch the latest pipelines of a project. A blocking manual job waiting in a running pipeline
+/// leaves it running, so such pipelines are reported as manual when manual pipelines are watched.
+/// Pipelines whose jobs could not be checked keep their status.
+async fn fetch_pipelines(
+    client: &GitlabClient,
+    gitlab_project: &str,
+    page_size: usize,
+    watch_manual: bool,
+) -> Result<Vec<GitlabPipeline>> {
+    let pipelines = client.fetch_pipelines(gitlab_project, page_size).await?;
+    if !watch_manual {
+        return Ok(pipelines);
+    }
+    Ok(stream::iter(pipelines)
+        .map(|mut pipeline| async move {
+            if pipeline.status == PipelineStatus::Running
+                && client
+                    .has_blocking_manual_job(gitlab_project, pipeline.id)
+                    .await
+                    .inspect_err(|err| {
+                        tracing::warn!(
+                            "Failed to check manual jobs of pipeline {} of {}: {}",
+                            pipeline.id,
+                            gitlab_project,
+                            err
+                        )
+                    })
+                    .unwrap_or(false)
+            {
+                pipeline.status = PipelineStatus::Manual;
+            }
+            pipeline
+        })
+        .buffered(CONCURRENT_REQUESTS)
+        .collect()
+        .await)
+}
@@ -0,0 +1,458 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use color_eyre::{eyre::Error, Result};
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use ratatui::{prelude::*, widgets::*};
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::{
+    utils::{get_block, prepare_layout, Body, Element, PendingRequest},
+    Component, ComponentId,
+};
+use crate::{
+    action::Action,
+    config::Config,
+    gitlab::{is_unreachable, GitlabBridge, GitlabClient, GitlabJob, GitlabPipeline},
+    state::{PipelineRef, State, View},
+};
+
+use layout::{column_x, node_y, Graph, NodeIndex, NodeKind, NODE_WIDTH};
+
+mod layout;
+
+/// Jobs of a pipeline, along with the bridges triggering downstream pipelines and the `needs:`
+/// of the jobs, by job name.
+struct GraphData {
+    gitlab_project: String,
+    pipeline: GitlabPipeline,
+    jobs: Vec<GitlabJob>,
+    bridges: Vec<GitlabBridge>,
+    needs: HashMap<String, Vec<String>>,
+}
+
+/// Jobs of the pipeline selected in the pipelines table, laid out by stage with the dependencies
+/// between them. Downstream pipelines can be expanded inline, and the log of a job opened.
+#[derive(Default)]
+pub struct PipelineGraphComponent {
+    command_tx: Option<UnboundedSender<Action>>,
+    config: Config,
+    client: GitlabClient,
+    // Pipeline for which `data` was loaded, and the project active at the time, which differs
+    // from the one of the pipeline for downstream pipelines of another project
+    loaded_pipeline: Option<PipelineRef>,
+    opened_in: Option<String>,
+    data: Option<GraphData>,
+    error: Option<Error>,
+    request: Option<PendingRequest<GraphData>>,
+    last_request_at: Option<Instant>,
+    // Bridges whose downstream pipeline is shown, and these pipelines by bridge ID
+    expanded: HashSet<u32>,
+    downstream: HashMap<u32, GraphData>,
+    downstream_requests: HashMap<u32, PendingRequest<GraphData>>,
+    graph: Graph,
+    selected: NodeIndex,
+    // Top left corner of the visible part of the graph
+    offset: (usize, usize),
+    // Area computed while drawing, used for mouse hit-testing
+    area: Rect,
+}
+
+impl PipelineGraphComponent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Refresh the graph while it is shown, going back to the previous view when another project
+    /// is selected.
+    fn refresh(&mut self, state: &mut State) {
+        if state.view != View::Graph {
+            // The graph is loaded again when it is shown next time
+            self.loaded_pipeline = None;
+            return;
+        }
+        let Some(pipeline) = state.graph_pipeline.clone() else {
+            self.close(state);
+            return;
+        };
+        if self.loaded_pipeline.as_ref() == Some(&pipeline)
+            && self.opened_in != state.active_gitlab_project
+        {
+            self.close(state);
+            return;
+        }
+        if self.loaded_pipeline.as_ref() != Some(&pipeline) {
+            self.loaded_pipeline = Some(pipeline.clone());
+            self.opened_in = state.active_gitlab_project.clone();
+            self.data = None;
+            self.error = None;
+            self.request = None;
+            self.last_request_at = None;
+            self.expanded.clear();
+            self.downstream.clear();
+            self.downstream_requests.clear();
+            self.selected = (0, 0);
+            self.offset = (0, 0);
+            self.rebuild();
+        }
+
+        if let Some(result) = self.request.as_mut().and_then(PendingRequest::poll) {
+            self.request = None;
+            match result {
+                Ok(data) => {
+                    state.offline = false;
+                    self.data = Some(data);
+                    self.error = None;
+                    self.rebuild();
+                }
+                Err(error) => {
+                    state.offline = is_unreachable(&error);
+                    self.error = Some(error);
+                }
+            }
+        }
+        let finished: Vec<(u32, Result<GraphData>)> = self
+            .downstream_requests
+            .iter_mut()
+            .filter_map(|(id, request)| Some((*id, request.poll()?)))
+            .collect();
+        for (id, result) in finished {
+            self.downstream_requests.remove(&id);
+            match result {
+                Ok(data) => {
+                    self.downstream.insert(id, data);
+                    self.rebuild();
+                }
+                Err(error) => self.error = Some(error),
+            }
+        }
+
+        let refresh_interval = Duration::from_secs(self.config.core.refresh_interval);
+        let refresh_due = self
+            .last_request_at
+            .is_none_or(|requested_at| requested_at.elapsed() >= refresh_interval);
+        if self.request.is_none() && refresh_due {
+            let client = self.client.clone();
+            self.last_request_at = Some(Instant::now());
+            self.request = Some(PendingRequest::spawn(async move {
+                fetch_graph(client, pipeline.gitlab_project, pipeline.id).await
+            }));
+            for id in self.expanded.clone() {
+                self.request_downstream(id);
+            }
+        }
+    }
+
+    /// Fetch the downstream pipeline of a bridge, unless it is being fetched already.
+    fn request_downstream(&mut self, bridge_id: u32) {
+        if self.downstream_requests.contains_key(&bridge_id) {
+            return;
+        }
+        let Some(downstream) = self
+            .data
+            .iter()
+            .chain(self.downstream.values())
+            .flat_map(|data| &data.bridges)
+            .find(|bridge| bridge.id == bridge_id)
+            .and_then(|bridge| bridge.downstream_pipeline.clone())
+        else {
+            return;
+        };
+        let client = self.client.clone();
+        self.downstream_requests.insert(
+            bridge_id,
+            PendingRequest::spawn(async move {
+                // Downstream pipelines only tell the ID of their project
+                let gitlab_project = client
+                    .fetch_project(&downstream.project_id.to_string())
+                    .await?
+                    .path_with_namespace;
+                fetch_graph(client, gitlab_project, downstream.id).await
+            }),
+        );
+    }
+
+    /// Lay out the graph again, e.g. after new data arrived, keeping the selection in bounds.
+    fn rebuild(&mut self) {
+        self.graph = match &self.data {
+            Some(data) => Graph::build(data, &self.downstream, &self.expanded),
+            None => Graph::default(),
+        };
+        let columns = &self.graph.columns;
+        let column = self.selected.0.min(columns.len().saturating_sub(1));
+        let rows = columns.get(column).map_or(0, |column| column.nodes.len());
+        self.selected = (column, self.selected.1.min(rows.saturating_sub(1)));
+    }
+
+    fn select(&mut self, column: usize, row: usize) {
+        if let Some(nodes) = self.graph.columns.get(column).map(|column| &column.nodes) {
+            self.selected = (column, row.min(nodes.len().saturating_sub(1)));
+        }
+    }
+
+    /// Open the log of the selected job, or expand or collapse the selected bridge.
+    fn enter(&mut self) -> Result<()> {
+        let Some(node) = self.graph.node(self.selected) else {
+            return Ok(());
+        };
+        match node.kind {
+            NodeKind::Job => {
+                if let Some(command_tx) = &self.command_tx {
+                    command_tx.send(Action::OpenJobLog(node.gitlab_project.clone(), node.id))?;
+                }
+            }
+            NodeKind::Bridge {
+                downstream: false, ..
+            } => {}
+            NodeKind::Bridge { expanded, .. } => {
+                let id = node.id;
+                if expanded {
+                    self.expanded.remove(&id);
+                } else {
+                    self.expanded.insert(id);
+                    if !self.downstream.contains_key(&id) {
+                        self.request_downstream(id);
+                    }
+                }
+                self.rebuild();
+            }
+        }
+        Ok(())
+    }
+
+    fn close(&mut self, state: &mut State) {
+        state.view = state.previous_view;
+        if state.focused_component == ComponentId::PipelineGraph {
+            state.focused_component = state.view.component();
+        }
+    }
+
+    /// Scroll so that the selected node is visible in an area of the given size, whose first line
+    /// shows the stage titles.
+    fn scroll_to_selection(&mut self, width: usize, height: usize) {
+        let (column, row) = self.selected;
+        // Scroll by whole columns, so that nodes are never cut on the left
+        if column_x(column) < self.offset.0 {
+            self.offset.0 = column_x(column);
+        }
+        while column_x(column) + NODE_WIDTH > self.offset.0 + width
+            && self.offset.0 < column_x(column)
+        {
+            self.offset.0 += column_x(1);
+        }
+        let y = node_y(row);
+        if y < self.offset.1 + 1 {
+            self.offset.1 = y.saturating_sub(1);
+        } else if y >= self.offset.1 + height {
+            self.offset.1 = y + 1 - height;
+        }
+    }
+
+    fn draw_graph(&mut self, frame: &mut Frame, area: Rect) {
+        // Stage titles stay on the first line, the rest of the graph scrolls below them
+        self.scroll_to_selection(area.width as usize, area.height as usize);
+        let theme = &self.config.ui.theme;
+        let (offset_x, offset_y) = self.offset;
+        // Position on screen of a position on the canvas, if it is visible
+        let screen = |x: usize, y: usize| {
+            let x = x.checked_sub(offset_x)? + area.x as usize;
+            let y = y.checked_sub(offset_y)? + area.y as usize;
+            (x < area.right() as usize && y > area.y as usize && y < area.bottom() as usize)
+                .then_some((x as u16, y as u16))
+        };
+        let buffer = frame.buffer_mut();
+
+        let edge_style = theme.fg(theme.palette.pipelines_border);
+        for ((x, y), symbol) in self.graph.edge_cells() {
+            if let Some((x, y)) = screen(x, y) {
+                buffer[(x, y)].set_symbol(symbol).set_style(edge_style);
+            }
+        }
+
+        for (column_index, column) in self.graph.columns.iter().enumerate() {
+            let Some(x) = column_x(column_index)
+                .checked_sub(offset_x)
+                .map(|x| x + area.x as usize)
+                .filter(|x| *x < area.right() as usize)
+            else {
+                continue;
+            };
+            let width = NODE_WIDTH.min(area.right() as usize - x);
+            buffer.set_stringn(
+                x as u16,
+                area.y,
+                &column.title,
+                width,
+                Style::default().bold(),
+            );
+            for (row, node) in column.nodes.iter().enumerate() {
+                let Some((x, y)) = screen(column_x(column_index), node_y(row)) else {
+                    continue;
+                };
+                let mut style = theme.status_style(&node.status);
+                if (column_index, row) == self.selected {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+                let label = format!(
+                    "{} {:<width$}",
+                    theme.status_icon(&node.status),
+                    node.label(),
+                    width = NODE_WIDTH
+                );
+                buffer.set_stringn(x, y, label, width, style);
+            }
+        }
+    }
+}
+
+impl Component for PipelineGraphComponent {
+    fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
+        self.command_tx = Some(tx);
+        Ok(())
+    }
+
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.client = GitlabClient::new(config.core.gitlab_url.clone());
+        self.config = config;
+        Ok(())
+    }
+
+    fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>> {
+        let (column, row) = self.selected;
+        match action {
+            Action::Tick => self.refresh(state),
+            Action::Next => self.select(column, row + 1),
+            Action::Previous => self.select(column, row.saturating_sub(1)),
+            Action::SelectLeft => self.select(column.saturating_sub(1), row),
+            Action::SelectRight => self.select(column + 1, row),
+            Action::Enter => self.enter()?,
+            Action::Close => self.close(state),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn handle_mouse_event(
+        &mut self,
+        mouse: MouseEvent,
+        state: &mut State,
+    ) -> Result<Option<Action>> {
+        if state.popup.is_some() || !self.area.contains(Position::new(mouse.column, mouse.row)) {
+            return Ok(None);
+        }
+        let (column, row) = self.selected;
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                state.focused_component = ComponentId::PipelineGraph;
+                // The graph starts below the border and the padding
+                let x = (mouse.column - self.area.x).checked_sub(2);
+                let y = (mouse.row - self.area.y).checked_sub(2);
+                if let Some(position) = x.zip(y).and_then(|(x, y)| {
+                    self.graph
+                        .node_at(x as usize + self.offset.0, y as usize + self.offset.1)
+                }) {
+                    self.selected = position;
+                }
+            }
+            MouseEventKind::ScrollDown => self.select(column, row + 1),
+            MouseEventKind::ScrollUp => self.select(column, row.saturating_sub(1)),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect, state: &State) -> Result<()> {
+        let area = prepare_layout(area, Element::Body(Body::RightColumn), state)
+            .filter(|_| state.view == View::Graph);
+        self.area = area.unwrap_or_default();
+        let Some(area) = area else {
+            return Ok(());
+        };
+        let theme = &self.config.ui.theme;
+        let mut block = get_block(
+            state,
+            ComponentId::PipelineGraph,
+            theme.color(theme.palette.pipelines_border),
+        )
+        .padding(Padding::uniform(1));
+
+        let Some(data) = &self.data else {
+            let message = match &self.error {
+                Some(error) => Span::styled(
+                    format!("ERROR: {}", error),
+                    theme.fg(theme.palette.error).add_modifier(Modifier::BOLD),
+                ),
+                None => Span::styled(
+                    "Loading...",
+                    theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD),
+                ),
+            };
+            let paragraph = Paragraph::new(Line::from(message))
+                .block(block.title("Pipeline graph"))
+                .alignment(Alignment::Center);
+            frame.render_widget(paragraph, area);
+            return Ok(());
+        };
+
+        block = block.title(Line::from(vec![
+            Span::raw(format!(
+                "Pipeline {} on {} ",
+                data.pipeline.id, data.pipeline.git_ref
+            )),
+            Span::styled(
+                theme.status_icon(&data.pipeline.status).to_string(),
+                theme.status_style(&data.pipeline.status),
+            ),
+        ]));
+        if let Some(error) = &self.error {
+            block = block.title(Line::styled(
+                format!(" STALE ({}) ", error),
+                theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD),
+            ));
+        }
+        if let Some(node) = self.graph.node(self.selected) {
+            block = block.title_bottom(Line::from(format!("{} #{}", node.name, node.id)));
+        }
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        if self.graph.columns.is_empty() {
+            frame.render_widget(
+                Paragraph::new("No jobs").alignment(Alignment::Center),
+                inner,
+            );
+        } else {
+            self.draw_graph(frame, inner);
+        }
+        Ok(())
+    }
+}
+
+async fn fetch_graph(
+    client: GitlabClient,
+    gitlab_project: String,
+    pipeline_id: u32,
+) -> Result<GraphData> {
+    let (pipeline, jobs, bridges) = futures::try_join!(
+        client.fetch_pipeline(&gitlab_project, pipeline_id),
+        client.fetch_pipeline_jobs(&gitlab_project, pipeline_id, false),
+        client.fetch_pipeline_bridges(&gitlab_project, pipeline_id),
+    )?;
+    // Without their needs, jobs are still shown by stage
+    let needs = match pipeline.iid {
+        Some(iid) => client
+            .fetch_job_needs(&gitlab_project, iid)
+            .await
+            .unwrap_or_else(|error| {
+                tracing::warn!("Failed to fetch the needs of jobs: {}", error);
+                HashMap::new()
+            }),
+        None => HashMap::new(),
+    };
+    Ok(GraphData {
+        gitlab_project,
+        pipeline,
+        jobs,
+        bridges,
+        needs,
+    })
+}
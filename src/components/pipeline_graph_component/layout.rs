@@ -0,0 +1,459 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use super::GraphData;
+use crate::gitlab::PipelineStatus;
+
+/// Width of a node, including its status icon.
+pub const NODE_WIDTH: usize = 24;
+/// Space between two columns, where edges are drawn.
+const GAP_WIDTH: usize = 6;
+/// Nodes start below the title of their stage and a line left for edges.
+const FIRST_NODE_ROW: usize = 2;
+
+const UP: u8 = 1;
+const DOWN: u8 = 2;
+const LEFT: u8 = 4;
+const RIGHT: u8 = 8;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum NodeKind {
+    Job,
+    /// A job triggering a downstream pipeline, which can be shown in the graph as well.
+    Bridge {
+        expanded: bool,
+        downstream: bool,
+    },
+}
+
+pub struct Node {
+    pub id: u32,
+    pub name: String,
+    pub status: PipelineStatus,
+    pub gitlab_project: String,
+    pub kind: NodeKind,
+}
+
+impl Node {
+    /// Name of the node followed by whether its downstream pipeline is shown, shortened with an
+    /// ellipsis to fit next to the status icon.
+    pub fn label(&self) -> String {
+        let suffix = match self.kind {
+            NodeKind::Bridge {
+                downstream: true,
+                expanded: true,
+            } => " ▾",
+            NodeKind::Bridge {
+                downstream: true,
+                expanded: false,
+            } => " ▸",
+            _ => "",
+        };
+        let width = NODE_WIDTH - 2 - suffix.chars().count();
+        let name = if self.name.chars().count() > width {
+            let mut name: String = self.name.chars().take(width - 1).collect();
+            name.push('…');
+            name
+        } else {
+            self.name.clone()
+        };
+        format!("{}{}", name, suffix)
+    }
+}
+
+pub struct Column {
+    pub title: String,
+    pub nodes: Vec<Node>,
+}
+
+/// Index of the column of a node, then of the node within its column.
+pub type NodeIndex = (usize, usize);
+
+/// Jobs laid out by stage, one column per stage, and the edges between them. Positions on the
+/// canvas are given in terminal cells, the origin being the top left corner of the graph.
+#[derive(Default)]
+pub struct Graph {
+    pub columns: Vec<Column>,
+    pub edges: Vec<(NodeIndex, NodeIndex)>,
+}
+
+impl Graph {
+    /// Lay out the jobs of a pipeline, followed by the downstream pipelines of the expanded
+    /// bridges which are loaded already, keyed by bridge ID.
+    pub fn build(
+        root: &GraphData,
+        downstream: &HashMap<u32, GraphData>,
+        expanded: &HashSet<u32>,
+    ) -> Self {
+        let mut graph = Self::default();
+        graph.append(root, None, "", downstream, expanded);
+        graph
+    }
+
+    fn append(
+        &mut self,
+        data: &GraphData,
+        parent: Option<NodeIndex>,
+        prefix: &str,
+        downstream: &HashMap<u32, GraphData>,
+        expanded: &HashSet<u32>,
+    ) {
+        let first_column = self.columns.len();
+        let jobs = data.jobs.iter().map(|job| {
+            let node = Node {
+                id: job.id,
+                name: job.name.clone(),
+                status: job.status.clone(),
+                gitlab_project: data.gitlab_project.clone(),
+                kind: NodeKind::Job,
+            };
+            (job.stage.as_str(), node)
+        });
+        let bridges = data.bridges.iter().map(|bridge| {
+            let node = Node {
+                id: bridge.id,
+                name: bridge.name.clone(),
+                status: bridge.status.clone(),
+                gitlab_project: data.gitlab_project.clone(),
+                kind: NodeKind::Bridge {
+                    expanded: expanded.contains(&bridge.id),
+                    downstream: bridge.downstream_pipeline.is_some(),
+                },
+            };
+            (bridge.stage.as_str(), node)
+        });
+
+        // Stages run in the order in which their first job was created
+        let mut stages: Vec<(u32, &str, Vec<Node>)> = vec![];
+        for (stage, node) in jobs.chain(bridges) {
+            match stages.iter_mut().find(|(_, name, _)| *name == stage) {
+                Some((first_id, _, nodes)) => {
+                    *first_id = (*first_id).min(node.id);
+                    nodes.push(node);
+                }
+                None => stages.push((node.id, stage, vec![node])),
+            }
+        }
+        stages.sort_by_key(|(first_id, _, _)| *first_id);
+        for (_, stage, mut nodes) in stages {
+            nodes.sort_by(|a, b| a.name.cmp(&b.name));
+            self.columns.push(Column {
+                title: format!("{}{}", prefix, stage),
+                nodes,
+            });
+        }
+
+        // Jobs depend on the jobs they need, which run in the same or earlier stages of the same
+        // pipeline
+        for column in first_column..self.columns.len() {
+            for row in 0..self.columns[column].nodes.len() {
+                let Some(needs) = data.needs.get(&self.columns[column].nodes[row].name) else {
+                    continue;
+                };
+                for need in needs {
+                    if let Some(from) = self.find(first_column..column + 1, need) {
+                        self.edges.push((from, (column, row)));
+                    }
+                }
+            }
+        }
+        // A downstream pipeline starts with its first stage
+        if let Some(parent) = parent.filter(|_| first_column < self.columns.len()) {
+            for row in 0..self.columns[first_column].nodes.len() {
+                self.edges.push((parent, (first_column, row)));
+            }
+        }
+
+        // Downstream pipelines come after the pipeline triggering them
+        let mut bridges = vec![];
+        for (column_index, column) in self.columns.iter().enumerate().skip(first_column) {
+            for (row, node) in column.nodes.iter().enumerate() {
+                if let NodeKind::Bridge { expanded: true, .. } = node.kind {
+                    bridges.push(((column_index, row), node.id, node.name.clone()));
+                }
+            }
+        }
+        for (position, id, name) in bridges {
+            if let Some(data) = downstream.get(&id) {
+                let prefix = format!("{}{} › ", prefix, name);
+                self.append(data, Some(position), &prefix, downstream, expanded);
+            }
+        }
+    }
+
+    /// Index of the node with the given name in the last of the given columns containing one.
+    fn find(&self, columns: Range<usize>, name: &str) -> Option<NodeIndex> {
+        columns.rev().find_map(|column| {
+            let row = self.columns[column]
+                .nodes
+                .iter()
+                .position(|node| node.name == name)?;
+            Some((column, row))
+        })
+    }
+
+    pub fn node(&self, (column, row): NodeIndex) -> Option<&Node> {
+        self.columns.get(column)?.nodes.get(row)
+    }
+
+    /// Node drawn at a given position of the canvas, if any.
+    pub fn node_at(&self, x: usize, y: usize) -> Option<NodeIndex> {
+        let column = x / (NODE_WIDTH + GAP_WIDTH);
+        if x - column_x(column) >= NODE_WIDTH
+            || y < FIRST_NODE_ROW
+            || !(y - FIRST_NODE_ROW).is_multiple_of(2)
+        {
+            return None;
+        }
+        let position = (column, (y - FIRST_NODE_ROW) / 2);
+        self.node(position).map(|_| position)
+    }
+
+    /// Box-drawing symbols of the edges by position on the canvas.
+    pub fn edge_cells(&self) -> HashMap<(usize, usize), &'static str> {
+        let mut directions: HashMap<(usize, usize), u8> = HashMap::new();
+        let mut arrows = vec![];
+        for (from, to) in &self.edges {
+            let waypoints = route(*from, *to);
+            for segment in waypoints.windows(2) {
+                connect(&mut directions, segment[0], segment[1]);
+            }
+            if let [.., (before_x, _), end] = waypoints[..] {
+                arrows.push((end, if end.0 < before_x { "◀" } else { "▶" }));
+            }
+        }
+        let mut cells: HashMap<(usize, usize), &'static str> = directions
+            .into_iter()
+            .map(|(cell, directions)| (cell, symbol(directions)))
+            .collect();
+        cells.extend(arrows);
+        cells
+    }
+}
+
+/// Left side of a column on the canvas.
+pub fn column_x(column: usize) -> usize {
+    column * (NODE_WIDTH + GAP_WIDTH)
+}
+
+/// Line of a node on the canvas.
+pub fn node_y(row: usize) -> usize {
+    FIRST_NODE_ROW + row * 2
+}
+
+/// Cells where an edge changes direction, from the right of its source to the left of its
+/// target. Edges skipping columns run on the free line above their target, so that they do not
+/// cross the nodes in between. Edges within a column loop back to the right of their target.
+fn route(from: NodeIndex, to: NodeIndex) -> Vec<(usize, usize)> {
+    let start = (column_x(from.0) + NODE_WIDTH, node_y(from.1));
+    if to.0 == from.0 {
+        let end = (start.0, node_y(to.1));
+        return vec![start, (start.0 + 1, start.1), (start.0 + 1, end.1), end];
+    }
+    let end = (column_x(to.0) - 1, node_y(to.1));
+    let after_source = start.0 + GAP_WIDTH / 2;
+    if to.0 == from.0 + 1 {
+        return vec![start, (after_source, start.1), (after_source, end.1), end];
+    }
+    let before_target = column_x(to.0) - GAP_WIDTH / 2;
+    let lane = end.1 - 1;
+    vec![
+        start,
+        (after_source, start.1),
+        (after_source, lane),
+        (before_target, lane),
+        (before_target, end.1),
+        end,
+    ]
+}
+
+/// Record the directions in which the cells of a straight segment connect to their neighbours.
+fn connect(directions: &mut HashMap<(usize, usize), u8>, from: (usize, usize), to: (usize, usize)) {
+    let ((x1, y1), (x2, y2)) = (from.min(to), from.max(to));
+    if y1 == y2 {
+        for x in x1..x2 {
+            *directions.entry((x, y1)).or_default() |= RIGHT;
+            *directions.entry((x + 1, y1)).or_default() |= LEFT;
+        }
+    } else {
+        for y in y1..y2 {
+            *directions.entry((x1, y)).or_default() |= DOWN;
+            *directions.entry((x1, y + 1)).or_default() |= UP;
+        }
+    }
+}
+
+fn symbol(directions: u8) -> &'static str {
+    match directions {
+        d if d == UP | DOWN | LEFT | RIGHT => "┼",
+        d if d == UP | DOWN | RIGHT => "├",
+        d if d == UP | DOWN | LEFT => "┤",
+        d if d == LEFT | RIGHT | DOWN => "┬",
+        d if d == LEFT | RIGHT | UP => "┴",
+        d if d == DOWN | RIGHT => "┌",
+        d if d == DOWN | LEFT => "┐",
+        d if d == UP | RIGHT => "└",
+        d if d == UP | LEFT => "┘",
+        d if d & (UP | DOWN) != 0 => "│",
+        _ => "─",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn data(
+        jobs: &[(u32, &str, &str)],
+        bridges: &[(u32, &str, &str)],
+        needs: &[(&str, &str)],
+    ) -> GraphData {
+        let job = |&(id, name, stage): &(u32, &str, &str)| {
+            json!({
+                "id": id,
+                "name": name,
+                "stage": stage,
+                "status": "success",
+                "web_url": "",
+                "created_at": "2024-01-01T00:00:00Z",
+                "downstream_pipeline": {
+                    "id": id * 100,
+                    "project_id": 1,
+                    "status": "success",
+                    "web_url": "",
+                },
+            })
+        };
+        let mut all_needs: HashMap<String, Vec<String>> = HashMap::new();
+        for (name, need) in needs {
+            all_needs
+                .entry(name.to_string())
+                .or_default()
+                .push(need.to_string());
+        }
+        GraphData {
+            gitlab_project: "group/project".to_string(),
+            pipeline: serde_json::from_value(json!({
+                "id": 1,
+                "status": "success",
+                "source": "push",
+                "ref": "main",
+                "web_url": "",
+                "created_at": "2024-01-01T00:00:00Z",
+                "updated_at": "2024-01-01T00:00:00Z",
+            }))
+            .unwrap(),
+            jobs: jobs
+                .iter()
+                .map(|j| serde_json::from_value(job(j)).unwrap())
+                .collect(),
+            bridges: bridges
+                .iter()
+                .map(|b| serde_json::from_value(job(b)).unwrap())
+                .collect(),
+            needs: all_needs,
+        }
+    }
+
+    fn names(graph: &Graph) -> Vec<(String, Vec<String>)> {
+        graph
+            .columns
+            .iter()
+            .map(|column| {
+                let nodes = column.nodes.iter().map(|node| node.name.clone()).collect();
+                (column.title.clone(), nodes)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lays_out_stages_in_order_with_needs_within_and_across_them() {
+        let root = data(
+            &[
+                (3, "unit", "test"),
+                (1, "compile", "build"),
+                (4, "lint", "test"),
+            ],
+            &[],
+            &[("unit", "compile"), ("lint", "unit")],
+        );
+        let graph = Graph::build(&root, &HashMap::new(), &HashSet::new());
+        assert_eq!(
+            names(&graph),
+            [
+                ("build".to_string(), vec!["compile".to_string()]),
+                (
+                    "test".to_string(),
+                    vec!["lint".to_string(), "unit".to_string()]
+                ),
+            ]
+        );
+        assert_eq!(graph.edges, [((1, 1), (1, 0)), ((0, 0), (1, 1))]);
+    }
+
+    #[test]
+    fn appends_the_downstream_pipelines_of_expanded_bridges() {
+        let root = data(&[(1, "compile", "build")], &[(2, "trigger", "deploy")], &[]);
+        let child = data(&[(5, "apply", "deploy"), (6, "verify", "check")], &[], &[]);
+        let graph = Graph::build(&root, &HashMap::from([(2, child)]), &HashSet::from([2]));
+        let titles: Vec<String> = names(&graph).into_iter().map(|(title, _)| title).collect();
+        assert_eq!(
+            titles,
+            ["build", "deploy", "trigger › deploy", "trigger › check"]
+        );
+        assert_eq!(graph.edges, [((1, 0), (2, 0))]);
+        assert!(matches!(
+            graph.columns[1].nodes[0].kind,
+            NodeKind::Bridge {
+                expanded: true,
+                downstream: true
+            }
+        ));
+    }
+
+    #[test]
+    fn routes_edges_around_the_nodes() {
+        // Next column: through the gap between the columns
+        assert_eq!(route((0, 0), (1, 2)), [(24, 2), (27, 2), (27, 6), (29, 6)]);
+        // Columns further: along the free line above the target
+        assert_eq!(
+            route((0, 0), (2, 1)),
+            [(24, 2), (27, 2), (27, 3), (57, 3), (57, 4), (59, 4)]
+        );
+        // Same column: back to the right of the target
+        assert_eq!(route((1, 1), (1, 0)), [(54, 4), (55, 4), (55, 2), (54, 2)]);
+    }
+
+    #[test]
+    fn points_arrows_at_the_targets() {
+        let graph = Graph {
+            columns: vec![],
+            edges: vec![((0, 0), (1, 0)), ((1, 1), (1, 0))],
+        };
+        let cells = graph.edge_cells();
+        assert_eq!(cells[&(29, 2)], "▶");
+        assert_eq!(cells[&(54, 2)], "◀");
+        assert_eq!(cells[&(55, 3)], "│");
+    }
+
+    #[test]
+    fn shortens_long_names_with_an_ellipsis() {
+        let node = |name: &str, kind| Node {
+            id: 1,
+            name: name.to_string(),
+            status: PipelineStatus::Success,
+            gitlab_project: String::new(),
+            kind,
+        };
+        assert_eq!(node("unit", NodeKind::Job).label(), "unit");
+        let long = "integration-tests-postgres-16";
+        assert_eq!(node(long, NodeKind::Job).label(), "integration-tests-pos…");
+        let bridge = NodeKind::Bridge {
+            expanded: false,
+            downstream: true,
+        };
+        assert_eq!(node(long, bridge).label(), "integration-tests-p… ▸");
+    }
+}
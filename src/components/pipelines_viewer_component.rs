@@ -1,29 +1,91 @@
-use color_eyre::Result;
+use chrono::{DateTime, Utc};
+use color_eyre::{eyre::Error, Result};
+use crossterm::event::{KeyEvent, MouseButton, MouseEvent, MouseEventKind};
+use futures::{stream, StreamExt};
 use layout::Flex;
 use ratatui::{prelude::*, widgets::*};
-use std::cmp::{max, min};
+use std::cmp::{max, min, Reverse};
+use std::collections::HashMap;
 use std::string::ToString;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::UnboundedSender;
 
-use super::utils::{get_block, prepare_layout, Body, Element};
-use super::Component;
-use crate::gitlab::fetch_pipelines;
-use crate::state::State;
+use super::utils::{
+    format_duration, format_timestamp, get_block, popup_area, prepare_layout, Body, ClickTracker,
+    Element, PendingRequest, Search, Seek,
+};
+use super::{Component, ComponentId};
+use crate::cache::{Cache, CacheKind};
+use crate::gitlab::{is_unreachable, GitlabClient, GitlabPipeline, Page};
+use crate::state::{PipelineRef, State, View};
 use crate::{
     action::Action,
-    config::Config,
-    gitlab::{PipelineStatus, PipelinesData},
+    config::{Config, PipelinesConfig},
+    gitlab::PipelinesData,
+    theme::Theme,
 };
 
+use columns::SortOrder;
+use commit::CommitTab;
+use downstream::{DownstreamPipeline, DownstreamPipelines, PipelineBridges, TableRow};
+use pending_jobs::PendingJobs;
+use test_report::TestReportTab;
+
+/// Number of pipeline details fetched at the same time.
+const CONCURRENT_REQUESTS: usize = 8;
+
+mod columns;
+mod commit;
+mod downstream;
+mod pending_jobs;
+mod test_report;
+
+/// Tabs of the pipeline details popup.
+#[derive(Default, Clone, Copy, PartialEq)]
+enum DetailsTab {
+    #[default]
+    Details,
+    Tests,
+    Commit,
+}
+
 #[derive(Default)]
 pub struct PipelinesViewerComponent {
     command_tx: Option<UnboundedSender<Action>>,
     config: Config,
+    client: GitlabClient,
+    cache: Cache,
     pub active_operation_index: usize,
     pub active_filters: Vec<String>,
     pub active_page: usize, // add 1 to this, as default will make it 0
+    pub total_pages: usize,
     pub pipelines_data: PipelinesData,
     show_details_popup: bool,
+    details_tab: DetailsTab,
+    test_report: TestReportTab,
+    commit: CommitTab,
+    pending_jobs: PendingJobs,
+    // Project for which `pipelines_data` was loaded
+    loaded_project: Option<String>,
+    request: Option<PendingRequest<Page<GitlabPipeline>>>,
+    last_request_at: Option<Instant>,
+    fetched_at: Option<DateTime<Utc>>,
+    // Pipelines fetched one by one, for the columns which are missing from lists of pipelines
+    details: HashMap<u32, GitlabPipeline>,
+    details_request: Option<PendingRequest<Vec<GitlabPipeline>>>,
+    // Index of the column the pipelines are sorted by, if any, and the sort order
+    sort: Option<(usize, SortOrder)>,
+    // Pipelines triggered by the ones on the current page, and the resulting rows of the table
+    downstream: DownstreamPipelines,
+    rows: Vec<TableRow>,
+    bridges_request: Option<PendingRequest<PipelineBridges>>,
+    downstream_requests: Vec<PendingRequest<Vec<DownstreamPipeline>>>,
+    table_state: TableState,
+    search: Search,
+    // Areas computed while drawing, used for mouse hit-testing
+    area: Rect,
+    paginator_hitboxes: Vec<(u16, u16, usize)>,
+    clicks: ClickTracker,
 }
 
 impl PipelinesViewerComponent {
@@ -31,47 +93,534 @@ impl PipelinesViewerComponent {
         Self::default()
     }
 
-    fn load_pipelines_data(&mut self, state: &State) {
-        match &state.active_gitlab_project {
-            Some(gitlab_project) => {
-                match fetch_pipelines(
-                    self.config.core.gitlab_url.clone(),
-                    gitlab_project.clone(),
-                    self.config.ui.max_page_size,
-                ) {
-                    Ok(results) => self.pipelines_data = PipelinesData::Loaded(results),
-                    Err(error) => self.pipelines_data = PipelinesData::Errors(error),
+    /// Refresh pipelines data: switch to the cached pipelines whenever the active project changes,
+    /// fetch fresh data periodically, and pick up the results of requests that have completed.
+    fn refresh_pipelines_data(&mut self, state: &mut State) {
+        if self.loaded_project != state.active_gitlab_project {
+            self.loaded_project = state.active_gitlab_project.clone();
+            self.active_operation_index = 0;
+            self.active_page = 0;
+            self.total_pages = 0;
+            self.request = None;
+            self.last_request_at = None;
+            self.fetched_at = None;
+            self.details.clear();
+            self.details_request = None;
+            self.downstream = DownstreamPipelines::default();
+            self.bridges_request = None;
+            self.downstream_requests.clear();
+            self.pipelines_data = match &self.loaded_project {
+                Some(gitlab_project) => self
+                    .cache
+                    .load::<Vec<GitlabPipeline>>(gitlab_project, CacheKind::Pipelines)
+                    .map_or(PipelinesData::Loading, |entry| PipelinesData::Stale {
+                        pipelines: entry.data,
+                        fetched_at: entry.fetched_at,
+                        error: None,
+                    }),
+                None => PipelinesData::Errors(Error::msg("Project not selected")),
+            };
+            self.sort_pipelines();
+            self.rebuild_rows();
+        }
+        let Some(gitlab_project) = self.loaded_project.clone() else {
+            return;
+        };
+
+        if let Some(result) = self.request.as_mut().and_then(PendingRequest::poll) {
+            self.request = None;
+            self.handle_pipelines_result(&gitlab_project, result, state);
+            self.request_details(&gitlab_project);
+            if self.bridges_request.is_none() {
+                self.request_bridges(false);
+            }
+        }
+
+        if let Some(result) = self.details_request.as_mut().and_then(PendingRequest::poll) {
+            self.details_request = None;
+            match result {
+                Ok(pipelines) => {
+                    self.details.extend(
+                        pipelines
+                            .into_iter()
+                            .map(|pipeline| (pipeline.id, pipeline)),
+                    );
+                    self.apply_details();
+                    self.sort_pipelines();
+                    self.rebuild_rows();
                 }
+                Err(error) => tracing::warn!("Failed to fetch pipeline details: {}", error),
             }
-            None => {
-                self.pipelines_data = PipelinesData::Errors(Error::msg("Project not selected"));
+        }
+
+        if let Some(result) = self.bridges_request.as_mut().and_then(PendingRequest::poll) {
+            self.bridges_request = None;
+            match result {
+                Ok(bridges) => {
+                    self.downstream.bridges.extend(bridges);
+                    self.rebuild_rows();
+                    self.request_downstream();
+                    // Go down one more level, to sum up the status of nested pipelines
+                    self.request_bridges(true);
+                }
+                Err(error) => tracing::warn!("Failed to fetch bridges: {}", error),
             }
-        };
+        }
+
+        let mut finished = vec![];
+        self.downstream_requests
+            .retain_mut(|request| match request.poll() {
+                Some(result) => {
+                    finished.push(result);
+                    false
+                }
+                None => true,
+            });
+        for result in finished {
+            match result {
+                Ok(pipelines) => {
+                    self.downstream.pipelines.extend(
+                        pipelines
+                            .into_iter()
+                            .map(|downstream| (downstream.pipeline.id, downstream)),
+                    );
+                    self.rebuild_rows();
+                    // Tell whether the new rows triggered pipelines in turn
+                    if self.bridges_request.is_none() {
+                        self.request_bridges(true);
+                    }
+                }
+                Err(error) => tracing::warn!("Failed to fetch downstream pipelines: {}", error),
+            }
+        }
+
+        let refresh_interval = Duration::from_secs(self.config.core.refresh_interval);
+        let refresh_due = self
+            .last_request_at
+            .is_none_or(|requested_at| requested_at.elapsed() >= refresh_interval);
+        if self.request.is_none() && refresh_due {
+            let client = self.client.clone();
+            let page_size = self.config.ui.max_page_size;
+            let page = self.active_page + 1;
+            let order = self.api_order();
+            self.last_request_at = Some(Instant::now());
+            self.request = Some(PendingRequest::spawn(async move {
+                client
+                    .fetch_pipelines_page(&gitlab_project, page_size, page, order)
+                    .await
+            }));
+        }
     }
 
-    fn next(&mut self, state: &State) {
-        if state.focused_component == 2 {
-            if let PipelinesData::Loaded(pipelines) = &self.pipelines_data {
-                if self.active_operation_index < pipelines.len() - 1 {
-                    self.active_operation_index += 1;
+    fn handle_pipelines_result(
+        &mut self,
+        gitlab_project: &str,
+        result: Result<Page<GitlabPipeline>>,
+        state: &mut State,
+    ) {
+        match result {
+            Ok(Page {
+                items: pipelines,
+                total_pages,
+            }) => {
+                state.offline = false;
+                // Only the first page in the order from GitLab is cached, as this is what is shown
+                // on startup
+                if self.active_page == 0 && self.api_order().is_none() {
+                    if let Err(error) =
+                        self.cache
+                            .store(gitlab_project, CacheKind::Pipelines, &pipelines)
+                    {
+                        tracing::warn!("Failed to cache pipelines: {}", error);
+                    }
                 }
+                // Without a total, assume there is another page as long as this one is full
+                let has_more = pipelines.len() >= self.config.ui.max_page_size;
+                self.total_pages = total_pages
+                    .unwrap_or(self.active_page + 1 + usize::from(has_more))
+                    .max(1);
+                self.fetched_at = Some(Utc::now());
+                self.pipelines_data = PipelinesData::Loaded(pipelines);
+                self.apply_details();
+                self.sort_pipelines();
+                self.rebuild_rows();
+            }
+            Err(error) => {
+                state.offline = is_unreachable(&error);
+                // Keep showing whatever we had, but mark it as stale
+                self.pipelines_data = match std::mem::take(&mut self.pipelines_data) {
+                    PipelinesData::Loaded(pipelines) => PipelinesData::Stale {
+                        pipelines,
+                        fetched_at: self.fetched_at.unwrap_or_else(Utc::now),
+                        error: Some(error),
+                    },
+                    PipelinesData::Stale {
+                        pipelines,
+                        fetched_at,
+                        ..
+                    } => PipelinesData::Stale {
+                        pipelines,
+                        fetched_at,
+                        error: Some(error),
+                    },
+                    _ => PipelinesData::Errors(error),
+                };
+            }
+        }
+    }
+
+    /// Fetch the details of pipelines on the current page which are needed by the columns, or
+    /// of the selected one when its details are shown, unless they are known already.
+    fn request_details(&mut self, gitlab_project: &str) {
+        if self.details_request.is_some() {
+            return;
+        }
+        let Some(pipelines) = self.pipelines_data.pipelines() else {
+            return;
+        };
+        let needs_details = self
+            .config
+            .ui
+            .pipelines
+            .columns
+            .iter()
+            .any(|column| column.column.needs_details());
+        // Downstream pipelines are always fetched one by one, so only the details of the
+        // pipelines of the project can be missing
+        let selected = self
+            .rows
+            .get(self.active_operation_index)
+            .map(|row| row.pipeline.id);
+        let pipelines: Vec<&GitlabPipeline> = if needs_details {
+            pipelines.iter().collect()
+        } else if self.show_details_popup {
+            pipelines
+                .iter()
+                .filter(|pipeline| Some(pipeline.id) == selected)
+                .collect()
+        } else {
+            return;
+        };
+        // Details of a pipeline change along with its status
+        let outdated: Vec<u32> = pipelines
+            .iter()
+            .filter(|pipeline| {
+                self.details
+                    .get(&pipeline.id)
+                    .is_none_or(|details| details.updated_at != pipeline.updated_at)
+            })
+            .map(|pipeline| pipeline.id)
+            .collect();
+        if outdated.is_empty() {
+            return;
+        }
+        let client = self.client.clone();
+        let gitlab_project = gitlab_project.to_string();
+        // A pipeline whose details cannot be fetched keeps the columns of the list
+        self.details_request = Some(PendingRequest::spawn(async move {
+            Ok(stream::iter(outdated)
+                .map(|id| {
+                    let client = &client;
+                    let gitlab_project = &gitlab_project;
+                    async move {
+                        client
+                            .fetch_pipeline(gitlab_project, id)
+                            .await
+                            .inspect_err(|error| {
+                                tracing::warn!(
+                                    "Failed to fetch the details of pipeline {}: {}",
+                                    id,
+                                    error
+                                )
+                            })
+                            .ok()
+                    }
+                })
+                .buffered(CONCURRENT_REQUESTS)
+                .filter_map(futures::future::ready)
+                .collect()
+                .await)
+        }));
+    }
+
+    /// Replace the pipelines on the current page with their details, if they are up to date.
+    fn apply_details(&mut self) {
+        let Some(pipelines) = self.pipelines_data.pipelines_mut() else {
+            return;
+        };
+        for pipeline in pipelines.iter_mut() {
+            if let Some(details) = self
+                .details
+                .get(&pipeline.id)
+                .filter(|details| details.updated_at == pipeline.updated_at)
+            {
+                *pipeline = details.clone();
             }
         }
     }
 
-    fn previous(&mut self, state: &State) {
-        if state.focused_component == 2 {
-            if let PipelinesData::Loaded(_) = &self.pipelines_data {
-                if self.active_operation_index > 0 {
-                    self.active_operation_index -= 1;
+    /// Field and direction GitLab sorts the pipelines by, if the sort column is one it supports.
+    fn api_order(&self) -> Option<(&'static str, &'static str)> {
+        let (index, order) = self.sort?;
+        let order_by = self.config.ui.pipelines.columns[index].column.order_by()?;
+        Some((order_by, order.api_name()))
+    }
+
+    /// Sort the pipelines on the current page by a column GitLab cannot sort by, or restore the
+    /// order from GitLab (the newest first) when no sort column is selected. Pipelines sorted by
+    /// GitLab are kept in its order.
+    fn sort_pipelines(&mut self) {
+        let columns = &self.config.ui.pipelines.columns;
+        let Some(pipelines) = self.pipelines_data.pipelines_mut() else {
+            return;
+        };
+        match self.sort {
+            Some((index, order)) => {
+                let column = columns[index].column;
+                if column.order_by().is_none() {
+                    pipelines.sort_by(|a, b| match order {
+                        SortOrder::Ascending => column.compare(a, b),
+                        SortOrder::Descending => column.compare(b, a),
+                    });
                 }
             }
+            None => pipelines.sort_by_key(|pipeline| Reverse(pipeline.id)),
+        }
+    }
+
+    /// Sort by another column, or in another order. Changing how GitLab sorts the pipelines
+    /// fetches them again from the first page, whereas other sorts apply to the current page.
+    fn set_sort(&mut self, sort: Option<(usize, SortOrder)>) {
+        let api_order = self.api_order();
+        self.sort = sort;
+        if self.api_order() != api_order {
+            self.active_page = 0;
+            self.active_operation_index = 0;
+            self.request = None;
+            self.last_request_at = None;
+            self.pipelines_data = PipelinesData::Loading;
+        } else {
+            self.sort_pipelines();
+        }
+        self.rebuild_rows();
+    }
+
+    /// Lay out the rows of the table again, e.g. after pipelines were fetched, sorted or
+    /// expanded. The selected pipeline stays selected wherever its row ends up.
+    fn rebuild_rows(&mut self) {
+        let selected = self
+            .rows
+            .get(self.active_operation_index)
+            .map(|row| (row.gitlab_project.clone(), row.pipeline.id));
+        self.rows = match (&self.loaded_project, self.pipelines_data.pipelines()) {
+            (Some(gitlab_project), Some(pipelines)) => {
+                self.downstream.rows(gitlab_project, pipelines)
+            }
+            _ => vec![],
+        };
+        let index = selected.and_then(|(gitlab_project, id)| {
+            self.rows
+                .iter()
+                .position(|row| row.pipeline.id == id && row.gitlab_project == gitlab_project)
+        });
+        self.active_operation_index = index.unwrap_or(min(
+            self.active_operation_index,
+            self.rows.len().saturating_sub(1),
+        ));
+    }
+
+    /// Fetch the bridges of the pipelines in the table, to learn about their downstream
+    /// pipelines.
+    fn request_bridges(&mut self, only_unknown: bool) {
+        let pipelines = self.downstream.outdated_bridges(&self.rows, only_unknown);
+        if pipelines.is_empty() {
+            return;
+        }
+        let client = self.client.clone();
+        self.bridges_request = Some(PendingRequest::spawn(downstream::fetch_bridges(
+            client, pipelines,
+        )));
+    }
+
+    /// Fetch the downstream pipelines of the expanded rows which are not up to date.
+    fn request_downstream(&mut self) {
+        let pipelines = self.downstream.outdated_pipelines();
+        if pipelines.is_empty() {
+            return;
+        }
+        let client = self.client.clone();
+        let project_paths = self.downstream.project_paths(&self.rows);
+        self.downstream_requests.push(PendingRequest::spawn(
+            downstream::fetch_downstream_pipelines(client, pipelines, project_paths),
+        ));
+    }
+
+    /// Show or hide the downstream pipelines of the selected pipeline.
+    fn toggle_expand(&mut self) {
+        let Some(row) = self.rows.get(self.active_operation_index) else {
+            return;
+        };
+        if !row.expandable {
+            return;
+        }
+        let id = row.pipeline.id;
+        if !self.downstream.expanded.remove(&id) {
+            self.downstream.expanded.insert(id);
+            self.request_downstream();
+        }
+        self.rebuild_rows();
+    }
+
+    /// Switch to the project of the selected downstream pipeline, which does not need to be in
+    /// the project list.
+    fn open_project(&mut self, state: &mut State) {
+        let Some(row) = self.rows.get(self.active_operation_index) else {
+            return;
+        };
+        if state.active_gitlab_project.as_ref() == Some(&row.gitlab_project) {
+            return;
+        }
+        if let Some(previous) = state
+            .active_gitlab_project
+            .replace(row.gitlab_project.clone())
+        {
+            state.project_history.push(previous);
+        }
+    }
+
+    /// Go back to the project from which the current one was opened.
+    fn go_back(&mut self, state: &mut State) {
+        if let Some(previous) = state.project_history.pop() {
+            state.active_gitlab_project = Some(previous);
+        }
+    }
+
+    /// Sort by the next column in ascending order, going back to the order from GitLab after
+    /// the last one.
+    fn sort_by_next_column(&mut self) {
+        let next_index = self.sort.map_or(0, |(index, _)| index + 1);
+        self.set_sort(
+            (next_index < self.config.ui.pipelines.columns.len())
+                .then_some((next_index, SortOrder::Ascending)),
+        );
+    }
+
+    fn reverse_sort_order(&mut self) {
+        if let Some((index, order)) = self.sort {
+            self.set_sort(Some((index, order.reversed())));
+        }
+    }
+
+    fn next(&mut self) {
+        if self.active_operation_index + 1 < self.rows.len() {
+            self.active_operation_index += 1;
+        }
+    }
+
+    fn previous(&mut self) {
+        self.active_operation_index = self.active_operation_index.saturating_sub(1);
+    }
+
+    /// Select a pipeline matching the search.
+    fn jump_to_match(&mut self, seek: Seek) {
+        let (rows, search) = (&self.rows, &self.search);
+        if let Some(index) = search.find(rows.len(), self.active_operation_index, seek, |index| {
+            rows[index].matches(search)
+        }) {
+            self.active_operation_index = index;
         }
     }
 
     fn show_details(&mut self, state: &mut State) {
-        let projects = &self.config.core.gitlab_projects;
-        state.active_gitlab_project = Some(projects[self.active_operation_index].clone());
+        if !self.rows.is_empty() {
+            self.show_details_popup = true;
+            state.popup = Some(ComponentId::PipelinesViewer);
+            if let Some(gitlab_project) = self.loaded_project.clone() {
+                self.request_details(&gitlab_project);
+            }
+        }
+    }
+
+    /// Replace the table with the graph of the jobs of the selected pipeline.
+    fn show_graph(&mut self, state: &mut State) {
+        let Some(row) = self.rows.get(self.active_operation_index) else {
+            return;
+        };
+        state.show_graph(PipelineRef {
+            gitlab_project: row.gitlab_project.clone(),
+            id: row.pipeline.id,
+        });
+    }
+
+    /// Open the artifacts of the jobs of the selected pipeline, in place of its details if they
+    /// are shown.
+    fn show_artifacts(&mut self, state: &mut State) -> Option<Action> {
+        let row = self.rows.get(self.active_operation_index)?;
+        let action = Action::OpenArtifacts(row.gitlab_project.clone(), row.pipeline.id);
+        self.hide_details(state);
+        Some(action)
+    }
+
+    fn next_tab(&mut self) {
+        self.details_tab = match self.details_tab {
+            DetailsTab::Details => DetailsTab::Tests,
+            DetailsTab::Tests => DetailsTab::Commit,
+            DetailsTab::Commit => DetailsTab::Details,
+        };
+    }
+
+    /// Keep the test report, the commit, or the explanation of the pending jobs, in line with the
+    /// selected pipeline while it is shown.
+    fn refresh_details_tab(&mut self) {
+        if !self.show_details_popup {
+            return;
+        }
+        let Some(row) = self.rows.get(self.active_operation_index) else {
+            return;
+        };
+        match self.details_tab {
+            DetailsTab::Details => {
+                self.pending_jobs.load(&row.gitlab_project, &row.pipeline);
+                self.pending_jobs.poll();
+            }
+            DetailsTab::Tests => {
+                self.test_report.load(&row.gitlab_project, &row.pipeline);
+                self.test_report.poll();
+            }
+            DetailsTab::Commit => {
+                self.commit.load(&row.gitlab_project, &row.pipeline);
+                self.commit.poll();
+            }
+        }
+    }
+
+    fn hide_details(&mut self, state: &mut State) {
+        self.show_details_popup = false;
+        state.popup = None;
+    }
+
+    /// Switch to another (0-based) page and fetch its pipelines.
+    fn go_to_page(&mut self, page: usize) {
+        if page == self.active_page || page >= self.total_pages {
+            return;
+        }
+        self.active_page = page;
+        self.active_operation_index = 0;
+        self.request = None;
+        self.last_request_at = None;
+        self.pipelines_data = PipelinesData::Loading;
+    }
+
+    /// Index of the pipeline displayed in a given terminal row, if any.
+    fn row_at(&self, row: u16) -> Option<usize> {
+        // Rows start below the border, the padding and the header row
+        let first_row = self.area.y + 3;
+        let last_row = self.area.bottom().saturating_sub(2);
+        if row < first_row || row >= last_row {
+            return None;
+        }
+        let index = (row - first_row) as usize + self.table_state.offset();
+        (index < self.rows.len()).then_some(index)
     }
 }
 
@@ -82,138 +631,257 @@ impl Component for PipelinesViewerComponent {
     }
 
     fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.client = GitlabClient::new(config.core.gitlab_url.clone());
+        self.cache = Cache::new(config.cache.enabled);
+        self.test_report = TestReportTab::new(self.client.clone());
+        self.commit = CommitTab::new(self.client.clone());
+        self.pending_jobs = PendingJobs::new(self.client.clone());
         self.config = config;
         Ok(())
     }
 
+    fn handle_key_event(&mut self, key: KeyEvent, _state: &mut State) -> Result<Option<Action>> {
+        if self.search.handle_key(key) {
+            self.jump_to_match(Seek::Current);
+        }
+        Ok(None)
+    }
+
     fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>> {
+        // Keys bound while typing the search only apply to it
+        if self.search.is_typing() {
+            match action {
+                Action::Enter => self.search.finish(state),
+                Action::Close => self.search.cancel(state),
+                Action::Next => self.jump_to_match(Seek::Next),
+                Action::Previous => self.jump_to_match(Seek::Previous),
+                Action::Tick => self.refresh_pipelines_data(state),
+                _ => {}
+            }
+            return Ok(None);
+        }
+        if self.show_details_popup {
+            if action == Action::ToggleNewFailures {
+                self.details_tab = DetailsTab::Tests;
+            }
+            if self.details_tab == DetailsTab::Tests && self.test_report.update(&action) {
+                return Ok(None);
+            }
+        }
         match action {
-            Action::Next => self.next(state),
-            Action::Previous => self.previous(state),
+            Action::Next => self.next(),
+            Action::Previous => self.previous(),
             Action::Enter => self.show_details(state),
-            Action::FocusUp => state.focused_component = 0, // change to header
-            Action::FocusDown => state.focused_component = 3, // change to footer
-            Action::FocusLeft => state.focused_component = 1, // change to project selector
+            Action::Search if !self.show_details_popup => self.search.start(state),
+            Action::NextMatch => self.jump_to_match(Seek::Next),
+            Action::PreviousMatch => self.jump_to_match(Seek::Previous),
+            Action::ShowGraph if !self.show_details_popup => self.show_graph(state),
+            Action::ShowArtifacts => return Ok(self.show_artifacts(state)),
+            Action::ToggleExpand if !self.show_details_popup => self.toggle_expand(),
+            Action::OpenProject if !self.show_details_popup => self.open_project(state),
+            Action::GoBack if !self.show_details_popup => self.go_back(state),
+            Action::Close => self.hide_details(state),
+            Action::NextTab if self.show_details_popup => self.next_tab(),
+            Action::NextPage => self.go_to_page(self.active_page + 1),
+            Action::PreviousPage => self.go_to_page(self.active_page.saturating_sub(1)),
+            Action::SortByNextColumn => self.sort_by_next_column(),
+            Action::ReverseSortOrder => self.reverse_sort_order(),
             Action::Tick => {
-                // add any logic here that should run on every tick
+                self.refresh_pipelines_data(state);
+                self.refresh_details_tab();
             }
-            Action::Render => {
-                self.load_pipelines_data(state);
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn handle_mouse_event(
+        &mut self,
+        mouse: MouseEvent,
+        state: &mut State,
+    ) -> Result<Option<Action>> {
+        if state.popup.is_some() || !self.area.contains(Position::new(mouse.column, mouse.row)) {
+            return Ok(None);
+        }
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                state.focused_component = ComponentId::PipelinesViewer;
+                let paginator_hit = self
+                    .paginator_hitboxes
+                    .iter()
+                    .find(|(start, end, _)| (*start..*end).contains(&mouse.column))
+                    .filter(|_| mouse.row == self.area.bottom() - 1);
+                if let Some((_, _, page)) = paginator_hit {
+                    self.go_to_page(*page);
+                } else if let Some(index) = self.row_at(mouse.row) {
+                    self.active_operation_index = index;
+                    if self.clicks.click(mouse.column, mouse.row) {
+                        self.show_details(state);
+                    }
+                }
             }
+            MouseEventKind::ScrollDown => self.next(),
+            MouseEventKind::ScrollUp => self.previous(),
             _ => {}
         }
         Ok(None)
     }
 
     fn draw(&mut self, frame: &mut Frame, area: Rect, state: &State) -> Result<()> {
-        let area = prepare_layout(area, Element::Body(Body::RightColumn));
-        let block = get_block(state, 2, Color::Green);
+        let area = prepare_layout(area, Element::Body(Body::RightColumn), state)
+            .filter(|_| state.view == View::Pipelines);
+        self.area = area.unwrap_or_default();
+        self.paginator_hitboxes.clear();
+        let Some(area) = area else {
+            return Ok(());
+        };
+        let theme = &self.config.ui.theme;
+        let block = get_block(
+            state,
+            ComponentId::PipelinesViewer,
+            theme.color(theme.palette.pipelines_border),
+        );
         match &self.pipelines_data {
             PipelinesData::Loading => {
                 let loading_message = vec![Line::from(Span::styled(
                     "Loading...",
-                    Style::default()
-                        .fg(Color::Yellow)
-                        .add_modifier(Modifier::BOLD),
+                    theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD),
                 ))];
                 let paragraph = Paragraph::new(loading_message)
                     .block(block)
                     .alignment(Alignment::Center);
                 frame.render_widget(paragraph, area);
             }
-            PipelinesData::Loaded(pipelines) => {
-                let header_row = vec![
-                    "ID",
-                    "Status",
-                    "Source",
-                    "Ref",
-                    "Created at",
-                    "Updated at",
-                    "URL",
-                ]
-                .into_iter()
-                .map(|e| Span::styled(e, Style::default().bold()))
-                .collect();
-
-                let rows = pipelines.iter().enumerate().map(|(i, pipeline)| {
-                    let hightlight_style = if i == self.active_operation_index {
-                        Style::default().fg(Color::Black).bg(Color::LightYellow)
-                    } else {
-                        Style::default()
-                    };
-                    let status_style = match pipeline.status {
-                        PipelineStatus::Failed => Style::default().red(),
-                        PipelineStatus::Success => Style::default().green(),
-                        PipelineStatus::Running => Style::default().italic(),
-                        _ => Style::default(),
-                    };
-                    Row::new(vec![
-                        Span::raw(pipeline.id.to_string()),
-                        Span::styled(pipeline.status.to_string(), status_style),
-                        Span::raw(pipeline.source.to_string()),
-                        Span::raw(&pipeline.git_ref),
-                        Span::raw(pipeline.created_at.format("%Y-%m-%d %H:%M:%S").to_string()),
-                        Span::raw(pipeline.updated_at.format("%Y-%m-%d %H:%M:%S").to_string()),
-                        // TODO: Display URL in a pop-up with details, together with other data
-                        // Span::raw(&pipeline.web_url),
-                    ])
-                    .style(hightlight_style)
+            PipelinesData::Loaded(_) | PipelinesData::Stale { .. } => {
+                let table_config = &self.config.ui.pipelines;
+                let header_row = Row::new(table_config.columns.iter().enumerate().map(
+                    |(index, column)| {
+                        let sort_order = self
+                            .sort
+                            .filter(|(sort_index, _)| *sort_index == index)
+                            .map(|(_, order)| order);
+                        column.header(sort_order)
+                    },
+                ));
+
+                let rows = self.rows.iter().map(|row| {
+                    Row::new(
+                        table_config
+                            .columns
+                            .iter()
+                            .enumerate()
+                            .map(|(index, column)| {
+                                column.cell(row, index == 0, &self.search, table_config, theme)
+                            }),
+                    )
                 });
 
-                let paginator = build_paginator(pipelines.len(), self.active_page + 1);
-                let table = Table::new(
-                    rows,
-                    // TODO: Display URL in a pop-up with details, together with other data
-                    vec![
-                        Constraint::Length(20), // ID
-                        Constraint::Length(20), // status
-                        Constraint::Length(30), // source
-                        Constraint::Min(30),    // ref
-                        Constraint::Min(20),    // created at
-                        Constraint::Min(20),    // updated at
-                    ],
-                )
-                .column_spacing(2)
-                .header(header_row)
-                .flex(Flex::SpaceAround)
-                .block(
-                    block
-                        .padding(Padding::uniform(1))
-                        .title("Pipelines")
-                        .title(
-                            Line::styled(
-                                format!("Filters: {}", &self.active_filters.join(", ")),
-                                Style::default().add_modifier(Modifier::ITALIC),
+                // The paginator is drawn at the bottom left, after the corner of the border
+                let mut paginator = vec![Span::raw("Pages: ")];
+                let mut x = area.x + 1 + "Pages: ".len() as u16;
+                let total_pages = self.total_pages.max(self.active_page + 1);
+                for item in build_paginator(total_pages, self.active_page + 1) {
+                    let width = item.label.len() as u16;
+                    if let Some(page) = item.page {
+                        self.paginator_hitboxes.push((x, x + width, page));
+                    }
+                    paginator.push(Span::raw(item.label));
+                    paginator.push(Span::raw(" "));
+                    x += width + 1;
+                }
+                let widths = table_config
+                    .columns
+                    .iter()
+                    .map(|column| column.constraint(table_config));
+                let matches = self
+                    .rows
+                    .iter()
+                    .filter(|row| row.matches(&self.search))
+                    .count();
+                let block = match self.search.prompt(matches) {
+                    Some(prompt) => block.title_bottom(prompt.centered()),
+                    None => block,
+                };
+                let table = Table::new(rows, widths)
+                    .column_spacing(2)
+                    .header(header_row)
+                    .row_highlight_style(
+                        theme.highlight(theme.palette.highlight_fg, theme.palette.highlight_bg),
+                    )
+                    .flex(Flex::SpaceAround)
+                    .block(
+                        block
+                            .padding(Padding::uniform(1))
+                            .title("Pipelines")
+                            .title(stale_title(&self.pipelines_data, theme))
+                            .title(page_sort_title(self.sort, table_config))
+                            .title(
+                                Line::styled(
+                                    format!("Filters: {}", &self.active_filters.join(", ")),
+                                    Style::default().add_modifier(Modifier::ITALIC),
+                                )
+                                .right_aligned(),
+                            )
+                            .title_bottom(
+                                Line::from(format!(
+                                    "{} of {}",
+                                    self.active_operation_index + 1,
+                                    self.rows.len()
+                                ))
+                                .right_aligned(),
                             )
-                            .right_aligned(),
-                        )
-                        .title_bottom(
-                            Line::from(format!(
-                                "{} of {}",
-                                self.active_operation_index + 1,
-                                pipelines.len()
-                            ))
-                            .right_aligned(),
-                        )
-                        .title_bottom(Line::from(format!("Pages: {}", paginator)).left_aligned()),
-                );
-
-                frame.render_widget(table, area);
-
-                if self.show_details_popup {
-                    let pipeline_id = pipelines[self.active_operation_index].id;
-                    // TODO: here we need to send a request to GitLab to fetch some pipeline
-                    // details (or process what we already have, tbd)
-                    let block = Block::bordered().title(format!("Details for: {}", pipeline_id));
-                    let area = popup_area(area, 60, 20);
-                    frame.render_widget(Clear, area); // this clears out the background
-                    frame.render_widget(block, area);
+                            .title_bottom(Line::from(paginator).left_aligned()),
+                    );
+
+                self.table_state.select(Some(self.active_operation_index));
+                frame.render_stateful_widget(table, area, &mut self.table_state);
+
+                if let Some(pipeline) = self
+                    .rows
+                    .get(self.active_operation_index)
+                    .map(|row| &row.pipeline)
+                    .filter(|_| self.show_details_popup)
+                {
+                    let block = Block::bordered()
+                        .border_type(BorderType::Thick)
+                        .border_style(theme.fg(theme.palette.popup_border))
+                        .padding(Padding::horizontal(1))
+                        .title(format!("Details for: {}", pipeline.id))
+                        .title(tabs_title(self.details_tab, theme).right_aligned());
+                    match self.details_tab {
+                        DetailsTab::Details => {
+                            let mut lines = details_lines(pipeline, table_config, theme);
+                            lines.extend(self.pending_jobs.lines(theme));
+                            let paragraph = Paragraph::new(lines)
+                                .block(block)
+                                .wrap(Wrap { trim: false });
+                            let area = popup_area(area, 60, 60);
+                            frame.render_widget(Clear, area); // this clears out the background
+                            frame.render_widget(paragraph, area);
+                        }
+                        // Test reports need more room
+                        DetailsTab::Tests => {
+                            let area = popup_area(area, 80, 80);
+                            frame.render_widget(Clear, area);
+                            let inner = block.inner(area);
+                            frame.render_widget(block, area);
+                            self.test_report.draw(frame, inner, theme);
+                        }
+                        DetailsTab::Commit => {
+                            let paragraph = Paragraph::new(self.commit.lines(table_config, theme))
+                                .block(block)
+                                .wrap(Wrap { trim: false });
+                            let area = popup_area(area, 80, 80);
+                            frame.render_widget(Clear, area);
+                            frame.render_widget(paragraph, area);
+                        }
+                    }
                 }
             }
             PipelinesData::Errors(error) => {
                 let loading_message = vec![Line::from(Span::styled(
                     format!("ERROR: {}", error),
-                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                    theme.fg(theme.palette.error).add_modifier(Modifier::BOLD),
                 ))];
                 let paragraph = Paragraph::new(loading_message)
                     .block(block)
@@ -225,54 +893,187 @@ impl Component for PipelinesViewerComponent {
     }
 }
 
-pub fn build_paginator(total_pages: usize, current_page: usize) -> String {
-    let mut pagination = String::new();
+/// Lines of the pipeline details popup. Fields only known once the pipeline has been fetched on
+/// its own are shown as "-" until then.
+fn details_lines<'a>(
+    pipeline: &'a GitlabPipeline,
+    config: &PipelinesConfig,
+    theme: &Theme,
+) -> Vec<Line<'a>> {
+    let optional = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+    let timestamp = |value: Option<DateTime<Utc>>| {
+        optional(value.map(|timestamp| format_timestamp(timestamp, config)))
+    };
+    // The detailed status tells e.g. "passed with warnings" apart from "passed"
+    let status_text = pipeline.detailed_status.as_ref().map_or_else(
+        || pipeline.status.to_string(),
+        |detailed_status| detailed_status.label.clone(),
+    );
+    let status = Span::styled(
+        format!("{} {}", theme.status_icon(&pipeline.status), status_text),
+        theme.status_style(&pipeline.status),
+    );
+    let fields = [
+        ("IID", optional(pipeline.iid.map(|iid| iid.to_string()))),
+        ("Name", optional(pipeline.name.clone())),
+        ("Source", pipeline.source.to_string()),
+        ("Ref", pipeline.git_ref.clone()),
+        ("SHA", optional(pipeline.sha.clone())),
+        (
+            "User",
+            optional(
+                pipeline
+                    .user
+                    .as_ref()
+                    .map(|user| format!("{} ({})", user.name, user.username)),
+            ),
+        ),
+        ("Created at", timestamp(Some(pipeline.created_at))),
+        ("Started at", timestamp(pipeline.started_at)),
+        ("Finished at", timestamp(pipeline.finished_at)),
+        ("Duration", optional(pipeline.duration.map(format_duration))),
+        (
+            "Queued",
+            optional(
+                pipeline
+                    .queued_duration
+                    .map(|seconds| format_duration(seconds as u64)),
+            ),
+        ),
+        (
+            "Coverage",
+            optional(
+                pipeline
+                    .coverage
+                    .as_ref()
+                    .map(|coverage| format!("{}%", coverage)),
+            ),
+        ),
+        ("URL", pipeline.web_url.clone()),
+    ];
+    let label = |name: &'a str| Span::styled(format!("{:<12}", name), Style::default().bold());
+    let mut lines = vec![Line::from(vec![label("Status"), status])];
+    lines.extend(
+        fields
+            .into_iter()
+            .map(|(name, value)| Line::from(vec![label(name), Span::raw(value)])),
+    );
+    lines
+}
+
+/// Names of the tabs of the details popup, the active one being highlighted.
+fn tabs_title(active: DetailsTab, theme: &Theme) -> Line<'static> {
+    let tab = |tab: DetailsTab, name: &'static str| {
+        if tab == active {
+            Span::styled(
+                format!(" {} ", name),
+                theme.highlight(theme.palette.highlight_fg, theme.palette.highlight_bg),
+            )
+        } else {
+            Span::raw(format!(" {} ", name))
+        }
+    };
+    Line::from(vec![
+        tab(DetailsTab::Details, "Details"),
+        Span::raw("|"),
+        tab(DetailsTab::Tests, "Tests"),
+        Span::raw("|"),
+        tab(DetailsTab::Commit, "Commit"),
+    ])
+}
+
+/// Title marking pipelines restored from the cache, or an empty line if the data is fresh.
+fn stale_title(pipelines_data: &PipelinesData, theme: &Theme) -> Line<'static> {
+    let PipelinesData::Stale {
+        fetched_at, error, ..
+    } = pipelines_data
+    else {
+        return Line::default();
+    };
+    let mut title = format!(
+        " STALE: cached at {} ",
+        fetched_at
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S")
+    );
+    if let Some(error) = error {
+        title.push_str(&format!("({}) ", error));
+    }
+    Line::styled(
+        title,
+        theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD),
+    )
+}
+
+/// Title telling that the sort column only sorts the current page, as GitLab cannot sort by it.
+fn page_sort_title(sort: Option<(usize, SortOrder)>, config: &PipelinesConfig) -> Line<'static> {
+    match sort.map(|(index, _)| config.columns[index].column) {
+        Some(column) if column.order_by().is_none() => Line::styled(
+            format!(" {} sorted on this page only ", column.title()),
+            Style::default().add_modifier(Modifier::ITALIC),
+        ),
+        _ => Line::default(),
+    }
+}
+
+/// An element of the paginator, together with the (0-based) page it leads to when clicked.
+pub struct PaginatorItem {
+    pub label: String,
+    pub page: Option<usize>,
+}
+
+pub fn build_paginator(total_pages: usize, current_page: usize) -> Vec<PaginatorItem> {
+    let mut pagination = vec![];
+    let mut push = |label: String, page: Option<usize>| {
+        pagination.push(PaginatorItem { label, page });
+    };
 
     // Add the "previous page" marker
-    pagination.push_str("<H ");
+    push(
+        "<".to_string(),
+        (current_page > 1).then(|| current_page - 2),
+    );
 
     // Add the first page
     if current_page == 1 {
-        pagination.push_str("[1] ");
+        push("[1]".to_string(), Some(0));
     } else {
-        pagination.push_str("1 ");
+        push("1".to_string(), Some(0));
     }
 
     // Add ellipsis if needed
     if current_page > 3 {
-        pagination.push_str("... ");
+        push("...".to_string(), None);
     }
 
     // Add the current page or nearby pages
-    for page in max(2, current_page - 1)..=min(total_pages - 1, current_page + 1) {
+    for page in max(2, current_page - 1)..=min(total_pages.saturating_sub(1), current_page + 1) {
         if page == current_page {
-            pagination.push_str(&format!("[{}] ", page));
+            push(format!("[{}]", page), Some(page - 1));
         } else {
-            pagination.push_str(&format!("{} ", page));
+            push(page.to_string(), Some(page - 1));
         }
     }
 
     // Add ellipsis if needed
-    if current_page < total_pages - 2 {
-        pagination.push_str("... ");
+    if current_page + 2 < total_pages {
+        push("...".to_string(), None);
     }
 
     // Add the last page
     if total_pages > 1 {
-        pagination.push_str(&format!("{} ", total_pages));
+        if current_page == total_pages {
+            push(format!("[{}]", total_pages), Some(total_pages - 1));
+        } else {
+            push(total_pages.to_string(), Some(total_pages - 1));
+        }
     }
 
     // Add the "next page" marker
-    pagination.push_str("L>");
+    push(
+        ">".to_string(),
+        (current_page < total_pages).then_some(current_page),
+    );
 
     pagination
 }
-
-/// helper function to create a centered rect using up certain percentage of the available rect `r`
-fn popup_area(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
-    let vertical = Layout::vertical([Constraint::Percentage(percent_y)]).flex(Flex::Center);
-    let horizontal = Layout::horizontal([Constraint::Percentage(percent_x)]).flex(Flex::Center);
-    let [area] = vertical.areas(area);
-    let [area] = horizontal.areas(area);
-    area
-}
@@ -0,0 +1,322 @@
+use std::cmp::Ordering;
+
+use ratatui::{prelude::*, widgets::Cell};
+
+use crate::{
+    components::utils::{format_age, format_duration, format_timestamp, Search},
+    config::{ColumnAlignment, ColumnConfig, PipelineColumn, PipelinesConfig, TimestampStyle},
+    gitlab::{GitlabPipeline, PipelineStatus},
+    theme::Theme,
+};
+
+use super::downstream::TableRow;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+impl SortOrder {
+    pub fn reversed(self) -> Self {
+        match self {
+            SortOrder::Ascending => SortOrder::Descending,
+            SortOrder::Descending => SortOrder::Ascending,
+        }
+    }
+
+    /// Direction of the sort in the GitLab API.
+    pub fn api_name(self) -> &'static str {
+        match self {
+            SortOrder::Ascending => "asc",
+            SortOrder::Descending => "desc",
+        }
+    }
+
+    fn indicator(self) -> &'static str {
+        match self {
+            SortOrder::Ascending => "▲",
+            SortOrder::Descending => "▼",
+        }
+    }
+}
+
+impl PipelineColumn {
+    pub fn title(self) -> &'static str {
+        match self {
+            PipelineColumn::Id => "ID",
+            PipelineColumn::Iid => "IID",
+            PipelineColumn::Status => "Status",
+            PipelineColumn::Source => "Source",
+            PipelineColumn::Ref => "Ref",
+            PipelineColumn::Sha => "SHA",
+            PipelineColumn::User => "User",
+            PipelineColumn::Duration => "Duration",
+            PipelineColumn::QueuedDuration => "Queued",
+            PipelineColumn::Age => "Age",
+            PipelineColumn::CreatedAt => "Created at",
+            PipelineColumn::UpdatedAt => "Updated at",
+            PipelineColumn::Url => "URL",
+            PipelineColumn::MergeRequest => "MR",
+        }
+    }
+
+    /// Whether the column shows data matched by searches, see `TableRow::matches`.
+    fn is_searchable(self) -> bool {
+        matches!(
+            self,
+            PipelineColumn::Id | PipelineColumn::Ref | PipelineColumn::Sha | PipelineColumn::User
+        )
+    }
+
+    /// Whether the column shows data which is missing from lists of pipelines, and has to be
+    /// fetched for every pipeline separately.
+    pub fn needs_details(self) -> bool {
+        matches!(
+            self,
+            PipelineColumn::User | PipelineColumn::Duration | PipelineColumn::QueuedDuration
+        )
+    }
+
+    /// Field GitLab sorts pipelines by for the column, if it can. Sorting by the other columns
+    /// only sorts the pipelines of the current page.
+    pub fn order_by(self) -> Option<&'static str> {
+        match self {
+            PipelineColumn::Id => Some("id"),
+            PipelineColumn::Status => Some("status"),
+            PipelineColumn::Ref => Some("ref"),
+            PipelineColumn::UpdatedAt => Some("updated_at"),
+            _ => None,
+        }
+    }
+
+    fn default_width(self, config: &PipelinesConfig) -> Constraint {
+        match self {
+            PipelineColumn::Id => Constraint::Length(12),
+            PipelineColumn::Iid => Constraint::Length(8),
+            PipelineColumn::Status => Constraint::Length(24),
+            PipelineColumn::Source => Constraint::Length(28),
+            PipelineColumn::Ref => Constraint::Min(30),
+            PipelineColumn::Sha => Constraint::Length(8),
+            PipelineColumn::User => Constraint::Length(20),
+            PipelineColumn::Duration | PipelineColumn::QueuedDuration => Constraint::Length(10),
+            PipelineColumn::Age => Constraint::Length(10),
+            PipelineColumn::CreatedAt | PipelineColumn::UpdatedAt => match config.timestamps {
+                TimestampStyle::Relative => Constraint::Length(10),
+                TimestampStyle::Absolute => Constraint::Min(20),
+            },
+            PipelineColumn::Url => Constraint::Min(40),
+            PipelineColumn::MergeRequest => Constraint::Length(8),
+        }
+    }
+
+    pub fn compare(self, a: &GitlabPipeline, b: &GitlabPipeline) -> Ordering {
+        match self {
+            PipelineColumn::Id => a.id.cmp(&b.id),
+            PipelineColumn::Iid => a.iid.cmp(&b.iid),
+            PipelineColumn::Status => a.status.to_string().cmp(&b.status.to_string()),
+            PipelineColumn::Source => a.source.to_string().cmp(&b.source.to_string()),
+            PipelineColumn::Ref => a.git_ref.cmp(&b.git_ref),
+            PipelineColumn::Sha => a.sha.cmp(&b.sha),
+            PipelineColumn::User => {
+                let username = |p: &GitlabPipeline| p.user.as_ref().map(|u| u.username.clone());
+                username(a).cmp(&username(b))
+            }
+            PipelineColumn::Duration => a.duration.cmp(&b.duration),
+            PipelineColumn::QueuedDuration => {
+                let seconds = |p: &GitlabPipeline| p.queued_duration.unwrap_or(-1.0);
+                seconds(a).total_cmp(&seconds(b))
+            }
+            // The older the pipeline, the greater its age
+            PipelineColumn::Age => b.created_at.cmp(&a.created_at),
+            PipelineColumn::CreatedAt => a.created_at.cmp(&b.created_at),
+            PipelineColumn::UpdatedAt => a.updated_at.cmp(&b.updated_at),
+            PipelineColumn::Url => a.web_url.cmp(&b.web_url),
+            PipelineColumn::MergeRequest => a.merge_request_iid().cmp(&b.merge_request_iid()),
+        }
+    }
+
+    /// Content of the cell of a pipeline, whose status may be replaced with the one of the
+    /// pipeline together with its downstream pipelines.
+    fn cell<'a>(
+        self,
+        pipeline: &'a GitlabPipeline,
+        status: &PipelineStatus,
+        config: &PipelinesConfig,
+        theme: &Theme,
+    ) -> Span<'a> {
+        match self {
+            PipelineColumn::Id => Span::raw(pipeline.id.to_string()),
+            PipelineColumn::Iid => Span::raw(optional(pipeline.iid)),
+            PipelineColumn::Status => Span::styled(
+                format!("{} {}", theme.status_icon(status), status),
+                theme.status_style(status),
+            ),
+            PipelineColumn::Source => Span::raw(pipeline.source.to_string()),
+            PipelineColumn::Ref => Span::raw(&pipeline.git_ref),
+            PipelineColumn::Sha => Span::raw(
+                pipeline
+                    .sha
+                    .as_deref()
+                    .map_or("-", |sha| &sha[..sha.len().min(8)]),
+            ),
+            PipelineColumn::User => Span::raw(
+                pipeline
+                    .user
+                    .as_ref()
+                    .map_or("-", |user| user.username.as_str()),
+            ),
+            PipelineColumn::Duration => {
+                Span::raw(pipeline.duration.map_or("-".to_string(), format_duration))
+            }
+            PipelineColumn::QueuedDuration => Span::raw(
+                pipeline
+                    .queued_duration
+                    .map_or("-".to_string(), |seconds| format_duration(seconds as u64)),
+            ),
+            PipelineColumn::Age => Span::raw(format_age(pipeline.created_at)),
+            PipelineColumn::CreatedAt => Span::raw(format_timestamp(pipeline.created_at, config)),
+            PipelineColumn::UpdatedAt => Span::raw(format_timestamp(pipeline.updated_at, config)),
+            PipelineColumn::Url => Span::raw(&pipeline.web_url),
+            PipelineColumn::MergeRequest => Span::raw(
+                pipeline
+                    .merge_request_iid()
+                    .map_or("-".to_string(), |iid| format!("!{}", iid)),
+            ),
+        }
+    }
+}
+
+impl ColumnConfig {
+    pub fn constraint(&self, config: &PipelinesConfig) -> Constraint {
+        self.width
+            .map_or_else(|| self.column.default_width(config), Constraint::Length)
+    }
+
+    fn alignment(&self) -> Alignment {
+        match self.align {
+            ColumnAlignment::Left => Alignment::Left,
+            ColumnAlignment::Center => Alignment::Center,
+            ColumnAlignment::Right => Alignment::Right,
+        }
+    }
+
+    /// Header of the column, marked with the sort order if the table is sorted by it.
+    pub fn header(&self, sort_order: Option<SortOrder>) -> Cell<'static> {
+        let title = match sort_order {
+            Some(order) => format!("{} {}", self.column.title(), order.indicator()),
+            None => self.column.title().to_string(),
+        };
+        Cell::from(Line::from(title).bold().alignment(self.alignment()))
+    }
+
+    /// Cell of a row of the table, the first column being prefixed with the nesting of the row.
+    /// Matches of the search are highlighted in the searchable columns.
+    pub fn cell<'a>(
+        &self,
+        row: &'a TableRow,
+        first: bool,
+        search: &Search,
+        config: &PipelinesConfig,
+        theme: &Theme,
+    ) -> Cell<'a> {
+        let mut spans = vec![];
+        if first {
+            spans.push(Span::raw(row.prefix()));
+        }
+        let content = self.column.cell(&row.pipeline, &row.status, config, theme);
+        if self.column.is_searchable() {
+            spans.extend(search.highlight(Line::from(content), theme.search_match()));
+        } else {
+            spans.push(content);
+        }
+        Cell::from(Line::from(spans).alignment(self.alignment()))
+    }
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map_or("-".to_string(), |value| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn pipeline(id: u32, extra: serde_json::Value) -> GitlabPipeline {
+        let mut value = json!({
+            "id": id,
+            "status": "success",
+            "source": "push",
+            "ref": "main",
+            "web_url": format!("https://gitlab.example.com/group/project/-/pipelines/{}", id),
+            "created_at": format!("2024-01-0{}T00:00:00Z", id),
+            "updated_at": "2024-01-01T00:00:00Z",
+        });
+        value
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn compare_by_id_status_and_ref() {
+        let a = pipeline(1, json!({ "status": "success", "ref": "feature" }));
+        let b = pipeline(2, json!({ "status": "failed", "ref": "main" }));
+        assert_eq!(PipelineColumn::Id.compare(&a, &b), Ordering::Less);
+        assert_eq!(PipelineColumn::Status.compare(&a, &b), Ordering::Greater);
+        assert_eq!(PipelineColumn::Ref.compare(&a, &b), Ordering::Less);
+        assert_eq!(PipelineColumn::Ref.compare(&a, &a), Ordering::Equal);
+    }
+
+    #[test]
+    fn compare_puts_missing_durations_first() {
+        let unknown = pipeline(1, json!({}));
+        let short = pipeline(2, json!({ "duration": 30, "queued_duration": 1.5 }));
+        let long = pipeline(3, json!({ "duration": 300, "queued_duration": 20.0 }));
+        for column in [PipelineColumn::Duration, PipelineColumn::QueuedDuration] {
+            assert_eq!(column.compare(&unknown, &short), Ordering::Less);
+            assert_eq!(column.compare(&short, &long), Ordering::Less);
+            assert_eq!(column.compare(&long, &short), Ordering::Greater);
+        }
+    }
+
+    #[test]
+    fn compare_by_age_is_the_reverse_of_creation() {
+        let older = pipeline(1, json!({}));
+        let newer = pipeline(2, json!({}));
+        assert_eq!(
+            PipelineColumn::CreatedAt.compare(&older, &newer),
+            Ordering::Less
+        );
+        assert_eq!(
+            PipelineColumn::Age.compare(&older, &newer),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn compare_by_merge_request() {
+        let branch = pipeline(1, json!({ "ref": "main" }));
+        let merge_request = pipeline(2, json!({ "ref": "refs/merge-requests/12/head" }));
+        let other = pipeline(3, json!({ "ref": "refs/merge-requests/9/head" }));
+        assert_eq!(
+            PipelineColumn::MergeRequest.compare(&branch, &merge_request),
+            Ordering::Less
+        );
+        assert_eq!(
+            PipelineColumn::MergeRequest.compare(&merge_request, &other),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn only_some_columns_are_sorted_by_gitlab() {
+        assert_eq!(PipelineColumn::Id.order_by(), Some("id"));
+        assert_eq!(PipelineColumn::UpdatedAt.order_by(), Some("updated_at"));
+        assert_eq!(PipelineColumn::Duration.order_by(), None);
+        assert_eq!(SortOrder::Descending.api_name(), "desc");
+    }
+}
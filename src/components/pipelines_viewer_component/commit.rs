@@ -0,0 +1,322 @@
+use std::time::{Duration, Instant};
+
+use color_eyre::{eyre::Error, Result};
+use ratatui::prelude::*;
+
+use crate::components::utils::{format_timestamp, PendingRequest};
+use crate::{
+    config::PipelinesConfig,
+    gitlab::{GitlabClient, GitlabCommit, GitlabMergeRequest, GitlabPipeline, PipelineStatus},
+    theme::Theme,
+};
+
+/// Number of recent pipelines of the ref searched for the previous one.
+const PREVIOUS_CANDIDATES: usize = 100;
+/// Commits since the previous pipeline beyond this number are only counted.
+const MAX_LISTED_COMMITS: usize = 50;
+/// Commits since the previous pipeline beyond this number are not fetched.
+const MAX_COUNTED_COMMITS: usize = 200;
+/// Time during which the error of a failed load is shown before loading again.
+const RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// The commit of a pipeline and where it comes from. Only the commit itself is required, the
+/// other lookups are shown failed on their own.
+struct CommitContext {
+    commit: GitlabCommit,
+    // Number of files changed by the commit, when GitLab could count them
+    changed_files: Option<usize>,
+    // Merge request the pipeline runs for, or else the first one containing its commit
+    merge_request: Result<Option<GitlabMergeRequest>>,
+    previous: Result<Option<Previous>>,
+}
+
+/// The previous pipeline of the ref which ran on another commit, and the commits since, which
+/// cannot be listed when the previous commit is gone, e.g. after a force-push.
+struct Previous {
+    pipeline: GitlabPipeline,
+    commits: Result<Vec<GitlabCommit>>,
+}
+
+/// "Commit" tab of the pipeline details: the commit of the pipeline with its message, author and
+/// diffstat, its merge request, and the commits which went into the pipeline since the previous
+/// one of the same ref, e.g. to find out what broke the default branch.
+#[derive(Default)]
+pub struct CommitTab {
+    client: GitlabClient,
+    // Pipeline of the commit, which does not change when the pipeline is updated
+    loaded: Option<u32>,
+    // Pipeline whose commit failed to load, and when
+    failed: Option<(u32, Instant)>,
+    context: Option<CommitContext>,
+    request: Option<PendingRequest<CommitContext>>,
+    error: Option<Error>,
+}
+
+impl CommitTab {
+    pub fn new(client: GitlabClient) -> Self {
+        Self {
+            client,
+            ..Self::default()
+        }
+    }
+
+    /// Show the commit of a pipeline, once its SHA is known.
+    pub fn load(&mut self, gitlab_project: &str, pipeline: &GitlabPipeline) {
+        if self.loaded == Some(pipeline.id) {
+            return;
+        }
+        if self
+            .failed
+            .is_some_and(|(id, failed_at)| id == pipeline.id && failed_at.elapsed() < RETRY_DELAY)
+        {
+            return;
+        }
+        self.failed = None;
+        self.context = None;
+        self.request = None;
+        self.error = None;
+        let Some(sha) = pipeline.sha.clone() else {
+            return;
+        };
+        self.loaded = Some(pipeline.id);
+        let client = self.client.clone();
+        let (gitlab_project, pipeline) = (gitlab_project.to_string(), pipeline.clone());
+        self.request = Some(PendingRequest::spawn(async move {
+            fetch_context(client, gitlab_project, pipeline, sha).await
+        }));
+    }
+
+    pub fn poll(&mut self) {
+        if let Some(result) = self.request.as_mut().and_then(PendingRequest::poll) {
+            self.request = None;
+            match result {
+                Ok(context) => self.context = Some(context),
+                Err(error) => {
+                    // Loaded again once the error has been shown for a while
+                    self.failed = self.loaded.take().map(|id| (id, Instant::now()));
+                    self.error = Some(error);
+                }
+            }
+        }
+    }
+
+    pub fn lines(&self, config: &PipelinesConfig, theme: &Theme) -> Vec<Line<'static>> {
+        if let Some(error) = &self.error {
+            return vec![Line::styled(
+                format!("ERROR: {}", error),
+                theme.fg(theme.palette.error).add_modifier(Modifier::BOLD),
+            )];
+        }
+        let Some(context) = &self.context else {
+            return vec![Line::styled(
+                "Loading...",
+                theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD),
+            )];
+        };
+        let label = |name: &str| Span::styled(format!("{:<15}", name), Style::default().bold());
+        let commit = &context.commit;
+        let author = match &commit.author_email {
+            Some(email) => format!("{} <{}>", commit.author_name, email),
+            None => commit.author_name.clone(),
+        };
+        let mut lines = vec![
+            Line::from(vec![
+                label("Commit"),
+                Span::styled(
+                    format!("{} ", commit.short_id),
+                    theme.fg(theme.palette.accent),
+                ),
+                Span::raw(commit.title.clone()).bold(),
+            ]),
+            Line::from(vec![
+                label("Author"),
+                Span::raw(format!(
+                    "{}, {}",
+                    author,
+                    format_timestamp(commit.authored_date, config)
+                )),
+            ]),
+        ];
+        if let Some(stats) = &commit.stats {
+            let mut changes = vec![
+                label("Changes"),
+                Span::styled(
+                    format!("+{}", stats.additions),
+                    theme.status_style(&PipelineStatus::Success),
+                ),
+                Span::raw(" "),
+                Span::styled(
+                    format!("-{}", stats.deletions),
+                    theme.status_style(&PipelineStatus::Failed),
+                ),
+            ];
+            if let Some(changed_files) = context.changed_files {
+                changes.push(Span::raw(format!(" in {} files", changed_files)));
+            }
+            lines.push(Line::from(changes));
+        }
+        // The title is shown already, only the body of the message is left
+        let message = commit.message.trim();
+        let body = message
+            .strip_prefix(commit.title.as_str())
+            .or_else(|| message.split_once('\n').map(|(_, body)| body))
+            .unwrap_or_default()
+            .trim();
+        if !body.is_empty() {
+            lines.push(Line::default());
+            lines.extend(body.lines().map(|line| Line::from(line.to_string()).dim()));
+        }
+
+        let error_style = theme.fg(theme.palette.error).add_modifier(Modifier::BOLD);
+        lines.push(Line::default());
+        lines.push(match &context.merge_request {
+            Err(error) => Line::from(vec![
+                label("Merge request"),
+                Span::styled(format!("ERROR: {}", error), error_style),
+            ]),
+            Ok(Some(merge_request)) => Line::from(vec![
+                label("Merge request"),
+                Span::raw(format!(
+                    "!{} {} ({} → {})",
+                    merge_request.iid,
+                    merge_request.title,
+                    merge_request.source_branch,
+                    merge_request.target_branch
+                )),
+            ]),
+            Ok(None) => Line::from(vec![label("Merge request"), Span::raw("-")]),
+        });
+        if let Ok(Some(merge_request)) = &context.merge_request {
+            lines.push(Line::from(vec![
+                label(""),
+                Span::raw(merge_request.web_url.clone()),
+            ]));
+        }
+
+        lines.push(Line::default());
+        let previous = match &context.previous {
+            Ok(Some(previous)) => previous,
+            Ok(None) => {
+                lines.push(Line::from(
+                    "No previous pipeline of the ref ran on another commit",
+                ));
+                return lines;
+            }
+            Err(error) => {
+                lines.push(Line::styled(
+                    format!("ERROR: Failed to find the previous pipeline: {}", error),
+                    error_style,
+                ));
+                return lines;
+            }
+        };
+        let pipeline = &previous.pipeline;
+        let since = Span::styled(
+            format!("{} #{}", theme.status_icon(&pipeline.status), pipeline.id),
+            theme.status_style(&pipeline.status),
+        );
+        let commits = match &previous.commits {
+            Ok(commits) => commits,
+            Err(error) => {
+                lines.push(Line::from(vec![Span::raw("Since pipeline "), since]));
+                lines.push(Line::styled(
+                    format!("ERROR: Failed to list the commits since: {}", error),
+                    error_style,
+                ));
+                return lines;
+            }
+        };
+        let count = if commits.len() >= MAX_COUNTED_COMMITS {
+            format!("{}+", MAX_COUNTED_COMMITS)
+        } else {
+            commits.len().to_string()
+        };
+        lines.push(Line::from(vec![
+            Span::raw("Since pipeline "),
+            since,
+            Span::raw(format!(": {} commits", count)),
+        ]));
+        // The newest commits first, as they are the most likely to have broken something
+        lines.extend(commits.iter().take(MAX_LISTED_COMMITS).map(|commit| {
+            Line::from(vec![
+                Span::styled(
+                    format!("{} ", commit.short_id),
+                    theme.fg(theme.palette.accent),
+                ),
+                Span::raw(commit.title.clone()),
+                Span::raw(format!(" ({})", commit.author_name)).dim(),
+            ])
+        }));
+        if commits.len() > MAX_LISTED_COMMITS {
+            lines.push(Line::from(format!(
+                "... and {} more",
+                commits.len() - MAX_LISTED_COMMITS
+            )));
+        }
+        lines
+    }
+}
+
+/// Fetch the commit of a pipeline, its merge request, and the commits since the previous
+/// pipeline of its ref. Pipelines which ran again on the same commit are skipped, since nothing
+/// went into them.
+async fn fetch_context(
+    client: GitlabClient,
+    gitlab_project: String,
+    pipeline: GitlabPipeline,
+    sha: String,
+) -> Result<CommitContext> {
+    let commit = client.fetch_commit(&gitlab_project, &sha).await?;
+    let changed_files = client
+        .count_commit_files(&gitlab_project, &sha)
+        .await
+        .inspect_err(|error| tracing::warn!("Failed to count the files of {}: {}", sha, error))
+        .ok()
+        .flatten();
+    let merge_request = match pipeline.merge_request_iid() {
+        Some(iid) => client
+            .fetch_merge_request(&gitlab_project, iid)
+            .await
+            .map(Some),
+        None => client
+            .fetch_commit_merge_requests(&gitlab_project, &sha)
+            .await
+            .map(|merge_requests| merge_requests.into_iter().next()),
+    };
+    let previous = fetch_previous(&client, &gitlab_project, &pipeline, &sha).await;
+    Ok(CommitContext {
+        commit,
+        changed_files,
+        merge_request,
+        previous,
+    })
+}
+
+async fn fetch_previous(
+    client: &GitlabClient,
+    gitlab_project: &str,
+    pipeline: &GitlabPipeline,
+    sha: &str,
+) -> Result<Option<Previous>> {
+    let candidates = client
+        .fetch_recent_pipelines(gitlab_project, Some(&pipeline.git_ref), PREVIOUS_CANDIDATES)
+        .await?;
+    let previous = candidates.into_iter().find(|candidate| {
+        candidate.id < pipeline.id
+            && candidate
+                .sha
+                .as_ref()
+                .is_some_and(|candidate_sha| candidate_sha != sha)
+    });
+    let Some(previous) = previous else {
+        return Ok(None);
+    };
+    let from = previous.sha.clone().unwrap_or_default();
+    let commits = client
+        .fetch_commits_between(gitlab_project, &from, sha, MAX_COUNTED_COMMITS)
+        .await;
+    Ok(Some(Previous {
+        pipeline: previous,
+        commits,
+    }))
+}
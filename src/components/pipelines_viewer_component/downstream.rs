@@ -0,0 +1,262 @@
+use std::collections::{HashMap, HashSet};
+use std::iter;
+
+use color_eyre::Result;
+use futures::{stream, StreamExt};
+
+use crate::components::utils::Search;
+use crate::gitlab::{GitlabBridge, GitlabClient, GitlabPipeline, PipelineStatus};
+
+/// Number of bridges or pipelines fetched at the same time.
+const CONCURRENT_REQUESTS: usize = 8;
+
+/// Bridges of several pipelines, together with the ID of their pipeline.
+pub type PipelineBridges = Vec<(u32, Vec<GitlabBridge>)>;
+
+/// A pipeline triggered by a bridge of another pipeline, possibly in another project.
+#[derive(Clone)]
+pub struct DownstreamPipeline {
+    pub gitlab_project: String,
+    pub pipeline: GitlabPipeline,
+}
+
+/// A row of the pipelines table: a pipeline of the project, or a downstream pipeline nested under
+/// the pipeline which triggered it.
+pub struct TableRow {
+    pub gitlab_project: String,
+    pub pipeline: GitlabPipeline,
+    pub depth: usize,
+    /// Whether the pipeline triggered downstream pipelines, and whether they are shown
+    pub expandable: bool,
+    pub expanded: bool,
+    /// Status of the pipeline together with the pipelines it triggered
+    pub status: PipelineStatus,
+}
+
+impl TableRow {
+    /// Whether the ID, ref, SHA or user of the pipeline match a search.
+    pub fn matches(&self, search: &Search) -> bool {
+        let pipeline = &self.pipeline;
+        let user = pipeline.user.as_ref();
+        [
+            Some(pipeline.id.to_string().as_str()),
+            Some(pipeline.git_ref.as_str()),
+            pipeline.sha.as_deref(),
+            user.map(|user| user.username.as_str()),
+            user.map(|user| user.name.as_str()),
+        ]
+        .into_iter()
+        .flatten()
+        .any(|text| search.is_match(text))
+    }
+
+    /// Marker shown in front of the first column, telling how the row is nested.
+    pub fn prefix(&self) -> String {
+        let marker = match (self.expandable, self.expanded) {
+            (true, true) => "▾ ",
+            (true, false) => "▸ ",
+            (false, _) => "  ",
+        };
+        if self.depth == 0 {
+            marker.to_string()
+        } else {
+            format!("{}↳ {}", "  ".repeat(self.depth), marker)
+        }
+    }
+}
+
+/// Downstream pipelines of the pipelines in the table. Pipeline IDs are unique across projects,
+/// so everything is keyed by pipeline ID.
+#[derive(Default)]
+pub struct DownstreamPipelines {
+    pub bridges: HashMap<u32, Vec<GitlabBridge>>,
+    pub pipelines: HashMap<u32, DownstreamPipeline>,
+    pub expanded: HashSet<u32>,
+}
+
+impl DownstreamPipelines {
+    /// Rows of the table: the pipelines of the project which were not triggered by another
+    /// pipeline of the list, each one followed by its downstream pipelines when expanded.
+    pub fn rows(&self, gitlab_project: &str, pipelines: &[GitlabPipeline]) -> Vec<TableRow> {
+        let nested: HashSet<u32> = pipelines
+            .iter()
+            .flat_map(|pipeline| self.downstream_ids(pipeline.id))
+            .collect();
+        let mut rows = vec![];
+        for pipeline in pipelines
+            .iter()
+            .filter(|pipeline| !nested.contains(&pipeline.id))
+        {
+            self.push_rows(&mut rows, gitlab_project, pipeline, 0);
+        }
+        rows
+    }
+
+    fn push_rows(
+        &self,
+        rows: &mut Vec<TableRow>,
+        gitlab_project: &str,
+        pipeline: &GitlabPipeline,
+        depth: usize,
+    ) {
+        let downstream: Vec<u32> = self.downstream_ids(pipeline.id).collect();
+        let expandable = !downstream.is_empty();
+        let expanded = expandable && self.expanded.contains(&pipeline.id);
+        let status = self.aggregate_status(pipeline.id, &pipeline.status);
+        rows.push(TableRow {
+            gitlab_project: gitlab_project.to_string(),
+            pipeline: pipeline.clone(),
+            depth,
+            expandable,
+            expanded,
+            status,
+        });
+        if expanded {
+            for id in downstream {
+                if let Some(child) = self.pipelines.get(&id) {
+                    self.push_rows(rows, &child.gitlab_project, &child.pipeline, depth + 1);
+                }
+            }
+        }
+    }
+
+    /// Status of a pipeline together with all the pipelines it triggered, directly or not, as far
+    /// as their bridges are known.
+    fn aggregate_status(&self, pipeline_id: u32, status: &PipelineStatus) -> PipelineStatus {
+        let downstream: Vec<PipelineStatus> = self
+            .downstream(pipeline_id)
+            .map(|downstream| self.aggregate_status(downstream.id, &downstream.status))
+            .collect();
+        PipelineStatus::aggregate(iter::once(status).chain(&downstream))
+            .unwrap_or_else(|| status.clone())
+    }
+
+    /// Pipelines triggered by the bridges of a pipeline, as returned along with the bridges.
+    fn downstream(
+        &self,
+        pipeline_id: u32,
+    ) -> impl Iterator<Item = &crate::gitlab::DownstreamPipeline> + '_ {
+        self.bridges
+            .get(&pipeline_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|bridge| bridge.downstream_pipeline.as_ref())
+    }
+
+    fn downstream_ids(&self, pipeline_id: u32) -> impl Iterator<Item = u32> + '_ {
+        self.downstream(pipeline_id).map(|downstream| downstream.id)
+    }
+
+    /// Pipelines whose bridges should be fetched, among the rows and the downstream pipelines
+    /// known so far: the unknown ones and, unless `only_unknown` is set, those which are still
+    /// running or whose downstream pipelines are. Downstream pipelines are given by the ID of
+    /// their project, which the API accepts as well.
+    pub fn outdated_bridges(&self, rows: &[TableRow], only_unknown: bool) -> Vec<(String, u32)> {
+        let outdated = |id: u32, status: &PipelineStatus| match self.bridges.get(&id) {
+            None => true,
+            Some(_) if only_unknown => false,
+            Some(_) => status.is_active(),
+        };
+        let listed: HashSet<u32> = rows.iter().map(|row| row.pipeline.id).collect();
+        let nested: HashMap<u32, String> = self
+            .bridges
+            .values()
+            .flatten()
+            .filter_map(|bridge| bridge.downstream_pipeline.as_ref())
+            .filter(|downstream| !listed.contains(&downstream.id))
+            .filter(|downstream| {
+                outdated(
+                    downstream.id,
+                    &self.aggregate_status(downstream.id, &downstream.status),
+                )
+            })
+            .map(|downstream| (downstream.id, downstream.project_id.to_string()))
+            .collect();
+        rows.iter()
+            .filter(|row| outdated(row.pipeline.id, &row.status))
+            .map(|row| (row.gitlab_project.clone(), row.pipeline.id))
+            .chain(nested.into_iter().map(|(id, project_id)| (project_id, id)))
+            .collect()
+    }
+
+    /// Downstream pipelines of the expanded rows which are unknown, or whose status changed
+    /// since they were fetched, together with the ID of their project.
+    pub fn outdated_pipelines(&self) -> Vec<(u32, u32)> {
+        self.expanded
+            .iter()
+            .flat_map(|id| self.bridges.get(id))
+            .flatten()
+            .filter_map(|bridge| bridge.downstream_pipeline.as_ref())
+            .filter(|downstream| {
+                self.pipelines
+                    .get(&downstream.id)
+                    .is_none_or(|known| known.pipeline.status != downstream.status)
+            })
+            .map(|downstream| (downstream.project_id, downstream.id))
+            .collect()
+    }
+
+    /// Paths of the projects known so far, by project ID.
+    pub fn project_paths(&self, rows: &[TableRow]) -> HashMap<u32, String> {
+        rows.iter()
+            .filter_map(|row| Some((row.pipeline.project_id?, row.gitlab_project.clone())))
+            .chain(self.pipelines.values().filter_map(|downstream| {
+                Some((
+                    downstream.pipeline.project_id?,
+                    downstream.gitlab_project.clone(),
+                ))
+            }))
+            .collect()
+    }
+}
+
+pub async fn fetch_bridges(
+    client: GitlabClient,
+    pipelines: Vec<(String, u32)>,
+) -> Result<PipelineBridges> {
+    stream::iter(pipelines)
+        .map(|(gitlab_project, id)| {
+            let client = &client;
+            async move {
+                let bridges = client.fetch_pipeline_bridges(&gitlab_project, id).await?;
+                Ok((id, bridges))
+            }
+        })
+        .buffer_unordered(CONCURRENT_REQUESTS)
+        .collect::<Vec<Result<_>>>()
+        .await
+        .into_iter()
+        .collect()
+}
+
+/// Fetch downstream pipelines given the IDs of their projects, which are looked up when their
+/// path is not known yet.
+pub async fn fetch_downstream_pipelines(
+    client: GitlabClient,
+    pipelines: Vec<(u32, u32)>,
+    mut project_paths: HashMap<u32, String>,
+) -> Result<Vec<DownstreamPipeline>> {
+    for (project_id, _) in &pipelines {
+        if !project_paths.contains_key(project_id) {
+            let project = client.fetch_project(&project_id.to_string()).await?;
+            project_paths.insert(*project_id, project.path_with_namespace);
+        }
+    }
+    stream::iter(pipelines)
+        .map(|(project_id, id)| {
+            let client = &client;
+            let gitlab_project = project_paths[&project_id].clone();
+            async move {
+                let pipeline = client.fetch_pipeline(&gitlab_project, id).await?;
+                Ok(DownstreamPipeline {
+                    gitlab_project,
+                    pipeline,
+                })
+            }
+        })
+        .buffer_unordered(CONCURRENT_REQUESTS)
+        .collect::<Vec<Result<_>>>()
+        .await
+        .into_iter()
+        .collect()
+}
@@ -0,0 +1,253 @@
+use chrono::{DateTime, Utc};
+use color_eyre::{eyre::Error, Result};
+use futures::{stream, StreamExt};
+use ratatui::prelude::*;
+
+use crate::components::utils::PendingRequest;
+use crate::{
+    gitlab::{GitlabClient, GitlabJob, GitlabPipeline, GitlabRunner, PipelineStatus},
+    theme::Theme,
+};
+
+/// Number of runners fetched at the same time.
+const CONCURRENT_REQUESTS: usize = 8;
+
+/// Pending jobs of a pipeline, with the runners which could pick them.
+struct Queue {
+    jobs: Vec<GitlabJob>,
+    runners: Vec<GitlabRunner>,
+}
+
+/// Explanation of why the jobs of a pipeline are pending, shown in the pipeline details: jobs
+/// which no online runner can pick because of their tags are listed with the missing tags.
+#[derive(Default)]
+pub struct PendingJobs {
+    client: GitlabClient,
+    // Pipeline of the jobs, as of its last update
+    loaded: Option<(u32, DateTime<Utc>)>,
+    queue: Option<Queue>,
+    request: Option<PendingRequest<Queue>>,
+    error: Option<Error>,
+}
+
+impl PendingJobs {
+    pub fn new(client: GitlabClient) -> Self {
+        Self {
+            client,
+            ..Self::default()
+        }
+    }
+
+    /// Explain the pending jobs of a pipeline, fetching them again whenever the pipeline is
+    /// updated. Only pipelines which are not finished may have pending jobs.
+    pub fn load(&mut self, gitlab_project: &str, pipeline: &GitlabPipeline) {
+        let loaded = Some((pipeline.id, pipeline.updated_at));
+        if self.loaded == loaded {
+            return;
+        }
+        if self.loaded.map(|(id, _)| id) != Some(pipeline.id) {
+            self.queue = None;
+        }
+        self.loaded = loaded;
+        self.error = None;
+        if !matches!(
+            pipeline.status,
+            PipelineStatus::Pending | PipelineStatus::Running
+        ) {
+            self.queue = None;
+            self.request = None;
+            return;
+        }
+        let client = self.client.clone();
+        let (gitlab_project, id) = (gitlab_project.to_string(), pipeline.id);
+        self.request = Some(PendingRequest::spawn(async move {
+            fetch_queue(client, gitlab_project, id).await
+        }));
+    }
+
+    pub fn poll(&mut self) {
+        if let Some(result) = self.request.as_mut().and_then(PendingRequest::poll) {
+            self.request = None;
+            match result {
+                Ok(queue) => self.queue = Some(queue),
+                Err(error) => self.error = Some(error),
+            }
+        }
+    }
+
+    /// Lines to append to the pipeline details, none if no job is pending.
+    pub fn lines(&self, theme: &Theme) -> Vec<Line<'static>> {
+        let warning = theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD);
+        if let Some(error) = &self.error {
+            return vec![
+                Line::default(),
+                Line::styled(
+                    format!("Pending jobs could not be checked: {}", error),
+                    warning,
+                ),
+            ];
+        }
+        let Some(queue) = self.queue.as_ref().filter(|queue| !queue.jobs.is_empty()) else {
+            return vec![];
+        };
+        let stuck: Vec<(&GitlabJob, String)> = queue
+            .jobs
+            .iter()
+            .filter(|job| {
+                !queue
+                    .runners
+                    .iter()
+                    .any(|runner| runner.can_pick(&job.tag_list))
+            })
+            .map(|job| (job, stuck_reason(job, &queue.runners)))
+            .collect();
+        let mut lines = vec![
+            Line::default(),
+            Line::from(format!("Pending jobs: {}", queue.jobs.len())).bold(),
+        ];
+        if stuck.is_empty() {
+            lines.push(Line::from(
+                "All of them can be picked by an online runner, which may be busy",
+            ));
+            return lines;
+        }
+        lines.extend(stuck.into_iter().map(|(job, reason)| {
+            Line::from(vec![
+                Span::styled(format!("{} ", job.name), warning),
+                Span::raw(reason),
+            ])
+        }));
+        lines
+    }
+}
+
+/// Why no runner can pick a job.
+fn stuck_reason(job: &GitlabJob, runners: &[GitlabRunner]) -> String {
+    let available: Vec<&GitlabRunner> = runners
+        .iter()
+        .filter(|runner| runner.is_online() && !runner.paused)
+        .collect();
+    if available.is_empty() {
+        return "has no online runner".to_string();
+    }
+    if job.tag_list.is_empty() {
+        return "has no tags, and no online runner picks untagged jobs".to_string();
+    }
+    // Tags missing from the runner which lacks the fewest of them
+    let missing = available
+        .iter()
+        .map(|runner| {
+            job.tag_list
+                .iter()
+                .filter(|tag| !runner.tag_list.contains(tag))
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+        .min_by_key(Vec::len)
+        .unwrap_or_default();
+    // A runner having all the tags may simply be busy
+    if missing.is_empty() {
+        return format!("needs tags {}", job.tag_list.join(", "));
+    }
+    format!(
+        "needs tags {} (the closest online runner lacks {})",
+        job.tag_list.join(", "),
+        missing.join(", ")
+    )
+}
+
+/// Fetch the pending jobs of a pipeline and, if there are any, the runners of the project with
+/// their tags.
+async fn fetch_queue(client: GitlabClient, gitlab_project: String, id: u32) -> Result<Queue> {
+    let jobs = client.fetch_pending_jobs(&gitlab_project, id).await?;
+    if jobs.is_empty() {
+        return Ok(Queue {
+            jobs,
+            runners: vec![],
+        });
+    }
+    let runners = client.fetch_runners(&gitlab_project).await?;
+    // Runners come with their tags only one by one, and the ones which cannot be seen in full are
+    // left out rather than assumed to have no tags
+    let runners = stream::iter(runners)
+        .map(|runner| {
+            let client = &client;
+            async move {
+                client
+                    .fetch_runner(runner.id)
+                    .await
+                    .inspect_err(|error| {
+                        tracing::warn!("Failed to fetch runner {}: {}", runner.id, error)
+                    })
+                    .ok()
+            }
+        })
+        .buffered(CONCURRENT_REQUESTS)
+        .filter_map(|runner| async move { runner })
+        .collect()
+        .await;
+    Ok(Queue { jobs, runners })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn job(tags: &[&str]) -> GitlabJob {
+        serde_json::from_value(json!({
+            "id": 1,
+            "name": "build",
+            "stage": "build",
+            "status": "pending",
+            "web_url": "",
+            "created_at": "2024-01-01T00:00:00Z",
+            "tag_list": tags,
+        }))
+        .unwrap()
+    }
+
+    fn runner(status: &str, paused: bool, tags: &[&str]) -> GitlabRunner {
+        serde_json::from_value(json!({
+            "id": 1,
+            "runner_type": "project_type",
+            "status": status,
+            "paused": paused,
+            "tag_list": tags,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn explains_missing_runners() {
+        let runners = [
+            runner("offline", false, &["docker"]),
+            runner("online", true, &["docker"]),
+        ];
+        assert_eq!(
+            stuck_reason(&job(&["docker"]), &runners),
+            "has no online runner"
+        );
+        assert_eq!(
+            stuck_reason(&job(&[]), &[runner("online", false, &["docker"])]),
+            "has no tags, and no online runner picks untagged jobs"
+        );
+    }
+
+    #[test]
+    fn names_the_tags_of_the_closest_runner() {
+        let runners = [
+            runner("online", false, &["linux"]),
+            runner("online", false, &["docker", "linux"]),
+        ];
+        assert_eq!(
+            stuck_reason(&job(&["docker", "gpu", "linux"]), &runners),
+            "needs tags docker, gpu, linux (the closest online runner lacks gpu)"
+        );
+        assert_eq!(
+            stuck_reason(&job(&["docker", "linux"]), &runners),
+            "needs tags docker, linux"
+        );
+    }
+}
@@ -0,0 +1,721 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use color_eyre::{eyre::Error, Result};
+use ratatui::{prelude::*, widgets::*};
+
+use crate::components::utils::{format_duration, PendingRequest};
+use crate::{
+    action::Action,
+    gitlab::{
+        GitlabClient, GitlabPipeline, PipelineStatus, TestCase, TestCaseStatus, TestReport,
+        TestReportSummary,
+    },
+    theme::Theme,
+};
+
+/// Number of recent pipelines of the target branch searched for one to compare with.
+const BASELINE_CANDIDATES: usize = 20;
+
+/// Identifies a test case across pipelines: its suite, class and name.
+type TestCaseKey = (String, Option<String>, String);
+
+/// Failed tests of the pipeline of the target branch which the report is compared with.
+struct Baseline {
+    git_ref: String,
+    // None if the target branch has no finished pipeline older than the one of the report
+    pipeline_id: Option<u32>,
+    failures: HashSet<TestCaseKey>,
+}
+
+/// A row of the list of test suites.
+struct SuiteRow<'a> {
+    name: &'a str,
+    total: u32,
+    success: u32,
+    failed: u32,
+    skipped: u32,
+    error: u32,
+    time: f64,
+    // Why the report of the suite could not be parsed, if it could not
+    suite_error: Option<&'a str>,
+    // Known once the full report and the baseline are loaded
+    new_failures: Option<usize>,
+}
+
+/// "Tests" tab of the pipeline details: the test suites of the pipeline with their counts, the
+/// failed test cases of a suite and finally the failure of a test case. Failures can be limited
+/// to the ones which do not happen on the target branch, i.e. the default branch or the target
+/// of the merge request.
+#[derive(Default)]
+pub struct TestReportTab {
+    client: GitlabClient,
+    // Pipeline of the report, with its project
+    pipeline: Option<(String, GitlabPipeline)>,
+    // Only the summary is needed to list the suites, the full report is fetched when needed
+    summary: Option<TestReportSummary>,
+    summary_request: Option<PendingRequest<TestReportSummary>>,
+    report: Option<TestReport>,
+    report_request: Option<PendingRequest<TestReport>>,
+    baseline: Option<Baseline>,
+    baseline_request: Option<PendingRequest<Baseline>>,
+    error: Option<Error>,
+    new_failures_only: bool,
+    // Suite whose failures are listed, and index of the failure shown among them
+    suite: Option<String>,
+    case: Option<usize>,
+    suites_state: TableState,
+    cases_state: TableState,
+    // Index of the first line of the failure shown
+    scroll: usize,
+}
+
+impl TestReportTab {
+    pub fn new(client: GitlabClient) -> Self {
+        Self {
+            client,
+            ..Self::default()
+        }
+    }
+
+    /// Show the report of a pipeline, fetching it again whenever the pipeline is updated.
+    pub fn load(&mut self, gitlab_project: &str, pipeline: &GitlabPipeline) {
+        let loaded: Option<(u32, DateTime<Utc>)> = self
+            .pipeline
+            .as_ref()
+            .map(|(_, loaded)| (loaded.id, loaded.updated_at));
+        if loaded == Some((pipeline.id, pipeline.updated_at)) {
+            return;
+        }
+        if loaded.map(|(id, _)| id) != Some(pipeline.id) {
+            *self = Self {
+                client: self.client.clone(),
+                new_failures_only: self.new_failures_only,
+                ..Self::default()
+            };
+        }
+        self.pipeline = Some((gitlab_project.to_string(), pipeline.clone()));
+        self.error = None;
+        let client = self.client.clone();
+        let (gitlab_project, id) = (gitlab_project.to_string(), pipeline.id);
+        self.summary_request = Some(PendingRequest::spawn(async move {
+            client.fetch_test_report_summary(&gitlab_project, id).await
+        }));
+        self.report_request = None;
+        if self.suite.is_some() || self.new_failures_only {
+            self.request_report();
+        }
+        if self.new_failures_only {
+            self.request_baseline();
+        }
+    }
+
+    fn request_report(&mut self) {
+        let Some((gitlab_project, pipeline)) = &self.pipeline else {
+            return;
+        };
+        if self.report_request.is_some() {
+            return;
+        }
+        let client = self.client.clone();
+        let (gitlab_project, id) = (gitlab_project.clone(), pipeline.id);
+        self.report_request = Some(PendingRequest::spawn(async move {
+            client.fetch_test_report(&gitlab_project, id).await
+        }));
+    }
+
+    fn request_baseline(&mut self) {
+        let Some((gitlab_project, pipeline)) = &self.pipeline else {
+            return;
+        };
+        if self.baseline.is_some() || self.baseline_request.is_some() {
+            return;
+        }
+        let client = self.client.clone();
+        let (gitlab_project, pipeline) = (gitlab_project.clone(), pipeline.clone());
+        self.baseline_request = Some(PendingRequest::spawn(async move {
+            fetch_baseline(client, gitlab_project, pipeline).await
+        }));
+    }
+
+    pub fn poll(&mut self) {
+        if let Some(result) = self.summary_request.as_mut().and_then(PendingRequest::poll) {
+            self.summary_request = None;
+            match result {
+                Ok(summary) => {
+                    if self.suites_state.selected().is_none() {
+                        self.suites_state.select(Some(0));
+                    }
+                    self.summary = Some(summary);
+                }
+                Err(error) => self.error = Some(error),
+            }
+        }
+        if let Some(result) = self.report_request.as_mut().and_then(PendingRequest::poll) {
+            self.report_request = None;
+            match result {
+                Ok(report) => self.report = Some(report),
+                Err(error) => self.error = Some(error),
+            }
+        }
+        if let Some(result) = self
+            .baseline_request
+            .as_mut()
+            .and_then(PendingRequest::poll)
+        {
+            self.baseline_request = None;
+            match result {
+                Ok(baseline) => self.baseline = Some(baseline),
+                Err(error) => self.error = Some(error),
+            }
+        }
+    }
+
+    /// Handle an action, returning whether it was used. Closing is only used to go back from a
+    /// suite or a test case, so that the popup closes from the list of suites.
+    pub fn update(&mut self, action: &Action) -> bool {
+        match action {
+            Action::Next => self.select_by(1),
+            Action::Previous => self.select_by(-1),
+            Action::Enter => self.enter(),
+            Action::ToggleNewFailures => {
+                self.new_failures_only = !self.new_failures_only;
+                self.suites_state.select(Some(0));
+                self.cases_state.select(Some(0));
+                self.case = None;
+                if self.new_failures_only {
+                    self.request_report();
+                    self.request_baseline();
+                }
+            }
+            Action::Close if self.case.is_some() => self.case = None,
+            Action::Close if self.suite.is_some() => self.suite = None,
+            _ => return false,
+        }
+        true
+    }
+
+    fn select_by(&mut self, offset: isize) {
+        if self.case.is_some() {
+            self.scroll = self.scroll.saturating_add_signed(offset);
+            return;
+        }
+        let (len, state) = if self.suite.is_some() {
+            (self.visible_cases().len(), &mut self.cases_state)
+        } else {
+            (self.suite_rows().len(), &mut self.suites_state)
+        };
+        let selected = state.selected().unwrap_or(0).saturating_add_signed(offset);
+        state.select(Some(selected.min(len.saturating_sub(1))));
+    }
+
+    fn enter(&mut self) {
+        if self.case.is_some() {
+            return;
+        }
+        if self.suite.is_some() {
+            let selected = self.cases_state.selected();
+            if selected.is_some_and(|index| index < self.visible_cases().len()) {
+                self.case = selected;
+                self.scroll = 0;
+            }
+            return;
+        }
+        let name = self
+            .suites_state
+            .selected()
+            .and_then(|index| Some(self.suite_rows().get(index)?.name.to_string()));
+        if let Some(name) = name {
+            self.suite = Some(name);
+            self.cases_state.select(Some(0));
+            self.request_report();
+        }
+    }
+
+    /// Suites of the summary, only the ones with new failures if asked to.
+    fn suite_rows(&self) -> Vec<SuiteRow<'_>> {
+        let Some(summary) = &self.summary else {
+            return vec![];
+        };
+        let rows = summary.test_suites.iter().map(|suite| SuiteRow {
+            name: &suite.name,
+            total: suite.total_count,
+            success: suite.success_count,
+            failed: suite.failed_count,
+            skipped: suite.skipped_count,
+            error: suite.error_count,
+            time: suite.total_time,
+            suite_error: suite.suite_error.as_deref(),
+            new_failures: self.new_failures(&suite.name).map(|cases| cases.len()),
+        });
+        if self.new_failures_only {
+            rows.filter(|row| row.new_failures.is_some_and(|count| count > 0))
+                .collect()
+        } else {
+            rows.collect()
+        }
+    }
+
+    /// Failed test cases of a suite which do not fail on the target branch, once known.
+    fn new_failures(&self, suite: &str) -> Option<Vec<&TestCase>> {
+        let baseline = self.baseline.as_ref()?;
+        Some(
+            self.failures(suite)?
+                .into_iter()
+                .filter(|case| !baseline.failures.contains(&key(suite, case)))
+                .collect(),
+        )
+    }
+
+    fn failures(&self, suite: &str) -> Option<Vec<&TestCase>> {
+        let suite = self
+            .report
+            .as_ref()?
+            .test_suites
+            .iter()
+            .find(|test_suite| test_suite.name == suite)?;
+        Some(
+            suite
+                .test_cases
+                .iter()
+                .filter(|case| case.is_failure())
+                .collect(),
+        )
+    }
+
+    /// Failed test cases of the selected suite.
+    fn visible_cases(&self) -> Vec<&TestCase> {
+        let Some(suite) = &self.suite else {
+            return vec![];
+        };
+        if self.new_failures_only {
+            self.new_failures(suite)
+        } else {
+            self.failures(suite)
+        }
+        .unwrap_or_default()
+    }
+
+    fn is_loading(&self) -> bool {
+        self.summary.is_none()
+            || (self.suite.is_some() && self.report.is_none())
+            || (self.new_failures_only && (self.report.is_none() || self.baseline.is_none()))
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let [totals_area, content_area] =
+            Layout::vertical([Constraint::Length(2), Constraint::Fill(1)]).areas(area);
+        frame.render_widget(Paragraph::new(self.totals_line(theme)), totals_area);
+
+        if let Some(error) = &self.error {
+            let paragraph = Paragraph::new(Span::styled(
+                format!("ERROR: {}", error),
+                theme.fg(theme.palette.error).add_modifier(Modifier::BOLD),
+            ))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: false });
+            frame.render_widget(paragraph, content_area);
+        } else if self.is_loading() {
+            let paragraph = Paragraph::new(Span::styled(
+                "Loading...",
+                theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD),
+            ))
+            .alignment(Alignment::Center);
+            frame.render_widget(paragraph, content_area);
+        } else if let Some(index) = self.case {
+            self.draw_case(frame, content_area, index, theme);
+        } else if self.suite.is_some() {
+            self.draw_cases(frame, content_area, theme);
+        } else {
+            self.draw_suites(frame, content_area, theme);
+        }
+    }
+
+    /// Counts of the whole pipeline, followed by what the failures are compared with.
+    fn totals_line(&self, theme: &Theme) -> Line<'static> {
+        let Some(summary) = &self.summary else {
+            return Line::default();
+        };
+        let total = &summary.total;
+        let mut spans = vec![
+            Span::raw(format!(
+                "{} tests in {}: ",
+                total.count,
+                format_time(total.time)
+            )),
+            Span::styled(
+                format!("{} passed", total.success),
+                theme.status_style(&PipelineStatus::Success),
+            ),
+            Span::raw(", "),
+            Span::styled(
+                format!("{} failed", total.failed),
+                theme.status_style(&PipelineStatus::Failed),
+            ),
+            Span::raw(format!(
+                ", {} skipped, {} errors",
+                total.skipped, total.error
+            )),
+        ];
+        if let Some(suite) = &self.suite {
+            spans.push(Span::raw(format!("  ›  {}", suite)).bold());
+        }
+        if self.new_failures_only {
+            let baseline = match &self.baseline {
+                Some(Baseline {
+                    git_ref,
+                    pipeline_id: Some(id),
+                    ..
+                }) => format!("new failures compared to {} (#{})", git_ref, id),
+                Some(Baseline { git_ref, .. }) => {
+                    format!("no pipeline on {} to compare with", git_ref)
+                }
+                None => "new failures".to_string(),
+            };
+            spans.push(Span::styled(
+                format!("  [{}]", baseline),
+                Style::default().add_modifier(Modifier::ITALIC),
+            ));
+        }
+        Line::from(spans)
+    }
+
+    fn draw_suites(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let rows: Vec<Row> = self
+            .suite_rows()
+            .into_iter()
+            .map(|row| {
+                let status = if row.failed + row.error > 0 {
+                    PipelineStatus::Failed
+                } else {
+                    PipelineStatus::Success
+                };
+                let mut name = Line::styled(
+                    format!("{} {}", theme.status_icon(&status), row.name),
+                    theme.status_style(&status),
+                );
+                if let Some(error) = row.suite_error {
+                    name.push_span(Span::styled(
+                        format!(" ({})", error),
+                        theme.fg(theme.palette.error),
+                    ));
+                }
+                Row::new([
+                    name,
+                    Line::from(row.total.to_string()).right_aligned(),
+                    Line::from(row.success.to_string()).right_aligned(),
+                    Line::from(row.failed.to_string()).right_aligned(),
+                    Line::from(row.skipped.to_string()).right_aligned(),
+                    Line::from(row.error.to_string()).right_aligned(),
+                    Line::from(
+                        row.new_failures
+                            .map_or("-".to_string(), |new| new.to_string()),
+                    )
+                    .right_aligned(),
+                    Line::from(format_time(row.time)).right_aligned(),
+                ])
+            })
+            .collect();
+        if rows.is_empty() {
+            frame.render_widget(Paragraph::new(self.empty_message()), area);
+            return;
+        }
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(1),
+                Constraint::Length(6),
+                Constraint::Length(6),
+                Constraint::Length(6),
+                Constraint::Length(7),
+                Constraint::Length(6),
+                Constraint::Length(4),
+                Constraint::Length(10),
+            ],
+        )
+        .header(
+            Row::new([
+                Line::from("Suite"),
+                Line::from("Total").right_aligned(),
+                Line::from("Passed").right_aligned(),
+                Line::from("Failed").right_aligned(),
+                Line::from("Skipped").right_aligned(),
+                Line::from("Errors").right_aligned(),
+                Line::from("New").right_aligned(),
+                Line::from("Time").right_aligned(),
+            ])
+            .bold(),
+        )
+        .column_spacing(1)
+        .row_highlight_style(
+            theme.highlight(theme.palette.highlight_fg, theme.palette.highlight_bg),
+        );
+        frame.render_stateful_widget(table, area, &mut self.suites_state);
+    }
+
+    fn draw_cases(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let suite = self.suite.clone().unwrap_or_default();
+        let new_failures: Option<Vec<TestCaseKey>> = self
+            .new_failures(&suite)
+            .map(|cases| cases.into_iter().map(|case| key(&suite, case)).collect());
+        let rows: Vec<Row> = self
+            .visible_cases()
+            .into_iter()
+            .map(|case| {
+                let status = pipeline_status(&case.status);
+                let new = new_failures
+                    .as_ref()
+                    .is_some_and(|new_failures| new_failures.contains(&key(&suite, case)));
+                Row::new([
+                    Line::styled(
+                        format!("{} {}", theme.status_icon(&status), case.status),
+                        theme.status_style(&status),
+                    ),
+                    Line::from(qualified_name(case)),
+                    Line::from(if new { "new" } else { "" }).bold(),
+                    Line::from(format_time(case.execution_time)).right_aligned(),
+                ])
+            })
+            .collect();
+        if rows.is_empty() {
+            frame.render_widget(Paragraph::new(self.empty_message()), area);
+            return;
+        }
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(10),
+                Constraint::Fill(1),
+                Constraint::Length(3),
+                Constraint::Length(10),
+            ],
+        )
+        .header(Row::new(["Status", "Test", "", "Time"]).bold())
+        .column_spacing(1)
+        .row_highlight_style(
+            theme.highlight(theme.palette.highlight_fg, theme.palette.highlight_bg),
+        );
+        frame.render_stateful_widget(table, area, &mut self.cases_state);
+    }
+
+    fn draw_case(&mut self, frame: &mut Frame, area: Rect, index: usize, theme: &Theme) {
+        let Some(case) = self.visible_cases().get(index).copied() else {
+            return;
+        };
+        let status = pipeline_status(&case.status);
+        let label =
+            |name: &'static str| Span::styled(format!("{:<8}", name), Style::default().bold());
+        let mut lines = vec![
+            Line::from(vec![label("Test"), Span::raw(case.name.clone())]),
+            Line::from(vec![
+                label("Class"),
+                Span::raw(case.classname.clone().unwrap_or_else(|| "-".to_string())),
+            ]),
+            Line::from(vec![
+                label("File"),
+                Span::raw(case.file.clone().unwrap_or_else(|| "-".to_string())),
+            ]),
+            Line::from(vec![
+                label("Status"),
+                Span::styled(
+                    format!("{} {}", theme.status_icon(&status), case.status),
+                    theme.status_style(&status),
+                ),
+            ]),
+            Line::from(vec![
+                label("Time"),
+                Span::raw(format_time(case.execution_time)),
+            ]),
+        ];
+        for (title, text) in [
+            ("Failure", &case.system_output),
+            ("Stack trace", &case.stack_trace),
+        ] {
+            let Some(text) = text.as_ref().filter(|text| !text.trim().is_empty()) else {
+                continue;
+            };
+            lines.push(Line::default());
+            lines.push(Line::styled(title, Style::default().bold().underlined()));
+            lines.extend(text.lines().map(|line| Line::from(line.to_string())));
+        }
+
+        // Scrolling stops once the end of the failure is visible
+        let page_height = area.height as usize;
+        self.scroll = self
+            .scroll
+            .min(lines.len().saturating_sub(page_height.max(1)));
+        let visible: Vec<Line> = lines
+            .into_iter()
+            .skip(self.scroll)
+            .take(page_height)
+            .collect();
+        frame.render_widget(Paragraph::new(visible), area);
+    }
+
+    fn empty_message(&self) -> &'static str {
+        match (&self.suite, self.new_failures_only) {
+            (None, false) => "No test report for this pipeline",
+            (None, true) => "No new failures",
+            (Some(_), false) => "No failed tests in this suite",
+            (Some(_), true) => "No new failures in this suite",
+        }
+    }
+}
+
+/// Find the last finished pipeline of the target branch started before the given pipeline, and
+/// the tests which failed in it.
+async fn fetch_baseline(
+    client: GitlabClient,
+    gitlab_project: String,
+    pipeline: GitlabPipeline,
+) -> Result<Baseline> {
+    let git_ref = match pipeline.merge_request_iid() {
+        Some(iid) => {
+            client
+                .fetch_merge_request(&gitlab_project, iid)
+                .await?
+                .target_branch
+        }
+        None => client.fetch_default_branch(&gitlab_project).await?,
+    };
+    let candidates = client
+        .fetch_recent_pipelines(&gitlab_project, Some(&git_ref), BASELINE_CANDIDATES)
+        .await?;
+    let Some(baseline) = candidates
+        .iter()
+        .find(|candidate| candidate.id < pipeline.id && !candidate.status.is_active())
+    else {
+        return Ok(Baseline {
+            git_ref,
+            pipeline_id: None,
+            failures: HashSet::new(),
+        });
+    };
+    let report = client
+        .fetch_test_report(&gitlab_project, baseline.id)
+        .await?;
+    let failures = report
+        .test_suites
+        .iter()
+        .flat_map(|suite| {
+            suite
+                .test_cases
+                .iter()
+                .filter(|case| case.is_failure())
+                .map(|case| key(&suite.name, case))
+        })
+        .collect();
+    Ok(Baseline {
+        git_ref,
+        pipeline_id: Some(baseline.id),
+        failures,
+    })
+}
+
+fn key(suite: &str, case: &TestCase) -> TestCaseKey {
+    (suite.to_string(), case.classname.clone(), case.name.clone())
+}
+
+/// Name of a test case prefixed with its class, e.g. `tests.test_api.test_login`.
+fn qualified_name(case: &TestCase) -> String {
+    match &case.classname {
+        Some(classname) => format!("{}.{}", classname, case.name),
+        None => case.name.clone(),
+    }
+}
+
+/// Pipeline status whose style a test case status is shown with.
+fn pipeline_status(status: &TestCaseStatus) -> PipelineStatus {
+    match status {
+        TestCaseStatus::Success => PipelineStatus::Success,
+        TestCaseStatus::Failed | TestCaseStatus::Error => PipelineStatus::Failed,
+        TestCaseStatus::Skipped => PipelineStatus::Skipped,
+        TestCaseStatus::Unknown(status) => PipelineStatus::Unknown(status.clone()),
+    }
+}
+
+/// Duration of tests, which often take less than a second.
+fn format_time(seconds: f64) -> String {
+    if seconds < 60.0 {
+        format!("{:.2}s", seconds)
+    } else {
+        format_duration(seconds as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn report(cases: &[(&str, &str, &str)]) -> TestReport {
+        let test_cases: Vec<_> = cases
+            .iter()
+            .map(|(classname, name, status)| {
+                json!({ "classname": classname, "name": name, "status": status })
+            })
+            .collect();
+        serde_json::from_value(json!({
+            "test_suites": [{ "name": "rspec", "test_cases": test_cases }],
+        }))
+        .unwrap()
+    }
+
+    fn names(cases: Option<Vec<&TestCase>>) -> Option<Vec<String>> {
+        cases.map(|cases| cases.into_iter().map(qualified_name).collect())
+    }
+
+    #[test]
+    fn new_failures_leave_out_the_ones_of_the_target_branch() {
+        let mut tab = TestReportTab {
+            report: Some(report(&[
+                ("api", "login", "failed"),
+                ("api", "logout", "error"),
+                ("web", "login", "failed"),
+                ("web", "home", "success"),
+            ])),
+            ..TestReportTab::default()
+        };
+        // Unknown until the baseline is loaded
+        assert_eq!(names(tab.new_failures("rspec")), None);
+
+        tab.baseline = Some(Baseline {
+            git_ref: "main".to_string(),
+            pipeline_id: Some(1),
+            failures: HashSet::from([
+                key(
+                    "rspec",
+                    &report(&[("api", "login", "failed")]).test_suites[0].test_cases[0],
+                ),
+                (
+                    "other".to_string(),
+                    Some("web".to_string()),
+                    "login".to_string(),
+                ),
+            ]),
+        });
+        assert_eq!(
+            names(tab.new_failures("rspec")),
+            Some(vec!["api.logout".to_string(), "web.login".to_string()])
+        );
+        assert_eq!(names(tab.new_failures("unknown")), None);
+    }
+
+    #[test]
+    fn every_failure_is_new_without_a_baseline_pipeline() {
+        let tab = TestReportTab {
+            report: Some(report(&[("api", "login", "failed")])),
+            baseline: Some(Baseline {
+                git_ref: "main".to_string(),
+                pipeline_id: None,
+                failures: HashSet::new(),
+            }),
+            ..TestReportTab::default()
+        };
+        assert_eq!(
+            names(tab.new_failures("rspec")),
+            Some(vec!["api.login".to_string()])
+        );
+    }
+}
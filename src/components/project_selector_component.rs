@@ -1,18 +1,108 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
 use color_eyre::Result;
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use futures::{stream, StreamExt};
+use globset::{Glob, GlobMatcher};
 use ratatui::{prelude::*, widgets::*};
 use tokio::sync::mpsc::UnboundedSender;
 
 use super::{
-    utils::{get_block, prepare_layout, Body, Element},
-    Component,
+    utils::{get_block, prepare_layout, Body, ClickTracker, Element, PendingRequest},
+    Component, ComponentId,
+};
+use crate::{
+    action::Action,
+    cache::{Cache, CacheKind},
+    config::{Config, GroupBy},
+    discovery::ProjectDiscovery,
+    gitlab::{GitlabClient, GitlabPipeline, PipelineStatus},
+    state::State,
 };
-use crate::{action::Action, config::Config, state::State};
+
+/// Number of recent pipelines shown in the sparkline next to each project.
+const SPARKLINE_WIDTH: u16 = 10;
+/// Number of projects polled at the same time.
+const CONCURRENT_REQUESTS: usize = 8;
+/// Section of the favourite projects, when the list is grouped.
+const FAVOURITES_SECTION: &str = "Favourites";
+/// Section of the projects matching none of the configured groups.
+const OTHER_SECTION: &str = "Other";
+
+/// Summary of a project shown next to its path. Parts which could not be fetched are left out.
+#[derive(Default)]
+struct Badges {
+    // Status of the latest pipeline of the default branch
+    latest_status: Option<PipelineStatus>,
+    running_pipelines: Option<usize>,
+    open_merge_requests: Option<usize>,
+}
+
+/// Statuses of the recent pipelines of some projects, None for the projects whose pipelines
+/// could not be fetched.
+type RecentStatuses = Vec<(String, Option<Vec<PipelineStatus>>)>;
+
+/// Statuses of the cached pipelines of the projects having any.
+type CachedStatuses = Vec<(String, Vec<PipelineStatus>)>;
+
+/// A row of the project list.
+enum Entry {
+    /// Heading of a section, listing `size` projects unless collapsed
+    Section {
+        name: String,
+        size: usize,
+        collapsed: bool,
+    },
+    Project {
+        path: String,
+        favourite: bool,
+    },
+}
+
+impl Entry {
+    /// Whether both entries stand for the same section or project.
+    fn is(&self, other: &Entry) -> bool {
+        match (self, other) {
+            (Entry::Section { name, .. }, Entry::Section { name: other, .. }) => name == other,
+            (Entry::Project { path, .. }, Entry::Project { path: other, .. }) => path == other,
+            _ => false,
+        }
+    }
+}
 
 #[derive(Default)]
 pub struct ProjectSelectorComponent {
     command_tx: Option<UnboundedSender<Action>>,
     config: Config,
     active_operation_index: usize,
+    // Sections of the configured groups, with the patterns of their projects
+    groups: Vec<(String, Vec<GlobMatcher>)>,
+    collapsed: HashSet<String>,
+    // Active project the selection last moved to, e.g. after opening it from the switcher
+    followed_project: Option<String>,
+    list_state: ListState,
+    client: GitlabClient,
+    cache: Cache,
+    discovery: ProjectDiscovery,
+    discovery_request: Option<PendingRequest<Vec<String>>>,
+    last_discovery_at: Option<Instant>,
+    // Projects in the rows drawn last, the only ones which are polled
+    visible_projects: Vec<String>,
+    // Statuses of the recent pipelines of every project, the newest first
+    recent_statuses: HashMap<String, Vec<PipelineStatus>>,
+    statuses_request: Option<PendingRequest<RecentStatuses>>,
+    // Statuses read from the cache in the background
+    cached_statuses_requests: Vec<PendingRequest<CachedStatuses>>,
+    last_poll_at: Option<Instant>,
+    badges: HashMap<String, Badges>,
+    badges_request: Option<PendingRequest<Vec<(String, Badges)>>>,
+    last_badges_at: Option<Instant>,
+    // Area computed while drawing, used for mouse hit-testing
+    area: Rect,
+    clicks: ClickTracker,
 }
 
 impl ProjectSelectorComponent {
@@ -21,26 +111,419 @@ impl ProjectSelectorComponent {
     }
 
     fn next(&mut self, state: &State) {
-        if state.focused_component == 1 {
-            let projects = &self.config.core.gitlab_projects;
+        if self.active_operation_index + 1 < self.entries(state).len() {
+            self.active_operation_index += 1;
+        }
+    }
+
+    fn previous(&mut self) {
+        if self.active_operation_index > 0 {
+            self.active_operation_index -= 1;
+        }
+    }
+
+    /// Index of the project displayed in a given terminal row, if any.
+    fn row_at(&self, row: u16, state: &State) -> Option<usize> {
+        // Rows start below the border
+        let first_row = self.area.y + 1;
+        if row < first_row || row >= self.area.bottom().saturating_sub(1) {
+            return None;
+        }
+        let index = (row - first_row) as usize + self.list_state.offset();
+        (index < self.entries(state).len()).then_some(index)
+    }
+
+    /// Rows of the project list: the favourite projects first, then the other ones, split into
+    /// sections if grouped. Projects of collapsed sections are left out.
+    fn entries(&self, state: &State) -> Vec<Entry> {
+        let favourites = &self.config.project_list.favourites;
+        let pinned = favourites
+            .iter()
+            .filter(|favourite| state.projects.contains(favourite));
+        let others = state
+            .projects
+            .iter()
+            .filter(|project| !favourites.contains(project));
+        let project_entry = |path: &String| Entry::Project {
+            path: path.clone(),
+            favourite: favourites.contains(path),
+        };
+        if self.config.project_list.group_by == GroupBy::None {
+            return pinned.chain(others).map(project_entry).collect();
+        }
+
+        // Configured groups keep their order, namespaces come in the order of their projects
+        let mut sections: Vec<(&str, Vec<&String>)> = vec![(FAVOURITES_SECTION, pinned.collect())];
+        sections.extend(self.groups.iter().map(|(name, _)| (name.as_str(), vec![])));
+        for project in others {
+            let name = self.section_of(project);
+            match sections.iter_mut().find(|(section, _)| *section == name) {
+                Some((_, projects)) => projects.push(project),
+                None => sections.push((name, vec![project])),
+            }
+        }
+        let mut entries = vec![];
+        for (name, projects) in sections {
+            if projects.is_empty() {
+                continue;
+            }
+            let collapsed = self.collapsed.contains(name);
+            entries.push(Entry::Section {
+                name: name.to_string(),
+                size: projects.len(),
+                collapsed,
+            });
+            if !collapsed {
+                entries.extend(projects.into_iter().map(project_entry));
+            }
+        }
+        entries
+    }
+
+    /// Name of the section of a project, when the list is grouped.
+    fn section_of<'a>(&'a self, project: &'a str) -> &'a str {
+        match self.config.project_list.group_by {
+            GroupBy::Namespace => project
+                .rsplit_once('/')
+                .map_or("", |(namespace, _)| namespace),
+            GroupBy::Groups => self
+                .groups
+                .iter()
+                .find(|(_, patterns)| patterns.iter().any(|pattern| pattern.is_match(project)))
+                .map_or(OTHER_SECTION, |(name, _)| name.as_str()),
+            GroupBy::None => "",
+        }
+    }
+
+    /// Collapse or expand the selected section, or the one of the selected project.
+    fn toggle_section(&mut self, state: &State) {
+        let entries = self.entries(state);
+        let Some((index, name)) = entries
+            .iter()
+            .enumerate()
+            .take(self.active_operation_index + 1)
+            .rev()
+            .find_map(|(index, entry)| match entry {
+                Entry::Section { name, .. } => Some((index, name)),
+                Entry::Project { .. } => None,
+            })
+        else {
+            return;
+        };
+        if !self.collapsed.remove(name) {
+            self.collapsed.insert(name.clone());
+        }
+        self.active_operation_index = index;
+    }
+
+    /// Discover projects periodically, and add them to the project list after the configured
+    /// ones.
+    fn discover_projects(&mut self, state: &mut State) {
+        if !self.config.discovery.is_enabled() {
+            return;
+        }
+        // Start with the projects discovered last time, which also keeps them while offline
+        if self.last_discovery_at.is_none() {
+            if let Some(discovered) = self.discovery.cached() {
+                self.set_projects(discovered, state);
+            }
+        }
+        if let Some(result) = self
+            .discovery_request
+            .as_mut()
+            .and_then(PendingRequest::poll)
+        {
+            self.discovery_request = None;
+            match result {
+                Ok(discovered) => self.set_projects(discovered, state),
+                Err(err) => tracing::warn!("Failed to discover projects: {}", err),
+            }
+        }
+        let refresh_interval = Duration::from_secs(self.config.discovery.refresh_interval);
+        let discovery_due = self
+            .last_discovery_at
+            .is_none_or(|discovered_at| discovered_at.elapsed() >= refresh_interval);
+        if self.discovery_request.is_none() && discovery_due {
+            self.last_discovery_at = Some(Instant::now());
+            let discovery = self.discovery.clone();
+            self.discovery_request =
+                Some(PendingRequest::spawn(
+                    async move { discovery.discover().await },
+                ));
+        }
+    }
+
+    /// Replace the discovered projects of the project list, keeping the same project selected.
+    fn set_projects(&mut self, discovered: Vec<String>, state: &mut State) {
+        let selected = self
+            .entries(state)
+            .into_iter()
+            .nth(self.active_operation_index);
+        let configured = &self.config.core.gitlab_projects;
+        let mut projects = configured.clone();
+        projects.extend(
+            discovered
+                .into_iter()
+                .filter(|project| !configured.contains(project)),
+        );
+        self.load_cached_statuses(&projects);
+        // Without the project list, there is no way to pick a project, so open the first one
+        if state.active_gitlab_project.is_none() && !state.layout.config.project_list {
+            state.active_gitlab_project = projects.first().cloned();
+        }
+        state.projects = projects;
+        self.active_operation_index = selected
+            .and_then(|selected| {
+                self.entries(state)
+                    .iter()
+                    .position(|entry| entry.is(&selected))
+            })
+            .unwrap_or(0);
+    }
+
+    /// Start with the cached pipelines of new projects, so that their sparklines show up before
+    /// the projects are polled.
+    fn load_cached_statuses(&mut self, projects: &[String]) {
+        let projects: Vec<String> = projects
+            .iter()
+            .filter(|project| !self.recent_statuses.contains_key(*project))
+            .cloned()
+            .collect();
+        if projects.is_empty() {
+            return;
+        }
+        // Reading a cache file per project would hold up the UI with long project lists
+        let cache = self.cache.clone();
+        self.cached_statuses_requests
+            .push(PendingRequest::spawn(async move {
+                Ok(tokio::task::spawn_blocking(move || {
+                    projects
+                        .into_iter()
+                        .filter_map(|project| {
+                            let entry = cache
+                                .load::<Vec<GitlabPipeline>>(&project, CacheKind::Pipelines)?;
+                            let statuses = entry
+                                .data
+                                .into_iter()
+                                .map(|pipeline| pipeline.status)
+                                .collect();
+                            Some((project, statuses))
+                        })
+                        .collect()
+                })
+                .await?)
+            }));
+    }
+
+    /// Fetch the recent pipelines of the visible projects periodically, for the sparklines, and
+    /// their badges less often. Projects scrolled into view are polled right away.
+    fn poll_projects(&mut self) {
+        if self.statuses_request.is_none() {
+            let projects = projects_to_poll(
+                &self.visible_projects,
+                &mut self.last_poll_at,
+                Duration::from_secs(self.config.core.refresh_interval),
+                |project| self.recent_statuses.contains_key(project),
+            );
+            if !projects.is_empty() {
+                let client = self.client.clone();
+                self.statuses_request = Some(PendingRequest::spawn(async move {
+                    Ok(stream::iter(projects)
+                        .map(|project| {
+                            let client = &client;
+                            async move {
+                                let statuses = client
+                                    .fetch_pipelines(&project, SPARKLINE_WIDTH as usize)
+                                    .await
+                                    .inspect_err(|err| {
+                                        tracing::warn!(
+                                            "Failed to fetch pipelines of {}: {}",
+                                            project,
+                                            err
+                                        )
+                                    })
+                                    .ok()
+                                    .map(|pipelines| {
+                                        pipelines
+                                            .into_iter()
+                                            .map(|pipeline| pipeline.status)
+                                            .collect()
+                                    });
+                                (project, statuses)
+                            }
+                        })
+                        .buffer_unordered(CONCURRENT_REQUESTS)
+                        .collect()
+                        .await)
+                }));
+            }
+        }
+        if self.badges_request.is_none() {
+            let projects = projects_to_poll(
+                &self.visible_projects,
+                &mut self.last_badges_at,
+                Duration::from_secs(self.config.project_list.badges_refresh_interval),
+                |project| self.badges.contains_key(project),
+            );
+            if !projects.is_empty() {
+                let client = self.client.clone();
+                self.badges_request = Some(PendingRequest::spawn(async move {
+                    Ok(stream::iter(projects)
+                        .map(|project| {
+                            let client = &client;
+                            async move {
+                                let badges = fetch_badges(client, &project).await;
+                                (project, badges)
+                            }
+                        })
+                        .buffer_unordered(CONCURRENT_REQUESTS)
+                        .collect()
+                        .await)
+                }));
+            }
+        }
+    }
 
-            if self.active_operation_index < projects.len() - 1 {
-                self.active_operation_index += 1;
+    fn handle_responses(&mut self) {
+        let mut cached = vec![];
+        self.cached_statuses_requests
+            .retain_mut(|request| match request.poll() {
+                Some(result) => {
+                    cached.extend(result.unwrap_or_default());
+                    false
+                }
+                None => true,
+            });
+        // Statuses fetched in the meantime are more recent than the cached ones
+        for (project, statuses) in cached {
+            self.recent_statuses.entry(project).or_insert(statuses);
+        }
+        if let Some(result) = self
+            .statuses_request
+            .as_mut()
+            .and_then(PendingRequest::poll)
+        {
+            self.statuses_request = None;
+            for (project, statuses) in result.unwrap_or_default() {
+                match statuses {
+                    Some(statuses) => {
+                        self.recent_statuses.insert(project, statuses);
+                    }
+                    // Keep the cached sparkline, without polling the project again right away
+                    None => {
+                        self.recent_statuses.entry(project).or_default();
+                    }
+                }
             }
         }
+        if let Some(result) = self.badges_request.as_mut().and_then(PendingRequest::poll) {
+            self.badges_request = None;
+            self.badges.extend(result.unwrap_or_default());
+        }
     }
 
-    fn previous(&mut self, state: &State) {
-        if state.focused_component == 1 {
-            if self.active_operation_index > 0 {
-                self.active_operation_index -= 1;
+    /// Sparkline of the recent pipelines of a project, the newest on the right. Finished
+    /// pipelines are drawn as full bars, the other ones as half bars.
+    fn sparkline(&self, project: &str) -> Sparkline<'_> {
+        let theme = &self.config.ui.theme;
+        let statuses = self
+            .recent_statuses
+            .get(project)
+            .map_or(&[][..], |statuses| statuses.as_slice());
+        let bars: Vec<SparklineBar> = statuses
+            .iter()
+            .take(SPARKLINE_WIDTH as usize)
+            .rev()
+            .map(|status| {
+                let value = match status {
+                    PipelineStatus::Success | PipelineStatus::Failed => 2,
+                    _ => 1,
+                };
+                SparklineBar::from(value).style(theme.status_style(status))
+            })
+            .collect();
+        Sparkline::default().data(bars).max(2)
+    }
+
+    /// Line of the list showing an entry: sections with their size, projects with their badges.
+    fn entry_line(&self, entry: &Entry, grouped: bool) -> Line<'static> {
+        let theme = &self.config.ui.theme;
+        let (path, favourite) = match entry {
+            Entry::Section {
+                name,
+                size,
+                collapsed,
+            } => {
+                let marker = if *collapsed { "▸" } else { "▾" };
+                return Line::from(format!("{} {} ({})", marker, name, size)).bold();
             }
+            Entry::Project { path, favourite } => (path, *favourite),
+        };
+        let badges = self.badges.get(path);
+        let mut spans = vec![];
+        if grouped {
+            spans.push(Span::raw("  "));
+        }
+        spans.push(
+            match badges.and_then(|badges| badges.latest_status.as_ref()) {
+                Some(status) => Span::styled(
+                    format!("{} ", theme.status_icon(status)),
+                    theme.status_style(status),
+                ),
+                None => Span::raw("  "),
+            },
+        );
+        spans.push(Span::raw(path.clone()));
+        if favourite {
+            spans.push(Span::raw(" ★"));
+        }
+        let running = badges.and_then(|badges| badges.running_pipelines);
+        if let Some(running) = running.filter(|running| *running > 0) {
+            spans.push(Span::styled(
+                format!(" ▶{}", running),
+                theme.status_style(&PipelineStatus::Running),
+            ));
+        }
+        let merge_requests = badges.and_then(|badges| badges.open_merge_requests);
+        if let Some(merge_requests) = merge_requests.filter(|count| *count > 0) {
+            spans.push(Span::raw(format!(" !{}", merge_requests)).dim());
+        }
+        Line::from(spans)
+    }
+
+    /// Select the active project when it was opened some other way than from the list.
+    fn follow_active_project(&mut self, state: &State) {
+        if self.followed_project == state.active_gitlab_project {
+            return;
+        }
+        self.followed_project = state.active_gitlab_project.clone();
+        if let Some(index) = self.entries(state).iter().position(|entry| {
+            matches!(entry, Entry::Project { path, .. } if Some(path) == state.active_gitlab_project.as_ref())
+        }) {
+            self.active_operation_index = index;
         }
     }
 
+    /// Open the selected project, or collapse or expand the selected section.
     fn select_project(&mut self, state: &mut State) {
-        let projects = &self.config.core.gitlab_projects;
-        state.active_gitlab_project = Some(projects[self.active_operation_index].clone());
+        let project = match self
+            .entries(state)
+            .into_iter()
+            .nth(self.active_operation_index)
+        {
+            Some(Entry::Project { path, .. }) => path,
+            Some(Entry::Section { .. }) => {
+                self.toggle_section(state);
+                return;
+            }
+            None => return,
+        };
+        state.active_gitlab_project = Some(project);
+        state.project_history.clear();
+        // Only one pane fits on the screen, so show the pipelines of the selected project
+        if state.layout.single_pane() {
+            state.focused_component = state.view.component();
+        }
     }
 }
 
@@ -51,6 +534,24 @@ impl Component for ProjectSelectorComponent {
     }
 
     fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.client = GitlabClient::new(config.core.gitlab_url.clone());
+        self.cache = Cache::new(config.cache.enabled);
+        self.discovery =
+            ProjectDiscovery::new(self.client.clone(), self.cache.clone(), &config.discovery)?;
+        self.groups = config
+            .project_list
+            .groups
+            .iter()
+            .map(|group| {
+                let patterns = group
+                    .projects
+                    .iter()
+                    .map(|pattern| Ok(Glob::new(pattern)?.compile_matcher()))
+                    .collect::<Result<_>>()?;
+                Ok((group.name.clone(), patterns))
+            })
+            .collect::<Result<_>>()?;
+        self.load_cached_statuses(&config.core.gitlab_projects);
         self.config = config;
         Ok(())
     }
@@ -58,39 +559,154 @@ impl Component for ProjectSelectorComponent {
     fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>> {
         match action {
             Action::Next => self.next(state),
-            Action::Previous => self.previous(state),
+            Action::Previous => self.previous(),
             Action::Enter => self.select_project(state),
-            Action::FocusUp => state.focused_component = 0, // change to header
-            Action::FocusDown => state.focused_component = 3, // change to footer
-            Action::FocusRight => state.focused_component = 2, // change to pipelines viewer
+            Action::ToggleExpand => self.toggle_section(state),
+            Action::Tick => {
+                self.discover_projects(state);
+                self.follow_active_project(state);
+                self.handle_responses();
+                self.poll_projects();
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn handle_mouse_event(
+        &mut self,
+        mouse: MouseEvent,
+        state: &mut State,
+    ) -> Result<Option<Action>> {
+        if state.popup.is_some() || !self.area.contains(Position::new(mouse.column, mouse.row)) {
+            return Ok(None);
+        }
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                state.focused_component = ComponentId::ProjectSelector;
+                if let Some(index) = self.row_at(mouse.row, state) {
+                    self.active_operation_index = index;
+                    if self.clicks.click(mouse.column, mouse.row) {
+                        self.select_project(state);
+                    }
+                }
+            }
+            MouseEventKind::ScrollDown => self.next(state),
+            MouseEventKind::ScrollUp => self.previous(),
             _ => {}
         }
         Ok(None)
     }
 
     fn draw(&mut self, frame: &mut Frame, area: Rect, state: &State) -> Result<()> {
-        let area = prepare_layout(area, Element::Body(Body::LeftColumn));
-        let block = get_block(state, 1, Color::LightMagenta);
-        let projects = &self.config.core.gitlab_projects;
-        let list_items: Vec<ListItem> = projects.iter().map(|i| ListItem::new(i.clone())).collect();
-
-        let mut list_state = ListState::default();
-        list_state.select(Some(self.active_operation_index));
-
-        let list = List::new(list_items)
-            .block(
-                block
-                    .title("Project Selector")
-                    .title_alignment(Alignment::Center),
-            )
-            .highlight_style(
-                Style::default()
-                    .fg(Color::Black)
-                    .bg(Color::White)
-                    .add_modifier(Modifier::BOLD),
-            );
+        let area = prepare_layout(area, Element::Body(Body::LeftColumn), state);
+        self.area = area.unwrap_or_default();
+        let Some(area) = area else {
+            self.visible_projects.clear();
+            return Ok(());
+        };
+        let theme = &self.config.ui.theme;
+        let block = get_block(
+            state,
+            ComponentId::ProjectSelector,
+            theme.color(theme.palette.project_selector_border),
+        );
+        let entries = self.entries(state);
+        let grouped = self.config.project_list.group_by != GroupBy::None;
+        let list_items: Vec<ListItem> = entries
+            .iter()
+            .map(|entry| ListItem::new(self.entry_line(entry, grouped)))
+            .collect();
+
+        self.list_state.select(Some(self.active_operation_index));
 
-        frame.render_stateful_widget(list, area, &mut list_state);
+        let block = block
+            .title("Project Selector")
+            .title_alignment(Alignment::Center);
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        // Sparklines only fit next to projects in wide enough panes
+        let sparklines_shown = inner.width >= 3 * SPARKLINE_WIDTH;
+        let [list_area, sparklines_area] = if sparklines_shown {
+            Layout::horizontal([Constraint::Fill(1), Constraint::Length(SPARKLINE_WIDTH + 1)])
+                .areas(inner)
+        } else {
+            [inner, Rect::default()]
+        };
+
+        let list = List::new(list_items).highlight_style(
+            theme
+                .highlight(
+                    theme.palette.selector_highlight_fg,
+                    theme.palette.selector_highlight_bg,
+                )
+                .add_modifier(Modifier::BOLD),
+        );
+
+        frame.render_stateful_widget(list, list_area, &mut self.list_state);
+        self.visible_projects = entries
+            .iter()
+            .skip(self.list_state.offset())
+            .take(list_area.height as usize)
+            .filter_map(|entry| match entry {
+                Entry::Project { path, .. } => Some(path.clone()),
+                Entry::Section { .. } => None,
+            })
+            .collect();
+
+        if sparklines_shown {
+            let offset = self.list_state.offset();
+            for (row, entry) in entries
+                .iter()
+                .skip(offset)
+                .take(sparklines_area.height as usize)
+                .enumerate()
+            {
+                let Entry::Project { path: project, .. } = entry else {
+                    continue;
+                };
+                let sparkline_area = Rect::new(
+                    sparklines_area.x + 1,
+                    sparklines_area.y + row as u16,
+                    SPARKLINE_WIDTH,
+                    1,
+                );
+                frame.render_widget(self.sparkline(project), sparkline_area);
+            }
+        }
         Ok(())
     }
 }
+
+/// Visible projects to poll: all of them once `interval` elapsed since the last poll, and
+/// otherwise the ones which were not polled yet.
+fn projects_to_poll(
+    visible: &[String],
+    last_poll_at: &mut Option<Instant>,
+    interval: Duration,
+    polled: impl Fn(&str) -> bool,
+) -> Vec<String> {
+    let due = last_poll_at.is_none_or(|polled_at| polled_at.elapsed() >= interval);
+    if due && !visible.is_empty() {
+        *last_poll_at = Some(Instant::now());
+    }
+    visible
+        .iter()
+        .filter(|project| due || !polled(project))
+        .cloned()
+        .collect()
+}
+
+async fn fetch_badges(client: &GitlabClient, gitlab_project: &str) -> Badges {
+    let (latest, running, merge_requests) = tokio::join!(
+        client.fetch_latest_pipeline(gitlab_project),
+        client.count_running_pipelines(gitlab_project),
+        client.count_open_merge_requests(gitlab_project),
+    );
+    // Badges are a bonus, e.g. merge requests may be disabled in a project
+    Badges {
+        latest_status: latest.ok().flatten().map(|pipeline| pipeline.status),
+        running_pipelines: running.ok().flatten(),
+        open_merge_requests: merge_requests.ok().flatten(),
+    }
+}
@@ -0,0 +1,317 @@
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+use color_eyre::Result;
+use crossterm::event::{KeyEvent, MouseEvent, MouseEventKind};
+use ratatui::{prelude::*, widgets::*};
+
+use super::{
+    utils::{fuzzy_match, highlight_chars, popup_area, PendingRequest, TextInput},
+    Component, ComponentId,
+};
+use crate::{
+    action::Action,
+    cache::{Cache, CacheKind},
+    config::Config,
+    gitlab::GitlabClient,
+    state::{InputMode, State},
+};
+
+/// Number of recently opened projects which are remembered.
+const MAX_RECENT_PROJECTS: usize = 20;
+/// GitLab is only searched once this many characters are typed.
+const MIN_SEARCH_LENGTH: usize = 3;
+/// GitLab is only searched once the user stops typing for this long.
+const SEARCH_DELAY: Duration = Duration::from_millis(300);
+
+/// A project matching the typed text.
+struct Match {
+    project: String,
+    // Positions of the matched characters, to highlight them
+    positions: Vec<usize>,
+    recent: bool,
+    // Found by searching GitLab, not in the project list
+    found: bool,
+}
+
+/// Popup switching to a project by typing part of its path: the projects of the project list,
+/// the recently opened ones and, if enabled, the projects found on GitLab are fuzzy-matched
+/// against the typed text. Recently opened projects are ranked first, and kept in the cache
+/// across sessions.
+#[derive(Default)]
+pub struct ProjectSwitcherComponent {
+    config: Config,
+    client: GitlabClient,
+    cache: Cache,
+    show_popup: bool,
+    input: TextInput,
+    list_state: ListState,
+    // Projects opened lately, the most recent first
+    recent: Vec<String>,
+    // Projects found on GitLab for the last search
+    found: Vec<String>,
+    searched: String,
+    search_request: Option<PendingRequest<Vec<String>>>,
+    search_error: Option<String>,
+    // When the text last changed, so that GitLab is not searched on every key
+    typed_at: Option<Instant>,
+    // Matches of the typed text, computed again only when the text or the candidates change
+    matches: Vec<Match>,
+    matches_outdated: bool,
+    // Project list the matches were computed for
+    matched_projects: Vec<String>,
+}
+
+impl ProjectSwitcherComponent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn open(&mut self, state: &mut State) {
+        self.show_popup = true;
+        self.input = TextInput::default();
+        self.list_state.select(Some(0));
+        self.found.clear();
+        self.searched.clear();
+        self.search_request = None;
+        self.search_error = None;
+        self.matches_outdated = true;
+        state.input_mode = InputMode::Insert;
+        state.popup = Some(ComponentId::ProjectSwitcher);
+    }
+
+    fn close(&mut self, state: &mut State) {
+        self.show_popup = false;
+        state.input_mode = InputMode::Normal;
+        state.popup = None;
+    }
+
+    /// Remember the active project, whichever way it was opened.
+    fn track_recent(&mut self, state: &State) {
+        let Some(project) = &state.active_gitlab_project else {
+            return;
+        };
+        if self.recent.first() == Some(project) {
+            return;
+        }
+        self.recent.retain(|recent| recent != project);
+        self.recent.insert(0, project.clone());
+        self.recent.truncate(MAX_RECENT_PROJECTS);
+        self.matches_outdated = true;
+        if let Err(error) = self
+            .cache
+            .store_shared(CacheKind::RecentProjects, &self.recent)
+        {
+            tracing::warn!("Failed to cache recent projects: {}", error);
+        }
+    }
+
+    /// Projects matching the typed text, the recent ones first and then the best matches.
+    /// Projects found on GitLab are kept even if they do not match, as GitLab also searches
+    /// their names.
+    fn find_matches(&self, state: &State) -> Vec<Match> {
+        let query = self.input.value();
+        let recent: HashSet<&String> = self.recent.iter().collect();
+        let listed: HashSet<&String> = state.projects.iter().collect();
+        let mut seen = HashSet::new();
+        let candidates = self
+            .recent
+            .iter()
+            .chain(&state.projects)
+            .map(|project| (project, false))
+            .chain(self.found.iter().map(|project| (project, true)))
+            .filter(|(project, _)| seen.insert(*project));
+        let mut matches: Vec<(i64, Match)> = candidates
+            .filter_map(|(project, found)| {
+                let (score, positions) = match fuzzy_match(query, project) {
+                    Some(fuzzy) => (fuzzy.score, fuzzy.positions),
+                    None if found => (i64::MIN, vec![]),
+                    None => return None,
+                };
+                let recent = recent.contains(project);
+                let found = found && !listed.contains(project);
+                let project = project.clone();
+                Some((
+                    score,
+                    Match {
+                        project,
+                        positions,
+                        recent,
+                        found,
+                    },
+                ))
+            })
+            .collect();
+        // The sort is stable, so equal matches keep the order of the project list
+        matches.sort_by_key(|(score, m)| (!m.recent, std::cmp::Reverse(*score)));
+        matches.into_iter().map(|(_, m)| m).collect()
+    }
+
+    /// Match the typed text again if it, the project list, the recent or the found projects
+    /// changed since the last time.
+    fn refresh_matches(&mut self, state: &State) {
+        if self.matches_outdated || self.matched_projects != state.projects {
+            self.matches = self.find_matches(state);
+            self.matched_projects = state.projects.clone();
+            self.matches_outdated = false;
+        }
+    }
+
+    fn select_project(&mut self, state: &mut State) {
+        self.refresh_matches(state);
+        let selected = self.list_state.selected().unwrap_or_default();
+        let Some(project) = self.matches.get(selected).map(|m| m.project.clone()) else {
+            return;
+        };
+        state.active_gitlab_project = Some(project);
+        state.project_history.clear();
+        if state.focused_component == ComponentId::ProjectSelector && state.layout.single_pane() {
+            state.focused_component = state.view.component();
+        }
+        self.close(state);
+    }
+
+    /// Search GitLab once the user stops typing, if enabled.
+    fn search(&mut self) {
+        if let Some(result) = self.search_request.as_mut().and_then(PendingRequest::poll) {
+            self.search_request = None;
+            match result {
+                Ok(found) => {
+                    self.found = found;
+                    self.matches_outdated = true;
+                    self.search_error = None;
+                }
+                Err(error) => self.search_error = Some(error.to_string()),
+            }
+        }
+        let query = self.input.value().trim();
+        let typing = self
+            .typed_at
+            .is_some_and(|typed_at| typed_at.elapsed() < SEARCH_DELAY);
+        if !self.config.project_switcher.search_gitlab
+            || self.search_request.is_some()
+            || typing
+            || query.chars().count() < MIN_SEARCH_LENGTH
+            || query == self.searched
+        {
+            return;
+        }
+        self.searched = query.to_string();
+        let client = self.client.clone();
+        let query = query.to_string();
+        self.search_request = Some(PendingRequest::spawn(async move {
+            let projects = client.search_projects(&query).await?;
+            Ok(projects
+                .into_iter()
+                .map(|project| project.path_with_namespace)
+                .collect())
+        }));
+    }
+}
+
+impl Component for ProjectSwitcherComponent {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.client = GitlabClient::new(config.core.gitlab_url.clone());
+        self.cache = Cache::new(config.cache.enabled);
+        if let Some(entry) = self.cache.load_shared(CacheKind::RecentProjects) {
+            self.recent = entry.data;
+        }
+        self.config = config;
+        Ok(())
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent, _state: &mut State) -> Result<Option<Action>> {
+        if self.show_popup && self.input.handle_key(key) {
+            self.list_state.select(Some(0));
+            self.typed_at = Some(Instant::now());
+            self.matches_outdated = true;
+        }
+        Ok(None)
+    }
+
+    fn handle_mouse_event(
+        &mut self,
+        mouse: MouseEvent,
+        _state: &mut State,
+    ) -> Result<Option<Action>> {
+        if self.show_popup {
+            match mouse.kind {
+                MouseEventKind::ScrollDown => self.list_state.select_next(),
+                MouseEventKind::ScrollUp => self.list_state.select_previous(),
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+
+    fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>> {
+        self.track_recent(state);
+        match action {
+            Action::SwitchProject if !self.show_popup && state.popup.is_none() => self.open(state),
+            Action::Close if self.show_popup => self.close(state),
+            Action::Next if self.show_popup => self.list_state.select_next(),
+            Action::Previous if self.show_popup => self.list_state.select_previous(),
+            Action::Enter if self.show_popup => self.select_project(state),
+            Action::Tick if self.show_popup => self.search(),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect, state: &State) -> Result<()> {
+        if !self.show_popup {
+            return Ok(());
+        }
+        self.refresh_matches(state);
+        let theme = &self.config.ui.theme;
+        let matches = &self.matches;
+        let status = if self.search_request.is_some() {
+            " searching GitLab... ".to_string()
+        } else if let Some(error) = &self.search_error {
+            format!(" search failed: {} ", error)
+        } else {
+            " type to search ".to_string()
+        };
+        let area = popup_area(area, 60, 60);
+        let block = Block::bordered()
+            .border_type(BorderType::Thick)
+            .border_style(theme.fg(theme.palette.popup_border))
+            .title(format!("Switch project ({})", matches.len()))
+            .title_bottom(Line::from(status).right_aligned());
+        let inner_area = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let [input_area, list_area] =
+            Layout::vertical([Constraint::Length(2), Constraint::Fill(1)]).areas(inner_area);
+        let mut spans = vec![Span::styled("Project: ", Style::default().bold())];
+        spans.extend(self.input.spans(true));
+        frame.render_widget(Line::from(spans), input_area);
+
+        let matched = theme.fg(theme.palette.accent).add_modifier(Modifier::BOLD);
+        let items: Vec<ListItem> = matches
+            .iter()
+            .map(|m| {
+                let mut line = highlight_chars(&m.project, &m.positions, matched);
+                if m.recent {
+                    line.push_span(Span::raw(" (recent)").dim());
+                } else if m.found {
+                    line.push_span(Span::raw(" (GitLab)").dim());
+                }
+                ListItem::new(line)
+            })
+            .collect();
+        if let Some(selected) = self.list_state.selected() {
+            if selected >= items.len() {
+                self.list_state.select(items.len().checked_sub(1));
+            }
+        }
+        let list = List::new(items).highlight_style(
+            theme.highlight(theme.palette.highlight_fg, theme.palette.highlight_bg),
+        );
+        frame.render_stateful_widget(list, list_area, &mut self.list_state);
+        Ok(())
+    }
+}
@@ -0,0 +1,505 @@
+use std::time::{Duration, Instant};
+
+use color_eyre::{eyre::Error, Result};
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use futures::{stream, StreamExt};
+use ratatui::{prelude::*, widgets::*};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    utils::{format_age, get_block, prepare_layout, Body, Element, PendingRequest},
+    Component, ComponentId,
+};
+use crate::{
+    action::Action,
+    cache::{Cache, CacheKind},
+    config::Config,
+    gitlab::{is_unreachable, GitlabClient, GitlabRunner, PipelineStatus, RunnerJob},
+    state::{PipelineRef, State, View},
+    theme::Theme,
+};
+
+/// Number of runners fetched at the same time.
+const CONCURRENT_REQUESTS: usize = 8;
+
+/// A runner together with the jobs it is running.
+#[derive(Clone, Serialize, Deserialize)]
+struct RunnerStatus {
+    runner: GitlabRunner,
+    // None if the jobs of the runner are not visible, e.g. for instance runners
+    running_jobs: Option<Vec<RunnerJob>>,
+    // Set if only the listing of the runner could be fetched, which leaves its tags unknown
+    #[serde(default)]
+    details_missing: bool,
+}
+
+/// Runners available to the selected project with their status, and the jobs a runner is
+/// running.
+#[derive(Default)]
+pub struct RunnersComponent {
+    config: Config,
+    client: GitlabClient,
+    cache: Cache,
+    // Project for which `runners` was loaded
+    loaded_project: Option<String>,
+    runners: Option<Vec<RunnerStatus>>,
+    error: Option<Error>,
+    request: Option<PendingRequest<Vec<RunnerStatus>>>,
+    last_request_at: Option<Instant>,
+    // ID of the runner whose jobs are listed
+    jobs_of: Option<u32>,
+    table_state: TableState,
+    jobs_state: TableState,
+    // Area computed while drawing, used for mouse hit-testing
+    area: Rect,
+}
+
+impl RunnersComponent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Refresh the runners while they are shown, starting from the cached ones whenever the
+    /// active project changes.
+    fn refresh(&mut self, state: &mut State) {
+        if state.view != View::Runners {
+            return;
+        }
+        if self.loaded_project != state.active_gitlab_project {
+            self.loaded_project = state.active_gitlab_project.clone();
+            self.request = None;
+            self.last_request_at = None;
+            self.error = None;
+            self.close_jobs();
+            let runners = self.loaded_project.as_ref().and_then(|gitlab_project| {
+                self.cache
+                    .load::<Vec<RunnerStatus>>(gitlab_project, CacheKind::Runners)
+                    .map(|entry| entry.data)
+            });
+            self.set_runners(runners);
+        }
+        let Some(gitlab_project) = self.loaded_project.clone() else {
+            return;
+        };
+
+        if let Some(result) = self.request.as_mut().and_then(PendingRequest::poll) {
+            self.request = None;
+            match result {
+                Ok(runners) => {
+                    state.offline = false;
+                    if let Err(error) =
+                        self.cache
+                            .store(&gitlab_project, CacheKind::Runners, &runners)
+                    {
+                        tracing::warn!("Failed to cache runners: {}", error);
+                    }
+                    self.set_runners(Some(runners));
+                    self.error = None;
+                }
+                Err(error) => {
+                    state.offline = is_unreachable(&error);
+                    self.error = Some(error);
+                }
+            }
+        }
+
+        let refresh_interval = Duration::from_secs(self.config.core.refresh_interval);
+        let refresh_due = self
+            .last_request_at
+            .is_none_or(|requested_at| requested_at.elapsed() >= refresh_interval);
+        if self.request.is_none() && refresh_due {
+            let client = self.client.clone();
+            self.last_request_at = Some(Instant::now());
+            self.request = Some(PendingRequest::spawn(async move {
+                fetch_runners(client, gitlab_project).await
+            }));
+        }
+    }
+
+    fn set_runners(&mut self, runners: Option<Vec<RunnerStatus>>) {
+        let len = runners.as_ref().map_or(0, Vec::len);
+        let selected = self.table_state.selected().unwrap_or(0);
+        self.table_state
+            .select((len > 0).then(|| selected.min(len - 1)));
+        self.runners = runners;
+        let jobs_len = self.running_jobs().map_or(0, <[RunnerJob]>::len);
+        let selected = self.jobs_state.selected().unwrap_or(0);
+        self.jobs_state
+            .select((jobs_len > 0).then(|| selected.min(jobs_len - 1)));
+    }
+
+    fn selected_runner(&self) -> Option<&RunnerStatus> {
+        self.runners.as_ref()?.get(self.table_state.selected()?)
+    }
+
+    /// Jobs listed once a runner was entered. They follow the runner across refreshes.
+    fn running_jobs(&self) -> Option<&[RunnerJob]> {
+        let id = self.jobs_of?;
+        self.runners
+            .as_ref()?
+            .iter()
+            .find(|runner| runner.runner.id == id)?
+            .running_jobs
+            .as_deref()
+    }
+
+    fn open_jobs(&mut self) {
+        let Some(runner) = self.selected_runner() else {
+            return;
+        };
+        // Unknown jobs leave nothing to list
+        if runner.running_jobs.is_none() {
+            return;
+        }
+        self.jobs_of = Some(runner.runner.id);
+        self.jobs_state = TableState::default();
+        let len = self.running_jobs().map_or(0, <[RunnerJob]>::len);
+        self.jobs_state.select((len > 0).then_some(0));
+    }
+
+    fn close_jobs(&mut self) {
+        self.jobs_of = None;
+        self.jobs_state = TableState::default();
+    }
+
+    fn show_graph(&mut self, state: &mut State) {
+        let Some(job) = self
+            .running_jobs()
+            .and_then(|jobs| jobs.get(self.jobs_state.selected()?))
+        else {
+            return;
+        };
+        if let (Some(pipeline), Some(project)) = (&job.pipeline, &job.project) {
+            state.show_graph(PipelineRef {
+                gitlab_project: project.path_with_namespace.clone(),
+                id: pipeline.id,
+            });
+        }
+    }
+
+    fn table_state(&mut self) -> (&mut TableState, usize) {
+        if self.jobs_of.is_some() {
+            let len = self.running_jobs().map_or(0, <[RunnerJob]>::len);
+            (&mut self.jobs_state, len)
+        } else {
+            (
+                &mut self.table_state,
+                self.runners.as_ref().map_or(0, Vec::len),
+            )
+        }
+    }
+
+    fn next(&mut self) {
+        let (table_state, len) = self.table_state();
+        if let Some(selected) = table_state.selected() {
+            if selected + 1 < len {
+                table_state.select(Some(selected + 1));
+            }
+        }
+    }
+
+    fn previous(&mut self) {
+        let (table_state, _) = self.table_state();
+        if let Some(selected) = table_state.selected() {
+            table_state.select(Some(selected.saturating_sub(1)));
+        }
+    }
+
+    fn draw_runners(&mut self, frame: &mut Frame, area: Rect, block: Block) {
+        let theme = &self.config.ui.theme;
+        let runners = self.runners.as_deref().unwrap_or_default();
+        let header_row = Row::new([
+            "Runner",
+            "Type",
+            "Status",
+            "Tags",
+            "Executor",
+            "Version",
+            "Running",
+            "Last contact",
+        ])
+        .bold();
+        let rows = runners.iter().map(|status| {
+            let runner = &status.runner;
+            let mut tags = runner.tag_list.join(", ");
+            if status.details_missing {
+                tags = "?".to_string();
+            } else if runner.run_untagged {
+                tags = if tags.is_empty() {
+                    "(untagged)".to_string()
+                } else {
+                    format!("{} (untagged)", tags)
+                };
+            }
+            let platform = match (&runner.platform, &runner.architecture) {
+                (Some(platform), Some(architecture)) => format!(" {}/{}", platform, architecture),
+                _ => String::new(),
+            };
+            let row = Row::new([
+                Line::from(format!(
+                    "#{} {}",
+                    runner.id,
+                    runner.description.as_deref().unwrap_or_default()
+                )),
+                Line::from(runner.runner_type.trim_end_matches("_type").to_string()),
+                runner_status_line(runner, theme),
+                Line::from(tags),
+                Line::from(runner.executor.clone().unwrap_or_default()),
+                Line::from(format!(
+                    "{}{}",
+                    runner.version.as_deref().unwrap_or_default(),
+                    platform
+                )),
+                Line::from(
+                    status
+                        .running_jobs
+                        .as_ref()
+                        .map_or("?".to_string(), |jobs| jobs.len().to_string()),
+                ),
+                Line::from(runner.contacted_at.map(format_age).unwrap_or_default()),
+            ]);
+            if runner.paused || !runner.is_online() {
+                row.add_modifier(Modifier::DIM)
+            } else {
+                row
+            }
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Min(20),
+                Constraint::Length(8),
+                Constraint::Length(16),
+                Constraint::Min(16),
+                Constraint::Length(16),
+                Constraint::Length(22),
+                Constraint::Length(7),
+                Constraint::Length(12),
+            ],
+        )
+        .column_spacing(2)
+        .header(header_row)
+        .row_highlight_style(
+            theme.highlight(theme.palette.highlight_fg, theme.palette.highlight_bg),
+        )
+        .block(block);
+        frame.render_stateful_widget(table, area, &mut self.table_state);
+    }
+
+    fn draw_jobs(&mut self, frame: &mut Frame, area: Rect, block: Block) {
+        let theme = &self.config.ui.theme;
+        let Some(id) = self.jobs_of else {
+            return;
+        };
+        let jobs = self.running_jobs().unwrap_or_default();
+        let header_row = Row::new(["Job", "Stage", "Project", "Pipeline", "Ref", "Started"]).bold();
+        let rows = jobs.iter().map(|job| {
+            Row::new([
+                Line::styled(
+                    format!(
+                        "{} #{} {}",
+                        theme.status_icon(&job.status),
+                        job.id,
+                        job.name
+                    ),
+                    theme.status_style(&job.status),
+                ),
+                Line::from(job.stage.clone()),
+                Line::from(
+                    job.project
+                        .as_ref()
+                        .map(|project| project.path_with_namespace.clone())
+                        .unwrap_or_default(),
+                ),
+                Line::from(
+                    job.pipeline
+                        .as_ref()
+                        .map(|pipeline| format!("#{}", pipeline.id))
+                        .unwrap_or_default(),
+                ),
+                Line::from(
+                    job.pipeline
+                        .as_ref()
+                        .map(|pipeline| pipeline.git_ref.clone())
+                        .unwrap_or_default(),
+                ),
+                Line::from(job.started_at.map(format_age).unwrap_or_default()),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Min(24),
+                Constraint::Length(12),
+                Constraint::Min(20),
+                Constraint::Length(10),
+                Constraint::Min(12),
+                Constraint::Length(10),
+            ],
+        )
+        .column_spacing(2)
+        .header(header_row)
+        .row_highlight_style(
+            theme.highlight(theme.palette.highlight_fg, theme.palette.highlight_bg),
+        )
+        .block(block.title(format!("Jobs running on runner #{}", id)));
+        frame.render_stateful_widget(table, area, &mut self.jobs_state);
+    }
+}
+
+impl Component for RunnersComponent {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.client = GitlabClient::new(config.core.gitlab_url.clone());
+        self.cache = Cache::new(config.cache.enabled);
+        self.config = config;
+        Ok(())
+    }
+
+    fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>> {
+        match action {
+            Action::Tick => self.refresh(state),
+            Action::Next => self.next(),
+            Action::Previous => self.previous(),
+            Action::Enter if self.jobs_of.is_none() => self.open_jobs(),
+            Action::ShowGraph => self.show_graph(state),
+            Action::Close => self.close_jobs(),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn handle_mouse_event(
+        &mut self,
+        mouse: MouseEvent,
+        state: &mut State,
+    ) -> Result<Option<Action>> {
+        if state.popup.is_some() || !self.area.contains(Position::new(mouse.column, mouse.row)) {
+            return Ok(None);
+        }
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                state.focused_component = ComponentId::Runners;
+                // Rows start below the border, the padding and the header row
+                let first_row = self.area.y + 3;
+                if mouse.row >= first_row {
+                    let (table_state, len) = self.table_state();
+                    let index = (mouse.row - first_row) as usize + table_state.offset();
+                    if index < len {
+                        table_state.select(Some(index));
+                    }
+                }
+            }
+            MouseEventKind::ScrollDown => self.next(),
+            MouseEventKind::ScrollUp => self.previous(),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect, state: &State) -> Result<()> {
+        let area = prepare_layout(area, Element::Body(Body::RightColumn), state)
+            .filter(|_| state.view == View::Runners);
+        self.area = area.unwrap_or_default();
+        let Some(area) = area else {
+            return Ok(());
+        };
+        let theme = &self.config.ui.theme;
+        let mut block = get_block(
+            state,
+            ComponentId::Runners,
+            theme.color(theme.palette.pipelines_border),
+        )
+        .padding(Padding::uniform(1));
+
+        if self.runners.is_none() {
+            let message = match &self.error {
+                Some(error) => Span::styled(
+                    format!("ERROR: {}", error),
+                    theme.fg(theme.palette.error).add_modifier(Modifier::BOLD),
+                ),
+                None if self.loaded_project.is_none() => Span::raw("Project not selected"),
+                None => Span::styled(
+                    "Loading...",
+                    theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD),
+                ),
+            };
+            let paragraph = Paragraph::new(Line::from(message))
+                .block(block.title("Runners"))
+                .alignment(Alignment::Center);
+            frame.render_widget(paragraph, area);
+            return Ok(());
+        }
+
+        if let Some(error) = &self.error {
+            block = block.title(Line::styled(
+                format!(" STALE ({}) ", error),
+                theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD),
+            ));
+        }
+        if self.jobs_of.is_some() {
+            self.draw_jobs(frame, area, block);
+        } else {
+            self.draw_runners(frame, area, block.title("Runners"));
+        }
+        Ok(())
+    }
+}
+
+/// Status of a runner, styled like the pipeline status it is the closest to.
+fn runner_status_line(runner: &GitlabRunner, theme: &Theme) -> Line<'static> {
+    let (text, status) = if runner.paused {
+        ("paused".to_string(), PipelineStatus::Manual)
+    } else if runner.is_online() {
+        ("online".to_string(), PipelineStatus::Success)
+    } else {
+        (runner.status.replace('_', " "), PipelineStatus::Failed)
+    };
+    Line::styled(
+        format!("{} {}", theme.status_icon(&status), text),
+        theme.status_style(&status),
+    )
+}
+
+/// Fetch the runners of a project with their details and running jobs, the ones able to pick
+/// jobs first.
+async fn fetch_runners(client: GitlabClient, gitlab_project: String) -> Result<Vec<RunnerStatus>> {
+    let runners = client.fetch_runners(&gitlab_project).await?;
+    let ids: Vec<u32> = runners.iter().map(|runner| runner.id).collect();
+    // Executors are a nicety, which older GitLab versions do not provide
+    let executors = client
+        .fetch_runner_executors(&ids)
+        .await
+        .inspect_err(|error| tracing::warn!("Failed to fetch runner executors: {}", error))
+        .unwrap_or_default();
+    let mut runners: Vec<RunnerStatus> = stream::iter(runners)
+        .map(|listed| {
+            let client = &client;
+            let executor = executors.get(&listed.id).cloned();
+            async move {
+                // A runner may not be visible in full, e.g. an instance runner for non-admins
+                let (mut runner, details_missing) = match client.fetch_runner(listed.id).await {
+                    Ok(runner) => (runner, false),
+                    Err(error) => {
+                        tracing::warn!("Failed to fetch runner {}: {}", listed.id, error);
+                        (listed, true)
+                    }
+                };
+                runner.executor = executor;
+                // Only administrators see the jobs of instance runners
+                let running_jobs = client.fetch_runner_jobs(runner.id).await.ok();
+                RunnerStatus {
+                    runner,
+                    running_jobs,
+                    details_missing,
+                }
+            }
+        })
+        .buffered(CONCURRENT_REQUESTS)
+        .collect()
+        .await;
+    runners.sort_by_key(|status| {
+        let runner = &status.runner;
+        (runner.paused || !runner.is_online(), runner.id)
+    });
+    Ok(runners)
+}
@@ -0,0 +1,784 @@
+use std::time::{Duration, Instant};
+
+use color_eyre::{eyre::Error, Result};
+use crossterm::event::{KeyEvent, MouseButton, MouseEvent, MouseEventKind};
+use futures::{stream, StreamExt};
+use ratatui::{prelude::*, widgets::*};
+
+use super::{
+    utils::{
+        format_timestamp, get_block, popup_area, prepare_layout, Body, Element, Form,
+        PendingRequest, OFFLINE_MESSAGE,
+    },
+    Component, ComponentId,
+};
+use crate::{
+    action::Action,
+    cache::{Cache, CacheKind},
+    config::Config,
+    gitlab::{is_unreachable, GitlabClient, GitlabSchedule, ScheduleVariable},
+    state::{InputMode, PipelineRef, State, View},
+};
+
+/// Number of schedules fetched at the same time.
+const CONCURRENT_REQUESTS: usize = 8;
+
+/// Change of a schedule waiting for the user to confirm it.
+enum Change {
+    Run {
+        id: u32,
+        description: String,
+    },
+    SetActive {
+        id: u32,
+        description: String,
+        active: bool,
+    },
+}
+
+impl Change {
+    fn prompt(&self) -> String {
+        match self {
+            Change::Run { description, .. } => {
+                format!("Run \"{}\" now? enter: confirm, esc: cancel", description)
+            }
+            Change::SetActive {
+                description,
+                active,
+                ..
+            } => format!(
+                "{} \"{}\"? enter: confirm, esc: cancel",
+                if *active { "Activate" } else { "Deactivate" },
+                description
+            ),
+        }
+    }
+}
+
+/// Form to add a variable, or change the value of an existing one.
+struct VariableForm {
+    fields: Form,
+    // Variables are identified by their key, so only the value of existing ones can change
+    existing: bool,
+}
+
+impl VariableForm {
+    fn new(variable: Option<&ScheduleVariable>) -> Self {
+        let fields = Form::new()
+            .text(
+                "Key",
+                variable.map_or("", |variable| &variable.key),
+                variable.is_none(),
+            )
+            .text(
+                "Value",
+                variable.map_or("", |variable| &variable.value),
+                true,
+            );
+        Self {
+            fields,
+            existing: variable.is_some(),
+        }
+    }
+}
+
+/// Popup listing the variables of a schedule, to add, change or delete them.
+struct VariablesEditor {
+    schedule_id: u32,
+    table_state: TableState,
+    form: Option<VariableForm>,
+    // Key of the variable to delete once confirmed
+    pending_delete: Option<String>,
+}
+
+/// Pipeline schedules of the selected project, with their last pipeline. Schedules can be run
+/// right away and (de)activated, each change being confirmed first, and their variables edited.
+#[derive(Default)]
+pub struct SchedulesComponent {
+    config: Config,
+    client: GitlabClient,
+    cache: Cache,
+    // Project for which `schedules` was loaded
+    loaded_project: Option<String>,
+    schedules: Option<Vec<GitlabSchedule>>,
+    error: Option<Error>,
+    request: Option<PendingRequest<Vec<GitlabSchedule>>>,
+    last_request_at: Option<Instant>,
+    // Change requested by the user, resolving to a message telling what was done
+    change_request: Option<PendingRequest<String>>,
+    confirmation: Option<Change>,
+    message: Option<std::result::Result<String, String>>,
+    editor: Option<VariablesEditor>,
+    table_state: TableState,
+    // Area computed while drawing, used for mouse hit-testing
+    area: Rect,
+}
+
+impl SchedulesComponent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Refresh the schedules while they are shown, starting from the cached ones whenever the
+    /// active project changes.
+    fn refresh(&mut self, state: &mut State) {
+        if state.view != View::Schedules {
+            return;
+        }
+        if self.loaded_project != state.active_gitlab_project {
+            self.loaded_project = state.active_gitlab_project.clone();
+            self.request = None;
+            self.last_request_at = None;
+            self.error = None;
+            self.message = None;
+            self.confirmation = None;
+            self.close_editor(state);
+            let schedules = self.loaded_project.as_ref().and_then(|gitlab_project| {
+                self.cache
+                    .load::<Vec<GitlabSchedule>>(gitlab_project, CacheKind::Schedules)
+                    .map(|entry| entry.data)
+            });
+            self.set_schedules(schedules);
+        }
+        let Some(gitlab_project) = self.loaded_project.clone() else {
+            return;
+        };
+
+        if let Some(result) = self.request.as_mut().and_then(PendingRequest::poll) {
+            self.request = None;
+            match result {
+                Ok(schedules) => {
+                    state.offline = false;
+                    // Values of variables are often secrets, which must not end up on disk
+                    let cached: Vec<GitlabSchedule> = schedules
+                        .iter()
+                        .cloned()
+                        .map(|mut schedule| {
+                            schedule.variables.clear();
+                            schedule
+                        })
+                        .collect();
+                    if let Err(error) =
+                        self.cache
+                            .store(&gitlab_project, CacheKind::Schedules, &cached)
+                    {
+                        tracing::warn!("Failed to cache pipeline schedules: {}", error);
+                    }
+                    self.set_schedules(Some(schedules));
+                    self.error = None;
+                }
+                Err(error) => {
+                    state.offline = is_unreachable(&error);
+                    self.error = Some(error);
+                }
+            }
+        }
+        if let Some(result) = self.change_request.as_mut().and_then(PendingRequest::poll) {
+            self.change_request = None;
+            self.message = Some(result.map_err(|error| error.to_string()));
+            // Show the outcome of the change right away
+            self.request = None;
+            self.last_request_at = None;
+        }
+
+        let refresh_interval = Duration::from_secs(self.config.core.refresh_interval);
+        let refresh_due = self
+            .last_request_at
+            .is_none_or(|requested_at| requested_at.elapsed() >= refresh_interval);
+        if self.request.is_none() && refresh_due {
+            let client = self.client.clone();
+            self.last_request_at = Some(Instant::now());
+            self.request = Some(PendingRequest::spawn(async move {
+                fetch_schedules(client, gitlab_project).await
+            }));
+        }
+    }
+
+    fn set_schedules(&mut self, schedules: Option<Vec<GitlabSchedule>>) {
+        let len = schedules.as_ref().map_or(0, Vec::len);
+        let selected = self.table_state.selected().unwrap_or(0);
+        self.table_state
+            .select((len > 0).then(|| selected.min(len - 1)));
+        self.schedules = schedules;
+        if let Some(editor) = &mut self.editor {
+            let len = self
+                .schedules
+                .iter()
+                .flatten()
+                .find(|schedule| schedule.id == editor.schedule_id)
+                .map_or(0, |schedule| schedule.variables.len());
+            let selected = editor.table_state.selected().unwrap_or(0);
+            editor
+                .table_state
+                .select((len > 0).then(|| selected.min(len - 1)));
+        }
+    }
+
+    fn selected_schedule(&self) -> Option<&GitlabSchedule> {
+        self.schedules.as_ref()?.get(self.table_state.selected()?)
+    }
+
+    fn edited_schedule(&self) -> Option<&GitlabSchedule> {
+        let editor = self.editor.as_ref()?;
+        self.schedules
+            .as_ref()?
+            .iter()
+            .find(|schedule| schedule.id == editor.schedule_id)
+    }
+
+    fn selected_variable(&self) -> Option<&ScheduleVariable> {
+        let index = self.editor.as_ref()?.table_state.selected()?;
+        self.edited_schedule()?.variables.get(index)
+    }
+
+    /// Send a change to GitLab in the background, unless another one is still running or GitLab
+    /// is unreachable. Returns whether the change was sent.
+    fn change<F>(&mut self, change: F, state: &State) -> bool
+    where
+        F: std::future::Future<Output = Result<String>> + Send + 'static,
+    {
+        if state.offline {
+            self.message = Some(Err(OFFLINE_MESSAGE.to_string()));
+            return false;
+        }
+        if self.change_request.is_some() {
+            self.message = Some(Err("Another change is in progress".to_string()));
+            return false;
+        }
+        self.message = None;
+        self.change_request = Some(PendingRequest::spawn(change));
+        true
+    }
+
+    /// Ask for a confirmation before creating a pipeline from the selected schedule.
+    fn run_schedule(&mut self) {
+        if let Some(schedule) = self.selected_schedule() {
+            self.confirmation = Some(Change::Run {
+                id: schedule.id,
+                description: schedule.description.clone(),
+            });
+        }
+    }
+
+    /// Ask for a confirmation before (de)activating the selected schedule.
+    fn toggle_active(&mut self) {
+        if let Some(schedule) = self.selected_schedule() {
+            self.confirmation = Some(Change::SetActive {
+                id: schedule.id,
+                description: schedule.description.clone(),
+                active: !schedule.active,
+            });
+        }
+    }
+
+    fn confirm(&mut self, state: &State) {
+        let (Some(gitlab_project), Some(change)) =
+            (self.loaded_project.clone(), self.confirmation.take())
+        else {
+            return;
+        };
+        let client = self.client.clone();
+        self.change(
+            async move {
+                match change {
+                    Change::Run { id, description } => {
+                        client.play_pipeline_schedule(&gitlab_project, id).await?;
+                        Ok(format!("Pipeline created for \"{}\"", description))
+                    }
+                    Change::SetActive {
+                        id,
+                        description,
+                        active,
+                    } => {
+                        client
+                            .set_pipeline_schedule_active(&gitlab_project, id, active)
+                            .await?;
+                        let change = if active { "activated" } else { "deactivated" };
+                        Ok(format!("\"{}\" {}", description, change))
+                    }
+                }
+            },
+            state,
+        );
+    }
+
+    fn show_graph(&mut self, state: &mut State) {
+        let Some(gitlab_project) = self.loaded_project.clone() else {
+            return;
+        };
+        if let Some(pipeline) = self
+            .selected_schedule()
+            .and_then(|schedule| schedule.last_pipeline.as_ref())
+        {
+            state.show_graph(PipelineRef {
+                gitlab_project,
+                id: pipeline.id,
+            });
+        }
+    }
+
+    fn open_editor(&mut self, state: &mut State) {
+        let Some(schedule) = self.selected_schedule() else {
+            return;
+        };
+        let mut table_state = TableState::default();
+        table_state.select((!schedule.variables.is_empty()).then_some(0));
+        self.editor = Some(VariablesEditor {
+            schedule_id: schedule.id,
+            table_state,
+            form: None,
+            pending_delete: None,
+        });
+        self.message = None;
+        state.popup = Some(ComponentId::Schedules);
+    }
+
+    fn close_editor(&mut self, state: &mut State) {
+        if self.editor.take().is_some() {
+            state.popup = None;
+            state.input_mode = InputMode::Normal;
+        }
+    }
+
+    /// Handle an action while the variables of a schedule are shown.
+    fn update_editor(&mut self, action: Action, state: &mut State) {
+        let variables_len = self
+            .edited_schedule()
+            .map_or(0, |schedule| schedule.variables.len());
+        let selected_variable = self.selected_variable().cloned();
+        let Some(editor) = &mut self.editor else {
+            return;
+        };
+        if action != Action::Delete && action != Action::Tick {
+            editor.pending_delete = None;
+        }
+        if let Some(form) = &mut editor.form {
+            match action {
+                Action::Next => form.fields.next_field(),
+                Action::Previous => form.fields.previous_field(),
+                Action::Enter => self.save_variable(state),
+                Action::Close => {
+                    editor.form = None;
+                    state.input_mode = InputMode::Normal;
+                }
+                _ => {}
+            }
+            return;
+        }
+        match action {
+            Action::Next => {
+                let selected = editor.table_state.selected().map_or(0, |index| index + 1);
+                editor
+                    .table_state
+                    .select(Some(selected.min(variables_len.saturating_sub(1))));
+            }
+            Action::Previous => editor.table_state.select_previous(),
+            Action::Enter => {
+                if let Some(variable) = selected_variable {
+                    editor.form = Some(VariableForm::new(Some(&variable)));
+                    state.input_mode = InputMode::Insert;
+                }
+            }
+            Action::NewItem => {
+                editor.form = Some(VariableForm::new(None));
+                state.input_mode = InputMode::Insert;
+            }
+            Action::Delete => {
+                let Some(variable) = selected_variable else {
+                    return;
+                };
+                // Deleting takes a second key press, as it cannot be undone
+                if editor.pending_delete.as_ref() != Some(&variable.key) {
+                    self.message = Some(Ok(format!(
+                        "Press the key again to delete {}",
+                        variable.key
+                    )));
+                    editor.pending_delete = Some(variable.key);
+                    return;
+                }
+                editor.pending_delete = None;
+                self.delete_variable(variable.key, state);
+            }
+            Action::Close => self.close_editor(state),
+            _ => {}
+        }
+    }
+
+    fn save_variable(&mut self, state: &mut State) {
+        let (Some(gitlab_project), Some(editor)) = (self.loaded_project.clone(), &self.editor)
+        else {
+            return;
+        };
+        let Some(form) = &editor.form else {
+            return;
+        };
+        let variable = ScheduleVariable {
+            key: form.fields.text_value("Key").trim().to_string(),
+            value: form.fields.text_value("Value").to_string(),
+            variable_type: self
+                .selected_variable()
+                .filter(|_| form.existing)
+                .map_or("env_var".to_string(), |variable| {
+                    variable.variable_type.clone()
+                }),
+        };
+        if variable.key.is_empty() {
+            self.message = Some(Err("The key of the variable is missing".to_string()));
+            return;
+        }
+        let (schedule_id, existing) = (editor.schedule_id, form.existing);
+        let client = self.client.clone();
+        let sent = self.change(
+            async move {
+                client
+                    .save_schedule_variable(&gitlab_project, schedule_id, &variable, existing)
+                    .await?;
+                Ok(format!("Variable {} saved", variable.key))
+            },
+            state,
+        );
+        // The form stays open otherwise, so that nothing typed is lost
+        if !sent {
+            return;
+        }
+        if let Some(editor) = &mut self.editor {
+            editor.form = None;
+        }
+        state.input_mode = InputMode::Normal;
+    }
+
+    fn delete_variable(&mut self, key: String, state: &State) {
+        let (Some(gitlab_project), Some(editor)) = (self.loaded_project.clone(), &self.editor)
+        else {
+            return;
+        };
+        let schedule_id = editor.schedule_id;
+        let client = self.client.clone();
+        self.change(
+            async move {
+                client
+                    .delete_schedule_variable(&gitlab_project, schedule_id, &key)
+                    .await?;
+                Ok(format!("Variable {} deleted", key))
+            },
+            state,
+        );
+    }
+
+    fn next(&mut self) {
+        let len = self.schedules.as_ref().map_or(0, Vec::len);
+        if let Some(selected) = self.table_state.selected() {
+            if selected + 1 < len {
+                self.table_state.select(Some(selected + 1));
+            }
+        }
+    }
+
+    fn previous(&mut self) {
+        if let Some(selected) = self.table_state.selected() {
+            self.table_state.select(Some(selected.saturating_sub(1)));
+        }
+    }
+
+    /// Pending confirmation, or the outcome of the last change.
+    fn message_line(&self) -> Option<Line<'static>> {
+        let theme = &self.config.ui.theme;
+        if let Some(change) = &self.confirmation {
+            return Some(Line::styled(
+                change.prompt(),
+                theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD),
+            ));
+        }
+        match &self.message {
+            Some(Ok(message)) => Some(Line::from(message.clone())),
+            Some(Err(error)) => Some(Line::styled(
+                error.clone(),
+                theme.fg(theme.palette.error).add_modifier(Modifier::BOLD),
+            )),
+            None if self.change_request.is_some() => Some(Line::styled(
+                "Saving...",
+                theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD),
+            )),
+            None => None,
+        }
+    }
+
+    fn draw_editor(&mut self, frame: &mut Frame, area: Rect) {
+        let message = self.message_line();
+        let schedule = self.edited_schedule().cloned();
+        let (Some(editor), Some(schedule)) = (&mut self.editor, schedule) else {
+            return;
+        };
+        let theme = &self.config.ui.theme;
+        let area = popup_area(area, 70, 60);
+        let mut block = Block::bordered()
+            .border_type(BorderType::Thick)
+            .border_style(theme.fg(theme.palette.popup_border))
+            .padding(Padding::horizontal(1))
+            .title(format!("Variables of \"{}\"", schedule.description));
+        if let Some(message) = message {
+            block = block.title_bottom(message);
+        }
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let form_height = editor
+            .form
+            .as_ref()
+            .map_or(0, |form| form.fields.height() + 1);
+        let [table_area, form_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(form_height)]).areas(inner);
+        if schedule.variables.is_empty() {
+            frame.render_widget(Paragraph::new("No variables"), table_area);
+        } else {
+            let rows = schedule.variables.iter().map(|variable| {
+                Row::new([
+                    variable.key.clone(),
+                    variable.value.clone(),
+                    variable.variable_type.clone(),
+                ])
+            });
+            let table = Table::new(
+                rows,
+                [
+                    Constraint::Length(30),
+                    Constraint::Fill(1),
+                    Constraint::Length(8),
+                ],
+            )
+            .column_spacing(2)
+            .header(Row::new(["Key", "Value", "Type"]).bold())
+            .row_highlight_style(
+                theme.highlight(theme.palette.highlight_fg, theme.palette.highlight_bg),
+            );
+            frame.render_stateful_widget(table, table_area, &mut editor.table_state);
+        }
+
+        if let Some(form) = &editor.form {
+            let mut lines = form.fields.lines(true);
+            lines.push(Line::from("enter: save, esc: cancel").italic());
+            frame.render_widget(Paragraph::new(lines), form_area);
+        }
+    }
+}
+
+impl Component for SchedulesComponent {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.client = GitlabClient::new(config.core.gitlab_url.clone());
+        self.cache = Cache::new(config.cache.enabled);
+        self.config = config;
+        Ok(())
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent, _state: &mut State) -> Result<Option<Action>> {
+        if let Some(form) = self.editor.as_mut().and_then(|editor| editor.form.as_mut()) {
+            form.fields.handle_key(key);
+        }
+        Ok(None)
+    }
+
+    fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>> {
+        if action == Action::Tick {
+            self.refresh(state);
+        }
+        if self.editor.is_some() {
+            self.update_editor(action, state);
+            return Ok(None);
+        }
+        if self.confirmation.is_some() {
+            match action {
+                Action::Enter => self.confirm(state),
+                Action::Close => self.confirmation = None,
+                _ => {}
+            }
+            return Ok(None);
+        }
+        match action {
+            Action::Next => self.next(),
+            Action::Previous => self.previous(),
+            Action::Enter => self.open_editor(state),
+            Action::RunSchedule => self.run_schedule(),
+            Action::ToggleActive => self.toggle_active(),
+            Action::ShowGraph => self.show_graph(state),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn handle_mouse_event(
+        &mut self,
+        mouse: MouseEvent,
+        state: &mut State,
+    ) -> Result<Option<Action>> {
+        if state.popup.is_some() || !self.area.contains(Position::new(mouse.column, mouse.row)) {
+            return Ok(None);
+        }
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                state.focused_component = ComponentId::Schedules;
+                // Rows start below the border, the padding and the header row
+                let first_row = self.area.y + 3;
+                let len = self.schedules.as_ref().map_or(0, Vec::len);
+                if mouse.row >= first_row {
+                    let index = (mouse.row - first_row) as usize + self.table_state.offset();
+                    if index < len {
+                        self.table_state.select(Some(index));
+                    }
+                }
+            }
+            MouseEventKind::ScrollDown => self.next(),
+            MouseEventKind::ScrollUp => self.previous(),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect, state: &State) -> Result<()> {
+        let full_area = area;
+        let area = prepare_layout(area, Element::Body(Body::RightColumn), state)
+            .filter(|_| state.view == View::Schedules);
+        self.area = area.unwrap_or_default();
+        let Some(area) = area else {
+            return Ok(());
+        };
+        let theme = &self.config.ui.theme;
+        let timestamps = &self.config.ui.pipelines;
+        let mut block = get_block(
+            state,
+            ComponentId::Schedules,
+            theme.color(theme.palette.pipelines_border),
+        )
+        .padding(Padding::uniform(1))
+        .title("Pipeline schedules");
+
+        let Some(schedules) = &self.schedules else {
+            let message = match &self.error {
+                Some(error) => Span::styled(
+                    format!("ERROR: {}", error),
+                    theme.fg(theme.palette.error).add_modifier(Modifier::BOLD),
+                ),
+                None if self.loaded_project.is_none() => Span::raw("Project not selected"),
+                None => Span::styled(
+                    "Loading...",
+                    theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD),
+                ),
+            };
+            let paragraph = Paragraph::new(Line::from(message))
+                .block(block)
+                .alignment(Alignment::Center);
+            frame.render_widget(paragraph, area);
+            return Ok(());
+        };
+
+        if let Some(error) = &self.error {
+            block = block.title(Line::styled(
+                format!(" STALE ({}) ", error),
+                theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD),
+            ));
+        }
+        if let Some(message) = self.message_line().filter(|_| self.editor.is_none()) {
+            block = block.title_bottom(message);
+        }
+
+        let header_row = Row::new([
+            "Description",
+            "Ref",
+            "Cron",
+            "Timezone",
+            "Next run",
+            "Owner",
+            "Active",
+            "Last pipeline",
+        ])
+        .bold();
+        let rows = schedules.iter().map(|schedule| {
+            let last_pipeline =
+                schedule
+                    .last_pipeline
+                    .as_ref()
+                    .map_or_else(Line::default, |pipeline| {
+                        Line::styled(
+                            format!("{} #{}", theme.status_icon(&pipeline.status), pipeline.id),
+                            theme.status_style(&pipeline.status),
+                        )
+                    });
+            let row = Row::new([
+                Line::from(schedule.description.clone()),
+                Line::from(schedule.git_ref.clone()),
+                Line::from(schedule.cron.clone()),
+                Line::from(schedule.cron_timezone.clone()),
+                Line::from(
+                    schedule
+                        .next_run_at
+                        .filter(|_| schedule.active)
+                        .map(|next_run_at| format_timestamp(next_run_at, timestamps))
+                        .unwrap_or_else(|| "-".to_string()),
+                ),
+                Line::from(
+                    schedule
+                        .owner
+                        .as_ref()
+                        .map(|owner| owner.username.clone())
+                        .unwrap_or_default(),
+                ),
+                Line::from(if schedule.active { "yes" } else { "no" }),
+                last_pipeline,
+            ]);
+            if schedule.active {
+                row
+            } else {
+                row.add_modifier(Modifier::DIM)
+            }
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Min(20),
+                Constraint::Min(12),
+                Constraint::Length(14),
+                Constraint::Length(16),
+                Constraint::Length(20),
+                Constraint::Length(14),
+                Constraint::Length(6),
+                Constraint::Length(14),
+            ],
+        )
+        .column_spacing(2)
+        .header(header_row)
+        .row_highlight_style(
+            theme.highlight(theme.palette.highlight_fg, theme.palette.highlight_bg),
+        )
+        .block(block);
+        frame.render_stateful_widget(table, area, &mut self.table_state);
+
+        if self.editor.is_some() {
+            self.draw_editor(frame, full_area);
+        }
+        Ok(())
+    }
+}
+
+/// Fetch the schedules of a project one by one, as only single schedules come with their last
+/// pipeline and their variables.
+async fn fetch_schedules(
+    client: GitlabClient,
+    gitlab_project: String,
+) -> Result<Vec<GitlabSchedule>> {
+    let schedules = client.fetch_pipeline_schedules(&gitlab_project).await?;
+    stream::iter(schedules)
+        .map(|schedule| {
+            let client = &client;
+            let gitlab_project = &gitlab_project;
+            async move {
+                client
+                    .fetch_pipeline_schedule(gitlab_project, schedule.id)
+                    .await
+            }
+        })
+        .buffered(CONCURRENT_REQUESTS)
+        .collect::<Vec<Result<_>>>()
+        .await
+        .into_iter()
+        .collect()
+}
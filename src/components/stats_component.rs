@@ -0,0 +1,379 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local, NaiveDate};
+use color_eyre::{eyre::Error, Result};
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use futures::{stream, StreamExt};
+use ratatui::{prelude::*, widgets::*};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    utils::{format_duration, get_block, prepare_layout, Body, Element, PendingRequest},
+    Component, ComponentId,
+};
+use crate::{
+    action::Action,
+    cache::{Cache, CacheKind},
+    config::Config,
+    gitlab::{is_unreachable, GitlabClient, GitlabPipeline, PipelineStatus},
+    state::{State, View},
+    theme::Theme,
+};
+
+/// Number of days shown in the success rate chart.
+const SUCCESS_RATE_DAYS: usize = 14;
+/// Number of pipelines fetched at the same time.
+const CONCURRENT_REQUESTS: usize = 8;
+
+/// Recent pipelines on a ref, fetched one by one to learn their durations.
+#[derive(Serialize, Deserialize)]
+struct StatsData {
+    git_ref: String,
+    pipelines: Vec<GitlabPipeline>,
+}
+
+/// Charts of the recent pipelines of the selected project: their durations, queue times and
+/// success rate per day.
+#[derive(Default)]
+pub struct StatsComponent {
+    config: Config,
+    client: GitlabClient,
+    cache: Cache,
+    // Project for which `data` was loaded
+    loaded_project: Option<String>,
+    data: Option<StatsData>,
+    error: Option<Error>,
+    request: Option<PendingRequest<StatsData>>,
+    last_request_at: Option<Instant>,
+    // Area computed while drawing, used for mouse hit-testing
+    area: Rect,
+}
+
+impl StatsComponent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Refresh the statistics while they are shown, starting from the cached ones whenever the
+    /// active project changes.
+    fn refresh(&mut self, state: &mut State) {
+        if state.view != View::Stats {
+            return;
+        }
+        if self.loaded_project != state.active_gitlab_project {
+            self.loaded_project = state.active_gitlab_project.clone();
+            self.request = None;
+            self.last_request_at = None;
+            self.error = None;
+            self.data = self.loaded_project.as_ref().and_then(|gitlab_project| {
+                self.cache
+                    .load::<StatsData>(gitlab_project, CacheKind::Stats)
+                    .map(|entry| entry.data)
+            });
+        }
+        let Some(gitlab_project) = self.loaded_project.clone() else {
+            return;
+        };
+
+        if let Some(result) = self.request.as_mut().and_then(PendingRequest::poll) {
+            self.request = None;
+            match result {
+                Ok(data) => {
+                    state.offline = false;
+                    if let Err(error) = self.cache.store(&gitlab_project, CacheKind::Stats, &data) {
+                        tracing::warn!("Failed to cache statistics: {}", error);
+                    }
+                    self.data = Some(data);
+                    self.error = None;
+                }
+                Err(error) => {
+                    state.offline = is_unreachable(&error);
+                    self.error = Some(error);
+                }
+            }
+        }
+
+        let refresh_interval = Duration::from_secs(self.config.core.refresh_interval);
+        let refresh_due = self
+            .last_request_at
+            .is_none_or(|requested_at| requested_at.elapsed() >= refresh_interval);
+        if self.request.is_none() && refresh_due {
+            let client = self.client.clone();
+            let git_ref = self.config.stats.git_ref.clone();
+            let count = self.config.stats.pipelines;
+            // Finished pipelines do not change, so there is no need to fetch them again
+            let known = self
+                .data
+                .as_ref()
+                .map(|data| data.pipelines.clone())
+                .unwrap_or_default();
+            self.last_request_at = Some(Instant::now());
+            self.request = Some(PendingRequest::spawn(async move {
+                fetch_stats(client, gitlab_project, git_ref, count, known).await
+            }));
+        }
+    }
+
+    fn draw_charts(&self, frame: &mut Frame, area: Rect, data: &StatsData) {
+        let theme = &self.config.ui.theme;
+        let [top, success_rate_area] =
+            Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(area);
+        let [duration_area, queued_area] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(top);
+
+        // Oldest pipelines come first
+        let finished: Vec<&GitlabPipeline> = data
+            .pipelines
+            .iter()
+            .rev()
+            .filter(|pipeline| pipeline.finished_at.is_some())
+            .collect();
+        let durations: Vec<(f64, f64)> = finished
+            .iter()
+            .filter_map(|pipeline| {
+                let duration = pipeline.duration? as f64;
+                Some((pipeline.created_at.timestamp() as f64, duration))
+            })
+            .collect();
+        let queued_durations: Vec<(f64, f64)> = finished
+            .iter()
+            .filter_map(|pipeline| {
+                Some((
+                    pipeline.created_at.timestamp() as f64,
+                    pipeline.queued_duration?,
+                ))
+            })
+            .collect();
+
+        draw_duration_chart(
+            frame,
+            duration_area,
+            "Duration",
+            &durations,
+            theme.color(theme.palette.accent),
+        );
+        draw_duration_chart(
+            frame,
+            queued_area,
+            "Queue time",
+            &queued_durations,
+            theme.color(theme.palette.warning),
+        );
+        draw_success_rate(frame, success_rate_area, &finished, theme);
+    }
+}
+
+impl Component for StatsComponent {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.client = GitlabClient::new(config.core.gitlab_url.clone());
+        self.cache = Cache::new(config.cache.enabled);
+        self.config = config;
+        Ok(())
+    }
+
+    fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>> {
+        if action == Action::Tick {
+            self.refresh(state);
+        }
+        Ok(None)
+    }
+
+    fn handle_mouse_event(
+        &mut self,
+        mouse: MouseEvent,
+        state: &mut State,
+    ) -> Result<Option<Action>> {
+        if state.popup.is_none()
+            && self.area.contains(Position::new(mouse.column, mouse.row))
+            && mouse.kind == MouseEventKind::Down(MouseButton::Left)
+        {
+            state.focused_component = ComponentId::Stats;
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect, state: &State) -> Result<()> {
+        let area = prepare_layout(area, Element::Body(Body::RightColumn), state)
+            .filter(|_| state.view == View::Stats);
+        self.area = area.unwrap_or_default();
+        let Some(area) = area else {
+            return Ok(());
+        };
+        let theme = &self.config.ui.theme;
+        let mut block = get_block(
+            state,
+            ComponentId::Stats,
+            theme.color(theme.palette.pipelines_border),
+        )
+        .padding(Padding::uniform(1));
+
+        match (&self.data, &self.error) {
+            (Some(data), error) => {
+                block = block.title(format!(
+                    "Statistics of the last {} pipelines on {}",
+                    data.pipelines.len(),
+                    data.git_ref
+                ));
+                if let Some(error) = error {
+                    block = block.title(Line::styled(
+                        format!(" STALE ({}) ", error),
+                        theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD),
+                    ));
+                }
+                let inner = block.inner(area);
+                frame.render_widget(block, area);
+                self.draw_charts(frame, inner, data);
+            }
+            (None, error) => {
+                let message = match error {
+                    Some(error) => Span::styled(
+                        format!("ERROR: {}", error),
+                        theme.fg(theme.palette.error).add_modifier(Modifier::BOLD),
+                    ),
+                    None if self.loaded_project.is_none() => Span::raw("Project not selected"),
+                    None => Span::styled(
+                        "Loading...",
+                        theme.fg(theme.palette.warning).add_modifier(Modifier::BOLD),
+                    ),
+                };
+                let paragraph = Paragraph::new(Line::from(message))
+                    .block(block.title("Statistics"))
+                    .alignment(Alignment::Center);
+                frame.render_widget(paragraph, area);
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn fetch_stats(
+    client: GitlabClient,
+    gitlab_project: String,
+    git_ref: Option<String>,
+    count: usize,
+    known: Vec<GitlabPipeline>,
+) -> Result<StatsData> {
+    let git_ref = match git_ref {
+        Some(git_ref) => git_ref,
+        None => client.fetch_default_branch(&gitlab_project).await?,
+    };
+    let recent = client
+        .fetch_recent_pipelines(&gitlab_project, Some(&git_ref), count)
+        .await?;
+
+    // Durations are only returned for single pipelines
+    let mut known: HashMap<u32, GitlabPipeline> = known
+        .into_iter()
+        .map(|pipeline| (pipeline.id, pipeline))
+        .collect();
+    let outdated: Vec<u32> = recent
+        .iter()
+        .filter(|pipeline| {
+            known
+                .get(&pipeline.id)
+                .is_none_or(|details| details.updated_at != pipeline.updated_at)
+        })
+        .map(|pipeline| pipeline.id)
+        .collect();
+    let fetched: Vec<Result<GitlabPipeline>> = stream::iter(outdated)
+        .map(|id| client.fetch_pipeline(&gitlab_project, id))
+        .buffer_unordered(CONCURRENT_REQUESTS)
+        .collect()
+        .await;
+    for pipeline in fetched {
+        let pipeline = pipeline?;
+        known.insert(pipeline.id, pipeline);
+    }
+
+    let pipelines = recent
+        .into_iter()
+        .map(|pipeline| known.remove(&pipeline.id).unwrap_or(pipeline))
+        .collect();
+    Ok(StatsData { git_ref, pipelines })
+}
+
+/// Line chart of durations (in seconds) over the creation time of pipelines.
+fn draw_duration_chart(
+    frame: &mut Frame,
+    area: Rect,
+    title: &str,
+    points: &[(f64, f64)],
+    color: Color,
+) {
+    let block = Block::bordered().title(title.to_string());
+    let (Some((first, _)), Some((last, _))) = (points.first(), points.last()) else {
+        let paragraph = Paragraph::new("No finished pipelines")
+            .block(block)
+            .alignment(Alignment::Center);
+        frame.render_widget(paragraph, area);
+        return;
+    };
+    let max = points.iter().map(|(_, y)| *y).fold(1.0, f64::max);
+    let date = |timestamp: f64| {
+        DateTime::from_timestamp(timestamp as i64, 0)
+            .map(|date| date.with_timezone(&Local).format("%m-%d %H:%M").to_string())
+            .unwrap_or_default()
+    };
+    let dataset = Dataset::default()
+        .marker(symbols::Marker::Braille)
+        .graph_type(GraphType::Line)
+        .style(Style::default().fg(color))
+        .data(points);
+    let chart = Chart::new(vec![dataset])
+        .block(block)
+        .x_axis(
+            Axis::default()
+                .bounds([*first, *last])
+                .labels([date(*first), date(*last)]),
+        )
+        .y_axis(Axis::default().bounds([0.0, max]).labels([
+            "0s".to_string(),
+            format_duration((max / 2.0) as u64),
+            format_duration(max as u64),
+        ]));
+    frame.render_widget(chart, area);
+}
+
+/// Bar chart of the share of successful pipelines among the finished ones, per day.
+fn draw_success_rate(frame: &mut Frame, area: Rect, finished: &[&GitlabPipeline], theme: &Theme) {
+    let mut days: BTreeMap<NaiveDate, (u64, u64)> = BTreeMap::new();
+    for pipeline in finished {
+        let succeeded = match pipeline.status {
+            PipelineStatus::Success => true,
+            PipelineStatus::Failed => false,
+            // Canceled or skipped pipelines say nothing about the health of the CI
+            _ => continue,
+        };
+        let day = pipeline.created_at.with_timezone(&Local).date_naive();
+        let (successes, total) = days.entry(day).or_default();
+        *successes += u64::from(succeeded);
+        *total += 1;
+    }
+    let bars: Vec<Bar> = days
+        .iter()
+        .rev()
+        .take(SUCCESS_RATE_DAYS)
+        .rev()
+        .map(|(day, (successes, total))| {
+            let rate = successes * 100 / total;
+            let status = if rate == 100 {
+                PipelineStatus::Success
+            } else {
+                PipelineStatus::Failed
+            };
+            Bar::default()
+                .value(rate)
+                .text_value(format!("{}%", rate))
+                .label(Line::from(day.format("%m-%d").to_string()))
+                .style(theme.status_style(&status))
+        })
+        .collect();
+    let chart = BarChart::default()
+        .block(Block::bordered().title("Success rate per day"))
+        .data(BarGroup::default().bars(&bars))
+        .bar_width(5)
+        .bar_gap(1)
+        .max(100);
+    frame.render_widget(chart, area);
+}
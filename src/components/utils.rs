@@ -1,11 +1,27 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local, Utc};
+use color_eyre::{eyre::eyre, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{
-    layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
+    layout::{Constraint, Flex, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
     widgets::{Block, BorderType},
 };
+use regex::{Regex, RegexBuilder};
+use tokio::sync::oneshot;
 
-use crate::state::State;
+use crate::{
+    components::ComponentId,
+    config::{PipelinesConfig, TimestampStyle},
+    state::{InputMode, State},
+};
 
+#[derive(Clone, Copy, PartialEq)]
 pub enum Body {
     LeftColumn,
     RightColumn,
@@ -17,34 +33,66 @@ pub enum Element {
     Footer,
 }
 
-pub fn prepare_layout(area: Rect, position: Element) -> Rect {
+/// Area of an element of the layout, or `None` if the element is currently hidden.
+pub fn prepare_layout(area: Rect, position: Element, state: &State) -> Option<Rect> {
+    let layout = &state.layout;
     // First, we do the vertical split into header, body and footer
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Max(2), Constraint::Fill(1), Constraint::Max(3)])
-        .split(area);
+    let [header, body, footer] = Layout::vertical([
+        Constraint::Length(if layout.config.header { 2 } else { 0 }),
+        Constraint::Fill(1),
+        Constraint::Length(if layout.config.footer { 3 } else { 0 }),
+    ])
+    .areas(area);
 
     // Then, depending on the position, we return the chunk which should be used to render specific
     // component
     match position {
-        Element::Header => chunks[0],
-        Element::Footer => chunks[2],
+        Element::Header => layout.config.header.then_some(header),
+        Element::Footer => layout.config.footer.then_some(footer),
+        Element::Body(body_position) if layout.single_pane() => {
+            let visible = if layout.config.project_list
+                && state.focused_component == ComponentId::ProjectSelector
+            {
+                Body::LeftColumn
+            } else {
+                Body::RightColumn
+            };
+            (body_position == visible).then_some(body)
+        }
         Element::Body(body_position) => {
-            // Here we split the main body into two columns
-            let body_chunks = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([Constraint::Percentage(15), Constraint::Percentage(85)])
-                .split(chunks[1]);
+            // Here we split the main body into two panes, the project list coming first
+            let constraints = [
+                Constraint::Percentage(layout.project_list_size),
+                Constraint::Fill(1),
+            ];
+            let [left, right] = if layout.stacked_vertically() {
+                Layout::vertical(constraints).areas(body)
+            } else {
+                Layout::horizontal(constraints).areas(body)
+            };
 
             match body_position {
-                Body::LeftColumn => body_chunks[0],
-                Body::RightColumn => body_chunks[1],
+                Body::LeftColumn => Some(left),
+                Body::RightColumn => Some(right),
             }
         }
     }
 }
 
-pub fn get_block(state: &State, focused_component: usize, focused_border_color: Color) -> Block {
+/// helper function to create a centered rect using up certain percentage of the available rect `r`
+pub fn popup_area(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+    let vertical = Layout::vertical([Constraint::Percentage(percent_y)]).flex(Flex::Center);
+    let horizontal = Layout::horizontal([Constraint::Percentage(percent_x)]).flex(Flex::Center);
+    let [area] = vertical.areas(area);
+    let [area] = horizontal.areas(area);
+    area
+}
+
+pub fn get_block(
+    state: &State,
+    focused_component: ComponentId,
+    focused_border_color: Color,
+) -> Block<'_> {
     Block::bordered()
         .border_type(if state.focused_component == focused_component {
             BorderType::Thick
@@ -57,3 +105,674 @@ pub fn get_block(state: &State, focused_component: usize, focused_border_color:
             Style::default()
         })
 }
+
+/// Duration given in seconds, e.g. "1h 02m 03s".
+pub fn format_duration(seconds: u64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+    if hours > 0 {
+        format!("{}h {:02}m {:02}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {:02}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// Timestamp formatted according to the configuration.
+pub fn format_timestamp(timestamp: DateTime<Utc>, config: &PipelinesConfig) -> String {
+    if config.timestamps == TimestampStyle::Relative {
+        return format_age(timestamp);
+    }
+    match config.timezone {
+        Some(timezone) => timestamp
+            .with_timezone(&timezone)
+            .format(&config.time_format)
+            .to_string(),
+        None => timestamp
+            .with_timezone(&Local)
+            .format(&config.time_format)
+            .to_string(),
+    }
+}
+
+/// Time elapsed since a given moment, e.g. "5m ago".
+pub fn format_age(timestamp: DateTime<Utc>) -> String {
+    let seconds = (Utc::now() - timestamp).num_seconds().max(0);
+    let (value, unit) = match seconds {
+        0..60 => (seconds, "s"),
+        60..3600 => (seconds / 60, "m"),
+        3600..86400 => (seconds / 3600, "h"),
+        _ => (seconds / 86400, "d"),
+    };
+    format!("{}{} ago", value, unit)
+}
+
+/// Size given in bytes, e.g. "1.5 MiB".
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Shown instead of sending a change to GitLab while it is unreachable, as the dashboard is then
+/// read-only.
+pub const OFFLINE_MESSAGE: &str = "GitLab is unreachable, changes are disabled while offline";
+
+/// A request running in the background, e.g. a call to the GitLab API. Components keep it around
+/// and poll it on every tick, so that slow network calls never block rendering.
+pub struct PendingRequest<T> {
+    rx: oneshot::Receiver<Result<T>>,
+}
+
+impl<T: Send + 'static> PendingRequest<T> {
+    pub fn spawn<F>(future: F) -> Self
+    where
+        F: Future<Output = Result<T>> + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let _ = tx.send(future.await);
+        });
+        Self { rx }
+    }
+
+    /// Return the result of the request if it has completed.
+    pub fn poll(&mut self) -> Option<Result<T>> {
+        match self.rx.try_recv() {
+            Ok(result) => Some(result),
+            Err(oneshot::error::TryRecvError::Empty) => None,
+            Err(oneshot::error::TryRecvError::Closed) => Some(Err(eyre!("Request was cancelled"))),
+        }
+    }
+}
+
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// Remembers the last mouse click, so that double-clicks can be told apart from single ones.
+#[derive(Default)]
+pub struct ClickTracker {
+    last_click: Option<(Instant, u16, u16)>,
+}
+
+impl ClickTracker {
+    /// Register a click at a given position, returning whether it completes a double-click.
+    pub fn click(&mut self, column: u16, row: u16) -> bool {
+        let double_click = self
+            .last_click
+            .is_some_and(|(clicked_at, last_column, last_row)| {
+                clicked_at.elapsed() < DOUBLE_CLICK_INTERVAL
+                    && (last_column, last_row) == (column, row)
+            });
+        // A double-click should not start another one
+        self.last_click = if double_click {
+            None
+        } else {
+            Some((Instant::now(), column, row))
+        };
+        double_click
+    }
+}
+
+/// A single line of text typed by the user, edited with the usual keys.
+#[derive(Default, Clone)]
+pub struct TextInput {
+    value: String,
+    // Position of the cursor, in characters
+    cursor: usize,
+}
+
+impl TextInput {
+    /// Input starting with some text, the cursor being at its end.
+    pub fn new(value: &str) -> Self {
+        Self {
+            value: value.to_string(),
+            cursor: value.chars().count(),
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Edit the text according to a key, returning whether the key was used.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let len = self.value.chars().count();
+        match key.code {
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.value.insert(self.byte_index(self.cursor), c);
+                self.cursor += 1;
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.value.remove(self.byte_index(self.cursor));
+            }
+            KeyCode::Delete if self.cursor < len => {
+                self.value.remove(self.byte_index(self.cursor));
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(len),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = len,
+            _ => return false,
+        }
+        true
+    }
+
+    fn byte_index(&self, cursor: usize) -> usize {
+        self.value
+            .char_indices()
+            .nth(cursor)
+            .map_or(self.value.len(), |(index, _)| index)
+    }
+
+    /// Text to display, with the cursor shown as a reversed character when the input is focused.
+    pub fn spans(&self, focused: bool) -> Vec<Span<'_>> {
+        if !focused {
+            return vec![Span::raw(self.value.as_str())];
+        }
+        let cursor = self.byte_index(self.cursor);
+        let (before, after) = self.value.split_at(cursor);
+        let mut chars = after.chars();
+        let under_cursor = chars.next().map_or(" ".to_string(), String::from);
+        vec![
+            Span::raw(before),
+            Span::styled(
+                under_cursor,
+                Style::default().add_modifier(Modifier::REVERSED),
+            ),
+            Span::raw(chars.as_str()),
+        ]
+    }
+
+    /// Like `spans`, each character being shown as a bullet.
+    pub fn masked_spans(&self, focused: bool) -> Vec<Span<'static>> {
+        let len = self.value.chars().count();
+        if !focused {
+            return vec![Span::raw("•".repeat(len))];
+        }
+        let under_cursor = if self.cursor < len { "•" } else { " " };
+        vec![
+            Span::raw("•".repeat(self.cursor)),
+            Span::styled(
+                under_cursor,
+                Style::default().add_modifier(Modifier::REVERSED),
+            ),
+            Span::raw("•".repeat(len.saturating_sub(self.cursor + 1))),
+        ]
+    }
+}
+
+/// Value of a field of a form.
+enum FieldValue {
+    Text(TextInput),
+    // Text shown as bullets until revealed, which ctrl-r toggles
+    Secret { input: TextInput, revealed: bool },
+    // Toggled with the space bar
+    Flag(bool),
+}
+
+struct FormField {
+    label: &'static str,
+    value: FieldValue,
+    // Fields which cannot change are shown, but skipped when moving between fields
+    editable: bool,
+}
+
+/// Form made of labelled text fields and flags, edited while in insert mode: typed keys go to
+/// the focused field, and `Next`/`Previous` move between the editable fields. Fields are looked
+/// up by their label.
+#[derive(Default)]
+pub struct Form {
+    fields: Vec<FormField>,
+    focused: usize,
+}
+
+impl Form {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(self, label: &'static str, value: &str, editable: bool) -> Self {
+        self.field(label, FieldValue::Text(TextInput::new(value)), editable)
+    }
+
+    pub fn secret(self, label: &'static str, value: &str, editable: bool, revealed: bool) -> Self {
+        let value = FieldValue::Secret {
+            input: TextInput::new(value),
+            revealed,
+        };
+        self.field(label, value, editable)
+    }
+
+    pub fn flag(self, label: &'static str, value: bool, editable: bool) -> Self {
+        self.field(label, FieldValue::Flag(value), editable)
+    }
+
+    fn field(mut self, label: &'static str, value: FieldValue, editable: bool) -> Self {
+        // Focus the first editable field
+        if !self.fields.iter().any(|field| field.editable) {
+            self.focused = self.fields.len();
+        }
+        self.fields.push(FormField {
+            label,
+            value,
+            editable,
+        });
+        self
+    }
+
+    pub fn next_field(&mut self) {
+        self.move_focus(1);
+    }
+
+    pub fn previous_field(&mut self) {
+        self.move_focus(self.fields.len().saturating_sub(1));
+    }
+
+    fn move_focus(&mut self, step: usize) {
+        let len = self.fields.len();
+        for offset in 1..len {
+            let index = (self.focused + offset * step) % len;
+            if self.fields[index].editable {
+                self.focused = index;
+                return;
+            }
+        }
+    }
+
+    /// Edit the focused field according to a key, returning whether the key was used.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let Some(field) = self
+            .fields
+            .get_mut(self.focused)
+            .filter(|field| field.editable)
+        else {
+            return false;
+        };
+        match &mut field.value {
+            FieldValue::Text(input) => input.handle_key(key),
+            FieldValue::Secret { revealed, .. }
+                if key.code == KeyCode::Char('r')
+                    && key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                *revealed = !*revealed;
+                true
+            }
+            FieldValue::Secret { input, .. } => input.handle_key(key),
+            FieldValue::Flag(value) if key.code == KeyCode::Char(' ') => {
+                *value = !*value;
+                true
+            }
+            FieldValue::Flag(_) => false,
+        }
+    }
+
+    /// Text of a text or secret field, empty if there is no such field.
+    pub fn text_value(&self, label: &str) -> &str {
+        self.fields
+            .iter()
+            .find_map(|field| match &field.value {
+                FieldValue::Text(input) | FieldValue::Secret { input, .. }
+                    if field.label == label =>
+                {
+                    Some(input.value())
+                }
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn flag_value(&self, label: &str) -> bool {
+        self.fields
+            .iter()
+            .any(|field| field.label == label && matches!(field.value, FieldValue::Flag(true)))
+    }
+
+    /// Number of lines taken by the form.
+    pub fn height(&self) -> u16 {
+        self.fields.len() as u16
+    }
+
+    /// One line per field, the cursor being shown in the focused one unless `focused` is false.
+    pub fn lines(&self, focused: bool) -> Vec<Line<'_>> {
+        let width = self
+            .fields
+            .iter()
+            .map(|field| field.label.len())
+            .max()
+            .unwrap_or_default()
+            + 2;
+        self.fields
+            .iter()
+            .enumerate()
+            .map(|(index, field)| {
+                let has_cursor = focused && field.editable && index == self.focused;
+                let mut spans = vec![Span::styled(
+                    format!("{:<width$}", field.label, width = width),
+                    Style::default().add_modifier(Modifier::BOLD),
+                )];
+                match &field.value {
+                    FieldValue::Text(input)
+                    | FieldValue::Secret {
+                        input,
+                        revealed: true,
+                    } => spans.extend(input.spans(has_cursor)),
+                    FieldValue::Secret { input, .. } => {
+                        spans.extend(input.masked_spans(has_cursor))
+                    }
+                    FieldValue::Flag(value) => {
+                        let style = if has_cursor {
+                            Style::default().add_modifier(Modifier::REVERSED)
+                        } else {
+                            Style::default()
+                        };
+                        spans.push(Span::styled(if *value { "[x]" } else { "[ ]" }, style));
+                    }
+                }
+                Line::from(spans)
+            })
+            .collect()
+    }
+}
+
+/// Where a pattern was found in a text by `fuzzy_match`, the higher the score the better.
+pub struct FuzzyMatch {
+    pub score: i64,
+    // Positions of the matched characters in the text, in characters
+    pub positions: Vec<usize>,
+}
+
+/// Match the characters of a pattern in order, though not necessarily next to each other,
+/// ignoring case. Runs of consecutive characters and characters starting a word (e.g. after a
+/// `/` or a `-`) score higher, gaps score lower. The best scoring positions are picked.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<FuzzyMatch> {
+    let lowercase = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let pattern: Vec<char> = pattern
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(lowercase)
+        .collect();
+    let text: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = text.iter().copied().map(lowercase).collect();
+    // Most texts do not match at all, so rule them out cheaply first
+    let mut rest = lower.iter();
+    if !pattern.iter().all(|p| rest.any(|c| c == p)) {
+        return None;
+    }
+    if pattern.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0,
+            positions: vec![],
+        });
+    }
+    // Best score of the first `i + 1` pattern characters, the last one being at position `j`,
+    // together with the position of the previous one
+    let mut scores: Vec<Vec<Option<(i64, usize)>>> = vec![vec![None; text.len()]; pattern.len()];
+    for i in 0..pattern.len() {
+        // Best score of the previous characters ending before the previous position
+        let mut best_before: Option<(i64, usize)> = None;
+        for j in 0..text.len() {
+            if i > 0 && j >= 2 {
+                if let Some((score, _)) = scores[i - 1][j - 2] {
+                    if best_before.is_none_or(|(best, _)| score > best) {
+                        best_before = Some((score, j - 2));
+                    }
+                }
+            }
+            if lower[j] != pattern[i] {
+                continue;
+            }
+            let word_start = j == 0 || matches!(text[j - 1], '/' | '-' | '_' | '.' | ' ');
+            let bonus = if word_start { 9 } else { 1 };
+            if i == 0 {
+                scores[i][j] = Some((bonus, 0));
+                continue;
+            }
+            let consecutive = j
+                .checked_sub(1)
+                .and_then(|previous| scores[i - 1][previous])
+                .map(|(score, _)| (score + 5 + bonus, j - 1));
+            let gapped = best_before.map(|(score, previous)| (score - 1 + bonus, previous));
+            scores[i][j] = match (consecutive, gapped) {
+                (Some(consecutive), Some(gapped)) if gapped.0 > consecutive.0 => Some(gapped),
+                (Some(consecutive), _) => Some(consecutive),
+                (None, gapped) => gapped,
+            };
+        }
+    }
+    let last = pattern.len() - 1;
+    let (mut position, score) = scores[last]
+        .iter()
+        .enumerate()
+        .filter_map(|(j, cell)| cell.map(|(score, _)| (j, score)))
+        .max_by_key(|(_, score)| *score)?;
+    let mut positions = vec![position; pattern.len()];
+    for i in (1..pattern.len()).rev() {
+        position = scores[i][position].map(|(_, previous)| previous)?;
+        positions[i - 1] = position;
+    }
+    Some(FuzzyMatch { score, positions })
+}
+
+/// Text with some of its characters highlighted, e.g. the ones matched by `fuzzy_match`.
+pub fn highlight_chars(text: &str, positions: &[usize], style: Style) -> Line<'static> {
+    Line::from(
+        text.chars()
+            .enumerate()
+            .map(|(index, c)| {
+                if positions.contains(&index) {
+                    Span::styled(c.to_string(), style)
+                } else {
+                    Span::raw(c.to_string())
+                }
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Where to look for a match of a search, see `Search::find`.
+#[derive(Clone, Copy)]
+pub enum Seek {
+    /// From the current item, e.g. while the search is typed
+    Current,
+    /// After the current item
+    Next,
+    /// Before the current item, going backward
+    Previous,
+}
+
+/// Incremental search of the items of a view, typed in insert mode. The text is looked for as is,
+/// ignoring case, or as a regular expression once `ctrl-r` is pressed. Matches stay highlighted
+/// once the search is done typing, until it is cancelled.
+#[derive(Default)]
+pub struct Search {
+    input: TextInput,
+    regex: bool,
+    typing: bool,
+    // Compiled from the typed text, `None` while it is empty or not a valid regular expression
+    pattern: Option<Regex>,
+}
+
+impl Search {
+    /// Start typing a new search, in the same mode as the previous one.
+    pub fn start(&mut self, state: &mut State) {
+        *self = Self {
+            regex: self.regex,
+            typing: true,
+            ..Self::default()
+        };
+        state.input_mode = InputMode::Insert;
+    }
+
+    /// Stop typing, the matches staying highlighted.
+    pub fn finish(&mut self, state: &mut State) {
+        self.typing = false;
+        state.input_mode = InputMode::Normal;
+    }
+
+    /// Stop typing and forget the search.
+    pub fn cancel(&mut self, state: &mut State) {
+        self.finish(state);
+        self.input = TextInput::default();
+        self.pattern = None;
+    }
+
+    pub fn is_typing(&self) -> bool {
+        self.typing
+    }
+
+    /// Edit the search according to a key while it is typed, returning whether it changed.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if !self.typing {
+            return false;
+        }
+        let changed =
+            if key.code == KeyCode::Char('r') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                self.regex = !self.regex;
+                true
+            } else {
+                self.input.handle_key(key)
+            };
+        if changed {
+            let text = self.input.value();
+            let pattern = if self.regex {
+                text.to_string()
+            } else {
+                regex::escape(text)
+            };
+            self.pattern = RegexBuilder::new(&pattern)
+                .case_insensitive(true)
+                .build()
+                .ok()
+                .filter(|_| !text.is_empty());
+        }
+        changed
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.pattern
+            .as_ref()
+            .is_some_and(|pattern| pattern.is_match(text))
+    }
+
+    /// Index of the item matching the search which comes first when looking from the current
+    /// one, out of `len` items. The search wraps around at the ends.
+    pub fn find(
+        &self,
+        len: usize,
+        current: usize,
+        seek: Seek,
+        is_match: impl Fn(usize) -> bool,
+    ) -> Option<usize> {
+        if self.pattern.is_none() || len == 0 {
+            return None;
+        }
+        let start = match seek {
+            Seek::Current => current,
+            Seek::Next => current + 1,
+            Seek::Previous => current + len - 1,
+        } % len;
+        (0..len)
+            .map(|offset| match seek {
+                Seek::Previous => (start + len - offset) % len,
+                Seek::Current | Seek::Next => (start + offset) % len,
+            })
+            .find(|index| is_match(*index))
+    }
+
+    /// Line with the matches of the search highlighted. Matches spanning several spans, e.g.
+    /// because of colours, are not highlighted.
+    pub fn highlight<'a>(&self, line: Line<'a>, style: Style) -> Line<'a> {
+        let Some(pattern) = &self.pattern else {
+            return line;
+        };
+        let mut spans = vec![];
+        for span in line.spans {
+            let text = span.content.as_ref();
+            let mut end = 0;
+            for found in pattern.find_iter(text).filter(|found| !found.is_empty()) {
+                if found.start() > end {
+                    spans.push(Span::styled(
+                        text[end..found.start()].to_string(),
+                        span.style,
+                    ));
+                }
+                spans.push(Span::styled(
+                    found.as_str().to_string(),
+                    span.style.patch(style),
+                ));
+                end = found.end();
+            }
+            match end {
+                0 => spans.push(span),
+                end if end < text.len() => {
+                    spans.push(Span::styled(text[end..].to_string(), span.style));
+                }
+                _ => {}
+            }
+        }
+        Line { spans, ..line }
+    }
+
+    /// Search as typed, e.g. `/main [regex]`, unless there is none.
+    pub fn prompt(&self, matches: usize) -> Option<Line<'_>> {
+        if !self.typing && self.pattern.is_none() {
+            return None;
+        }
+        let mut spans = vec![Span::raw("/")];
+        spans.extend(self.input.spans(self.typing));
+        if self.regex {
+            spans.push(Span::raw(" [regex]"));
+        }
+        let status = if self.pattern.is_some() {
+            format!(" ({} matches) ", matches)
+        } else if self.regex && !self.input.value().is_empty() {
+            " (invalid) ".to_string()
+        } else {
+            " ".to_string()
+        };
+        spans.push(Span::raw(status));
+        Some(Line::from(spans))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_matches_characters_in_order_ignoring_case() {
+        let found = fuzzy_match("GrPr", "group/project").unwrap();
+        assert_eq!(found.positions, [0, 1, 6, 7]);
+        assert!(fuzzy_match("pg", "group/project").is_none());
+        assert!(fuzzy_match("groupx", "group").is_none());
+        // Spaces in the pattern are ignored
+        assert!(fuzzy_match("group proj", "group/project").is_some());
+    }
+
+    #[test]
+    fn fuzzy_matches_anything_with_an_empty_pattern() {
+        let found = fuzzy_match("", "group/project").unwrap();
+        assert_eq!((found.score, found.positions.len()), (0, 0));
+    }
+
+    #[test]
+    fn fuzzy_match_prefers_word_starts_and_runs() {
+        // The `p` starting the project name beats the one inside the group name
+        let found = fuzzy_match("pro", "apps/project").unwrap();
+        assert_eq!(found.positions, [5, 6, 7]);
+
+        let score = |text| fuzzy_match("api", text).unwrap().score;
+        assert!(score("backend/api") > score("backend/xapi"));
+        assert!(score("backend/xapi") > score("backend/axpxi"));
+        assert!(score("api") > score("capital"));
+    }
+}
@@ -1,26 +1,73 @@
-use color_eyre::Result;
+use chrono::format::{Item, StrftimeItems};
+use chrono_tz::Tz;
+use color_eyre::{eyre::eyre, Result};
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
-use serde::{Deserialize, Deserializer};
-use std::{collections::HashMap, fs, ops::Deref};
-use validator::Validate;
+use serde::{
+    de::{Error as _, IntoDeserializer},
+    Deserialize, Deserializer, Serialize,
+};
+use std::{collections::HashMap, fs, ops::Deref, path::PathBuf};
+use validator::{Validate, ValidationError};
 
-use crate::{action::Action, app::Mode};
+use crate::{
+    action::Action, app::Mode, gitlab::PipelineStatus, notifications::Delivery, theme::Theme,
+};
 
 #[derive(Default, Clone, Validate, Deserialize)]
+#[validate(schema(function = "validate_projects"))]
 pub struct Config {
     #[validate(nested)]
     pub core: CoreConfig,
     #[validate(nested)]
     pub ui: UIConfig,
     #[serde(default)]
+    pub cache: CacheConfig,
+    #[validate(nested)]
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+    #[validate(nested)]
+    #[serde(default)]
+    pub stats: StatsConfig,
+    #[validate(nested)]
+    #[serde(default)]
+    pub flaky_jobs: FlakyJobsConfig,
+    #[validate(nested)]
+    #[serde(default)]
+    pub environments: EnvironmentsConfig,
+    #[validate(nested)]
+    #[serde(default)]
+    pub artifacts: ArtifactsConfig,
+    #[validate(nested)]
+    #[serde(default)]
+    pub discovery: DiscoveryConfig,
+    #[validate(nested)]
+    #[serde(default)]
+    pub project_list: ProjectListConfig,
+    #[serde(default)]
+    pub project_switcher: ProjectSwitcherConfig,
+    #[serde(default)]
     pub keybindings: KeyBindings,
 }
 
+/// Projects are either listed in `core.gitlab_projects`, or discovered.
+fn validate_projects(config: &Config) -> Result<(), ValidationError> {
+    if config.core.gitlab_projects.is_empty() && !config.discovery.is_enabled() {
+        return Err(ValidationError::new("no_projects").with_message(
+            "List projects in `core.gitlab_projects`, or discover them in `[discovery]`".into(),
+        ));
+    }
+    Ok(())
+}
+
 #[derive(Default, Clone, Validate, Deserialize)]
 pub struct CoreConfig {
     pub gitlab_url: String,
-    #[validate(length(min = 1))]
+    /// Projects listed first in the project list, before the discovered ones.
+    #[serde(default)]
     pub gitlab_projects: Vec<String>,
+    #[validate(range(min = 1))]
+    #[serde(default = "default_refresh_interval")]
+    pub refresh_interval: u64,
 }
 
 #[derive(Default, Clone, Validate, Deserialize)]
@@ -28,30 +75,905 @@ pub struct UIConfig {
     #[validate(range(min = 1))]
     #[serde(default = "default_max_page_size")]
     pub max_page_size: usize,
+    #[serde(default = "default_mouse")]
+    pub mouse: bool,
+    #[serde(default)]
+    pub theme: Theme,
+    #[validate(nested)]
+    #[serde(default)]
+    pub layout: LayoutConfig,
+    #[validate(nested)]
+    #[serde(default)]
+    pub pipelines: PipelinesConfig,
+}
+
+/// Arrangement of the panes. The project list and the pipelines share the body of the screen,
+/// either side by side or stacked on top of each other.
+#[derive(Clone, Validate, Deserialize)]
+pub struct LayoutConfig {
+    #[serde(default = "default_pane_visible")]
+    pub header: bool,
+    #[serde(default = "default_pane_visible")]
+    pub footer: bool,
+    #[serde(default = "default_pane_visible")]
+    pub project_list: bool,
+    /// Share of the body taken by the project list, in percent.
+    #[validate(range(min = 5, max = 95))]
+    #[serde(default = "default_project_list_size")]
+    pub project_list_size: u16,
+    #[serde(default)]
+    pub stacking: Stacking,
+    /// Terminal width (in columns) below which the panes are stacked vertically, with the
+    /// `auto` stacking.
+    #[serde(default = "default_vertical_below_width")]
+    pub vertical_below_width: u16,
+    /// Terminal width (in columns) below which only the focused pane of the body is shown.
+    #[serde(default = "default_collapse_below_width")]
+    pub collapse_below_width: u16,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            header: default_pane_visible(),
+            footer: default_pane_visible(),
+            project_list: default_pane_visible(),
+            project_list_size: default_project_list_size(),
+            stacking: Stacking::default(),
+            vertical_below_width: default_vertical_below_width(),
+            collapse_below_width: default_collapse_below_width(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stacking {
+    Horizontal,
+    Vertical,
+    /// Horizontal, unless the terminal is narrower than `vertical_below_width`
+    #[default]
+    Auto,
+}
+
+/// `[ui.pipelines]` section of the configuration file, describing the pipelines table.
+#[derive(Clone, Validate, Deserialize)]
+pub struct PipelinesConfig {
+    #[validate(length(min = 1))]
+    #[serde(default = "default_pipeline_columns")]
+    pub columns: Vec<ColumnConfig>,
+    #[serde(default)]
+    pub timestamps: TimestampStyle,
+    /// Time zone of absolute timestamps, the local one if not set.
+    pub timezone: Option<Tz>,
+    #[validate(custom(function = "validate_time_format"))]
+    #[serde(default = "default_time_format")]
+    pub time_format: String,
+}
+
+impl Default for PipelinesConfig {
+    fn default() -> Self {
+        Self {
+            columns: default_pipeline_columns(),
+            timestamps: TimestampStyle::default(),
+            timezone: None,
+            time_format: default_time_format(),
+        }
+    }
+}
+
+/// Column of the pipelines table.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineColumn {
+    Id,
+    Iid,
+    Status,
+    Source,
+    Ref,
+    Sha,
+    User,
+    Duration,
+    QueuedDuration,
+    Age,
+    CreatedAt,
+    UpdatedAt,
+    Url,
+    #[serde(alias = "mr")]
+    MergeRequest,
+}
+
+/// A column, either given by its name alone or as a table with its width and alignment.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "RawColumn")]
+pub struct ColumnConfig {
+    pub column: PipelineColumn,
+    pub width: Option<u16>,
+    pub align: ColumnAlignment,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawColumn {
+    Name(PipelineColumn),
+    Table {
+        name: PipelineColumn,
+        width: Option<u16>,
+        #[serde(default)]
+        align: ColumnAlignment,
+    },
+}
+
+impl From<RawColumn> for ColumnConfig {
+    fn from(raw: RawColumn) -> Self {
+        match raw {
+            RawColumn::Name(column) => Self {
+                column,
+                width: None,
+                align: ColumnAlignment::default(),
+            },
+            RawColumn::Table { name, width, align } => Self {
+                column: name,
+                width,
+                align,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnAlignment {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampStyle {
+    /// E.g. "5m ago"
+    Relative,
+    #[default]
+    Absolute,
+}
+
+fn default_pipeline_columns() -> Vec<ColumnConfig> {
+    [
+        PipelineColumn::Id,
+        PipelineColumn::Status,
+        PipelineColumn::Source,
+        PipelineColumn::Ref,
+        PipelineColumn::CreatedAt,
+        PipelineColumn::UpdatedAt,
+    ]
+    .into_iter()
+    .map(|column| RawColumn::Name(column).into())
+    .collect()
+}
+
+fn default_time_format() -> String {
+    "%Y-%m-%d %H:%M:%S".to_string()
+}
+
+fn validate_time_format(format: &str) -> Result<(), ValidationError> {
+    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        return Err(ValidationError::new("time_format")
+            .with_message(format!("Invalid time format `{}`", format).into()));
+    }
+    Ok(())
+}
+
+fn default_pane_visible() -> bool {
+    true
+}
+
+fn default_project_list_size() -> u16 {
+    20
+}
+
+fn default_vertical_below_width() -> u16 {
+    100
+}
+
+fn default_collapse_below_width() -> u16 {
+    60
 }
 
 fn default_max_page_size() -> usize {
     25
 }
 
-#[derive(Clone, Debug, Default)]
-pub struct KeyBindings(pub HashMap<Mode, HashMap<Vec<KeyEvent>, Action>>);
+fn default_mouse() -> bool {
+    true
+}
+
+fn default_refresh_interval() -> u64 {
+    30
+}
+
+#[derive(Clone, Deserialize)]
+pub struct CacheConfig {
+    #[serde(default = "default_cache_enabled")]
+    pub enabled: bool,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_cache_enabled(),
+        }
+    }
+}
+
+fn default_cache_enabled() -> bool {
+    true
+}
+
+#[derive(Clone, Validate, Deserialize)]
+pub struct StatsConfig {
+    /// Number of the most recent pipelines the statistics are computed from.
+    #[validate(range(min = 1, max = 1000))]
+    #[serde(default = "default_stats_pipelines")]
+    pub pipelines: usize,
+    /// Ref of the pipelines, the default branch of the project if not set.
+    #[serde(rename = "ref")]
+    pub git_ref: Option<String>,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            pipelines: default_stats_pipelines(),
+            git_ref: None,
+        }
+    }
+}
+
+fn default_stats_pipelines() -> usize {
+    100
+}
+
+#[derive(Clone, Validate, Deserialize)]
+pub struct FlakyJobsConfig {
+    /// Number of the most recent pipelines whose jobs are analysed.
+    #[validate(range(min = 1, max = 1000))]
+    #[serde(default = "default_flaky_jobs_pipelines")]
+    pub pipelines: usize,
+    /// Ref of the pipelines, the default branch of the project if not set.
+    #[serde(rename = "ref")]
+    pub git_ref: Option<String>,
+}
+
+impl Default for FlakyJobsConfig {
+    fn default() -> Self {
+        Self {
+            pipelines: default_flaky_jobs_pipelines(),
+            git_ref: None,
+        }
+    }
+}
+
+fn default_flaky_jobs_pipelines() -> usize {
+    50
+}
+
+#[derive(Clone, Validate, Deserialize)]
+pub struct EnvironmentsConfig {
+    /// Whether stopped environments are listed along with the available ones.
+    #[serde(default)]
+    pub include_stopped: bool,
+    /// Number of deployments shown in the history of an environment.
+    #[validate(range(min = 1, max = 1000))]
+    #[serde(default = "default_environments_history")]
+    pub history: usize,
+}
+
+impl Default for EnvironmentsConfig {
+    fn default() -> Self {
+        Self {
+            include_stopped: false,
+            history: default_environments_history(),
+        }
+    }
+}
+
+fn default_environments_history() -> usize {
+    50
+}
+
+#[derive(Clone, Validate, Deserialize)]
+pub struct ArtifactsConfig {
+    /// Directory where artifacts are downloaded, the download directory of the user if not set.
+    pub download_dir: Option<PathBuf>,
+    /// Files up to this size (in bytes) can be previewed.
+    #[validate(range(min = 1))]
+    #[serde(default = "default_max_preview_size")]
+    pub max_preview_size: u64,
+    /// Archives larger than this size (in bytes) are only downloaded to be browsed once
+    /// confirmed.
+    #[validate(range(min = 1))]
+    #[serde(default = "default_max_archive_size")]
+    pub max_archive_size: u64,
+}
+
+impl ArtifactsConfig {
+    pub fn download_dir(&self) -> PathBuf {
+        self.download_dir
+            .clone()
+            .or_else(dirs::download_dir)
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+}
+
+impl Default for ArtifactsConfig {
+    fn default() -> Self {
+        Self {
+            download_dir: None,
+            max_preview_size: default_max_preview_size(),
+            max_archive_size: default_max_archive_size(),
+        }
+    }
+}
+
+fn default_max_preview_size() -> u64 {
+    1024 * 1024
+}
+
+fn default_max_archive_size() -> u64 {
+    100 * 1024 * 1024
+}
+
+/// Projects added to the project list from GitLab, in addition to `core.gitlab_projects`.
+#[derive(Clone, Validate, Deserialize)]
+pub struct DiscoveryConfig {
+    /// Groups whose projects are listed, including the ones of their subgroups.
+    #[serde(default)]
+    pub groups: Vec<String>,
+    /// List the projects the user is a member of.
+    #[serde(default)]
+    pub membership: bool,
+    /// List the projects starred by the user.
+    #[serde(default)]
+    pub starred: bool,
+    /// Glob patterns of the discovered projects to keep, all of them if empty.
+    #[validate(custom(function = "validate_globs"))]
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Glob patterns of the discovered projects to leave out.
+    #[validate(custom(function = "validate_globs"))]
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Keep archived projects.
+    #[serde(default)]
+    pub archived: bool,
+    /// Seconds between two discoveries.
+    #[validate(range(min = 60))]
+    #[serde(default = "default_discovery_interval")]
+    pub refresh_interval: u64,
+}
+
+impl DiscoveryConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.groups.is_empty() || self.membership || self.starred
+    }
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            groups: vec![],
+            membership: false,
+            starred: false,
+            include: vec![],
+            exclude: vec![],
+            archived: false,
+            refresh_interval: default_discovery_interval(),
+        }
+    }
+}
+
+fn default_discovery_interval() -> u64 {
+    600
+}
+
+/// `[project_list]` section of the configuration file, describing how projects are arranged in
+/// the project list.
+#[derive(Clone, Validate, Deserialize)]
+pub struct ProjectListConfig {
+    #[serde(default)]
+    pub group_by: GroupBy,
+    /// Sections used when grouping by `groups`.
+    #[validate(custom(function = "validate_project_groups"))]
+    #[serde(default)]
+    pub groups: Vec<ProjectGroup>,
+    /// Projects pinned to the top of the list, in this order.
+    #[serde(default)]
+    pub favourites: Vec<String>,
+    /// Seconds between two refreshes of the badges of the visible projects.
+    #[validate(range(min = 30))]
+    #[serde(default = "default_badges_refresh_interval")]
+    pub badges_refresh_interval: u64,
+}
+
+impl Default for ProjectListConfig {
+    fn default() -> Self {
+        Self {
+            group_by: GroupBy::default(),
+            groups: vec![],
+            favourites: vec![],
+            badges_refresh_interval: default_badges_refresh_interval(),
+        }
+    }
+}
+
+fn default_badges_refresh_interval() -> u64 {
+    300
+}
+
+/// How the project list is split into collapsible sections.
+#[derive(Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    #[default]
+    None,
+    /// One section per GitLab namespace, e.g. `group/subgroup`
+    Namespace,
+    /// The sections listed in `groups`, the projects matching none of them coming last
+    Groups,
+}
+
+/// A section of the project list, gathering the projects matching any of `projects` (glob
+/// patterns) which did not match a previous section.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProjectGroup {
+    pub name: String,
+    pub projects: Vec<String>,
+}
+
+fn validate_project_groups(groups: &[ProjectGroup]) -> Result<(), ValidationError> {
+    let patterns: Vec<String> = groups
+        .iter()
+        .flat_map(|group| group.projects.iter().cloned())
+        .collect();
+    validate_globs(&patterns)
+}
+
+#[derive(Default, Clone, Deserialize)]
+pub struct ProjectSwitcherConfig {
+    /// Search all the projects visible to the user on GitLab as well, not only the listed ones.
+    #[serde(default)]
+    pub search_gitlab: bool,
+}
+
+fn validate_globs(patterns: &[String]) -> Result<(), ValidationError> {
+    for pattern in patterns {
+        if globset::Glob::new(pattern).is_err() {
+            return Err(ValidationError::new("invalid_glob")
+                .with_message(format!("Invalid glob pattern `{}`", pattern).into()));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Default, Clone, Validate, Deserialize)]
+pub struct NotificationsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_notification_delivery")]
+    pub delivery: Vec<Delivery>,
+    pub command: Option<String>,
+    #[validate(custom(function = "validate_notification_rules"))]
+    #[serde(default)]
+    pub rules: Vec<NotificationRule>,
+}
+
+fn default_notification_delivery() -> Vec<Delivery> {
+    vec![Delivery::Toast]
+}
+
+/// Pipelines of projects matching `project` and with a ref matching any of `refs` (all refs if
+/// empty) are watched, and a notification is sent when they transition into any of `statuses`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NotificationRule {
+    #[serde(default = "default_notification_project")]
+    pub project: String,
+    #[serde(default)]
+    pub refs: Vec<String>,
+    #[serde(default = "default_notification_statuses")]
+    pub statuses: Vec<PipelineStatus>,
+}
+
+impl Default for NotificationRule {
+    fn default() -> Self {
+        Self {
+            project: default_notification_project(),
+            refs: vec![],
+            statuses: default_notification_statuses(),
+        }
+    }
+}
+
+fn default_notification_project() -> String {
+    "*".to_string()
+}
+
+fn default_notification_statuses() -> Vec<PipelineStatus> {
+    vec![PipelineStatus::Failed, PipelineStatus::Manual]
+}
+
+fn validate_notification_rules(rules: &[NotificationRule]) -> Result<(), ValidationError> {
+    let patterns: Vec<String> = rules
+        .iter()
+        .flat_map(|rule| std::iter::once(&rule.project).chain(&rule.refs))
+        .cloned()
+        .collect();
+    validate_globs(&patterns)
+}
+
+/// Keybindings used unless overridden in the configuration file.
+const DEFAULT_KEYBINDINGS: &str = r#"
+[Home]
+"<q>" = "Quit"
+"<?>" = "Help"
+"<shift-k>" = "FocusUp"
+"<shift-j>" = "FocusDown"
+"<shift-h>" = "FocusLeft"
+"<shift-l>" = "FocusRight"
+"<+>" = "GrowPane"
+"<->" = "ShrinkPane"
+"<z>" = "ToggleZoom"
+"<c>" = "ToggleStats"
+"<f>" = "ToggleFlakyJobs"
+"<e>" = "ToggleEnvironments"
+"<p>" = "ToggleSchedules"
+"<u>" = "ToggleRunners"
+"<v>" = "ToggleVariables"
+"<m>" = "ToggleMyWork"
+"<ctrl-p>" = "SwitchProject"
+
+[Home.project_list]
+"<j>" = "Next"
+"<down>" = "Next"
+"<k>" = "Previous"
+"<up>" = "Previous"
+"<enter>" = "Enter"
+"<space>" = "ToggleExpand"
+
+[Home.pipelines_table]
+"<j>" = "Next"
+"<down>" = "Next"
+"<k>" = "Previous"
+"<up>" = "Previous"
+"<enter>" = "Enter"
+"<l>" = "NextPage"
+"<right>" = "NextPage"
+"<h>" = "PreviousPage"
+"<left>" = "PreviousPage"
+"<s>" = "SortByNextColumn"
+"<shift-s>" = "ReverseSortOrder"
+"<g>" = "ShowGraph"
+"<a>" = "ShowArtifacts"
+"<space>" = "ToggleExpand"
+"<o>" = "OpenProject"
+"<backspace>" = "GoBack"
+"</>" = "Search"
+"<n>" = "NextMatch"
+"<shift-n>" = "PreviousMatch"
+
+[Home.flaky_jobs]
+"<j>" = "Next"
+"<down>" = "Next"
+"<k>" = "Previous"
+"<up>" = "Previous"
+"<enter>" = "Enter"
+
+[Home.environments]
+"<j>" = "Next"
+"<down>" = "Next"
+"<k>" = "Previous"
+"<up>" = "Previous"
+"<enter>" = "Enter"
+"<g>" = "ShowGraph"
+"<esc>" = "Close"
+
+[Home.schedules]
+"<j>" = "Next"
+"<down>" = "Next"
+"<k>" = "Previous"
+"<up>" = "Previous"
+"<enter>" = "Enter"
+"<r>" = "RunSchedule"
+"<space>" = "ToggleActive"
+"<g>" = "ShowGraph"
+"<esc>" = "Close"
+
+[Home.runners]
+"<j>" = "Next"
+"<down>" = "Next"
+"<k>" = "Previous"
+"<up>" = "Previous"
+"<enter>" = "Enter"
+"<g>" = "ShowGraph"
+"<esc>" = "Close"
+
+[Home.my_work]
+"<j>" = "Next"
+"<down>" = "Next"
+"<k>" = "Previous"
+"<up>" = "Previous"
+"<enter>" = "Enter"
+"<g>" = "ShowGraph"
+
+[Home.variables]
+"<j>" = "Next"
+"<down>" = "Next"
+"<k>" = "Previous"
+"<up>" = "Previous"
+"<enter>" = "Enter"
+"<n>" = "NewItem"
+"<r>" = "RevealValue"
+"<d>" = "Delete"
+"<delete>" = "Delete"
+"<esc>" = "Close"
+
+[Home.pipeline_graph]
+"<j>" = "Next"
+"<down>" = "Next"
+"<k>" = "Previous"
+"<up>" = "Previous"
+"<h>" = "SelectLeft"
+"<left>" = "SelectLeft"
+"<l>" = "SelectRight"
+"<right>" = "SelectRight"
+"<enter>" = "Enter"
+"<esc>" = "Close"
+
+[Home.log_viewer]
+"<j>" = "Next"
+"<down>" = "Next"
+"<k>" = "Previous"
+"<up>" = "Previous"
+"<pagedown>" = "NextPage"
+"<pageup>" = "PreviousPage"
+"<g>" = "GoToTop"
+"<shift-g>" = "GoToBottom"
+"</>" = "Search"
+"<n>" = "NextMatch"
+"<shift-n>" = "PreviousMatch"
+"<esc>" = "Close"
+
+[Home.artifacts]
+"<j>" = "Next"
+"<down>" = "Next"
+"<k>" = "Previous"
+"<up>" = "Previous"
+"<pagedown>" = "NextPage"
+"<pageup>" = "PreviousPage"
+"<enter>" = "Enter"
+"<d>" = "Download"
+"<shift-d>" = "DownloadAll"
+"<esc>" = "Close"
+
+[Home.popup]
+"<j>" = "Next"
+"<down>" = "Next"
+"<k>" = "Previous"
+"<up>" = "Previous"
+"<a>" = "ShowArtifacts"
+"<tab>" = "NextTab"
+"<n>" = "ToggleNewFailures"
+"<enter>" = "Enter"
+"<esc>" = "Close"
+
+[Home.input]
+"<down>" = "Next"
+"<up>" = "Previous"
+"<tab>" = "Next"
+"<backtab>" = "Previous"
+"<enter>" = "Enter"
+"<esc>" = "Close"
+"#;
+
+/// Scope in which a keybinding applies. Bindings in the global scope apply everywhere except while
+/// typing text, the other scopes only apply while the corresponding pane is focused (or a popup is
+/// open), so that the same key can mean different things in different panes.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    strum::Display,
+    strum::EnumString,
+    strum::EnumIter,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum Scope {
+    Global,
+    ProjectList,
+    PipelinesTable,
+    FlakyJobs,
+    Environments,
+    Schedules,
+    Runners,
+    MyWork,
+    PipelineGraph,
+    Artifacts,
+    Variables,
+    Popup,
+    LogViewer,
+    Input,
+}
+
+impl Scope {
+    /// Whether global bindings apply on top of the bindings of this scope.
+    pub fn includes_global(&self) -> bool {
+        *self != Scope::Input
+    }
+}
+
+pub type KeyMap = HashMap<Vec<KeyEvent>, Action>;
+
+#[derive(Clone, Debug)]
+pub struct KeyBindings(pub HashMap<Mode, HashMap<Scope, KeyMap>>);
 
 // We implement Deref for KeyBindings so we can access HashMap methods directly on KeyBindings
 // struct.
 impl Deref for KeyBindings {
-    type Target = HashMap<Mode, HashMap<Vec<KeyEvent>, Action>>;
+    type Target = HashMap<Mode, HashMap<Scope, KeyMap>>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
     }
 }
 
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let raw = toml::from_str(DEFAULT_KEYBINDINGS).expect("default keybindings are valid");
+        let mut keybindings = KeyBindings(HashMap::new());
+        keybindings
+            .merge(raw)
+            .expect("default keybindings are valid");
+        keybindings
+    }
+}
+
+impl KeyBindings {
+    /// Key sequences bound to an action in a given mode and scope, rendered for display and sorted
+    /// so that shorter sequences come first.
+    pub fn keys_for(&self, mode: Mode, scope: Scope, action: &Action) -> Vec<String> {
+        let mut keys: Vec<String> = self
+            .keymap(mode, scope)
+            .into_iter()
+            .flatten()
+            .filter(|(_, bound_action)| *bound_action == action)
+            .map(|(key_events, _)| key_sequence_to_string(key_events))
+            .collect();
+        keys.sort_by(|a, b| a.len().cmp(&b.len()).then(a.cmp(b)));
+        keys
+    }
+
+    pub fn keymap(&self, mode: Mode, scope: Scope) -> Option<&KeyMap> {
+        self.get(&mode).and_then(|scopes| scopes.get(&scope))
+    }
+
+    /// Find the action bound to a key sequence in the first of the given scopes which binds it.
+    pub fn resolve(
+        &self,
+        mode: Mode,
+        scopes: &[Scope],
+        key_events: &[KeyEvent],
+    ) -> Option<(Scope, Action)> {
+        scopes.iter().find_map(|scope| {
+            self.keymap(mode, *scope)
+                .and_then(|keymap| keymap.get(key_events))
+                .map(|action| (*scope, action.clone()))
+        })
+    }
+
+    /// Add bindings parsed from the configuration file, overriding existing bindings of the same
+    /// key sequences in the same scope. An empty action removes the binding of the scope instead.
+    fn merge(&mut self, raw: HashMap<Mode, HashMap<String, RawBinding>>) -> Result<(), String> {
+        for (mode, raw_bindings) in raw {
+            let scopes = self.0.entry(mode).or_default();
+            for (key, binding) in raw_bindings {
+                let (scope, bindings) = match binding {
+                    RawBinding::Action(action) => (Scope::Global, HashMap::from([(key, action)])),
+                    RawBinding::Scope(bindings) => {
+                        let scope = key.parse::<Scope>().map_err(|_| {
+                            format!("Unknown keybinding scope `{}` in {} mode", key, mode)
+                        })?;
+                        (scope, bindings)
+                    }
+                };
+                let keymap = scopes.entry(scope).or_default();
+                for (key_str, action) in bindings {
+                    let key_events = parse_key_sequence(&key_str)?;
+                    match parse_action(&action)? {
+                        Some(action) => keymap.insert(key_events, action),
+                        None => keymap.remove(&key_events),
+                    };
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Detect bindings which would make other bindings unreachable: a key sequence which is a
+    /// prefix of another one in the same scope, or in the global scope. A binding of a scope may
+    /// shadow a global binding of the same key sequence, as the scope is resolved first.
+    fn check_conflicts(&self) -> Result<(), String> {
+        for (mode, scopes) in self.iter() {
+            let empty = KeyMap::new();
+            let global = scopes.get(&Scope::Global).unwrap_or(&empty);
+            for (scope, keymap) in scopes {
+                let visible: Vec<(&Vec<KeyEvent>, &Action, Scope)> = if scope.includes_global() {
+                    let global_only = global
+                        .iter()
+                        .filter(|(keys, _)| !keymap.contains_key(*keys));
+                    keymap
+                        .iter()
+                        .map(|(keys, action)| (keys, action, *scope))
+                        .chain(global_only.map(|(keys, action)| (keys, action, Scope::Global)))
+                        .collect()
+                } else {
+                    keymap
+                        .iter()
+                        .map(|(keys, action)| (keys, action, *scope))
+                        .collect()
+                };
+                for (keys, action, key_scope) in &visible {
+                    for (other_keys, other_action, other_scope) in &visible {
+                        if other_keys.len() < keys.len() && keys.starts_with(other_keys) {
+                            return Err(format!(
+                                "{} ({}, {} scope) can never be triggered, because {} is bound to {} ({} scope) in {} mode",
+                                key_sequence_to_string(keys),
+                                action,
+                                key_scope,
+                                key_sequence_to_string(other_keys),
+                                other_action,
+                                other_scope,
+                                mode
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A value in a `[keybindings.<mode>]` table: either an action bound in the global scope, or a
+/// table of bindings for a specific scope. Actions are parsed once merged, as an empty action
+/// unbinds the key.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawBinding {
+    Action(String),
+    Scope(HashMap<String, String>),
+}
+
+/// Parse the action of a binding, None meaning that the key is unbound.
+fn parse_action(raw: &str) -> Result<Option<Action>, String> {
+    if raw.is_empty() {
+        return Ok(None);
+    }
+    Action::deserialize(raw.into_deserializer())
+        .map(Some)
+        .map_err(|_: serde::de::value::Error| format!("Unknown action `{}`", raw))
+}
+
 impl Config {
     pub fn new(path: String) -> Result<Self> {
-        // TODO: Proper error handling comes later
-        let config_content = fs::read_to_string(path).unwrap();
-        let config: Self = toml::from_str(&config_content).unwrap();
+        let config_content = fs::read_to_string(&path)
+            .map_err(|err| eyre!("Failed to read configuration file {}: {}", path, err))?;
+        let config: Self = toml::from_str(&config_content)?;
         config.validate()?;
         Ok(config)
     }
@@ -62,20 +984,12 @@ impl<'de> Deserialize<'de> for KeyBindings {
     where
         D: Deserializer<'de>,
     {
-        let parsed_map = HashMap::<Mode, HashMap<String, Action>>::deserialize(deserializer)?;
-
-        let keybindings = parsed_map
-            .into_iter()
-            .map(|(mode, inner_map)| {
-                let converted_inner_map = inner_map
-                    .into_iter()
-                    .map(|(key_str, cmd)| (parse_key_sequence(&key_str).unwrap(), cmd))
-                    .collect();
-                (mode, converted_inner_map)
-            })
-            .collect();
+        let parsed_map = HashMap::<Mode, HashMap<String, RawBinding>>::deserialize(deserializer)?;
 
-        Ok(KeyBindings(keybindings))
+        let mut keybindings = KeyBindings::default();
+        keybindings.merge(parsed_map).map_err(D::Error::custom)?;
+        keybindings.check_conflicts().map_err(D::Error::custom)?;
+        Ok(keybindings)
     }
 }
 
@@ -178,10 +1092,14 @@ pub fn key_event_to_string(key_event: &KeyEvent) -> String {
         KeyCode::Delete => "delete",
         KeyCode::Insert => "insert",
         KeyCode::F(c) => {
-            char = format!("f({c})");
+            char = format!("f{c}");
             &char
         }
         KeyCode::Char(' ') => "space",
+        KeyCode::Char(c) if key_event.modifiers.contains(KeyModifiers::SHIFT) => {
+            char = c.to_ascii_lowercase().to_string();
+            &char
+        }
         KeyCode::Char(c) => {
             char = c.to_string();
             &char
@@ -223,6 +1141,25 @@ pub fn key_event_to_string(key_event: &KeyEvent) -> String {
     key
 }
 
+/// Render a key sequence using the same syntax as the configuration file, e.g. `<ctrl-x><k>`.
+pub fn key_sequence_to_string(key_events: &[KeyEvent]) -> String {
+    key_events
+        .iter()
+        .map(|key_event| format!("<{}>", key_event_to_string(key_event)))
+        .collect()
+}
+
+/// Terminals report some characters, such as `?` or `:`, together with the SHIFT modifier
+/// needed to type them. Drop the modifier so that such keys match `<?>` in the configuration.
+pub fn normalize_key_event(mut key_event: KeyEvent) -> KeyEvent {
+    if let KeyCode::Char(c) = key_event.code {
+        if !c.is_alphabetic() {
+            key_event.modifiers.remove(KeyModifiers::SHIFT);
+        }
+    }
+    key_event
+}
+
 pub fn parse_key_sequence(raw: &str) -> Result<Vec<KeyEvent>, String> {
     if raw.chars().filter(|c| *c == '>').count() != raw.chars().filter(|c| *c == '<').count() {
         return Err(format!("Unable to parse `{}`", raw));
@@ -249,3 +1186,82 @@ pub fn parse_key_sequence(raw: &str) -> Result<Vec<KeyEvent>, String> {
 
     sequences.into_iter().map(parse_key_event).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keybindings(raw: &str) -> Result<KeyBindings, String> {
+        toml::from_str::<KeyBindings>(raw).map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn default_keybindings_do_not_conflict() {
+        assert_eq!(KeyBindings::default().check_conflicts(), Ok(()));
+    }
+
+    #[test]
+    fn scoped_bindings_shadow_global_ones() {
+        let keybindings = keybindings(
+            r#"
+            [Home.flaky_jobs]
+            "<q>" = "Next"
+            "#,
+        )
+        .unwrap();
+        let q = parse_key_sequence("<q>").unwrap();
+        assert_eq!(
+            keybindings[&Mode::Home][&Scope::FlakyJobs][&q],
+            Action::Next
+        );
+        assert_eq!(keybindings[&Mode::Home][&Scope::Global][&q], Action::Quit);
+    }
+
+    #[test]
+    fn rejects_prefixes_of_other_bindings() {
+        let error = keybindings(
+            r#"
+            [Home.flaky_jobs]
+            "<x>" = "Next"
+            "<x><y>" = "Previous"
+            "#,
+        )
+        .unwrap_err();
+        assert!(error.contains("<x><y> (Previous, flaky_jobs scope) can never be triggered"));
+
+        let error = keybindings(
+            r#"
+            [Home.runners]
+            "<q><q>" = "Next"
+            "#,
+        )
+        .unwrap_err();
+        assert!(error.contains("because <q> is bound to Quit (global scope)"));
+    }
+
+    #[test]
+    fn global_bindings_do_not_apply_while_typing() {
+        assert!(keybindings(
+            r#"
+            [Home.input]
+            "<q><q>" = "Close"
+            "#,
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn empty_actions_unbind_keys() {
+        let keybindings = keybindings(
+            r#"
+            [Home]
+            "<q>" = ""
+            [Home.runners]
+            "<q><q>" = "Next"
+            "#,
+        )
+        .unwrap();
+        let q = parse_key_sequence("<q>").unwrap();
+        assert!(!keybindings[&Mode::Home][&Scope::Global].contains_key(&q));
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use color_eyre::Result;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+use crate::{
+    cache::{Cache, CacheKind},
+    config::DiscoveryConfig,
+    gitlab::{GitlabClient, GitlabProject},
+};
+
+/// Paths of the projects found by each source of discovery, e.g. "group foo", before filtering.
+type Found = BTreeMap<String, Vec<String>>;
+
+/// Finds the projects of the project list which are not listed in the configuration: the
+/// projects of some groups, the ones the user is a member of, or the ones they starred.
+#[derive(Clone, Default)]
+pub struct ProjectDiscovery {
+    client: GitlabClient,
+    cache: Cache,
+    config: DiscoveryConfig,
+    include: GlobSet,
+    exclude: GlobSet,
+}
+
+impl ProjectDiscovery {
+    pub fn new(client: GitlabClient, cache: Cache, config: &DiscoveryConfig) -> Result<Self> {
+        Ok(Self {
+            client,
+            cache,
+            include: glob_set(&config.include)?,
+            exclude: glob_set(&config.exclude)?,
+            config: config.clone(),
+        })
+    }
+
+    /// Whether a discovered project belongs to the project list.
+    fn keeps(&self, project: &str) -> bool {
+        (self.config.include.is_empty() || self.include.is_match(project))
+            && !self.exclude.is_match(project)
+    }
+
+    /// Paths of the projects found by the last discovery, if it was cached.
+    pub fn cached(&self) -> Option<Vec<String>> {
+        let entry = self
+            .cache
+            .load_shared::<Found>(CacheKind::DiscoveredProjects)?;
+        Some(self.projects(&entry.data))
+    }
+
+    /// Paths of the discovered projects, sorted and without duplicates. Sources which cannot be
+    /// listed, e.g. a group the user has no access to, are replaced with what they found last
+    /// time, if anything, and discovery only fails if no project could be listed at all.
+    pub async fn discover(&self) -> Result<Vec<String>> {
+        let archived = self.config.archived;
+        let mut results: Vec<(String, Result<Vec<String>>)> = vec![];
+        for group in &self.config.groups {
+            let projects = self.client.fetch_group_projects(group, archived).await;
+            results.push((format!("group {}", group), paths(projects)));
+        }
+        if self.config.membership {
+            let projects = self
+                .client
+                .fetch_user_projects("membership", archived)
+                .await;
+            results.push(("the projects of the user".to_string(), paths(projects)));
+        }
+        if self.config.starred {
+            let projects = self.client.fetch_user_projects("starred", archived).await;
+            results.push(("the starred projects".to_string(), paths(projects)));
+        }
+
+        let cached = self
+            .cache
+            .load_shared::<Found>(CacheKind::DiscoveredProjects)
+            .map(|entry| entry.data)
+            .unwrap_or_default();
+        let found = merge_sources(results, cached)?;
+        if let Err(err) = self
+            .cache
+            .store_shared(CacheKind::DiscoveredProjects, &found)
+        {
+            tracing::warn!("Failed to cache discovered projects: {}", err);
+        }
+        Ok(self.projects(&found))
+    }
+
+    /// Projects of the project list among the ones found by every source.
+    fn projects(&self, found: &Found) -> Vec<String> {
+        let projects: BTreeSet<&String> = found
+            .values()
+            .flatten()
+            .filter(|project| self.keeps(project))
+            .collect();
+        projects.into_iter().cloned().collect()
+    }
+}
+
+/// Paths of the projects listed by a source.
+fn paths(projects: Result<Vec<GitlabProject>>) -> Result<Vec<String>> {
+    Ok(projects?
+        .into_iter()
+        .map(|project| project.path_with_namespace)
+        .collect())
+}
+
+/// Projects found by each source, falling back to the cached ones for the sources which failed.
+/// Fails if no source could be listed, neither now nor before.
+fn merge_sources(results: Vec<(String, Result<Vec<String>>)>, mut cached: Found) -> Result<Found> {
+    let mut found = Found::new();
+    let mut last_error = None;
+    for (source, result) in results {
+        match result {
+            Ok(projects) => {
+                found.insert(source, projects);
+            }
+            Err(error) => match cached.remove(&source) {
+                Some(projects) => {
+                    tracing::warn!(
+                        "Failed to discover {}, keeping the projects found last time: {}",
+                        source,
+                        error
+                    );
+                    found.insert(source, projects);
+                }
+                None => {
+                    tracing::warn!("Failed to discover {}: {}", source, error);
+                    last_error = Some(error);
+                }
+            },
+        }
+    }
+    match last_error {
+        Some(error) if found.is_empty() => Err(error),
+        _ => Ok(found),
+    }
+}
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern)?);
+    }
+    Ok(builder.build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use color_eyre::eyre::eyre;
+
+    use super::*;
+
+    fn discovery(include: &[&str], exclude: &[&str]) -> ProjectDiscovery {
+        let config = DiscoveryConfig {
+            include: include.iter().map(ToString::to_string).collect(),
+            exclude: exclude.iter().map(ToString::to_string).collect(),
+            ..Default::default()
+        };
+        ProjectDiscovery::new(GitlabClient::default(), Cache::default(), &config).unwrap()
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn keeps_everything_without_patterns() {
+        let discovery = discovery(&[], &[]);
+        assert!(discovery.keeps("group/project"));
+        assert!(discovery.keeps("other/sub/project"));
+    }
+
+    #[test]
+    fn keeps_included_projects_which_are_not_excluded() {
+        let discovery = discovery(&["group/**"], &["group/archive/*", "**/sandbox"]);
+        assert!(discovery.keeps("group/project"));
+        assert!(discovery.keeps("group/sub/project"));
+        assert!(!discovery.keeps("other/project"));
+        assert!(!discovery.keeps("group/archive/project"));
+        assert!(!discovery.keeps("group/sub/sandbox"));
+    }
+
+    #[test]
+    fn projects_are_filtered_sorted_and_deduplicated() {
+        let discovery = discovery(&[], &["**/sandbox"]);
+        let found = Found::from([
+            (
+                "group a".to_string(),
+                strings(&["a/two", "a/one", "a/sandbox"]),
+            ),
+            (
+                "the starred projects".to_string(),
+                strings(&["a/one", "b/three"]),
+            ),
+        ]);
+        assert_eq!(
+            discovery.projects(&found),
+            strings(&["a/one", "a/two", "b/three"])
+        );
+    }
+
+    #[test]
+    fn failed_sources_fall_back_to_the_cache() {
+        let cached = Found::from([
+            ("group a".to_string(), strings(&["a/old"])),
+            ("group b".to_string(), strings(&["b/old"])),
+        ]);
+        let results = vec![
+            ("group a".to_string(), Ok(strings(&["a/new"]))),
+            ("group b".to_string(), Err(eyre!("403 Forbidden"))),
+            ("group c".to_string(), Err(eyre!("404 Not Found"))),
+        ];
+        let found = merge_sources(results, cached).unwrap();
+        assert_eq!(
+            found,
+            Found::from([
+                ("group a".to_string(), strings(&["a/new"])),
+                ("group b".to_string(), strings(&["b/old"])),
+            ])
+        );
+    }
+
+    #[test]
+    fn discovery_fails_when_nothing_could_be_listed() {
+        let results = vec![("group a".to_string(), Err(eyre!("403 Forbidden")))];
+        assert!(merge_sources(results, Found::new()).is_err());
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use crate::gitlab::{GitlabJob, GitlabPipeline, PipelineStatus};
+
+/// Outcomes of a job across the analysed pipelines.
+pub struct FlakyJob {
+    pub name: String,
+    pub stage: String,
+    /// Finished attempts, including retries
+    pub runs: usize,
+    pub failures: usize,
+    /// Commits on which the job failed first and then passed on a retry
+    pub flaky_commits: usize,
+    /// The most recent failed attempt, linking to its log
+    pub last_failure: Option<GitlabJob>,
+}
+
+impl FlakyJob {
+    /// Share of failed attempts, in percent.
+    pub fn failure_rate(&self) -> f64 {
+        if self.runs == 0 {
+            return 0.0;
+        }
+        self.failures as f64 * 100.0 / self.runs as f64
+    }
+}
+
+/// Rank the jobs which failed in the given pipelines, the flakiest first. Jobs are expected to
+/// include the retried ones, and are keyed by the ID of their pipeline.
+pub fn find_flaky_jobs(
+    pipelines: &[GitlabPipeline],
+    jobs: &HashMap<u32, Vec<GitlabJob>>,
+) -> Vec<FlakyJob> {
+    let mut flaky_jobs: HashMap<&str, FlakyJob> = HashMap::new();
+    // Attempts of every job on every commit, to spot the ones which passed after failing
+    let mut attempts: HashMap<(&str, &str), Vec<&GitlabJob>> = HashMap::new();
+
+    for pipeline in pipelines {
+        let Some(pipeline_jobs) = jobs.get(&pipeline.id) else {
+            continue;
+        };
+        // Retrying a whole pipeline creates a new one for the same commit
+        let sha = pipeline.sha.as_deref().unwrap_or(&pipeline.git_ref);
+        for job in pipeline_jobs {
+            let failed = match job.status {
+                PipelineStatus::Success => false,
+                PipelineStatus::Failed => true,
+                _ => continue,
+            };
+            let flaky_job = flaky_jobs.entry(&job.name).or_insert_with(|| FlakyJob {
+                name: job.name.clone(),
+                stage: job.stage.clone(),
+                runs: 0,
+                failures: 0,
+                flaky_commits: 0,
+                last_failure: None,
+            });
+            flaky_job.runs += 1;
+            if failed {
+                flaky_job.failures += 1;
+                if flaky_job
+                    .last_failure
+                    .as_ref()
+                    .is_none_or(|last_failure| last_failure.created_at < job.created_at)
+                {
+                    flaky_job.last_failure = Some(job.clone());
+                }
+            }
+            attempts.entry((&job.name, sha)).or_default().push(job);
+        }
+    }
+
+    for ((name, _), mut commit_attempts) in attempts {
+        commit_attempts.sort_by_key(|job| job.created_at);
+        let passed_after_failing = commit_attempts
+            .iter()
+            .skip_while(|job| job.status != PipelineStatus::Failed)
+            .any(|job| job.status == PipelineStatus::Success);
+        if passed_after_failing {
+            if let Some(flaky_job) = flaky_jobs.get_mut(name) {
+                flaky_job.flaky_commits += 1;
+            }
+        }
+    }
+
+    let mut flaky_jobs: Vec<FlakyJob> = flaky_jobs
+        .into_values()
+        .filter(|flaky_job| flaky_job.failures > 0)
+        .collect();
+    flaky_jobs.sort_by(|a, b| {
+        b.flaky_commits
+            .cmp(&a.flaky_commits)
+            .then(b.failure_rate().total_cmp(&a.failure_rate()))
+            .then(a.name.cmp(&b.name))
+    });
+    flaky_jobs
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn pipeline(id: u32, sha: &str) -> GitlabPipeline {
+        serde_json::from_value(json!({
+            "id": id,
+            "sha": sha,
+            "status": "failed",
+            "source": "push",
+            "ref": "main",
+            "web_url": "",
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    fn job(id: u32, name: &str, status: &str) -> GitlabJob {
+        serde_json::from_value(json!({
+            "id": id,
+            "name": name,
+            "stage": "test",
+            "status": status,
+            "web_url": "",
+            "created_at": format!("2024-01-01T00:00:{:02}Z", id),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn ranks_jobs_passing_on_a_retry_first() {
+        let pipelines = [pipeline(1, "a"), pipeline(2, "b")];
+        let jobs = HashMap::from([
+            (
+                1,
+                vec![
+                    job(1, "unit", "failed"),
+                    job(2, "lint", "failed"),
+                    job(3, "unit", "success"),
+                ],
+            ),
+            (2, vec![job(4, "lint", "failed"), job(5, "unit", "success")]),
+        ]);
+        let report = find_flaky_jobs(&pipelines, &jobs);
+        let names: Vec<&str> = report.iter().map(|job| job.name.as_str()).collect();
+        assert_eq!(names, ["unit", "lint"]);
+        assert_eq!(report[0].flaky_commits, 1);
+        assert_eq!((report[0].runs, report[0].failures), (3, 1));
+        assert_eq!(report[1].flaky_commits, 0);
+        assert_eq!(report[1].failure_rate(), 100.0);
+        assert_eq!(report[1].last_failure.as_ref().map(|job| job.id), Some(4));
+    }
+
+    #[test]
+    fn counts_pipelines_retried_on_the_same_commit() {
+        let pipelines = [pipeline(1, "a"), pipeline(2, "a")];
+        let jobs = HashMap::from([
+            (1, vec![job(1, "e2e", "failed")]),
+            (2, vec![job(2, "e2e", "success")]),
+        ]);
+        let report = find_flaky_jobs(&pipelines, &jobs);
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].flaky_commits, 1);
+    }
+
+    #[test]
+    fn leaves_out_jobs_which_never_failed_or_did_not_finish() {
+        let pipelines = [pipeline(1, "a"), pipeline(2, "b")];
+        let jobs = HashMap::from([(
+            1,
+            vec![
+                job(1, "build", "success"),
+                job(2, "deploy", "running"),
+                job(3, "docs", "skipped"),
+            ],
+        )]);
+        assert!(find_flaky_jobs(&pipelines, &jobs).is_empty());
+    }
+}
@@ -1,22 +1,59 @@
 use color_eyre::Result;
-use reqwest::Client;
-use std::env;
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use std::{
+    collections::HashMap,
+    env, fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 
 use chrono::{DateTime, Utc};
 use color_eyre::eyre::Error;
-use serde::Deserialize;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 #[derive(Default)]
 pub enum PipelinesData {
     #[default]
     Loading,
     Loaded(Vec<GitlabPipeline>),
+    /// Pipelines restored from the on-disk cache which have not been confirmed by GitLab yet. The
+    /// error is set when the last attempt to refresh them failed.
+    Stale {
+        pipelines: Vec<GitlabPipeline>,
+        fetched_at: DateTime<Utc>,
+        error: Option<Error>,
+    },
     Errors(Error),
 }
 
+impl PipelinesData {
+    /// Pipelines available for display, regardless of whether they are fresh or stale.
+    pub fn pipelines(&self) -> Option<&Vec<GitlabPipeline>> {
+        match self {
+            PipelinesData::Loaded(pipelines) | PipelinesData::Stale { pipelines, .. } => {
+                Some(pipelines)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn pipelines_mut(&mut self) -> Option<&mut Vec<GitlabPipeline>> {
+        match self {
+            PipelinesData::Loaded(pipelines) | PipelinesData::Stale { pipelines, .. } => {
+                Some(pipelines)
+            }
+            _ => None,
+        }
+    }
+}
+
 /// Pipeline status, see
-/// https://docs.gitlab.com/ee/api/pipelines.html for reference.
-#[derive(PartialEq, Deserialize, strum::Display)]
+/// https://docs.gitlab.com/ee/api/pipelines.html for reference. Statuses added to GitLab later
+/// end up as `Unknown`, so that they do not break the whole page of pipelines.
+#[derive(PartialEq, Eq, Hash, Clone, Serialize, Deserialize, strum::AsRefStr)]
 #[serde(rename_all = "snake_case")]
 pub enum PipelineStatus {
     Created,
@@ -30,12 +67,62 @@ pub enum PipelineStatus {
     Skipped,
     Manual,
     Scheduled,
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+impl fmt::Display for PipelineStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PipelineStatus::Unknown(status) => f.write_str(&unknown_name(status)),
+            _ => f.write_str(self.as_ref()),
+        }
+    }
+}
+
+impl PipelineStatus {
+    /// Whether the pipeline (or job) may still change status.
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self,
+            PipelineStatus::Created
+                | PipelineStatus::WaitingForResource
+                | PipelineStatus::Preparing
+                | PipelineStatus::Pending
+                | PipelineStatus::Running
+        )
+    }
+
+    /// Status summing up several pipelines, e.g. a pipeline and the ones it triggered: a failure
+    /// anywhere comes first, then whatever is still running.
+    pub fn aggregate<'a>(statuses: impl IntoIterator<Item = &'a PipelineStatus>) -> Option<Self> {
+        statuses
+            .into_iter()
+            .max_by_key(|status| status.severity())
+            .cloned()
+    }
+
+    fn severity(&self) -> u8 {
+        match self {
+            PipelineStatus::Skipped => 0,
+            PipelineStatus::Success => 1,
+            PipelineStatus::Canceled => 2,
+            PipelineStatus::Manual | PipelineStatus::Scheduled => 3,
+            PipelineStatus::Unknown(_) => 4,
+            PipelineStatus::Created
+            | PipelineStatus::WaitingForResource
+            | PipelineStatus::Preparing
+            | PipelineStatus::Pending => 5,
+            PipelineStatus::Running => 6,
+            PipelineStatus::Failed => 7,
+        }
+    }
 }
 
 /// GitLab pipeline sources, see
 /// https://docs.gitlab.com/ee/ci/jobs/job_rules.html#ci_pipeline_source-predefined-variable
-/// for reference.
-#[derive(Deserialize, strum::Display)]
+/// for reference. Unknown sources are kept as they are.
+#[derive(Clone, Serialize, Deserialize, strum::AsRefStr)]
 #[serde(rename_all = "snake_case")]
 pub enum PipelineSource {
     Push,
@@ -53,12 +140,46 @@ pub enum PipelineSource {
     OnDemandDastScan,
     OnDemandDastValidation,
     SecurityOrchestrationPolicy,
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+impl fmt::Display for PipelineSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PipelineSource::Unknown(source) => f.write_str(&unknown_name(source)),
+            _ => f.write_str(self.as_ref()),
+        }
+    }
+}
+
+/// Name of a value unknown to the dashboard, written like the names of the known ones so that it
+/// is shown and sorted alongside them, e.g. "MergeTrain" for "merge_train".
+fn unknown_name(value: &str) -> String {
+    value
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect()
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct GitlabPipeline {
     pub id: u32,
+    #[serde(default)]
+    pub iid: Option<u32>,
+    #[serde(default)]
+    pub project_id: Option<u32>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub sha: Option<String>,
     pub status: PipelineStatus,
     pub source: PipelineSource,
     #[serde(rename = "ref")]
@@ -66,37 +187,1632 @@ pub struct GitlabPipeline {
     pub web_url: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    // Only returned for a single pipeline, see `GitlabClient::fetch_pipeline`
+    #[serde(default)]
+    pub user: Option<GitlabUser>,
+    #[serde(default)]
+    pub started_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Duration in seconds
+    #[serde(default)]
+    pub duration: Option<u64>,
+    /// Time spent in the queue, in seconds
+    #[serde(default)]
+    pub queued_duration: Option<f64>,
+    /// Test coverage in percent, e.g. "83.50"
+    #[serde(default)]
+    pub coverage: Option<String>,
+    #[serde(default)]
+    pub detailed_status: Option<DetailedStatus>,
+}
+
+impl GitlabPipeline {
+    /// IID of the merge request the pipeline runs for, taken from refs such as
+    /// `refs/merge-requests/123/head`.
+    pub fn merge_request_iid(&self) -> Option<u32> {
+        self.git_ref
+            .strip_prefix("refs/merge-requests/")?
+            .split('/')
+            .next()?
+            .parse()
+            .ok()
+    }
+}
+
+/// A job of a pipeline, see https://docs.gitlab.com/ee/api/jobs.html for reference. Jobs share
+/// their statuses with pipelines.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GitlabJob {
+    pub id: u32,
+    pub name: String,
+    pub stage: String,
+    pub status: PipelineStatus,
+    pub web_url: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub started_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Duration in seconds
+    #[serde(default)]
+    pub duration: Option<f64>,
+    #[serde(default)]
+    pub allow_failure: bool,
+    /// Set for jobs which were retried, when retried jobs are requested
+    #[serde(default)]
+    pub retried: bool,
+    /// Archive of the artifacts of the job, if it kept any
+    #[serde(default)]
+    pub artifacts_file: Option<ArtifactsFile>,
+    /// Tags a runner needs to have to pick the job
+    #[serde(default)]
+    pub tag_list: Vec<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ArtifactsFile {
+    pub filename: String,
+    pub size: u64,
+}
+
+/// Progress of a download, updated while the download runs in the background.
+#[derive(Clone, Default)]
+pub struct DownloadProgress {
+    received: Arc<AtomicU64>,
+    total: Arc<AtomicU64>,
+}
+
+impl DownloadProgress {
+    pub fn received(&self) -> u64 {
+        self.received.load(Ordering::Relaxed)
+    }
+
+    /// Size of the download, if known.
+    pub fn total(&self) -> Option<u64> {
+        Some(self.total.load(Ordering::Relaxed)).filter(|total| *total > 0)
+    }
+}
+
+/// A job triggering a downstream pipeline, either a child pipeline or one in another project.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GitlabBridge {
+    pub id: u32,
+    pub name: String,
+    pub stage: String,
+    pub status: PipelineStatus,
+    pub web_url: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub downstream_pipeline: Option<DownstreamPipeline>,
+}
+
+/// Pipeline triggered by a bridge, as returned along with the bridge.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DownstreamPipeline {
+    pub id: u32,
+    pub project_id: u32,
+    pub status: PipelineStatus,
+    pub web_url: String,
+}
+
+/// Status as presented in the GitLab UI, e.g. "passed with warnings".
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DetailedStatus {
+    pub text: String,
+    pub label: String,
+    pub group: String,
+    #[serde(default)]
+    pub tooltip: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GitlabUser {
+    pub id: u32,
+    pub username: String,
+    pub name: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GitlabProject {
+    pub id: u32,
+    pub path_with_namespace: String,
+    #[serde(default)]
+    pub default_branch: Option<String>,
+    pub web_url: String,
+}
+
+/// An environment of a project, see https://docs.gitlab.com/ee/api/environments.html for
+/// reference.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GitlabEnvironment {
+    pub id: u32,
+    pub name: String,
+    /// "available", "stopping" or "stopped"
+    pub state: String,
+    /// E.g. "production" or "staging"
+    #[serde(default)]
+    pub tier: Option<String>,
+    #[serde(default)]
+    pub external_url: Option<String>,
+}
+
+/// A deployment to an environment, see https://docs.gitlab.com/ee/api/deployments.html for
+/// reference. Deployments share their statuses with pipelines, except for "blocked" which ends up
+/// as `Unknown`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GitlabDeployment {
+    pub id: u32,
+    pub iid: u32,
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+    pub sha: String,
+    pub status: PipelineStatus,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub finished_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub user: Option<GitlabUser>,
+    #[serde(default)]
+    pub deployable: Option<Deployable>,
+}
+
+/// Job which ran a deployment.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Deployable {
+    pub id: u32,
+    pub name: String,
+    #[serde(default)]
+    pub pipeline: Option<DeployablePipeline>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DeployablePipeline {
+    pub id: u32,
+    pub status: PipelineStatus,
+    pub web_url: String,
+}
+
+/// A pipeline schedule, see https://docs.gitlab.com/ee/api/pipeline_schedules.html for reference.
+/// The last pipeline and the variables are only returned for a single schedule.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GitlabSchedule {
+    pub id: u32,
+    pub description: String,
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+    pub cron: String,
+    pub cron_timezone: String,
+    #[serde(default)]
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub active: bool,
+    #[serde(default)]
+    pub owner: Option<GitlabUser>,
+    #[serde(default)]
+    pub last_pipeline: Option<ScheduledPipeline>,
+    #[serde(default)]
+    pub variables: Vec<ScheduleVariable>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ScheduledPipeline {
+    pub id: u32,
+    pub status: PipelineStatus,
+    pub web_url: String,
+}
+
+/// A variable passed to the pipelines of a schedule.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ScheduleVariable {
+    pub key: String,
+    pub value: String,
+    /// "env_var" or "file"
+    #[serde(default = "default_variable_type")]
+    pub variable_type: String,
+}
+
+fn default_variable_type() -> String {
+    "env_var".to_string()
+}
+
+/// A CI/CD variable of a project or a group, see
+/// https://docs.gitlab.com/ee/api/project_level_variables.html for reference. The value of hidden
+/// variables is never returned.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CiVariable {
+    pub key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// "env_var" or "file"
+    #[serde(default = "default_variable_type")]
+    pub variable_type: String,
+    #[serde(default)]
+    pub protected: bool,
+    #[serde(default)]
+    pub masked: bool,
+    /// Environments the variable applies to, "*" for all of them (always "*" for groups on the
+    /// free tier)
+    #[serde(default = "default_environment_scope")]
+    pub environment_scope: String,
+}
+
+fn default_environment_scope() -> String {
+    "*".to_string()
+}
+
+/// A runner available to a project, see https://docs.gitlab.com/ee/api/runners.html for
+/// reference. Tags, versions and platforms are only returned for a single runner, and the
+/// executor only through the GraphQL API.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GitlabRunner {
+    pub id: u32,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// "instance_type", "group_type" or "project_type"
+    pub runner_type: String,
+    #[serde(default)]
+    pub paused: bool,
+    /// "online", "offline", "stale" or "never_contacted"
+    pub status: String,
+    #[serde(default)]
+    pub tag_list: Vec<String>,
+    /// Whether the runner picks jobs without tags
+    #[serde(default)]
+    pub run_untagged: bool,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub platform: Option<String>,
+    #[serde(default)]
+    pub architecture: Option<String>,
+    #[serde(default)]
+    pub contacted_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub executor: Option<String>,
+}
+
+impl GitlabRunner {
+    pub fn is_online(&self) -> bool {
+        self.status == "online"
+    }
+
+    /// Whether the runner would pick a job with the given tags right now.
+    pub fn can_pick(&self, tags: &[String]) -> bool {
+        !self.paused
+            && self.is_online()
+            && (!tags.is_empty() || self.run_untagged)
+            && tags.iter().all(|tag| self.tag_list.contains(tag))
+    }
+}
+
+/// A job run by a runner, with the pipeline and project it belongs to.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RunnerJob {
+    pub id: u32,
+    pub name: String,
+    pub stage: String,
+    pub status: PipelineStatus,
+    pub web_url: String,
+    #[serde(default)]
+    pub started_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub pipeline: Option<RunnerJobPipeline>,
+    #[serde(default)]
+    pub project: Option<RunnerJobProject>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RunnerJobPipeline {
+    pub id: u32,
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RunnerJobProject {
+    pub path_with_namespace: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GitlabMergeRequest {
+    pub id: u32,
+    pub iid: u32,
+    pub title: String,
+    pub source_branch: String,
+    pub target_branch: String,
+    pub web_url: String,
+    #[serde(default)]
+    pub draft: bool,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub references: Option<MergeRequestReferences>,
+}
+
+impl GitlabMergeRequest {
+    /// Path of the project of the merge request, taken from its full reference.
+    pub fn project_path(&self) -> Option<&str> {
+        let (path, _) = self.references.as_ref()?.full.rsplit_once('!')?;
+        Some(path)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MergeRequestReferences {
+    /// Reference which is unique across projects, e.g. `group/project!12`
+    pub full: String,
+}
+
+/// A commit, see https://docs.gitlab.com/ee/api/commits.html for reference.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GitlabCommit {
+    pub id: String,
+    pub short_id: String,
+    pub title: String,
+    #[serde(default)]
+    pub message: String,
+    pub author_name: String,
+    #[serde(default)]
+    pub author_email: Option<String>,
+    pub authored_date: DateTime<Utc>,
+    // Only returned for a single commit, see `GitlabClient::fetch_commit`
+    #[serde(default)]
+    pub stats: Option<CommitStats>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CommitStats {
+    pub additions: u32,
+    pub deletions: u32,
+}
+
+/// A pending to-do item of the user, see https://docs.gitlab.com/ee/api/todos.html for reference.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GitlabTodo {
+    pub id: u32,
+    // None for to-do items of groups
+    #[serde(default)]
+    pub project: Option<TodoProject>,
+    /// Why the item is to do, e.g. `review_requested`
+    pub action_name: String,
+    /// Kind of the target, e.g. `MergeRequest`
+    pub target_type: String,
+    pub target: TodoTarget,
+    pub target_url: String,
+    pub updated_at: DateTime<Utc>,
 }
 
-pub fn fetch_pipelines(
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TodoProject {
+    pub path_with_namespace: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TodoTarget {
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+/// Test counts of a pipeline, see https://docs.gitlab.com/ee/api/pipelines.html for reference.
+#[derive(Clone, Deserialize)]
+pub struct TestReportSummary {
+    pub total: TestTotals,
+    pub test_suites: Vec<TestSuiteSummary>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct TestTotals {
+    /// Duration in seconds
+    pub time: f64,
+    pub count: u32,
+    pub success: u32,
+    pub failed: u32,
+    pub skipped: u32,
+    pub error: u32,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct TestSuiteSummary {
+    pub name: String,
+    /// Duration in seconds
+    pub total_time: f64,
+    pub total_count: u32,
+    pub success_count: u32,
+    pub failed_count: u32,
+    pub skipped_count: u32,
+    pub error_count: u32,
+    /// Set when the report of the suite could not be parsed
+    #[serde(default)]
+    pub suite_error: Option<String>,
+}
+
+/// Test cases of a pipeline, as parsed by GitLab from the JUnit reports of its jobs.
+#[derive(Clone, Deserialize)]
+pub struct TestReport {
+    pub test_suites: Vec<TestSuite>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct TestSuite {
+    pub name: String,
+    pub test_cases: Vec<TestCase>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct TestCase {
+    pub status: TestCaseStatus,
+    pub name: String,
+    #[serde(default)]
+    pub classname: Option<String>,
+    #[serde(default)]
+    pub file: Option<String>,
+    /// Duration in seconds
+    #[serde(default)]
+    pub execution_time: f64,
+    /// Failure message, for failed test cases
+    #[serde(default)]
+    pub system_output: Option<String>,
+    #[serde(default)]
+    pub stack_trace: Option<String>,
+}
+
+impl TestCase {
+    pub fn is_failure(&self) -> bool {
+        matches!(self.status, TestCaseStatus::Failed | TestCaseStatus::Error)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Deserialize, strum::Display)]
+#[serde(rename_all = "snake_case")]
+pub enum TestCaseStatus {
+    Success,
+    Failed,
+    Skipped,
+    Error,
+    #[serde(untagged)]
+    #[strum(to_string = "{0}")]
+    Unknown(String),
+}
+
+/// Largest page size accepted by the GitLab API.
+const MAX_PAGE_SIZE: usize = 100;
+/// Environments beyond this number are not fetched, e.g. for projects with review apps.
+const MAX_ENVIRONMENTS: usize = 500;
+/// Pipeline schedules beyond this number are not fetched.
+const MAX_SCHEDULES: usize = 500;
+/// Runners available to a project beyond this number are not fetched.
+const MAX_RUNNERS: usize = 500;
+/// Runners whose executors are asked for in a single GraphQL query, which GitLab limits in
+/// complexity.
+const RUNNERS_PER_QUERY: usize = 50;
+/// CI/CD variables of a project beyond this number are not fetched.
+const MAX_VARIABLES: usize = 1000;
+/// Discovered projects beyond this number are not fetched.
+const MAX_PROJECTS: usize = 5000;
+/// Number of projects returned by a search.
+const MAX_SEARCH_RESULTS: usize = 20;
+/// Open merge requests of the user fetched for each of their roles.
+const MAX_MERGE_REQUESTS: usize = 100;
+/// Pending to-do items of the user beyond this number are not fetched.
+const MAX_TODOS: usize = 100;
+
+/// Thin asynchronous client for the GitLab REST API.
+#[derive(Clone, Default)]
+pub struct GitlabClient {
+    client: Client,
     gitlab_url: String,
-    gitlab_project: String,
-    pagination_limit: usize,
-) -> Result<Vec<GitlabPipeline>, color_eyre::eyre::Report> {
-    let token = env::var("GITLAB_PERSONAL_ACCESS_TOKEN")?;
-    let client = Client::new();
-    let url = format!("{}/projects/{}/pipelines", gitlab_url, gitlab_project);
-
-    // Create a tokio runtime to block on the async operation
-    let rt = tokio::runtime::Runtime::new()?;
-
-    // Block on the async task
-    let response = rt.block_on(async {
-        client
-            .get(&url)
+}
+
+impl GitlabClient {
+    pub fn new(gitlab_url: String) -> Self {
+        Self {
+            client: Client::new(),
+            gitlab_url,
+        }
+    }
+
+    /// Prepare an authenticated GET request for a given API path, e.g. `/projects/:id/pipelines`.
+    fn get(&self, path: &str) -> Result<RequestBuilder> {
+        self.request(Method::GET, path)
+    }
+
+    /// Prepare an authenticated request for a given API path, e.g. to change something.
+    fn request(&self, method: Method, path: &str) -> Result<RequestBuilder> {
+        let token = env::var("GITLAB_PERSONAL_ACCESS_TOKEN")?;
+        let url = format!("{}{}", self.gitlab_url, path);
+        Ok(self.client.request(method, url).bearer_auth(token))
+    }
+
+    /// Prepare an authenticated GraphQL query. The GraphQL endpoint lives next to the REST API,
+    /// e.g. `https://gitlab.example.com/api/graphql`.
+    fn post_graphql(&self, query: &str, variables: serde_json::Value) -> Result<RequestBuilder> {
+        let token = env::var("GITLAB_PERSONAL_ACCESS_TOKEN")?;
+        let base_url = self.gitlab_url.trim_end_matches('/');
+        let url = format!("{}/api/graphql", base_url.trim_end_matches("/api/v4"));
+        Ok(self
+            .client
+            .post(url)
             .bearer_auth(token)
-            .query(&[("per_page", pagination_limit.to_string())])
-            .send()
+            .json(&serde_json::json!({ "query": query, "variables": variables })))
+    }
+
+    pub async fn fetch_pipelines(
+        &self,
+        gitlab_project: &str,
+        pagination_limit: usize,
+    ) -> Result<Vec<GitlabPipeline>> {
+        let page = self
+            .fetch_pipelines_page(gitlab_project, pagination_limit, 1, None)
+            .await?;
+        Ok(page.items)
+    }
+
+    pub async fn fetch_project(&self, gitlab_project: &str) -> Result<GitlabProject> {
+        let request = self.get(&format!("/projects/{}", encode_project(gitlab_project)))?;
+        Self::send(request, "project").await
+    }
+
+    /// Fetch up to `count` most recent pipelines, optionally only the ones running on a given ref.
+    pub async fn fetch_recent_pipelines(
+        &self,
+        gitlab_project: &str,
+        git_ref: Option<&str>,
+        count: usize,
+    ) -> Result<Vec<GitlabPipeline>> {
+        let query: Vec<(&str, &str)> = git_ref
+            .map(|git_ref| ("ref", git_ref))
+            .into_iter()
+            .collect();
+        self.fetch_pages(
+            &format!("/projects/{}/pipelines", encode_project(gitlab_project)),
+            &query,
+            count,
+            "pipelines",
+        )
+        .await
+    }
+
+    /// Fetch the projects of a group and of its subgroups, leaving out archived projects unless
+    /// asked otherwise.
+    pub async fn fetch_group_projects(
+        &self,
+        group: &str,
+        archived: bool,
+    ) -> Result<Vec<GitlabProject>> {
+        let mut query = vec![("include_subgroups", "true"), ("simple", "true")];
+        if !archived {
+            query.push(("archived", "false"));
+        }
+        self.fetch_pages(
+            &format!("/groups/{}/projects", encode_project(group)),
+            &query,
+            MAX_PROJECTS,
+            "group projects",
+        )
+        .await
+    }
+
+    /// Fetch the projects visible to the user matching a filter, e.g. `membership` or `starred`,
+    /// leaving out archived projects unless asked otherwise.
+    pub async fn fetch_user_projects(
+        &self,
+        filter: &str,
+        archived: bool,
+    ) -> Result<Vec<GitlabProject>> {
+        let mut query = vec![(filter, "true"), ("simple", "true")];
+        if !archived {
+            query.push(("archived", "false"));
+        }
+        self.fetch_pages("/projects", &query, MAX_PROJECTS, "projects")
             .await
-    })?;
-
-    if response.status().is_success() {
-        let pipelines = rt.block_on(response.json::<Vec<GitlabPipeline>>())?;
-        Ok(pipelines)
-    } else {
-        Err(color_eyre::eyre::eyre!(
-            "Failed to fetch pipelines: {}",
-            response.status()
-        ))
+    }
+
+    /// Latest pipeline of the default branch of a project, if there is any.
+    pub async fn fetch_latest_pipeline(
+        &self,
+        gitlab_project: &str,
+    ) -> Result<Option<GitlabPipeline>> {
+        let request = self.get(&format!(
+            "/projects/{}/pipelines/latest",
+            encode_project(gitlab_project)
+        ))?;
+        let response = request.send().await?;
+        // GitLab answers with a 404 when the default branch has no pipeline
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(color_eyre::eyre::eyre!(
+                "Failed to fetch latest pipeline: {}",
+                response.status()
+            ));
+        }
+        Ok(Some(response.json().await?))
+    }
+
+    /// Number of pipelines of a project which are running.
+    pub async fn count_running_pipelines(&self, gitlab_project: &str) -> Result<Option<usize>> {
+        self.count(
+            &format!("/projects/{}/pipelines", encode_project(gitlab_project)),
+            &[("status", "running")],
+            "running pipelines",
+        )
+        .await
+    }
+
+    /// Number of merge requests of a project which are open.
+    pub async fn count_open_merge_requests(&self, gitlab_project: &str) -> Result<Option<usize>> {
+        self.count(
+            &format!(
+                "/projects/{}/merge_requests",
+                encode_project(gitlab_project)
+            ),
+            &[("state", "opened")],
+            "merge requests",
+        )
+        .await
+    }
+
+    /// The user the token belongs to.
+    pub async fn fetch_current_user(&self) -> Result<GitlabUser> {
+        Self::send(self.get("/user")?, "user").await
+    }
+
+    /// Recent pipelines of a project triggered by a user, the most recently updated first.
+    pub async fn fetch_user_pipelines(
+        &self,
+        gitlab_project: &str,
+        username: &str,
+        count: usize,
+    ) -> Result<Vec<GitlabPipeline>> {
+        self.fetch_pages(
+            &format!("/projects/{}/pipelines", encode_project(gitlab_project)),
+            &[("username", username), ("order_by", "updated_at")],
+            count,
+            "pipelines",
+        )
+        .await
+    }
+
+    /// Open merge requests in which a user has a role, given as `author`, `assignee` or
+    /// `reviewer`, the most recently updated first. They are searched across all projects, unless
+    /// `gitlab_project` is given.
+    pub async fn fetch_user_merge_requests(
+        &self,
+        gitlab_project: Option<&str>,
+        role: &str,
+        user_id: u32,
+    ) -> Result<Vec<GitlabMergeRequest>> {
+        let filter = format!("{}_id", role);
+        let user_id = user_id.to_string();
+        let query = [
+            ("scope", "all"),
+            ("state", "opened"),
+            ("order_by", "updated_at"),
+            (filter.as_str(), user_id.as_str()),
+        ];
+        let path = match gitlab_project {
+            Some(gitlab_project) => format!(
+                "/projects/{}/merge_requests",
+                encode_project(gitlab_project)
+            ),
+            None => "/merge_requests".to_string(),
+        };
+        self.fetch_pages(&path, &query, MAX_MERGE_REQUESTS, "merge requests")
+            .await
+    }
+
+    /// Pending to-do items of the user the token belongs to, the most recent first.
+    pub async fn fetch_todos(&self) -> Result<Vec<GitlabTodo>> {
+        self.fetch_pages("/todos", &[("state", "pending")], MAX_TODOS, "to-do items")
+            .await
+    }
+
+    /// Search the projects visible to the user by name or path, the most active first.
+    pub async fn search_projects(&self, search: &str) -> Result<Vec<GitlabProject>> {
+        let query = [
+            ("search", search),
+            ("search_namespaces", "true"),
+            ("simple", "true"),
+            ("archived", "false"),
+            ("order_by", "last_activity_at"),
+        ];
+        self.fetch_pages("/projects", &query, MAX_SEARCH_RESULTS, "projects")
+            .await
+    }
+
+    /// Name of the default branch of a project.
+    pub async fn fetch_default_branch(&self, gitlab_project: &str) -> Result<String> {
+        self.fetch_project(gitlab_project)
+            .await?
+            .default_branch
+            .ok_or_else(|| color_eyre::eyre::eyre!("Project has no default branch"))
+    }
+
+    /// Fetch all jobs of a pipeline, including the retried ones if asked to.
+    pub async fn fetch_pipeline_jobs(
+        &self,
+        gitlab_project: &str,
+        pipeline_id: u32,
+        include_retried: bool,
+    ) -> Result<Vec<GitlabJob>> {
+        self.fetch_pages(
+            &format!(
+                "/projects/{}/pipelines/{}/jobs",
+                encode_project(gitlab_project),
+                pipeline_id
+            ),
+            &[("include_retried", &include_retried.to_string())],
+            usize::MAX,
+            "jobs",
+        )
+        .await
+    }
+
+    /// Whether a pipeline has a manual job waiting to be started which blocks the pipeline, i.e.
+    /// which is not allowed to fail. Optional manual jobs never hold a pipeline back.
+    pub async fn has_blocking_manual_job(
+        &self,
+        gitlab_project: &str,
+        pipeline_id: u32,
+    ) -> Result<bool> {
+        let jobs: Vec<GitlabJob> = self
+            .fetch_pages(
+                &format!(
+                    "/projects/{}/pipelines/{}/jobs",
+                    encode_project(gitlab_project),
+                    pipeline_id
+                ),
+                &[("scope[]", "manual")],
+                usize::MAX,
+                "jobs",
+            )
+            .await?;
+        Ok(jobs.iter().any(|job| !job.allow_failure))
+    }
+
+    /// Fetch the bridges of a pipeline, i.e. the jobs triggering downstream pipelines.
+    pub async fn fetch_pipeline_bridges(
+        &self,
+        gitlab_project: &str,
+        pipeline_id: u32,
+    ) -> Result<Vec<GitlabBridge>> {
+        self.fetch_pages(
+            &format!(
+                "/projects/{}/pipelines/{}/bridges",
+                encode_project(gitlab_project),
+                pipeline_id
+            ),
+            &[],
+            usize::MAX,
+            "bridges",
+        )
+        .await
+    }
+
+    /// `needs:` of the jobs of a pipeline, by job name. They are only available through the
+    /// GraphQL API, whose connections are paginated: first the jobs, then the needs of the jobs
+    /// having more of them than the first page holds.
+    pub async fn fetch_job_needs(
+        &self,
+        gitlab_project: &str,
+        pipeline_iid: u32,
+    ) -> Result<HashMap<String, Vec<String>>> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct PageInfo {
+            has_next_page: bool,
+            end_cursor: Option<String>,
+        }
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Connection<T> {
+            page_info: PageInfo,
+            nodes: Vec<T>,
+        }
+        #[derive(Deserialize)]
+        struct Need {
+            name: String,
+        }
+        #[derive(Deserialize)]
+        struct Job {
+            name: String,
+            needs: Option<Connection<Need>>,
+        }
+        #[derive(Deserialize)]
+        struct Pipeline {
+            #[serde(default)]
+            jobs: Option<Connection<Job>>,
+            #[serde(default)]
+            job: Option<Job>,
+        }
+        #[derive(Deserialize)]
+        struct Project {
+            pipeline: Option<Pipeline>,
+        }
+        #[derive(Deserialize)]
+        struct Data {
+            project: Option<Project>,
+        }
+
+        let fetch_pipeline = |query: &'static str, variables: serde_json::Value| async move {
+            let mut variables = variables;
+            variables["project"] = gitlab_project.into();
+            variables["iid"] = pipeline_iid.to_string().into();
+            let request = self.post_graphql(query, variables)?;
+            let data: Data = Self::send_graphql(request, "job needs").await?;
+            Ok::<_, Error>(data.project.and_then(|project| project.pipeline))
+        };
+
+        let jobs_query = "query($project: ID!, $iid: ID!, $after: String) { \
+            project(fullPath: $project) { pipeline(iid: $iid) { \
+            jobs(after: $after) { pageInfo { hasNextPage endCursor } nodes { name \
+            needs { pageInfo { hasNextPage endCursor } nodes { name } } } } } } }";
+        let mut needs_by_job = HashMap::new();
+        // Jobs with more needs to fetch, and where to resume
+        let mut unfinished = Vec::new();
+        let mut after: Option<String> = None;
+        loop {
+            let Some(jobs) = fetch_pipeline(jobs_query, serde_json::json!({ "after": after }))
+                .await?
+                .and_then(|pipeline| pipeline.jobs)
+            else {
+                break;
+            };
+            for job in jobs.nodes {
+                let needs = match job.needs {
+                    Some(needs) => {
+                        if needs.page_info.has_next_page {
+                            unfinished.push((job.name.clone(), needs.page_info.end_cursor));
+                        }
+                        needs.nodes.into_iter().map(|need| need.name).collect()
+                    }
+                    None => Vec::new(),
+                };
+                needs_by_job.insert(job.name, needs);
+            }
+            match jobs.page_info.end_cursor {
+                Some(cursor) if jobs.page_info.has_next_page => after = Some(cursor),
+                _ => break,
+            }
+        }
+
+        let needs_query = "query($project: ID!, $iid: ID!, $job: String, $after: String) { \
+            project(fullPath: $project) { pipeline(iid: $iid) { job(name: $job) { name \
+            needs(after: $after) { pageInfo { hasNextPage endCursor } nodes { name } } } } } }";
+        for (job_name, mut after) in unfinished {
+            while let Some(cursor) = after.take() {
+                let Some(needs) = fetch_pipeline(
+                    needs_query,
+                    serde_json::json!({ "job": job_name, "after": cursor }),
+                )
+                .await?
+                .and_then(|pipeline| pipeline.job)
+                .and_then(|job| job.needs) else {
+                    break;
+                };
+                needs_by_job
+                    .entry(job_name.clone())
+                    .or_insert_with(Vec::new)
+                    .extend(needs.nodes.into_iter().map(|need| need.name));
+                if needs.page_info.has_next_page {
+                    after = needs.page_info.end_cursor;
+                }
+            }
+        }
+        Ok(needs_by_job)
+    }
+
+    /// Fetch the log of a job, which contains ANSI escape codes.
+    pub async fn fetch_job_log(&self, gitlab_project: &str, job_id: u32) -> Result<String> {
+        let request = self.get(&format!(
+            "/projects/{}/jobs/{}/trace",
+            encode_project(gitlab_project),
+            job_id
+        ))?;
+        Ok(Self::fetch(request, "job log").await?.text().await?)
+    }
+
+    /// Download the artifacts archive of a job, or a single file of the archive given its path,
+    /// reporting the progress along the way.
+    pub async fn download_job_artifacts<W: AsyncWrite + Unpin>(
+        &self,
+        gitlab_project: &str,
+        job_id: u32,
+        artifact_path: Option<&str>,
+        progress: &DownloadProgress,
+        writer: &mut W,
+    ) -> Result<()> {
+        let mut path = format!(
+            "/projects/{}/jobs/{}/artifacts",
+            encode_project(gitlab_project),
+            job_id
+        );
+        if let Some(artifact_path) = artifact_path {
+            path = format!("{}/{}", path, encode_path(artifact_path));
+        }
+        let mut response = Self::fetch(self.get(&path)?, "artifacts").await?;
+        progress
+            .total
+            .store(response.content_length().unwrap_or(0), Ordering::Relaxed);
+        while let Some(chunk) = response.chunk().await? {
+            writer.write_all(&chunk).await?;
+            progress
+                .received
+                .fetch_add(chunk.len() as u64, Ordering::Relaxed);
+        }
+        writer.flush().await?;
+        Ok(())
+    }
+
+    /// Fetch a single pipeline, which comes with more details than the pipelines in a list.
+    pub async fn fetch_pipeline(&self, gitlab_project: &str, id: u32) -> Result<GitlabPipeline> {
+        let request = self.get(&format!(
+            "/projects/{}/pipelines/{}",
+            encode_project(gitlab_project),
+            id
+        ))?;
+        Self::send(request, "pipeline").await
+    }
+
+    /// Fetch the environments of a project, only the available ones unless asked otherwise.
+    pub async fn fetch_environments(
+        &self,
+        gitlab_project: &str,
+        include_stopped: bool,
+    ) -> Result<Vec<GitlabEnvironment>> {
+        let query: Vec<(&str, &str)> = if include_stopped {
+            vec![]
+        } else {
+            vec![("states", "available")]
+        };
+        self.fetch_pages(
+            &format!("/projects/{}/environments", encode_project(gitlab_project)),
+            &query,
+            MAX_ENVIRONMENTS,
+            "environments",
+        )
+        .await
+    }
+
+    /// Fetch up to `count` most recent deployments to an environment, the latest first.
+    pub async fn fetch_deployments(
+        &self,
+        gitlab_project: &str,
+        environment: &str,
+        count: usize,
+    ) -> Result<Vec<GitlabDeployment>> {
+        self.fetch_pages(
+            &format!("/projects/{}/deployments", encode_project(gitlab_project)),
+            &[
+                ("environment", environment),
+                ("order_by", "id"),
+                ("sort", "desc"),
+            ],
+            count,
+            "deployments",
+        )
+        .await
+    }
+
+    /// Fetch the most recent deployment to an environment, only among the ones with a given
+    /// status (e.g. "success") if any.
+    pub async fn fetch_last_deployment(
+        &self,
+        gitlab_project: &str,
+        environment: &str,
+        status: Option<&str>,
+    ) -> Result<Option<GitlabDeployment>> {
+        let mut query = vec![
+            ("environment", environment),
+            ("order_by", "id"),
+            ("sort", "desc"),
+        ];
+        query.extend(status.map(|status| ("status", status)));
+        let deployments = self
+            .fetch_pages(
+                &format!("/projects/{}/deployments", encode_project(gitlab_project)),
+                &query,
+                1,
+                "deployments",
+            )
+            .await?;
+        Ok(deployments.into_iter().next())
+    }
+
+    pub async fn fetch_pipeline_schedules(
+        &self,
+        gitlab_project: &str,
+    ) -> Result<Vec<GitlabSchedule>> {
+        self.fetch_pages(
+            &format!(
+                "/projects/{}/pipeline_schedules",
+                encode_project(gitlab_project)
+            ),
+            &[],
+            MAX_SCHEDULES,
+            "pipeline schedules",
+        )
+        .await
+    }
+
+    /// Fetch a single schedule, which comes with its last pipeline and its variables.
+    pub async fn fetch_pipeline_schedule(
+        &self,
+        gitlab_project: &str,
+        id: u32,
+    ) -> Result<GitlabSchedule> {
+        let request = self.get(&format!(
+            "/projects/{}/pipeline_schedules/{}",
+            encode_project(gitlab_project),
+            id
+        ))?;
+        Self::send(request, "pipeline schedule").await
+    }
+
+    /// Run a schedule now, regardless of its cron.
+    pub async fn play_pipeline_schedule(&self, gitlab_project: &str, id: u32) -> Result<()> {
+        let request = self.request(
+            Method::POST,
+            &format!(
+                "/projects/{}/pipeline_schedules/{}/play",
+                encode_project(gitlab_project),
+                id
+            ),
+        )?;
+        Self::execute(request, "run pipeline schedule").await
+    }
+
+    pub async fn set_pipeline_schedule_active(
+        &self,
+        gitlab_project: &str,
+        id: u32,
+        active: bool,
+    ) -> Result<()> {
+        let request = self
+            .request(
+                Method::PUT,
+                &format!(
+                    "/projects/{}/pipeline_schedules/{}",
+                    encode_project(gitlab_project),
+                    id
+                ),
+            )?
+            .json(&serde_json::json!({ "active": active }));
+        Self::execute(request, "update pipeline schedule").await
+    }
+
+    /// Create a variable of a schedule, or update its value if it exists already.
+    pub async fn save_schedule_variable(
+        &self,
+        gitlab_project: &str,
+        schedule_id: u32,
+        variable: &ScheduleVariable,
+        exists: bool,
+    ) -> Result<()> {
+        let path = format!(
+            "/projects/{}/pipeline_schedules/{}/variables",
+            encode_project(gitlab_project),
+            schedule_id
+        );
+        let request = if exists {
+            self.request(Method::PUT, &format!("{}/{}", path, variable.key))?
+        } else {
+            self.request(Method::POST, &path)?
+        };
+        let request = request.json(variable);
+        Self::execute(request, "save variable").await
+    }
+
+    pub async fn delete_schedule_variable(
+        &self,
+        gitlab_project: &str,
+        schedule_id: u32,
+        key: &str,
+    ) -> Result<()> {
+        let request = self.request(
+            Method::DELETE,
+            &format!(
+                "/projects/{}/pipeline_schedules/{}/variables/{}",
+                encode_project(gitlab_project),
+                schedule_id,
+                key
+            ),
+        )?;
+        Self::execute(request, "delete variable").await
+    }
+
+    /// Fetch the variables of a project, which requires the Maintainer role.
+    pub async fn fetch_project_variables(&self, gitlab_project: &str) -> Result<Vec<CiVariable>> {
+        self.fetch_pages(
+            &format!("/projects/{}/variables", encode_project(gitlab_project)),
+            &[],
+            MAX_VARIABLES,
+            "variables",
+        )
+        .await
+    }
+
+    /// Fetch the variables of a group, which requires the Maintainer role in the group.
+    pub async fn fetch_group_variables(&self, group: &str) -> Result<Vec<CiVariable>> {
+        self.fetch_pages(
+            &format!("/groups/{}/variables", encode_project(group)),
+            &[],
+            MAX_VARIABLES,
+            "group variables",
+        )
+        .await
+    }
+
+    /// Create a variable, or update the one with the same key and environment scope if `exists`.
+    pub async fn save_project_variable(
+        &self,
+        gitlab_project: &str,
+        variable: &CiVariable,
+        exists: bool,
+    ) -> Result<()> {
+        let path = format!("/projects/{}/variables", encode_project(gitlab_project));
+        let request = if exists {
+            self.request(Method::PUT, &format!("{}/{}", path, variable.key))?
+                .query(&[("filter[environment_scope]", &variable.environment_scope)])
+        } else {
+            self.request(Method::POST, &path)?
+        };
+        Self::execute(request.json(variable), "save variable").await
+    }
+
+    pub async fn delete_project_variable(
+        &self,
+        gitlab_project: &str,
+        key: &str,
+        environment_scope: &str,
+    ) -> Result<()> {
+        let request = self
+            .request(
+                Method::DELETE,
+                &format!(
+                    "/projects/{}/variables/{}",
+                    encode_project(gitlab_project),
+                    key
+                ),
+            )?
+            .query(&[("filter[environment_scope]", environment_scope)]);
+        Self::execute(request, "delete variable").await
+    }
+
+    /// Fetch the runners available to a project, including the ones of its groups and the
+    /// instance runners it may use. Listing them requires the Maintainer role.
+    pub async fn fetch_runners(&self, gitlab_project: &str) -> Result<Vec<GitlabRunner>> {
+        self.fetch_pages(
+            &format!("/projects/{}/runners", encode_project(gitlab_project)),
+            &[],
+            MAX_RUNNERS,
+            "runners",
+        )
+        .await
+    }
+
+    /// Fetch a single runner, which comes with its tags, version and platform.
+    pub async fn fetch_runner(&self, id: u32) -> Result<GitlabRunner> {
+        let request = self.get(&format!("/runners/{}", id))?;
+        Self::send(request, "runner").await
+    }
+
+    /// Executors of runners, by runner ID. They are only available through the GraphQL API.
+    pub async fn fetch_runner_executors(&self, ids: &[u32]) -> Result<HashMap<u32, String>> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Runner {
+            executor_name: Option<String>,
+        }
+        #[derive(Deserialize)]
+        struct Response {
+            // Runners the user may not see are null
+            data: HashMap<String, Option<Runner>>,
+        }
+
+        // A query per chunk of runners, with an alias per runner
+        let mut executors = HashMap::new();
+        for chunk in ids.chunks(RUNNERS_PER_QUERY) {
+            let fields: Vec<String> = chunk
+                .iter()
+                .map(|id| {
+                    format!(
+                        "r{}: runner(id: \"gid://gitlab/Ci::Runner/{}\") {{ executorName }}",
+                        id, id
+                    )
+                })
+                .collect();
+            let query = format!("query {{ {} }}", fields.join(" "));
+            let request = self.post_graphql(&query, serde_json::json!({}))?;
+            let response: Response = Self::send(request, "runner executors").await?;
+            executors.extend(response.data.into_iter().filter_map(|(alias, runner)| {
+                let id = alias.strip_prefix('r')?.parse().ok()?;
+                Some((id, runner?.executor_name?))
+            }));
+        }
+        Ok(executors)
+    }
+
+    /// Fetch the jobs a runner is running. Only administrators see the jobs of instance runners.
+    pub async fn fetch_runner_jobs(&self, id: u32) -> Result<Vec<RunnerJob>> {
+        self.fetch_pages(
+            &format!("/runners/{}/jobs", id),
+            &[("status", "running")],
+            usize::MAX,
+            "runner jobs",
+        )
+        .await
+    }
+
+    /// Fetch the jobs of a pipeline waiting for a runner.
+    pub async fn fetch_pending_jobs(
+        &self,
+        gitlab_project: &str,
+        pipeline_id: u32,
+    ) -> Result<Vec<GitlabJob>> {
+        self.fetch_pages(
+            &format!(
+                "/projects/{}/pipelines/{}/jobs",
+                encode_project(gitlab_project),
+                pipeline_id
+            ),
+            &[("scope[]", "pending")],
+            usize::MAX,
+            "jobs",
+        )
+        .await
+    }
+
+    pub async fn fetch_merge_request(
+        &self,
+        gitlab_project: &str,
+        iid: u32,
+    ) -> Result<GitlabMergeRequest> {
+        let request = self.get(&format!(
+            "/projects/{}/merge_requests/{}",
+            encode_project(gitlab_project),
+            iid
+        ))?;
+        Self::send(request, "merge request").await
+    }
+
+    /// Merge requests which contain a commit.
+    pub async fn fetch_commit_merge_requests(
+        &self,
+        gitlab_project: &str,
+        sha: &str,
+    ) -> Result<Vec<GitlabMergeRequest>> {
+        let request = self.get(&format!(
+            "/projects/{}/repository/commits/{}/merge_requests",
+            encode_project(gitlab_project),
+            sha
+        ))?;
+        Self::send(request, "merge requests").await
+    }
+
+    /// Fetch a commit along with its stats.
+    pub async fn fetch_commit(&self, gitlab_project: &str, sha: &str) -> Result<GitlabCommit> {
+        let request = self.get(&format!(
+            "/projects/{}/repository/commits/{}",
+            encode_project(gitlab_project),
+            sha
+        ))?;
+        Self::send(request, "commit").await
+    }
+
+    /// Number of files changed by a commit, counted without fetching their diffs.
+    pub async fn count_commit_files(
+        &self,
+        gitlab_project: &str,
+        sha: &str,
+    ) -> Result<Option<usize>> {
+        self.count(
+            &format!(
+                "/projects/{}/repository/commits/{}/diff",
+                encode_project(gitlab_project),
+                sha
+            ),
+            &[],
+            "commit diff",
+        )
+        .await
+    }
+
+    /// Commits reachable from `to` but not from `from`, the newest first and without their diffs.
+    pub async fn fetch_commits_between(
+        &self,
+        gitlab_project: &str,
+        from: &str,
+        to: &str,
+        limit: usize,
+    ) -> Result<Vec<GitlabCommit>> {
+        let range = format!("{}..{}", from, to);
+        self.fetch_pages(
+            &format!(
+                "/projects/{}/repository/commits",
+                encode_project(gitlab_project)
+            ),
+            &[("ref_name", range.as_str())],
+            limit,
+            "commits",
+        )
+        .await
+    }
+
+    /// Fetch the test counts of a pipeline, which are much cheaper than its full test report.
+    pub async fn fetch_test_report_summary(
+        &self,
+        gitlab_project: &str,
+        pipeline_id: u32,
+    ) -> Result<TestReportSummary> {
+        let request = self.get(&format!(
+            "/projects/{}/pipelines/{}/test_report_summary",
+            encode_project(gitlab_project),
+            pipeline_id
+        ))?;
+        Self::send(request, "test report summary").await
+    }
+
+    pub async fn fetch_test_report(
+        &self,
+        gitlab_project: &str,
+        pipeline_id: u32,
+    ) -> Result<TestReport> {
+        let request = self.get(&format!(
+            "/projects/{}/pipelines/{}/test_report",
+            encode_project(gitlab_project),
+            pipeline_id
+        ))?;
+        Self::send(request, "test report").await
+    }
+
+    /// Fetch a single page of pipelines, `page` being 1-based as in the GitLab API.
+    /// Fetch a page of pipelines, the newest first unless `order` gives a field to sort them by and
+    /// the direction, e.g. `("updated_at", "asc")`.
+    pub async fn fetch_pipelines_page(
+        &self,
+        gitlab_project: &str,
+        pagination_limit: usize,
+        page: usize,
+        order: Option<(&str, &str)>,
+    ) -> Result<Page<GitlabPipeline>> {
+        let mut request = self
+            .get(&format!(
+                "/projects/{}/pipelines",
+                encode_project(gitlab_project)
+            ))?
+            .query(&[
+                ("per_page", pagination_limit.to_string()),
+                ("page", page.to_string()),
+            ]);
+        if let Some((order_by, sort)) = order {
+            request = request.query(&[("order_by", order_by), ("sort", sort)]);
+        }
+        Self::send_paginated(request, "pipelines").await
+    }
+
+    /// Fetch up to `limit` items of a paginated collection, page by page.
+    async fn fetch_pages<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+        limit: usize,
+        what: &str,
+    ) -> Result<Vec<T>> {
+        let per_page = limit.min(MAX_PAGE_SIZE);
+        let mut items = vec![];
+        for page in 1.. {
+            let request = self.get(path)?.query(query).query(&[
+                ("per_page", per_page.to_string()),
+                ("page", page.to_string()),
+            ]);
+            let page = Self::send_paginated::<T>(request, what).await?;
+            let last_page = page.items.len() < per_page;
+            items.extend(page.items);
+            if last_page || items.len() >= limit {
+                break;
+            }
+        }
+        items.truncate(limit);
+        Ok(items)
+    }
+
+    /// Number of items of a collection, read from the pagination headers by fetching a single
+    /// item per page. GitLab omits them for very large collections, whose size is then unknown.
+    async fn count(&self, path: &str, query: &[(&str, &str)], what: &str) -> Result<Option<usize>> {
+        let request = self.get(path)?.query(query).query(&[("per_page", "1")]);
+        let page = Self::send_paginated::<serde_json::Value>(request, what).await?;
+        if page.items.is_empty() {
+            return Ok(Some(0));
+        }
+        Ok(page.total_pages)
+    }
+
+    /// Send a request, failing unless GitLab responds successfully. `what` tells what is fetched,
+    /// e.g. "job log".
+    async fn fetch(request: RequestBuilder, what: &str) -> Result<Response> {
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(color_eyre::eyre::eyre!(
+                "Failed to fetch {}: {}",
+                what,
+                response.status()
+            ));
+        }
+        Ok(response)
+    }
+
+    async fn send<T: DeserializeOwned>(request: RequestBuilder, what: &str) -> Result<T> {
+        Ok(Self::fetch(request, what).await?.json::<T>().await?)
+    }
+
+    /// Send a GraphQL query and return its data. GraphQL reports errors in the body of successful
+    /// responses, and these fail the query as a whole rather than leaving parts of the data out.
+    async fn send_graphql<T: DeserializeOwned>(request: RequestBuilder, what: &str) -> Result<T> {
+        #[derive(Deserialize)]
+        struct GraphqlError {
+            message: String,
+        }
+        #[derive(Deserialize)]
+        struct Response<T> {
+            data: Option<T>,
+            #[serde(default)]
+            errors: Vec<GraphqlError>,
+        }
+
+        let response: Response<T> = Self::send(request, what).await?;
+        if !response.errors.is_empty() {
+            let messages: Vec<String> = response
+                .errors
+                .into_iter()
+                .map(|error| error.message)
+                .collect();
+            return Err(color_eyre::eyre::eyre!(
+                "Failed to fetch {}: {}",
+                what,
+                messages.join("; ")
+            ));
+        }
+        response
+            .data
+            .ok_or_else(|| color_eyre::eyre::eyre!("Failed to fetch {}: no data", what))
+    }
+
+    /// Send a request whose response does not matter, e.g. one changing something. `what` tells
+    /// what the request does, e.g. "delete variable".
+    async fn execute(request: RequestBuilder, what: &str) -> Result<()> {
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(color_eyre::eyre::eyre!(
+                "Failed to {}: {}",
+                what,
+                response.status()
+            ));
+        }
+        Ok(())
+    }
+
+    async fn send_paginated<T: DeserializeOwned>(
+        request: RequestBuilder,
+        what: &str,
+    ) -> Result<Page<T>> {
+        let response = Self::fetch(request, what).await?;
+        // GitLab omits the total for very large collections
+        let total_pages = response
+            .headers()
+            .get("x-total-pages")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok());
+        Ok(Page {
+            items: response.json::<Vec<T>>().await?,
+            total_pages,
+        })
+    }
+}
+
+/// A single page of a paginated collection.
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total_pages: Option<usize>,
+}
+
+/// Encode a project path (e.g. `group/subgroup/project`) so that it can be used in place of a
+/// project ID in API paths.
+pub fn encode_project(gitlab_project: &str) -> String {
+    gitlab_project.replace('/', "%2F")
+}
+
+/// Encode a file path for a URL, keeping its slashes.
+fn encode_path(path: &str) -> String {
+    path.replace('%', "%25")
+        .replace(' ', "%20")
+        .replace('#', "%23")
+        .replace('?', "%3F")
+}
+
+/// Whether an error means that the GitLab instance could not be reached at all (as opposed to,
+/// for example, the request being rejected).
+pub fn is_unreachable(error: &Error) -> bool {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .is_some_and(|error| error.is_connect() || error.is_timeout())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::cache::CacheEntry;
+
+    #[test]
+    fn unknown_status_and_source_survive_the_cache() {
+        let pipeline: GitlabPipeline = serde_json::from_value(json!({
+            "id": 1,
+            "status": "canceling",
+            "source": "merge_train",
+            "ref": "main",
+            "web_url": "https://gitlab.example.com/group/project/-/pipelines/1",
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap();
+        let entry = CacheEntry {
+            fetched_at: Utc::now(),
+            data: vec![pipeline],
+        };
+        let cached: CacheEntry<Vec<GitlabPipeline>> =
+            serde_json::from_slice(&serde_json::to_vec(&entry).unwrap()).unwrap();
+        let pipeline = &cached.data[0];
+        assert!(pipeline.status == PipelineStatus::Unknown("canceling".to_string()));
+        assert!(
+            matches!(&pipeline.source, PipelineSource::Unknown(source) if source == "merge_train")
+        );
+        assert_eq!(pipeline.status.to_string(), "Canceling");
+        assert_eq!(pipeline.source.to_string(), "MergeTrain");
+    }
+
+    #[test]
+    fn unknown_statuses_sort_among_known_ones() {
+        use PipelineStatus::*;
+        let mut statuses = [
+            Success,
+            Unknown("canceling".to_string()),
+            Failed,
+            WaitingForResource,
+        ]
+        .map(|status| status.to_string());
+        statuses.sort();
+        assert_eq!(
+            statuses,
+            ["Canceling", "Failed", "Success", "WaitingForResource"]
+        );
+    }
+
+    #[test]
+    fn aggregate_puts_failures_first_then_active_pipelines() {
+        use PipelineStatus::*;
+        let aggregate = |statuses: &[PipelineStatus]| {
+            PipelineStatus::aggregate(statuses).map(|status| status.to_string())
+        };
+        assert_eq!(aggregate(&[]), None);
+        assert_eq!(aggregate(&[Success, Skipped]).as_deref(), Some("Success"));
+        assert_eq!(
+            aggregate(&[Success, Manual, Canceled]).as_deref(),
+            Some("Manual")
+        );
+        assert_eq!(aggregate(&[Success, Pending]).as_deref(), Some("Pending"));
+        assert_eq!(
+            aggregate(&[Pending, Running, Success]).as_deref(),
+            Some("Running")
+        );
+        assert_eq!(
+            aggregate(&[Running, Failed, Success]).as_deref(),
+            Some("Failed")
+        );
+        assert_eq!(
+            aggregate(&[Success, Unknown("canceling".to_string())]).as_deref(),
+            Some("Canceling")
+        );
     }
 }
@@ -1,10 +1,15 @@
 mod action;
 mod app;
+mod cache;
 mod cli;
 mod components;
 mod config;
+mod discovery;
+mod flaky;
 mod gitlab;
+mod notifications;
 mod state;
+mod theme;
 mod tui;
 
 use app::App;
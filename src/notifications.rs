@@ -0,0 +1,326 @@
+use std::{
+    collections::HashMap,
+    io::{stdout, Write},
+    process::Stdio,
+};
+
+use color_eyre::Result;
+use globset::{Glob, GlobMatcher};
+use serde::{Deserialize, Serialize};
+use tokio::{io::AsyncWriteExt, process::Command};
+use tracing::{error, warn};
+
+use crate::{
+    config::{NotificationRule, NotificationsConfig},
+    gitlab::{GitlabPipeline, PipelineStatus},
+};
+
+/// Ways of delivering a notification to the user.
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Delivery {
+    /// Short-lived popup drawn inside the dashboard
+    Toast,
+    /// Terminal bell
+    Bell,
+    /// OSC 9 desktop notification (iTerm2, WezTerm, Windows Terminal, ...)
+    Osc9,
+    /// OSC 777 desktop notification (rxvt-unicode, foot, Ghostty, ...)
+    Osc777,
+    /// Shell command receiving the event as JSON on its standard input
+    Command,
+}
+
+/// A change of status of a watched pipeline, detected between two successive fetches.
+#[derive(Clone, Serialize)]
+pub struct PipelineEvent {
+    pub project: String,
+    pub pipeline_id: u32,
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+    pub previous_status: Option<PipelineStatus>,
+    pub status: PipelineStatus,
+    pub web_url: String,
+}
+
+impl PipelineEvent {
+    pub fn title(&self) -> String {
+        format!("{} #{}", self.project, self.pipeline_id)
+    }
+
+    pub fn body(&self) -> String {
+        match &self.previous_status {
+            Some(previous_status) => {
+                format!("{}: {} → {}", self.git_ref, previous_status, self.status)
+            }
+            None => format!("{}: {}", self.git_ref, self.status),
+        }
+    }
+}
+
+/// Compare two successive fetches of pipelines of a project and return status transitions.
+/// Pipelines which appeared since the previous fetch are reported with no previous status.
+pub fn detect_transitions(
+    project: &str,
+    previous: &[GitlabPipeline],
+    current: &[GitlabPipeline],
+) -> Vec<PipelineEvent> {
+    let previous_statuses: HashMap<u32, &PipelineStatus> = previous
+        .iter()
+        .map(|pipeline| (pipeline.id, &pipeline.status))
+        .collect();
+    // Pipelines older than anything seen before merely scrolled into the fetched page
+    let oldest_known = previous.iter().map(|pipeline| pipeline.id).min();
+
+    current
+        .iter()
+        .filter_map(|pipeline| {
+            let previous_status = match previous_statuses.get(&pipeline.id) {
+                Some(status) if **status == pipeline.status => return None,
+                Some(status) => Some((*status).clone()),
+                None if oldest_known.is_some_and(|oldest| pipeline.id < oldest) => return None,
+                None => None,
+            };
+            Some(PipelineEvent {
+                project: project.to_string(),
+                pipeline_id: pipeline.id,
+                git_ref: pipeline.git_ref.clone(),
+                previous_status,
+                status: pipeline.status.clone(),
+                web_url: pipeline.web_url.clone(),
+            })
+        })
+        .collect()
+}
+
+struct CompiledRule {
+    project: GlobMatcher,
+    refs: Vec<GlobMatcher>,
+    statuses: Vec<PipelineStatus>,
+}
+
+impl CompiledRule {
+    fn new(rule: &NotificationRule) -> Result<Self> {
+        Ok(Self {
+            project: Glob::new(&rule.project)?.compile_matcher(),
+            refs: rule
+                .refs
+                .iter()
+                .map(|git_ref| Ok(Glob::new(git_ref)?.compile_matcher()))
+                .collect::<Result<_>>()?,
+            statuses: rule.statuses.clone(),
+        })
+    }
+
+    fn matches_project(&self, project: &str) -> bool {
+        self.project.is_match(project)
+    }
+
+    fn matches(&self, event: &PipelineEvent) -> bool {
+        self.matches_project(&event.project)
+            && (self.refs.is_empty() || self.refs.iter().any(|r| r.is_match(&event.git_ref)))
+            && self.statuses.contains(&event.status)
+    }
+}
+
+/// Decides which pipeline events are worth notifying about and delivers them.
+#[derive(Default)]
+pub struct Notifier {
+    rules: Vec<CompiledRule>,
+    delivery: Vec<Delivery>,
+    command: Option<String>,
+}
+
+impl Notifier {
+    /// Notifier following the configured rules, or else the default rule watching failed and
+    /// manual pipelines of every project.
+    pub fn new(config: &NotificationsConfig) -> Result<Self> {
+        if !config.enabled {
+            return Ok(Self::default());
+        }
+        let default_rules = [NotificationRule::default()];
+        let rules = if config.rules.is_empty() {
+            &default_rules[..]
+        } else {
+            &config.rules
+        };
+        Ok(Self {
+            rules: rules.iter().map(CompiledRule::new).collect::<Result<_>>()?,
+            delivery: config.delivery.clone(),
+            command: config.command.clone(),
+        })
+    }
+
+    /// Whether pipelines of a project need to be watched at all.
+    pub fn watches(&self, project: &str) -> bool {
+        self.rules.iter().any(|rule| rule.matches_project(project))
+    }
+
+    /// Whether a project is watched for pipelines transitioning into a given status.
+    pub fn watches_status(&self, project: &str, status: &PipelineStatus) -> bool {
+        self.rules
+            .iter()
+            .any(|rule| rule.matches_project(project) && rule.statuses.contains(status))
+    }
+
+    pub fn should_notify(&self, event: &PipelineEvent) -> bool {
+        self.rules.iter().any(|rule| rule.matches(event))
+    }
+
+    pub fn shows_toasts(&self) -> bool {
+        self.delivery.contains(&Delivery::Toast)
+    }
+
+    /// Deliver an event using every configured method other than in-app toasts, which are drawn by
+    /// the notifications component.
+    pub fn deliver(&self, event: &PipelineEvent) {
+        let mut escape_sequences = String::new();
+        for delivery in &self.delivery {
+            match delivery {
+                Delivery::Toast => {}
+                Delivery::Bell => escape_sequences.push('\x07'),
+                Delivery::Osc9 => escape_sequences.push_str(&format!(
+                    "\x1b]9;{}: {}\x07",
+                    osc_text(&event.title()),
+                    osc_text(&event.body())
+                )),
+                Delivery::Osc777 => escape_sequences.push_str(&format!(
+                    "\x1b]777;notify;{};{}\x07",
+                    osc_text(&event.title()),
+                    osc_text(&event.body())
+                )),
+                Delivery::Command => match &self.command {
+                    Some(command) => run_command_hook(command.clone(), event.clone()),
+                    None => warn!("Command notifications enabled, but no command configured"),
+                },
+            }
+        }
+        if !escape_sequences.is_empty() {
+            let mut stdout = stdout();
+            if let Err(err) = stdout
+                .write_all(escape_sequences.as_bytes())
+                .and_then(|_| stdout.flush())
+            {
+                error!("Failed to write notification to the terminal: {}", err);
+            }
+        }
+    }
+}
+
+/// Text safe to embed in an OSC sequence. Project paths and refs come from GitLab, and a control
+/// character or a `;` in them could end the sequence early or split it into other parameters.
+fn osc_text(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_control() && *c != ';')
+        .collect()
+}
+
+/// Run a shell command in the background, passing the event as JSON on its standard input.
+fn run_command_hook(command: String, event: PipelineEvent) {
+    tokio::spawn(async move {
+        let result: Result<()> = async {
+            let mut child = Command::new("sh")
+                .arg("-c")
+                .arg(&command)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()?;
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(&serde_json::to_vec(&event)?).await?;
+            }
+            child.wait().await?;
+            Ok(())
+        }
+        .await;
+        if let Err(err) = result {
+            error!("Notification command `{}` failed: {}", command, err);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn pipeline(id: u32, status: &str) -> GitlabPipeline {
+        serde_json::from_value(json!({
+            "id": id,
+            "iid": id,
+            "project_id": 1,
+            "status": status,
+            "source": "push",
+            "ref": "main",
+            "sha": "abc",
+            "web_url": format!("https://gitlab.example.com/group/project/-/pipelines/{id}"),
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    fn transitions(events: &[PipelineEvent]) -> Vec<(u32, Option<String>, String)> {
+        events
+            .iter()
+            .map(|event| {
+                (
+                    event.pipeline_id,
+                    event.previous_status.as_ref().map(ToString::to_string),
+                    event.status.to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn detect_transitions_reports_status_changes() {
+        let previous = [
+            pipeline(10, "running"),
+            pipeline(11, "running"),
+            pipeline(12, "running"),
+            pipeline(13, "running"),
+        ];
+        let current = [
+            pipeline(10, "failed"),
+            pipeline(11, "success"),
+            pipeline(12, "manual"),
+            pipeline(13, "running"),
+        ];
+        let expected = [
+            (10, Some("Running"), "Failed"),
+            (11, Some("Running"), "Success"),
+            (12, Some("Running"), "Manual"),
+        ]
+        .map(|(id, previous, status)| (id, previous.map(str::to_string), status.to_string()));
+        assert_eq!(
+            transitions(&detect_transitions("group/project", &previous, &current)),
+            expected
+        );
+    }
+
+    #[test]
+    fn detect_transitions_reports_new_pipelines_only() {
+        let previous = [pipeline(10, "running"), pipeline(11, "success")];
+        // 14 is new, whereas 9 is older than anything seen and merely scrolled into the page
+        let current = [
+            pipeline(14, "pending"),
+            pipeline(11, "success"),
+            pipeline(10, "running"),
+            pipeline(9, "failed"),
+        ];
+        assert_eq!(
+            transitions(&detect_transitions("group/project", &previous, &current)),
+            [(14, None, "Pending".to_string())]
+        );
+    }
+
+    #[test]
+    fn osc_text_drops_control_characters_and_separators() {
+        assert_eq!(
+            osc_text("group/pro;ject\x1b]0;x\x07 #1"),
+            "group/project]0x #1"
+        );
+    }
+}
@@ -1,15 +1,114 @@
+use crate::{
+    app::Mode,
+    components::ComponentId,
+    config::{LayoutConfig, Scope, Stacking},
+    gitlab::{DownloadProgress, GitlabUser},
+};
+
+const MIN_PROJECT_LIST_SIZE: u16 = 5;
+const MAX_PROJECT_LIST_SIZE: u16 = 95;
+const RESIZE_STEP: u16 = 5;
+
 #[derive(Default)]
 pub struct State {
+    pub mode: Mode,
+    // Projects of the project list: the configured ones, followed by the discovered ones
+    pub projects: Vec<String>,
     pub active_gitlab_project: Option<String>,
+    #[allow(dead_code)]
     pub active_operation_index: usize,
+    #[allow(dead_code)]
     pub active_filter: String,
     pub input_mode: InputMode,
-    // Focused components:
-    // 0 - header
-    // 1 - project selector
-    // 2 - pipelines viewer
-    // 3 - footer
-    pub focused_component: usize,
+    pub focused_component: ComponentId,
+    // Component showing a popup, which receives non-global actions instead of the focused one
+    pub popup: Option<ComponentId>,
+    // Set when GitLab could not be reached and the data on screen comes from the cache
+    pub offline: bool,
+    pub layout: PaneLayout,
+    // What is shown next to the project list
+    pub view: View,
+    // Pipeline shown in the graph view, and the view to go back to once it is closed
+    pub graph_pipeline: Option<PipelineRef>,
+    pub previous_view: View,
+    // Projects from which the active one was opened, e.g. by following a downstream pipeline
+    pub project_history: Vec<String>,
+    // Download running in the background, shown in the footer
+    pub download: Option<Download>,
+    // User the token belongs to, resolved once it is needed
+    pub user: Option<GitlabUser>,
+}
+
+pub struct Download {
+    pub label: String,
+    pub progress: DownloadProgress,
+}
+
+/// Identifies a pipeline, possibly in a project which is not in the project list (e.g. a
+/// downstream pipeline).
+#[derive(Clone, PartialEq)]
+pub struct PipelineRef {
+    pub gitlab_project: String,
+    pub id: u32,
+}
+
+#[derive(Default, Clone, Copy, PartialEq)]
+pub enum View {
+    #[default]
+    Pipelines,
+    Stats,
+    FlakyJobs,
+    Environments,
+    Schedules,
+    Runners,
+    Variables,
+    MyWork,
+    Graph,
+}
+
+impl View {
+    /// Component drawing the view.
+    pub fn component(&self) -> ComponentId {
+        match self {
+            View::Pipelines => ComponentId::PipelinesViewer,
+            View::Stats => ComponentId::Stats,
+            View::FlakyJobs => ComponentId::FlakyJobs,
+            View::Environments => ComponentId::Environments,
+            View::Schedules => ComponentId::Schedules,
+            View::Runners => ComponentId::Runners,
+            View::Variables => ComponentId::CiVariables,
+            View::MyWork => ComponentId::MyWork,
+            View::Graph => ComponentId::PipelineGraph,
+        }
+    }
+}
+
+impl State {
+    /// Replace the current view with the graph of a pipeline.
+    pub fn show_graph(&mut self, pipeline: PipelineRef) {
+        if self.view != View::Graph {
+            self.previous_view = self.view;
+        }
+        self.graph_pipeline = Some(pipeline);
+        self.view = View::Graph;
+        self.focused_component = ComponentId::PipelineGraph;
+    }
+
+    /// Keybinding scopes which currently apply, from the most specific one.
+    pub fn active_scopes(&self) -> Vec<Scope> {
+        let scope = if self.input_mode == InputMode::Insert {
+            Some(Scope::Input)
+        } else if let Some(popup) = self.popup {
+            Some(popup.popup_scope())
+        } else {
+            self.focused_component.scope()
+        };
+        match scope {
+            Some(scope) if scope.includes_global() => vec![scope, Scope::Global],
+            Some(scope) => vec![scope],
+            None => vec![Scope::Global],
+        }
+    }
 }
 
 #[derive(Default, PartialEq)]
@@ -17,5 +116,74 @@ pub enum InputMode {
     #[default]
     Normal,
     Insert,
+    #[allow(dead_code)]
     Command,
 }
+
+/// Layout of the panes, as configured and adjusted at runtime.
+#[derive(Default)]
+pub struct PaneLayout {
+    pub config: LayoutConfig,
+    pub project_list_size: u16,
+    pub zoomed: bool,
+    // Width of the terminal during the last render
+    pub width: u16,
+}
+
+impl PaneLayout {
+    pub fn new(config: LayoutConfig) -> Self {
+        Self {
+            project_list_size: config.project_list_size,
+            config,
+            zoomed: false,
+            width: u16::MAX,
+        }
+    }
+
+    pub fn stacked_vertically(&self) -> bool {
+        match self.config.stacking {
+            Stacking::Horizontal => false,
+            Stacking::Vertical => true,
+            Stacking::Auto => self.width < self.config.vertical_below_width,
+        }
+    }
+
+    /// Whether only one pane of the body is shown: the focused one when zoomed in or on narrow
+    /// terminals, the pipelines when the project list is disabled.
+    pub fn single_pane(&self) -> bool {
+        !self.config.project_list || self.zoomed || self.width < self.config.collapse_below_width
+    }
+
+    pub fn is_focusable(&self, component: ComponentId) -> bool {
+        match component {
+            ComponentId::Header => self.config.header,
+            ComponentId::Footer => self.config.footer,
+            ComponentId::ProjectSelector => self.config.project_list,
+            _ => true,
+        }
+    }
+
+    /// Grow (or shrink) the focused pane of the body at the expense of the other one.
+    pub fn resize(&mut self, focused: ComponentId, grow: bool) {
+        let grow_project_list = match focused {
+            ComponentId::ProjectSelector => grow,
+            ComponentId::PipelinesViewer
+            | ComponentId::Stats
+            | ComponentId::FlakyJobs
+            | ComponentId::Environments
+            | ComponentId::Schedules
+            | ComponentId::Runners
+            | ComponentId::CiVariables
+            | ComponentId::MyWork
+            | ComponentId::PipelineGraph => !grow,
+            _ => return,
+        };
+        self.project_list_size = if grow_project_list {
+            (self.project_list_size + RESIZE_STEP).min(MAX_PROJECT_LIST_SIZE)
+        } else {
+            self.project_list_size
+                .saturating_sub(RESIZE_STEP)
+                .max(MIN_PROJECT_LIST_SIZE)
+        };
+    }
+}
@@ -0,0 +1,399 @@
+use std::{collections::HashMap, env};
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+
+use crate::gitlab::PipelineStatus;
+
+/// Built-in colour themes, used as a base for the overrides from the configuration file.
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemePreset {
+    #[default]
+    Dark,
+    Light,
+    HighContrast,
+    /// Based on the Okabe-Ito palette, distinguishable with all common forms of colour blindness
+    ColourBlind,
+}
+
+/// Named colour slots used throughout the user interface.
+#[derive(Clone)]
+pub struct Palette {
+    pub accent: Color,
+    pub active_project: Color,
+    pub project_selector_border: Color,
+    pub pipelines_border: Color,
+    pub footer_border: Color,
+    pub popup_border: Color,
+    pub toast_border: Color,
+    pub highlight_fg: Color,
+    pub highlight_bg: Color,
+    pub selector_highlight_fg: Color,
+    pub selector_highlight_bg: Color,
+    pub keys: Color,
+    pub warning: Color,
+    pub error: Color,
+}
+
+/// How a pipeline status is displayed.
+#[derive(Clone)]
+pub struct StatusStyle {
+    pub color: Color,
+    pub icon: String,
+    pub modifier: Modifier,
+}
+
+/// Colour theme resolved from a preset, the overrides from the configuration file and the
+/// `NO_COLOR` environment variable.
+#[derive(Clone, Deserialize)]
+#[serde(from = "ThemeConfig")]
+pub struct Theme {
+    pub palette: Palette,
+    statuses: HashMap<PipelineStatus, StatusStyle>,
+    // Style of statuses missing from `statuses`, e.g. ones added to GitLab recently
+    unknown_status: StatusStyle,
+    no_color: bool,
+}
+
+/// `[ui.theme]` section of the configuration file.
+#[derive(Default, Deserialize)]
+struct ThemeConfig {
+    #[serde(default)]
+    preset: ThemePreset,
+    #[serde(default)]
+    palette: PaletteOverrides,
+    #[serde(default)]
+    statuses: HashMap<PipelineStatus, StatusOverride>,
+}
+
+#[derive(Default, Deserialize)]
+struct PaletteOverrides {
+    accent: Option<Color>,
+    active_project: Option<Color>,
+    project_selector_border: Option<Color>,
+    pipelines_border: Option<Color>,
+    footer_border: Option<Color>,
+    popup_border: Option<Color>,
+    toast_border: Option<Color>,
+    highlight_fg: Option<Color>,
+    highlight_bg: Option<Color>,
+    selector_highlight_fg: Option<Color>,
+    selector_highlight_bg: Option<Color>,
+    keys: Option<Color>,
+    warning: Option<Color>,
+    error: Option<Color>,
+}
+
+#[derive(Default, Deserialize)]
+struct StatusOverride {
+    color: Option<Color>,
+    icon: Option<String>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::from(ThemeConfig::default())
+    }
+}
+
+impl From<ThemeConfig> for Theme {
+    fn from(config: ThemeConfig) -> Self {
+        let mut theme = Theme::preset(config.preset);
+        let overrides = config.palette;
+        let palette = &mut theme.palette;
+        let slots = [
+            (&mut palette.accent, overrides.accent),
+            (&mut palette.active_project, overrides.active_project),
+            (
+                &mut palette.project_selector_border,
+                overrides.project_selector_border,
+            ),
+            (&mut palette.pipelines_border, overrides.pipelines_border),
+            (&mut palette.footer_border, overrides.footer_border),
+            (&mut palette.popup_border, overrides.popup_border),
+            (&mut palette.toast_border, overrides.toast_border),
+            (&mut palette.highlight_fg, overrides.highlight_fg),
+            (&mut palette.highlight_bg, overrides.highlight_bg),
+            (
+                &mut palette.selector_highlight_fg,
+                overrides.selector_highlight_fg,
+            ),
+            (
+                &mut palette.selector_highlight_bg,
+                overrides.selector_highlight_bg,
+            ),
+            (&mut palette.keys, overrides.keys),
+            (&mut palette.warning, overrides.warning),
+            (&mut palette.error, overrides.error),
+        ];
+        for (slot, color) in slots {
+            if let Some(color) = color {
+                *slot = color;
+            }
+        }
+        for (status, status_override) in config.statuses {
+            let style = theme
+                .statuses
+                .entry(status)
+                .or_insert_with(|| theme.unknown_status.clone());
+            if let Some(color) = status_override.color {
+                style.color = color;
+            }
+            if let Some(icon) = status_override.icon {
+                style.icon = icon;
+            }
+        }
+        // See https://no-color.org
+        theme.no_color = env::var("NO_COLOR").is_ok_and(|value| !value.is_empty());
+        theme
+    }
+}
+
+impl Theme {
+    fn preset(preset: ThemePreset) -> Self {
+        let (palette, colors) = match preset {
+            ThemePreset::Dark => (
+                Palette {
+                    accent: Color::LightBlue,
+                    active_project: Color::LightRed,
+                    project_selector_border: Color::LightMagenta,
+                    pipelines_border: Color::Green,
+                    footer_border: Color::LightBlue,
+                    popup_border: Color::LightBlue,
+                    toast_border: Color::LightCyan,
+                    highlight_fg: Color::Black,
+                    highlight_bg: Color::LightYellow,
+                    selector_highlight_fg: Color::Black,
+                    selector_highlight_bg: Color::White,
+                    keys: Color::LightBlue,
+                    warning: Color::Yellow,
+                    error: Color::Red,
+                },
+                StatusColors {
+                    success: Color::Green,
+                    failed: Color::Red,
+                    running: Color::LightBlue,
+                    pending: Color::Yellow,
+                    manual: Color::LightMagenta,
+                    inactive: Color::Gray,
+                },
+            ),
+            ThemePreset::Light => (
+                Palette {
+                    accent: Color::Blue,
+                    active_project: Color::Red,
+                    project_selector_border: Color::Magenta,
+                    pipelines_border: Color::Green,
+                    footer_border: Color::Blue,
+                    popup_border: Color::Blue,
+                    toast_border: Color::Cyan,
+                    highlight_fg: Color::White,
+                    highlight_bg: Color::Blue,
+                    selector_highlight_fg: Color::White,
+                    selector_highlight_bg: Color::DarkGray,
+                    keys: Color::Blue,
+                    warning: Color::Rgb(0xb3, 0x6b, 0x00),
+                    error: Color::Red,
+                },
+                StatusColors {
+                    success: Color::Green,
+                    failed: Color::Red,
+                    running: Color::Blue,
+                    pending: Color::Rgb(0xb3, 0x6b, 0x00),
+                    manual: Color::Magenta,
+                    inactive: Color::DarkGray,
+                },
+            ),
+            ThemePreset::HighContrast => (
+                Palette {
+                    accent: Color::White,
+                    active_project: Color::LightYellow,
+                    project_selector_border: Color::LightYellow,
+                    pipelines_border: Color::LightYellow,
+                    footer_border: Color::LightYellow,
+                    popup_border: Color::White,
+                    toast_border: Color::White,
+                    highlight_fg: Color::Black,
+                    highlight_bg: Color::White,
+                    selector_highlight_fg: Color::Black,
+                    selector_highlight_bg: Color::White,
+                    keys: Color::LightYellow,
+                    warning: Color::LightYellow,
+                    error: Color::LightRed,
+                },
+                StatusColors {
+                    success: Color::LightGreen,
+                    failed: Color::LightRed,
+                    running: Color::LightCyan,
+                    pending: Color::LightYellow,
+                    manual: Color::LightMagenta,
+                    inactive: Color::White,
+                },
+            ),
+            ThemePreset::ColourBlind => {
+                let orange = Color::Rgb(0xe6, 0x9f, 0x00);
+                let sky_blue = Color::Rgb(0x56, 0xb4, 0xe9);
+                let yellow = Color::Rgb(0xf0, 0xe4, 0x42);
+                let blue = Color::Rgb(0x00, 0x72, 0xb2);
+                let vermillion = Color::Rgb(0xd5, 0x5e, 0x00);
+                let reddish_purple = Color::Rgb(0xcc, 0x79, 0xa7);
+                (
+                    Palette {
+                        accent: sky_blue,
+                        active_project: orange,
+                        project_selector_border: reddish_purple,
+                        pipelines_border: sky_blue,
+                        footer_border: sky_blue,
+                        popup_border: sky_blue,
+                        toast_border: yellow,
+                        highlight_fg: Color::Black,
+                        highlight_bg: yellow,
+                        selector_highlight_fg: Color::Black,
+                        selector_highlight_bg: Color::White,
+                        keys: sky_blue,
+                        warning: orange,
+                        error: vermillion,
+                    },
+                    StatusColors {
+                        success: blue,
+                        failed: vermillion,
+                        running: sky_blue,
+                        pending: yellow,
+                        manual: reddish_purple,
+                        inactive: Color::Gray,
+                    },
+                )
+            }
+        };
+        let bold = matches!(preset, ThemePreset::HighContrast);
+        Self {
+            palette,
+            statuses: colors.statuses(bold),
+            unknown_status: StatusStyle {
+                color: colors.inactive,
+                icon: "?".to_string(),
+                modifier: if bold {
+                    Modifier::BOLD
+                } else {
+                    Modifier::empty()
+                },
+            },
+            no_color: false,
+        }
+    }
+
+    /// Replace a colour with the terminal default if colours are disabled.
+    pub fn color(&self, color: Color) -> Color {
+        if self.no_color {
+            Color::Reset
+        } else {
+            color
+        }
+    }
+
+    /// Style with a foreground colour from the palette.
+    pub fn fg(&self, color: Color) -> Style {
+        Style::default().fg(self.color(color))
+    }
+
+    /// Style of selected rows. Without colours, selection is shown by reversing the row.
+    pub fn highlight(&self, fg: Color, bg: Color) -> Style {
+        if self.no_color {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default().fg(fg).bg(bg)
+        }
+    }
+
+    /// Style of the text matching a search.
+    pub fn search_match(&self) -> Style {
+        self.fg(self.palette.warning)
+            .add_modifier(Modifier::REVERSED | Modifier::BOLD)
+    }
+
+    pub fn status_style(&self, status: &PipelineStatus) -> Style {
+        let style = self.status(status);
+        self.fg(style.color).add_modifier(style.modifier)
+    }
+
+    pub fn status_icon(&self, status: &PipelineStatus) -> &str {
+        &self.status(status).icon
+    }
+
+    fn status(&self, status: &PipelineStatus) -> &StatusStyle {
+        self.statuses.get(status).unwrap_or(&self.unknown_status)
+    }
+}
+
+/// Status colours of a preset. Statuses are grouped, as most of them only need to be told apart
+/// by their icon.
+struct StatusColors {
+    success: Color,
+    failed: Color,
+    running: Color,
+    pending: Color,
+    manual: Color,
+    inactive: Color,
+}
+
+impl StatusColors {
+    fn statuses(&self, bold: bool) -> HashMap<PipelineStatus, StatusStyle> {
+        let base = if bold {
+            Modifier::BOLD
+        } else {
+            Modifier::empty()
+        };
+        let style = |color: Color, icon: &str, modifier: Modifier| StatusStyle {
+            color,
+            icon: icon.to_string(),
+            modifier: base | modifier,
+        };
+        HashMap::from([
+            (
+                PipelineStatus::Created,
+                style(self.inactive, "○", Modifier::empty()),
+            ),
+            (
+                PipelineStatus::WaitingForResource,
+                style(self.pending, "◔", Modifier::empty()),
+            ),
+            (
+                PipelineStatus::Preparing,
+                style(self.pending, "◑", Modifier::empty()),
+            ),
+            (
+                PipelineStatus::Pending,
+                style(self.pending, "◕", Modifier::empty()),
+            ),
+            (
+                PipelineStatus::Running,
+                style(self.running, "●", Modifier::ITALIC),
+            ),
+            (
+                PipelineStatus::Success,
+                style(self.success, "✔", Modifier::empty()),
+            ),
+            (
+                PipelineStatus::Failed,
+                style(self.failed, "✘", Modifier::BOLD),
+            ),
+            (
+                PipelineStatus::Canceled,
+                style(self.inactive, "⊘", Modifier::empty()),
+            ),
+            (
+                PipelineStatus::Skipped,
+                style(self.inactive, "»", Modifier::empty()),
+            ),
+            (
+                PipelineStatus::Manual,
+                style(self.manual, "⚙", Modifier::empty()),
+            ),
+            (
+                PipelineStatus::Scheduled,
+                style(self.inactive, "◷", Modifier::empty()),
+            ),
+        ])
+    }
+}